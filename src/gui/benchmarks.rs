@@ -0,0 +1,134 @@
+
+/*
+    rough benchmarks for the drawing code at 4k. they're ignored so they
+    don't slow down the normal test run, use:
+
+        cargo test --release benchmarks -- --ignored --nocapture --test-threads=1
+
+    fill_rect_per_pixel is the original fill_rect that checked bounds for
+    every pixel, kept here to compare the row based version against.
+*/
+
+use std::hint::black_box;
+use std::time::Instant;
+use crate::gui::Pixel;
+use crate::gui::PixelBuffer;
+use crate::gui::color::Color;
+use crate::gui::draw::fill_rect;
+use crate::gui::draw::draw_image;
+use crate::gui::draw::blend_mask;
+use crate::gui::image::Image;
+use crate::gui::image::ImageFilter;
+use crate::gui::raster::blend_coverage_row;
+use crate::gui::raster::blend_coverage_row_scalar;
+use crate::gui::Rect;
+
+const WIDTH: i32 = 3840;
+const HEIGHT: i32 = 2160;
+
+fn buffer_4k() -> PixelBuffer {
+    PixelBuffer::new(0, 0, WIDTH, HEIGHT)
+}
+
+fn bench<F: FnMut()>(name: &str, iterations: u32, mut f: F) -> f64 {
+    f(); // warm up
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+    let ms = start.elapsed().as_secs_f64() * 1000.0 / iterations as f64;
+    println!("{:<40} {:>9.3} ms", name, ms);
+    ms
+}
+
+fn fill_rect_per_pixel(buffer: &mut PixelBuffer, left: i32, top: i32, width: i32, height: i32, color: Color) {
+    let right = left + width;
+    let bottom = top + height;
+    let stride = buffer.width;
+    for y in top..bottom {
+        for x in left..right {
+            if  y < buffer.height && x < buffer.width {
+                let offset = ((y * stride) + x) as usize;
+                let pixel = &mut buffer.pixels[offset];
+                pixel.red = color.red;
+                pixel.green = color.green;
+                pixel.blue = color.blue;
+            }
+        }
+    }
+}
+
+#[test]
+#[ignore]
+fn bench_fill_rect_4k() {
+    let mut buffer = buffer_4k();
+    let color = Color::from_rgb(0x1E, 0x1E, 0x1E);
+    let old = bench("fill_rect per pixel 4k", 20, || fill_rect_per_pixel(black_box(&mut buffer), 0, 0, WIDTH, HEIGHT, color));
+    let new = bench("fill_rect rows 4k", 20, || fill_rect(black_box(&mut buffer), 0, 0, WIDTH, HEIGHT, color));
+    println!("{:<40} {:>9.1}x", "speedup", old / new);
+}
+
+#[test]
+#[ignore]
+fn bench_update_back_buffer_fills_4k() {
+    // the same fills update_back_buffer does every frame
+    let mut buffer = buffer_4k();
+    let light = Color::from_rgb(0x25, 0x25, 0x26);
+    let dark = Color::from_rgb(0x1E, 0x1E, 0x1E);
+    let old = bench("frame fills per pixel 4k", 20, || {
+        fill_rect_per_pixel(&mut buffer, 0, 0, WIDTH / 5, HEIGHT, light);
+        fill_rect_per_pixel(&mut buffer, WIDTH / 5, 0, WIDTH * 4 / 5, HEIGHT, dark);
+        fill_rect_per_pixel(&mut buffer, WIDTH * 3 / 5, 0, 4, HEIGHT, light);
+    });
+    let new = bench("frame fills rows 4k", 20, || {
+        fill_rect(&mut buffer, 0, 0, WIDTH / 5, HEIGHT, light);
+        fill_rect(&mut buffer, WIDTH / 5, 0, WIDTH * 4 / 5, HEIGHT, dark);
+        fill_rect(&mut buffer, WIDTH * 3 / 5, 0, 4, HEIGHT, light);
+    });
+    println!("{:<40} {:>9.1}x", "speedup", old / new);
+}
+
+#[test]
+#[ignore]
+fn bench_blend_coverage_4k() {
+    let mut buffer = buffer_4k();
+    let coverage: Vec<u8> = (0..WIDTH * HEIGHT).map(|i| (i % 256) as u8).collect();
+    let color = Color::WHITE;
+    let scalar = bench("coverage blend scalar 4k", 10, || {
+        blend_coverage_row_scalar(&mut buffer.pixels, &coverage, color);
+    });
+    let fast = bench("coverage blend simd 4k", 10, || {
+        blend_coverage_row(&mut buffer.pixels, &coverage, color);
+    });
+    println!("{:<40} {:>9.1}x", "speedup", scalar / fast);
+    bench("blend_mask clipped 4k", 10, || {
+        let clip = Rect { x: 10, y: 10, w: WIDTH - 20, h: HEIGHT - 20 };
+        blend_mask(&mut buffer, &coverage, Rect { x: 0, y: 0, w: WIDTH, h: HEIGHT }, clip, color);
+    });
+}
+
+#[test]
+#[ignore]
+fn bench_draw_image_4k() {
+    let mut buffer = buffer_4k();
+    let mut image = Image::new(256, 256);
+    for (i, pixel) in image.pixels.iter_mut().enumerate() {
+        *pixel = Pixel::from_rgba(i as u8, (i / 256) as u8, 128, (i % 256) as u8);
+    }
+    // a screen full of 256px thumbnails
+    let tiles_x = WIDTH / 256;
+    let tiles_y = HEIGHT / 256;
+    bench("draw_image 1:1 4k", 10, || {
+        for y in 0..tiles_y {
+            for x in 0..tiles_x {
+                draw_image(&mut buffer, &image, x * 256, y * 256, 256, 256, ImageFilter::Nearest);
+            }
+        }
+    });
+    bench("draw_image nearest 4k", 10, || {
+        draw_image(&mut buffer, &image, 0, 0, WIDTH, HEIGHT, ImageFilter::Nearest);
+    });
+    bench("draw_image bilinear 4k", 5, || {
+        draw_image(&mut buffer, &image, 0, 0, WIDTH, HEIGHT, ImageFilter::Bilinear);
+    });
+}
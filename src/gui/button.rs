@@ -3,22 +3,46 @@ use crate::gui::is_point_in_rect;
 use crate::gui::style::BoxStyle;
 use crate::gui::Rect;
 use crate::gui::control::Control;
+use crate::gui::text::line::truncate_text;
+use crate::gui::text::manager::FontManager;
+use crate::gui::widget::Widget;
+use crate::gui::widget::Event;
+use crate::gui::mouse::MouseButton;
+use crate::gui::keyboard::Key;
+use crate::gui::keyboard::KeyState;
+use crate::gui::draw::draw_button;
+use crate::gui::Cursor;
+use crate::gui::PixelBuffer;
 
 pub struct Button {
     pub text: &'static str,
     pub bounds: Rect,
     pub hot: bool,
     pub active: bool,
-    pub on_click: Option<ButtonClick>,
     pub click_count: i32,
     pub style: BoxStyle,
     pub style_hot: BoxStyle,
-    pub style_active: BoxStyle
+    pub style_active: BoxStyle,
+    // a click waiting to go out as Event::Click, what to do about it is up
+    // to the listeners on the button's node
+    clicked: bool
 }
 
-type ButtonClick = fn(&mut Button) -> ();
-
 impl Button {
+    pub fn new(text: &'static str, style: BoxStyle, style_hot: BoxStyle, style_active: BoxStyle) -> Button {
+        Button {
+            text,
+            bounds: Rect { x: 0, y: 0, w: 0, h: 0 },
+            hot: false,
+            active: false,
+            click_count: 0,
+            style,
+            style_hot,
+            style_active,
+            clicked: false
+        }
+    }
+
     pub fn left_mouse_button_down(&mut self, mouse_x: i32, mouse_y: i32) {
         let hit = is_point_in_rect(mouse_x, mouse_y, self.get_bounds());
         self.hot = hit;
@@ -26,18 +50,70 @@ impl Button {
         crate::update_window();
     }
 
+    // space or enter on the focused button
+    pub fn click(&mut self) {
+        self.click_count += 1;
+        self.clicked = true;
+        crate::update_window();
+    }
+
     pub fn left_mouse_button_up(&mut self, mouse_x: i32, mouse_y: i32) {
         let hit = is_point_in_rect(mouse_x, mouse_y, self.get_bounds());
         self.hot = hit;
         crate::update_window();
         if self.active && hit {
-            match self.on_click {
-                Some(method) => method(self),
-                None => { }
-            }
+            self.click();
         }
         self.active = false;
     }
+
+    // the full text when it's drawn shortened with an ellipsis, for the tooltip
+    pub fn get_tooltip(&self) -> Option<String> {
+        let fonts = unsafe { &crate::APPLICATION_STATE.fonts };
+        let style = self.get_scaled_style();
+        let text: Vec<char> = self.text.chars().collect();
+        let box_width = self.bounds.w - style.border_size.left - style.padding_size.left - style.border_size.right - style.padding_size.right;
+        truncate_text(&text, fonts, fonts.select_style(&style), style.font_size, box_width, style.text_overflow)
+            .map(|_| self.text.to_string())
+    }
+}
+
+impl Widget for Button {
+    fn bounds(&self) -> Rect { self.bounds }
+    fn set_bounds_rect(&mut self, rect: Rect) { self.bounds = rect }
+
+    fn draw(&self, buffer: &mut PixelBuffer, fonts: &FontManager, _draw_cursor: bool) {
+        draw_button(buffer, self, fonts);
+    }
+
+    fn event(&mut self, event: &Event) -> bool {
+        match event {
+            Event::MouseDown { button: MouseButton::Left, x, y, .. } => self.left_mouse_button_down(*x, *y),
+            Event::MouseUp { button: MouseButton::Left, x, y } => self.left_mouse_button_up(*x, *y),
+            Event::MouseMove { x, y } => return self.hit_check(*x, *y).0,
+            // enter clicks right away, space like a mouse button, pressed on
+            // key down and clicked when it comes back up
+            Event::Key(key) => match (key.key, key.state) {
+                (Key::Enter, KeyState::Pressed) => self.click(),
+                (Key::Space, KeyState::Pressed) => self.active = true,
+                (Key::Space, KeyState::Released) if self.active => {
+                    self.active = false;
+                    self.click();
+                },
+                _ => return false
+            },
+            _ => return false
+        }
+        true
+    }
+
+    fn raised(&mut self) -> Option<Event> {
+        if std::mem::take(&mut self.clicked) { Some(Event::Click) } else { None }
+    }
+
+    fn cursor(&self) -> Cursor { Cursor::Hand }
+    fn tooltip(&self, _x: i32, _y: i32) -> Option<String> { self.get_tooltip() }
+    fn focus_style(&self) -> Option<BoxStyle> { Some(self.get_scaled_style()) }
 }
 
 impl Control for Button {
@@ -45,7 +121,7 @@ impl Control for Button {
     fn get_hot(&self) -> bool { self.hot }
     fn set_hot(&mut self, hit: bool) { self.hot = hit }
 
-    fn get_style<'a>(&'a self) -> &'a BoxStyle {
+    fn get_style(&self) -> &BoxStyle {
         if self.active {
             &self.style_active
         }
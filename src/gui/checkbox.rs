@@ -0,0 +1,130 @@
+use crate::gui::is_point_in_rect;
+use crate::gui::style::BoxStyle;
+use crate::gui::style::StateStyles;
+use crate::gui::Rect;
+use crate::gui::control::Control;
+use crate::gui::control::ControlState;
+use crate::gui::text::manager::FontManager;
+use crate::gui::widget::Widget;
+use crate::gui::widget::Event;
+use crate::gui::mouse::MouseButton;
+use crate::gui::keyboard::Key;
+use crate::gui::keyboard::KeyState;
+use crate::gui::draw::draw_checkbox;
+use crate::gui::Cursor;
+use crate::gui::PixelBuffer;
+
+/*
+    a box with a label that's ticked or not. a click anywhere on it or
+    space flips it, like a button it happens when the mouse or the key
+    comes back up. it raises Event::Changed when it flips.
+*/
+pub struct CheckBox {
+    pub text: &'static str,
+    pub checked: bool,
+    pub bounds: Rect,
+    pub state: ControlState,
+    pub styles: StateStyles,
+    changed: bool
+}
+
+impl CheckBox {
+    pub fn new(text: &'static str, checked: bool, styles: StateStyles) -> CheckBox {
+        CheckBox { text, checked, bounds: Rect::default(), state: ControlState::default(), styles, changed: false }
+    }
+
+    pub fn toggle(&mut self) {
+        self.checked = !self.checked;
+        self.changed = true;
+    }
+}
+
+impl Widget for CheckBox {
+    fn bounds(&self) -> Rect { self.bounds }
+    fn set_bounds_rect(&mut self, rect: Rect) { self.bounds = rect }
+
+    fn draw(&self, buffer: &mut PixelBuffer, fonts: &FontManager, _draw_cursor: bool) {
+        draw_checkbox(buffer, self, fonts);
+    }
+
+    fn event(&mut self, event: &Event) -> bool {
+        if self.state.disabled {
+            return false;
+        }
+        match event {
+            Event::MouseMove { x, y } => return self.hit_check(*x, *y).0,
+            Event::MouseDown { button: MouseButton::Left, x, y, .. } => self.state.active = is_point_in_rect(*x, *y, self.bounds),
+            Event::MouseUp { button: MouseButton::Left, x, y } => {
+                if std::mem::take(&mut self.state.active) && is_point_in_rect(*x, *y, self.bounds) {
+                    self.toggle();
+                }
+            },
+            Event::Key(key) => match (key.key, key.state) {
+                (Key::Space, KeyState::Pressed) => self.state.active = true,
+                (Key::Space, KeyState::Released) if self.state.active => {
+                    self.state.active = false;
+                    self.toggle();
+                },
+                _ => return false
+            },
+            _ => return false
+        }
+        true
+    }
+
+    fn raised(&mut self) -> Option<Event> {
+        if std::mem::take(&mut self.changed) { Some(Event::Changed) } else { None }
+    }
+
+    fn cursor(&self) -> Cursor { Cursor::Hand }
+
+    fn focus_style(&self) -> Option<BoxStyle> {
+        if self.state.disabled { None } else { Some(self.get_scaled_style()) }
+    }
+
+    fn set_focused(&mut self, focused: bool) {
+        self.state.focused = focused;
+        self.state.active = false;
+    }
+}
+
+impl Control for CheckBox {
+    fn get_bounds(&self) -> Rect { self.bounds }
+    fn get_hot(&self) -> bool { self.state.hot }
+    fn set_hot(&mut self, hit: bool) { self.state.hot = hit }
+    fn get_style(&self) -> &BoxStyle { self.styles.select(&self.state) }
+}
+
+#[cfg(test)]
+mod checkbox_tests {
+    use super::*;
+    use crate::gui::keyboard::{KeyCode, KeyEvent, KeyboardModifiers};
+
+    #[test]
+    fn test_click_and_space_toggle() {
+        let mut checkbox = CheckBox::new("Show hidden files", false, StateStyles::control_default());
+        checkbox.set_bounds_rect(Rect { x: 0, y: 0, w: 200, h: 24 });
+        checkbox.event(&Event::MouseDown { button: MouseButton::Left, x: 150, y: 10, clicks: 1 });
+        assert!(!checkbox.checked);
+        checkbox.event(&Event::MouseUp { button: MouseButton::Left, x: 150, y: 10 });
+        assert!(checkbox.checked);
+        assert!(matches!(checkbox.raised(), Some(Event::Changed)));
+        assert!(checkbox.raised().is_none());
+
+        // let go somewhere else and nothing happens
+        checkbox.event(&Event::MouseDown { button: MouseButton::Left, x: 150, y: 10, clicks: 1 });
+        checkbox.event(&Event::MouseUp { button: MouseButton::Left, x: 250, y: 10 });
+        assert!(checkbox.checked);
+
+        let key = |state| Event::Key(KeyEvent { code: KeyCode::Unknown(0), key: Key::Space, modifiers: KeyboardModifiers::default(), state, repeat: false });
+        checkbox.event(&key(KeyState::Pressed));
+        checkbox.event(&key(KeyState::Released));
+        assert!(!checkbox.checked);
+
+        checkbox.state.disabled = true;
+        assert!(checkbox.focus_style().is_none());
+        checkbox.event(&key(KeyState::Pressed));
+        checkbox.event(&key(KeyState::Released));
+        assert!(!checkbox.checked);
+    }
+}
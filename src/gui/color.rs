@@ -39,5 +39,7 @@ impl DarkTheme {
     pub const SCROLL_FOREGROUND: Color = Color::from_rgb(0x4f, 0x4f, 0x4f);
     pub const SCROLL_BACKGROUND: Color = Color::from_rgb(0x1e, 0x1e, 0x1e);
     pub const TEXT: Color = Color::WHITE;
+    pub const TEXT_DISABLED: Color = Color::from_rgb(0x80, 0x80, 0x80);
     pub const HIGHLIGHT: Color = Color::from_rgb(0x26, 0x4f, 0x78);
+    pub const FOCUS: Color = Color::from_rgb(0x00, 0x7f, 0xd4);
 }
\ No newline at end of file
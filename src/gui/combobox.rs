@@ -0,0 +1,310 @@
+use crate::gui::is_point_in_rect;
+use crate::gui::style::BoxStyle;
+use crate::gui::style::StateStyles;
+use crate::gui::Rect;
+use crate::gui::scale_i32;
+use crate::gui::get_scale_factor;
+use crate::gui::control::Control;
+use crate::gui::control::ControlState;
+use crate::gui::text::manager::FontManager;
+use crate::gui::widget::Widget;
+use crate::gui::widget::Event;
+use crate::gui::mouse::MouseButton;
+use crate::gui::keyboard::Key;
+use crate::gui::keyboard::KeyState;
+use crate::gui::draw::draw_combobox;
+use crate::gui::Cursor;
+use crate::gui::PixelBuffer;
+
+/*
+    a box showing the picked item with an arrow that drops down the list of
+    all of them. a click, space, enter, f4 or alt+down opens the list, up
+    and down change the pick right away while it's closed. while it's open
+    the arrows move the highlight, enter or a click picks and escape or a
+    click outside closes it. like a menu it covers the window while open
+    so put it after anything its list could cover. it raises Event::Changed
+    when the pick changes.
+*/
+pub struct ComboBox {
+    pub items: Vec<String>,
+    pub selected: Option<usize>,
+    pub bounds: Rect,
+    // logical pixels
+    pub item_height: i32,
+    pub visible_items: usize,
+    // what the list has to stay inside
+    pub window: Rect,
+    pub state: ControlState,
+    pub styles: StateStyles,
+    open: bool,
+    // the highlighted item in the open list and the first one showing
+    hot: Option<usize>,
+    first: usize,
+    changed: bool
+}
+
+impl ComboBox {
+    pub fn new(items: Vec<String>, selected: Option<usize>, styles: StateStyles) -> ComboBox {
+        ComboBox {
+            items,
+            selected,
+            bounds: Rect::default(),
+            item_height: 24,
+            visible_items: 10,
+            window: Rect::default(),
+            state: ControlState::default(),
+            styles,
+            open: false,
+            hot: None,
+            first: 0,
+            changed: false
+        }
+    }
+
+    pub fn selected_text(&self) -> Option<&str> {
+        self.selected.and_then(|i| self.items.get(i)).map(|item| item.as_str())
+    }
+
+    pub fn select(&mut self, index: usize) {
+        if index < self.items.len() && self.selected != Some(index) {
+            self.selected = Some(index);
+            self.changed = true;
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn open_list(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
+        self.open = true;
+        self.state.active = true;
+        self.first = 0;
+        self.set_hot(self.selected.unwrap_or(0) as i32);
+    }
+
+    pub fn close_list(&mut self) {
+        self.open = false;
+        self.state.active = false;
+        self.hot = None;
+    }
+
+    pub fn hot_item(&self) -> Option<usize> {
+        self.hot
+    }
+
+    pub fn first_visible(&self) -> usize {
+        self.first
+    }
+
+    fn rows(&self) -> usize {
+        std::cmp::min(self.visible_items, self.items.len())
+    }
+
+    // under the box, or above it when there's no room below
+    pub fn list_rect(&self) -> Rect {
+        let style = self.styles.normal.scaled(get_scale_factor());
+        let h = scale_i32(self.item_height, get_scale_factor()) * self.rows() as i32 + style.border_size.top + style.border_size.bottom;
+        let below = self.bounds.y + self.bounds.h;
+        let y = if below + h <= self.window.y + self.window.h || self.bounds.y - h < self.window.y { below } else { self.bounds.y - h };
+        Rect { x: self.bounds.x, y, w: self.bounds.w, h }
+    }
+
+    // a row of the open list, counted from the first one showing
+    pub fn row_rect(&self, row: usize) -> Rect {
+        let style = self.styles.normal.scaled(get_scale_factor());
+        let list = self.list_rect();
+        let height = scale_i32(self.item_height, get_scale_factor());
+        Rect {
+            x: list.x + style.border_size.left,
+            y: list.y + style.border_size.top + height * row as i32,
+            w: list.w - style.border_size.left - style.border_size.right,
+            h: height
+        }
+    }
+
+    fn item_at(&self, x: i32, y: i32) -> Option<usize> {
+        (0..self.rows()).find(|row| is_point_in_rect(x, y, self.row_rect(*row))).map(|row| self.first + row)
+    }
+
+    // highlights an item and scrolls the list to show it
+    fn set_hot(&mut self, index: i32) {
+        if self.items.is_empty() {
+            return;
+        }
+        let index = index.clamp(0, self.items.len() as i32 - 1) as usize;
+        self.hot = Some(index);
+        if index < self.first {
+            self.first = index;
+        }
+        else if index >= self.first + self.rows() {
+            self.first = index + 1 - self.rows();
+        }
+    }
+
+    fn pick_hot(&mut self) {
+        if let Some(hot) = self.hot {
+            self.select(hot);
+        }
+        self.close_list();
+    }
+
+    fn key_closed(&mut self, key: Key, alt: bool) -> bool {
+        let selected = self.selected.map_or(-1, |i| i as i32);
+        match key {
+            Key::Down if alt => self.open_list(),
+            Key::F(4) | Key::Space | Key::Enter => self.open_list(),
+            Key::Up => self.select(std::cmp::max(0, selected - 1) as usize),
+            Key::Down => self.select((selected + 1) as usize),
+            Key::Home => self.select(0),
+            Key::End => self.select(self.items.len().saturating_sub(1)),
+            _ => return false
+        }
+        true
+    }
+
+    fn key_open(&mut self, key: Key, alt: bool) -> bool {
+        let hot = self.hot.map_or(0, |i| i as i32);
+        let page = self.visible_items as i32;
+        match key {
+            Key::Up if alt => self.close_list(),
+            Key::Escape | Key::F(4) => self.close_list(),
+            Key::Enter | Key::Space => self.pick_hot(),
+            Key::Up => self.set_hot(hot - 1),
+            Key::Down => self.set_hot(hot + 1),
+            Key::PageUp => self.set_hot(hot - page),
+            Key::PageDown => self.set_hot(hot + page),
+            Key::Home => self.set_hot(0),
+            Key::End => self.set_hot(self.items.len() as i32 - 1),
+            _ => return false
+        }
+        true
+    }
+}
+
+impl Widget for ComboBox {
+    // the window while the list is open, it's modal
+    fn bounds(&self) -> Rect {
+        if self.open { self.window } else { self.bounds }
+    }
+
+    fn set_bounds_rect(&mut self, rect: Rect) { self.bounds = rect }
+
+    fn draw(&self, buffer: &mut PixelBuffer, fonts: &FontManager, _draw_cursor: bool) {
+        draw_combobox(buffer, self, fonts);
+    }
+
+    fn event(&mut self, event: &Event) -> bool {
+        if self.state.disabled {
+            return false;
+        }
+        match event {
+            Event::MouseMove { x, y } => {
+                let hot_changed = self.hit_check(*x, *y).0;
+                match self.item_at(*x, *y) {
+                    Some(index) if self.open && self.hot != Some(index) => self.hot = Some(index),
+                    _ => return hot_changed
+                }
+            },
+            Event::MouseDown { button: MouseButton::Left, x, y, .. } => {
+                if !self.open {
+                    if !is_point_in_rect(*x, *y, self.bounds) {
+                        return false;
+                    }
+                    self.open_list();
+                }
+                else if let Some(index) = self.item_at(*x, *y) {
+                    self.hot = Some(index);
+                    self.pick_hot();
+                }
+                else {
+                    self.close_list();
+                }
+            },
+            // a click outside only closes the list
+            Event::MouseDown { .. } if self.open => self.close_list(),
+            Event::Wheel { lines_y, .. } if self.open => {
+                let last = self.items.len().saturating_sub(self.rows());
+                self.first = (self.first as i32 - lines_y.round() as i32).clamp(0, last as i32) as usize;
+            },
+            Event::Key(key) if key.state == KeyState::Pressed => {
+                let alt = key.modifiers.alt;
+                return if self.open { self.key_open(key.key, alt) } else { self.key_closed(key.key, alt) };
+            },
+            _ => return false
+        }
+        true
+    }
+
+    fn raised(&mut self) -> Option<Event> {
+        if std::mem::take(&mut self.changed) { Some(Event::Changed) } else { None }
+    }
+
+    fn cursor(&self) -> Cursor { Cursor::Arrow }
+
+    fn focus_style(&self) -> Option<BoxStyle> {
+        if self.state.disabled { None } else { Some(self.get_scaled_style()) }
+    }
+
+    // the list closes when something else gets the focus
+    fn set_focused(&mut self, focused: bool) {
+        self.state.focused = focused;
+        if !focused {
+            self.close_list();
+        }
+    }
+}
+
+impl Control for ComboBox {
+    fn get_bounds(&self) -> Rect { self.bounds }
+    fn get_hot(&self) -> bool { self.state.hot }
+    fn set_hot(&mut self, hit: bool) { self.state.hot = hit }
+    fn get_style(&self) -> &BoxStyle { self.styles.select(&self.state) }
+}
+
+#[cfg(test)]
+mod combobox_tests {
+    use super::*;
+    use crate::gui::keyboard::{KeyCode, KeyEvent, KeyboardModifiers};
+
+    #[test]
+    fn test_open_pick_and_close() {
+        let items = ["All files", "Images", "Documents", "Music"].iter().map(|s| s.to_string()).collect();
+        let mut combo = ComboBox::new(items, Some(0), StateStyles::control_default());
+        combo.window = Rect { x: 0, y: 0, w: 800, h: 600 };
+        combo.set_bounds_rect(Rect { x: 10, y: 500, w: 200, h: 30 });
+        let key = |key| Event::Key(KeyEvent { code: KeyCode::Unknown(0), key, modifiers: KeyboardModifiers::default(), state: KeyState::Pressed, repeat: false });
+
+        // down while it's closed picks the next one straight away
+        combo.event(&key(Key::Down));
+        assert_eq!(combo.selected_text(), Some("Images"));
+        assert!(matches!(combo.raised(), Some(Event::Changed)));
+
+        // no room below so the list opens above the box
+        combo.event(&Event::MouseDown { button: MouseButton::Left, x: 50, y: 510, clicks: 1 });
+        assert!(combo.is_open());
+        assert_eq!(combo.list_rect().y + combo.list_rect().h, 500);
+        assert_eq!(combo.hot_item(), Some(1));
+        combo.event(&key(Key::End));
+        combo.event(&key(Key::Enter));
+        assert!(!combo.is_open());
+        assert_eq!(combo.selected, Some(3));
+
+        // a click on an item picks it, escape changes nothing
+        combo.event(&key(Key::Space));
+        let row = combo.row_rect(2);
+        combo.event(&Event::MouseMove { x: row.x + 5, y: row.y + 5 });
+        assert_eq!(combo.hot_item(), Some(2));
+        combo.event(&Event::MouseDown { button: MouseButton::Left, x: row.x + 5, y: row.y + 5, clicks: 1 });
+        assert_eq!(combo.selected_text(), Some("Documents"));
+        combo.raised();
+        combo.event(&key(Key::F(4)));
+        combo.event(&key(Key::Up));
+        combo.event(&key(Key::Escape));
+        assert!(!combo.is_open());
+        assert!(combo.raised().is_none());
+    }
+}
@@ -0,0 +1,84 @@
+/*
+    everything the user can ask the application to do by name. key
+    bindings and menus refer to commands by their name so they never need
+    to know what the command does or who handles it.
+
+    names are dotted, the part before the dot groups related commands:
+    edit.copy, view.toggle_line_numbers.
+*/
+
+#[derive(Copy, Clone)]
+pub struct Command {
+    pub name: &'static str,
+    pub title: &'static str,
+    pub run: fn()
+}
+
+pub struct CommandRegistry {
+    commands: Vec<Command>
+}
+
+impl CommandRegistry {
+    pub const fn new() -> CommandRegistry {
+        CommandRegistry { commands: Vec::new() }
+    }
+
+    // registering a name again replaces the earlier command
+    pub fn register(&mut self, name: &'static str, title: &'static str, run: fn()) {
+        let command = Command { name, title, run };
+        match self.commands.iter_mut().find(|c| c.name == name) {
+            Some(existing) => *existing = command,
+            None => self.commands.push(command)
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Command> {
+        self.commands.iter().find(|c| c.name == name)
+    }
+
+    pub fn commands(&self) -> &[Command] {
+        &self.commands
+    }
+}
+
+// runs a command by name, false when nothing is registered under it. the
+// command is copied out first since running it may touch the registry.
+pub fn run_command(name: &str) -> bool {
+    let command = unsafe { crate::APPLICATION_STATE.commands.get(name).copied() };
+    match command {
+        Some(command) => {
+            (command.run)();
+            true
+        },
+        None => false
+    }
+}
+
+// runs the commands widgets picked while handling the last event
+pub fn run_chosen_commands() {
+    let widgets = unsafe { &mut crate::APPLICATION_STATE.widgets };
+    let mut chosen = Vec::new();
+    widgets.walk_mut(&mut |_, node| chosen.extend(node.widget.take_command()));
+    for command in chosen {
+        run_command(command);
+    }
+}
+
+#[cfg(test)]
+mod command_tests {
+    use super::*;
+
+    fn first() { }
+    fn second() { }
+
+    #[test]
+    fn test_register_and_replace() {
+        let mut registry = CommandRegistry::new();
+        registry.register("edit.copy", "Copy", first);
+        registry.register("edit.paste", "Paste", first);
+        registry.register("edit.copy", "Copy Selection", second);
+        assert_eq!(registry.commands().len(), 2);
+        assert_eq!(registry.get("edit.copy").unwrap().title, "Copy Selection");
+        assert!(registry.get("edit.undo").is_none());
+    }
+}
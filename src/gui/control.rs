@@ -1,5 +1,7 @@
 
 use crate::gui::is_point_in_rect;
+use crate::gui::get_scale_factor;
+use crate::gui::scale_i32;
 use crate::gui::Rect;
 use crate::gui::style::BoxStyle;
 
@@ -18,7 +20,38 @@ pub trait Control {
         (hot_changed, hit)
     }
 
-    fn get_style<'a>(&'a self) -> &'a BoxStyle;
+    fn get_style(&self) -> &BoxStyle;
+
+    fn get_scaled_style(&self) -> BoxStyle {
+        self.get_style().scaled(get_scale_factor())
+    }
 }
 
 
+
+// logical pixels, the box of a check box, the circle of a radio button and
+// the height of a toggle switch's track, and the gap before their labels
+pub const INDICATOR_SIZE: i32 = 16;
+pub const LABEL_GAP: i32 = 8;
+
+// what an input control is doing, it picks the style out of its StateStyles
+#[derive(Debug, Copy, Clone, Default)]
+pub struct ControlState {
+    pub hot: bool,
+    // pressed, or being dragged
+    pub active: bool,
+    pub focused: bool,
+    pub disabled: bool
+}
+
+// the indicator of a control with a label, at the left of its row and
+// centered up and down
+pub fn indicator_rect(row: Rect, style: &BoxStyle, width: i32) -> Rect {
+    let size = scale_i32(INDICATOR_SIZE, get_scale_factor());
+    Rect { x: row.x + style.padding_size.left, y: row.y + (row.h - size) / 2, w: width, h: size }
+}
+
+// where the label starts after an indicator
+pub fn label_left(indicator: Rect) -> i32 {
+    indicator.x + indicator.w + scale_i32(LABEL_GAP, get_scale_factor())
+}
@@ -1,58 +1,571 @@
 
 use crate::TextBox;
+use crate::gui::textarea::TextArea;
+use crate::gui::listview::ListView;
+use crate::gui::scrollview::ScrollView;
+use crate::gui::scrollview::Axis;
+use crate::gui::splitter::Splitter;
+use crate::gui::menu::Menu;
+use crate::gui::menu::CHECK_WIDTH;
+use crate::gui::menu::ARROW_WIDTH;
+use crate::gui::menubar::MenuBar;
+use crate::gui::tabbar::TabBar;
+use crate::gui::palette::CommandPalette;
+use crate::gui::tooltip::Tooltip;
+use crate::gui::checkbox::CheckBox;
+use crate::gui::radio::RadioGroup;
+use crate::gui::toggle::ToggleSwitch;
+use crate::gui::combobox::ComboBox;
+use crate::gui::slider::Slider;
+use crate::gui::spinbox::SpinBox;
+use crate::gui::control::INDICATOR_SIZE;
+use crate::gui::control::indicator_rect;
+use crate::gui::control::label_left;
 use crate::Button;
 use crate::gui::control::Control;
 use crate::gui::style::VerticalAlign;
 use crate::gui::style::HorizontalAlign;
+use crate::gui::style::TextOverflow;
 use crate::gui::style::BoxStyle;
 use crate::gui::style::BoxSize;
-use crate::gui::is_point_in_rect_a;
 use crate::gui::color::Color;
 use crate::gui::PixelBuffer;
 use crate::gui::Rect;
+use crate::gui::scale_i32;
+use crate::gui::get_scale_factor;
+use crate::gui::Pixel;
+use crate::gui::image::Image;
+use crate::gui::image::ImageFilter;
+use crate::gui::raster::fill_row;
+use crate::gui::raster::blend_coverage_row;
+use crate::gui::raster::blend_pixel_row;
+use crate::gui::text::manager::FontManager;
+use crate::gui::text::shape::shape_text;
+use crate::gui::text::shape::ShapedText;
+use crate::gui::text::line::justify;
+use crate::gui::text::line::truncate_text;
+use crate::gui::text::layout::layout_text;
+use crate::gui::text::layout::LayoutOptions;
+use crate::gui::text::layout::TextLayout;
 
-pub fn draw_textbox(mut buffer: &mut PixelBuffer, textbox: &TextBox, font: &fontdue::Font, draw_cursor: bool) {
-    let left = textbox.bounds_rect.x;
-    let top = textbox.bounds_rect.y;
-    let width = textbox.bounds_rect.w;
-    let height = textbox.bounds_rect.h;
-    let style = textbox.get_style();
+pub fn draw_textbox(mut buffer: &mut PixelBuffer, textbox: &TextBox, fonts: &FontManager, draw_cursor: bool) {
+    let style = textbox.get_scaled_style();
     draw_border_box(&mut buffer, &textbox.bounds_rect, &style);
-    fill_text(&mut buffer, 
-        &textbox.text, 
-        left + style.border_size.left + style.padding_size.left, 
-        top + style.border_size.top + style.padding_size.top, 
-        width - style.border_size.left - style.padding_size.left - style.border_size.right - style.padding_size.right, 
-        height - style.border_size.top - style.padding_size.top - style.border_size.bottom - style.padding_size.bottom, 
-        &font, style.font_size, 
-        textbox.scroll_offset_x,
-        style.text_color, style.highlight_color, style.text_highlight_color,
-        style.horizontal_align,
-        style.vertical_align,
-        textbox.cursor_index,
-        textbox.selection_index,
-        draw_cursor);
-}
-
-pub fn draw_button(mut buffer: &mut PixelBuffer, button: &Button, font: &fontdue::Font) {
-    let left = button.bounds.x;
-    let top = button.bounds.y;
-    let width = button.bounds.w;
-    let height = button.bounds.h;
-    let style = button.get_style();
+    // text being composed by an input method is drawn underlined at the
+    // cursor, the caret sits where the input method says inside it
+    let text = textbox.display_text();
+    let preedit = if textbox.preedit.is_empty() {
+        None
+    }
+    else {
+        Some((textbox.cursor_index, textbox.cursor_index + textbox.preedit.len()))
+    };
+    let run = TextRun {
+        scroll_offset_x: textbox.scroll_offset_x,
+        // the whole text is shown while editing
+        overflow: if textbox.active { TextOverflow::Clip } else { style.text_overflow },
+        underline: preedit,
+        cursor_index: textbox.cursor_index + textbox.preedit_cursor,
+        selection_index: textbox.selection_index,
+        draw_cursor,
+        ..TextRun::new(&text, &style)
+    };
+    fill_text(&mut buffer, &run, inner_rect(&textbox.bounds_rect, &style), fonts, &style);
+}
+
+pub fn draw_button(mut buffer: &mut PixelBuffer, button: &Button, fonts: &FontManager) {
+    let style = button.get_scaled_style();
     draw_border_box(&mut buffer, &button.bounds, &style);
-    fill_text(&mut buffer, 
-        &button.text.chars().collect(),
-        left + style.border_size.left + style.padding_size.left, 
-        top + style.border_size.top + style.padding_size.top, 
-        width - style.border_size.left - style.padding_size.left - style.border_size.right - style.padding_size.right, 
-        height - style.border_size.top - style.padding_size.top - style.border_size.bottom - style.padding_size.bottom, 
-        &font, style.font_size, 
-        0, // scroll_offset_x
-        style.text_color, style.highlight_color, style.text_highlight_color,
-        style.horizontal_align,
-        style.vertical_align,
-        0,usize::MAX, false);
+    let text: Vec<char> = button.text.chars().collect();
+    fill_text(&mut buffer, &TextRun::new(&text, &style), inner_rect(&button.bounds, &style), fonts, &style);
+}
+
+// the bounds without the border and padding
+fn inner_rect(bounds: &Rect, style: &BoxStyle) -> Rect {
+    Rect {
+        x: bounds.x + style.border_size.left + style.padding_size.left,
+        y: bounds.y + style.border_size.top + style.padding_size.top,
+        w: bounds.w - style.border_size.left - style.padding_size.left - style.border_size.right - style.padding_size.right,
+        h: bounds.h - style.border_size.top - style.padding_size.top - style.border_size.bottom - style.padding_size.bottom
+    }
+}
+
+/*
+    draws a text area: the visible lines scrolled by the area's offsets, the
+    selection as one rect per line and the line numbers in a gutter on the
+    left. a wrapped paragraph gets a single number on its first line.
+*/
+pub fn draw_textarea(buffer: &mut PixelBuffer, textarea: &TextArea, fonts: &FontManager, draw_cursor: bool) {
+    let style = textarea.get_scaled_style();
+    let face = fonts.select_style(&style);
+    let layout = &textarea.layout;
+    draw_border_box(buffer, &textarea.bounds_rect, &style);
+    let text_rect = textarea.text_rect();
+    let text_left = text_rect.x + textarea.scroll_offset_x;
+    let text_top = text_rect.y + textarea.scroll_offset_y;
+    let caret_width = std::cmp::max(1, scale_i32(2, get_scale_factor()));
+    let selection = if textarea.selection_index == usize::MAX {
+        None
+    }
+    else {
+        let start = std::cmp::min(textarea.cursor_index, textarea.selection_index);
+        let end = std::cmp::min(std::cmp::max(textarea.cursor_index, textarea.selection_index), textarea.text.len());
+        if start < end { Some((start, end)) } else { None }
+    };
+
+    let gutter_width = textarea.gutter_width();
+    let gutter = Rect { x: text_rect.x - gutter_width, y: text_rect.y, w: gutter_width, h: text_rect.h };
+    let digit_width = shape_text(&['0'], fonts, face, style.font_size).width;
+    let mut line_number = 0;
+    for (i, line) in layout.lines.iter().enumerate() {
+        let starts_paragraph = i == 0 || layout.lines[i - 1].end != line.start;
+        if starts_paragraph {
+            line_number += 1;
+        }
+        let top = text_top + line.top;
+        let baseline = text_top + line.baseline;
+        if top + layout.line_height < text_rect.y || top > text_rect.y + text_rect.h {
+            continue;
+        }
+
+        if let Some((start, end)) = selection {
+            if start <= line.end && end > line.start {
+                let from = std::cmp::max(start, line.start) - line.start;
+                let to = std::cmp::min(end, line.end) - line.start;
+                let sel_left = text_left + line.x + line.shaped.caret_positions[from];
+                // a selected newline shows as a sliver past the end of the line
+                let newline_width = if end > line.end { caret_width * 3 } else { 0 };
+                let sel_right = text_left + line.x + line.shaped.caret_positions[to] + newline_width;
+                if let Some(r) = intersect_rect(Rect { x: sel_left, y: top, w: sel_right - sel_left, h: layout.line_height }, text_rect) {
+                    fill_rect(buffer, r.x, r.y, r.w, r.h, style.highlight_color);
+                }
+            }
+        }
+
+        for glyph in &line.shaped.glyphs {
+            let (font_metrics, font_bitmap) = fonts.font(glyph.font).font.rasterize_indexed(glyph.glyph_index, style.font_size);
+            let index = line.start + glyph.cluster;
+            let color = match selection {
+                Some((start, end)) if index >= start && index < end => style.text_highlight_color,
+                _ => style.text_color
+            };
+            let glyph_rect = Rect { x: text_left + line.x + glyph.x + font_metrics.xmin, y: baseline - font_metrics.height as i32 - font_metrics.ymin, w: font_metrics.width as i32, h: font_metrics.height as i32 };
+            blend_mask(buffer, &font_bitmap, glyph_rect, text_rect, color);
+        }
+
+        if starts_paragraph && gutter_width > 0 {
+            let number: Vec<char> = line_number.to_string().chars().collect();
+            let shaped = shape_text(&number, fonts, face, style.font_size);
+            // right aligned with half a digit of room before the text
+            let number_left = gutter.x + gutter.w - shaped.width - digit_width / 2;
+            for glyph in &shaped.glyphs {
+                let (font_metrics, font_bitmap) = fonts.font(glyph.font).font.rasterize_indexed(glyph.glyph_index, style.font_size);
+                let glyph_rect = Rect { x: number_left + glyph.x + font_metrics.xmin, y: baseline - font_metrics.height as i32 - font_metrics.ymin, w: font_metrics.width as i32, h: font_metrics.height as i32 };
+                blend_mask(buffer, &font_bitmap, glyph_rect, gutter, style.border_color);
+            }
+        }
+    }
+
+    if draw_cursor && !layout.lines.is_empty() {
+        let (x, line) = layout.caret_position(textarea.cursor_index);
+        let caret = Rect { x: text_left + x, y: text_top + layout.lines[line].top, w: caret_width, h: layout.line_height };
+        if let Some(r) = intersect_rect(caret, text_rect) {
+            fill_rect(buffer, r.x, r.y, r.w, r.h, style.text_color);
+        }
+    }
+}
+
+// the overlap of two rects, None when they don't overlap
+fn intersect_rect(a: Rect, b: Rect) -> Option<Rect> {
+    let x0 = std::cmp::max(a.x, b.x);
+    let y0 = std::cmp::max(a.y, b.y);
+    let x1 = std::cmp::min(a.x + a.w, b.x + b.w);
+    let y1 = std::cmp::min(a.y + a.h, b.y + b.h);
+    if x1 <= x0 || y1 <= y0 {
+        None
+    }
+    else {
+        Some(Rect { x: x0, y: y0, w: x1 - x0, h: y1 - y0 })
+    }
+}
+
+/*
+    draws multi-line text in a box, for labels under icons, properties
+    dialogs and the text previewer. lines wrap at the inner width when the
+    style has word_wrap set, newlines always start a new line. the block as
+    a whole is aligned vertically, each line horizontally.
+*/
+pub fn draw_text_block(buffer: &mut PixelBuffer, text: &[char], bounds: &Rect, fonts: &FontManager, style: &BoxStyle) {
+    draw_border_box(buffer, bounds, style);
+    let inner = inner_rect(bounds, style);
+    let face = fonts.select_style(style);
+    let layout = layout_text_block(text, fonts, face, inner.w, style);
+    let top = inner.y + calculate_v_align_offset(inner.h, layout.height, style.vertical_align);
+    for line in &layout.lines {
+        // skip lines outside the box before rasterizing anything
+        if top + line.top + layout.line_height < inner.y || top + line.top > inner.y + inner.h {
+            continue;
+        }
+        for glyph in &line.shaped.glyphs {
+            let (font_metrics, font_bitmap) = fonts.font(glyph.font).font.rasterize_indexed(glyph.glyph_index, style.font_size);
+            let glyph_top = top + line.baseline - font_metrics.height as i32 - font_metrics.ymin;
+            let glyph_rect = Rect { x: inner.x + line.x + glyph.x + font_metrics.xmin, y: glyph_top, w: font_metrics.width as i32, h: font_metrics.height as i32 };
+            blend_mask(buffer, &font_bitmap, glyph_rect, inner, style.text_color);
+        }
+    }
+}
+
+// lays text out the way draw_text_block draws it, for measuring and hit
+// testing. positions are relative to the top left of the inner box
+pub fn layout_text_block(text: &[char], fonts: &FontManager, face: usize, width: i32, style: &BoxStyle) -> TextLayout {
+    let options = LayoutOptions {
+        wrap_width: if style.word_wrap { Some(width) } else { None },
+        align: style.horizontal_align,
+        line_height: style.line_height,
+        line_spacing: style.line_spacing
+    };
+    layout_text(text, fonts, face, style.font_size, &options)
+}
+
+/*
+    runs draw with everything it draws cut off at clip. the draw functions
+    only clip to the buffer so this hands them a buffer that's just the
+    clip rect, copied out of the real one and back in afterwards.
+*/
+pub fn draw_clipped(buffer: &mut PixelBuffer, clip: Rect, draw: impl FnOnce(&mut PixelBuffer)) {
+    let visible = match clip_rect(buffer, clip.x, clip.y, clip.w, clip.h) {
+        Some(r) => r,
+        None => return
+    };
+    let mut clipped = PixelBuffer::new(visible.x, visible.y, visible.w, visible.h);
+    for y in visible.y..visible.y + visible.h {
+        clipped.row_mut(visible.x, y, visible.w).copy_from_slice(buffer.row(visible.x, y, visible.w));
+    }
+    draw(&mut clipped);
+    for y in visible.y..visible.y + visible.h {
+        buffer.row_mut(visible.x, y, visible.w).copy_from_slice(clipped.row(visible.x, y, visible.w));
+    }
+}
+
+// the scrollbars on top of whatever the scroll view shows
+pub fn draw_scrollbars(buffer: &mut PixelBuffer, view: &ScrollView) {
+    if !view.scrollbars_visible {
+        return;
+    }
+    let style = view.get_scaled_style();
+    for axis in [Axis::Vertical, Axis::Horizontal].iter().copied() {
+        if let (Some(track), Some(thumb)) = (view.scrollbar_rect(axis), view.thumb_rect(axis)) {
+            let thumb_color = if view.hot_thumb == Some(axis) || view.is_dragging() { style.thumb_hot_color } else { style.thumb_color };
+            fill_rect(buffer, track.x, track.y, track.w, track.h, style.track_color);
+            fill_rect(buffer, thumb.x, thumb.y, thumb.w, thumb.h, thumb_color);
+        }
+    }
+}
+
+pub fn draw_splitter(buffer: &mut PixelBuffer, splitter: &Splitter) {
+    let rect = splitter.get_rect();
+    let color = if splitter.hot || splitter.is_dragging() { splitter.hot_color } else { splitter.color };
+    fill_rect(buffer, rect.x, rect.y, rect.w, rect.h, color);
+}
+
+pub fn draw_listview(buffer: &mut PixelBuffer, list: &ListView, fonts: &FontManager) {
+    let view = list.scroll.bounds_rect;
+    let (offset_x, offset_y) = list.scroll.offset();
+    let style = list.get_scaled_style();
+    let style_selected = list.style_selected.scaled(get_scale_factor());
+    draw_clipped(buffer, view, |buffer| {
+        fill_rect(buffer, view.x, view.y, view.w, view.h, style.background_color);
+        let item_height = list.scaled_item_height();
+        // only the items that show
+        let first = std::cmp::max(0, -offset_y / item_height) as usize;
+        let count = (view.h / item_height + 2) as usize;
+        for (i, item) in list.items.iter().enumerate().skip(first).take(count) {
+            let rect = list.item_rect(i);
+            let bounds = Rect { x: view.x + rect.x + offset_x, y: view.y + rect.y + offset_y, w: rect.w, h: rect.h };
+            let style = if list.selected == Some(i) { &style_selected } else { &style };
+            draw_border_box(buffer, &bounds, style);
+            let text: Vec<char> = item.chars().collect();
+            fill_text(buffer, &TextRun::new(&text, style), inner_rect(&bounds, style), fonts, style);
+        }
+        draw_scrollbars(buffer, &list.scroll);
+    });
+}
+
+/*
+    draws a menu and its open submenus. a row is the check mark, the title,
+    the accelerator lined up on the right and an arrow for a submenu.
+    disabled items are greyed out and separators are a line across.
+*/
+pub fn draw_menu(buffer: &mut PixelBuffer, menu: &Menu, fonts: &FontManager) {
+    let scale = get_scale_factor();
+    let style = menu.style.scaled(scale);
+    let check_width = scale_i32(CHECK_WIDTH, scale);
+    let arrow_width = scale_i32(ARROW_WIDTH, scale);
+    let text = |buffer: &mut PixelBuffer, text: &str, left: i32, row: &Rect, width: i32, color: Color, align: HorizontalAlign| {
+        fill_row_text(buffer, row, RowText { text, left, width, color, align }, fonts, &style);
+    };
+    for level in 0..menu.level_count() {
+        draw_border_box(buffer, &menu.level_rect(level), &style);
+        for (i, item) in menu.level_items(level).iter().enumerate() {
+            let row = menu.item_rect(level, i);
+            if item.separator {
+                let thickness = std::cmp::max(1, scale_i32(1, scale));
+                fill_rect(buffer, row.x + check_width, row.y + (row.h - thickness) / 2, row.w - check_width - arrow_width, thickness, style.border_color);
+                continue;
+            }
+            if menu.hot(level) == Some(i) {
+                fill_rect(buffer, row.x, row.y, row.w, row.h, style.highlight_color);
+            }
+            let color = if item.enabled { style.text_color } else { menu.disabled_color };
+            if item.checked == Some(true) {
+                let size = check_width / 3;
+                fill_rect(buffer, row.x + (check_width - size) / 2, row.y + (row.h - size) / 2, size, size, color);
+            }
+            let width = row.w - check_width - arrow_width;
+            text(buffer, item.title, row.x + check_width, &row, width, color, HorizontalAlign::Left);
+            if let Some(accelerator) = &item.accelerator {
+                text(buffer, accelerator, row.x + check_width, &row, width, color, HorizontalAlign::Right);
+            }
+            if !item.submenu.is_empty() {
+                text(buffer, "\u{203A}", row.x + row.w - arrow_width, &row, arrow_width, color, HorizontalAlign::Center);
+            }
+        }
+    }
+}
+
+pub fn draw_menu_bar(buffer: &mut PixelBuffer, bar: &MenuBar, fonts: &FontManager) {
+    let style = bar.style.scaled(get_scale_factor());
+    let rect = bar.bounds_rect;
+    draw_border_box(buffer, &rect, &style);
+    for (i, (title, _)) in bar.menus.iter().enumerate() {
+        let row = bar.title_rect(i);
+        if bar.highlighted() == Some(i) {
+            fill_rect(buffer, row.x, row.y, row.w, row.h, style.highlight_color);
+        }
+        fill_row_text(buffer, &row, RowText { text: title, left: row.x, width: row.w, color: style.text_color, align: HorizontalAlign::Center }, fonts, &style);
+    }
+}
+
+// every tab is a box with its title in it, like a button
+pub fn draw_tab_bar(buffer: &mut PixelBuffer, bar: &TabBar, fonts: &FontManager) {
+    let scale = get_scale_factor();
+    for (i, title) in bar.titles.iter().enumerate() {
+        let style = if i == bar.active { bar.active_style.scaled(scale) } else { bar.style.scaled(scale) };
+        let rect = bar.tab_rect(i);
+        draw_border_box(buffer, &rect, &style);
+        let text: Vec<char> = title.chars().collect();
+        fill_text(buffer, &TextRun::new(&text, &style), inner_rect(&rect, &style), fonts, &style);
+    }
+}
+
+// the search box with the matching commands under it, their key bindings
+// on the right
+pub fn draw_palette(buffer: &mut PixelBuffer, palette: &CommandPalette, fonts: &FontManager, draw_cursor: bool) {
+    let scale = get_scale_factor();
+    let style = palette.style.scaled(scale);
+    let style_selected = palette.style_selected.scaled(scale);
+    draw_border_box(buffer, &palette.palette_rect(), &style);
+    draw_textbox(buffer, &palette.query, fonts, palette.query.active && draw_cursor);
+    let padding = style.padding_size.left;
+    let first = palette.first_visible();
+    for (row, entry) in palette.matches().skip(first).take(palette.visible_items).enumerate() {
+        let rect = palette.row_rect(row);
+        let style = if first + row == palette.selected() { &style_selected } else { &style };
+        fill_rect(buffer, rect.x, rect.y, rect.w, rect.h, style.background_color);
+        let width = rect.w - padding * 2;
+        let left = rect.x + padding;
+        fill_row_text(buffer, &rect, RowText { text: entry.title, left, width, color: style.text_color, align: HorizontalAlign::Left }, fonts, style);
+        if let Some(keys) = &entry.keys {
+            fill_row_text(buffer, &rect, RowText { text: keys, left, width, color: style.text_color, align: HorizontalAlign::Right }, fonts, style);
+        }
+    }
+}
+
+/*
+    the input controls. a check box, radio button or toggle switch is an
+    indicator in the style's border and background colors with its label
+    after it. whatever is on, the tick, the picked option's dot, a switch's
+    track, is in the highlight color.
+*/
+pub fn draw_checkbox(buffer: &mut PixelBuffer, checkbox: &CheckBox, fonts: &FontManager) {
+    let style = checkbox.get_scaled_style();
+    let size = scale_i32(INDICATOR_SIZE, get_scale_factor());
+    let indicator = indicator_rect(checkbox.bounds, &style, size);
+    draw_border_box(buffer, &indicator, &BoxStyle { background_color: if checkbox.checked { style.highlight_color } else { style.background_color }, ..style });
+    if checkbox.checked {
+        let tick = size / 3;
+        fill_rect(buffer, indicator.x + (size - tick) / 2, indicator.y + (size - tick) / 2, tick, tick, style.text_highlight_color);
+    }
+    draw_label(buffer, checkbox.text, &checkbox.bounds, indicator, fonts, &style);
+}
+
+pub fn draw_radio_group(buffer: &mut PixelBuffer, group: &RadioGroup, fonts: &FontManager) {
+    let style = group.get_scaled_style();
+    let size = scale_i32(INDICATOR_SIZE, get_scale_factor());
+    let border = std::cmp::max(1, style.border_size.left) as f32;
+    for (i, option) in group.options.iter().enumerate() {
+        let row = group.option_rect(i);
+        let indicator = indicator_rect(row, &style, size);
+        let (x, y, radius) = (indicator.x as f32 + size as f32 / 2.0, indicator.y as f32 + size as f32 / 2.0, size as f32 / 2.0);
+        fill_circle(buffer, x, y, radius, style.border_color);
+        fill_circle(buffer, x, y, radius - border, style.background_color);
+        if group.selected == i {
+            fill_circle(buffer, x, y, radius / 2.0, style.highlight_color);
+        }
+        draw_label(buffer, option, &row, indicator, fonts, &style);
+    }
+}
+
+pub fn draw_toggle_switch(buffer: &mut PixelBuffer, switch: &ToggleSwitch, fonts: &FontManager) {
+    let style = switch.get_scaled_style();
+    let size = scale_i32(INDICATOR_SIZE, get_scale_factor());
+    let track = indicator_rect(switch.bounds, &style, size * 2);
+    // a bar with round ends
+    let color = if switch.on { style.highlight_color } else { style.border_color };
+    let radius = size as f32 / 2.0;
+    let y = track.y as f32 + radius;
+    fill_circle(buffer, track.x as f32 + radius, y, radius, color);
+    fill_circle(buffer, (track.x + track.w) as f32 - radius, y, radius, color);
+    fill_rect(buffer, track.x + size / 2, track.y, track.w - size, track.h, color);
+    let knob_x = if switch.on { (track.x + track.w) as f32 - radius } else { track.x as f32 + radius };
+    fill_circle(buffer, knob_x, y, radius - std::cmp::max(2, size / 8) as f32, style.text_color);
+    draw_label(buffer, switch.text, &switch.bounds, track, fonts, &style);
+}
+
+fn draw_label(buffer: &mut PixelBuffer, text: &str, row: &Rect, indicator: Rect, fonts: &FontManager, style: &BoxStyle) {
+    let left = label_left(indicator);
+    let width = row.x + row.w - style.padding_size.right - left;
+    fill_row_text(buffer, row, RowText { text, left, width, color: style.text_color, align: HorizontalAlign::Left }, fonts, style);
+}
+
+// the box with the picked item and a down arrow, and the list under it
+// while it's open
+pub fn draw_combobox(buffer: &mut PixelBuffer, combo: &ComboBox, fonts: &FontManager) {
+    let style = combo.get_scaled_style();
+    let rect = combo.bounds;
+    draw_border_box(buffer, &rect, &style);
+    let left = rect.x + style.border_size.left + style.padding_size.left;
+    let arrow = Rect { x: rect.x + rect.w - rect.h, y: rect.y, w: rect.h, h: rect.h };
+    if let Some(text) = combo.selected_text() {
+        fill_row_text(buffer, &rect, RowText { text, left, width: arrow.x - left, color: style.text_color, align: style.horizontal_align }, fonts, &style);
+    }
+    fill_arrow(buffer, &arrow, false, style.text_color);
+    if !combo.is_open() {
+        return;
+    }
+    let list_style = combo.styles.normal.scaled(get_scale_factor());
+    draw_border_box(buffer, &combo.list_rect(), &list_style);
+    let first = combo.first_visible();
+    for (row, item) in combo.items.iter().enumerate().skip(first).take(combo.visible_items) {
+        let row_rect = combo.row_rect(row - first);
+        let color = if combo.hot_item() == Some(row) {
+            fill_rect(buffer, row_rect.x, row_rect.y, row_rect.w, row_rect.h, list_style.highlight_color);
+            list_style.text_highlight_color
+        }
+        else {
+            list_style.text_color
+        };
+        let padding = list_style.padding_size.left;
+        let line = RowText { text: item, left: row_rect.x + padding, width: row_rect.w - padding * 2, color, align: HorizontalAlign::Left };
+        fill_row_text(buffer, &row_rect, line, fonts, &list_style);
+    }
+}
+
+// the part of the track up to the thumb is in the highlight color
+pub fn draw_slider(buffer: &mut PixelBuffer, slider: &Slider, _fonts: &FontManager) {
+    let style = slider.get_scaled_style();
+    let track = slider.track_rect();
+    let thumb_x = slider.thumb_x();
+    fill_rect(buffer, track.x, track.y, thumb_x - track.x, track.h, style.highlight_color);
+    fill_rect(buffer, thumb_x, track.y, track.x + track.w - thumb_x, track.h, style.border_color);
+    let radius = slider.thumb_radius() as f32;
+    let y = track.y as f32 + track.h as f32 / 2.0;
+    fill_circle(buffer, thumb_x as f32, y, radius, style.border_color);
+    fill_circle(buffer, thumb_x as f32, y, radius - std::cmp::max(1, style.border_size.left) as f32, style.text_color);
+}
+
+// the text box and the two arrows beside it
+pub fn draw_spinbox(buffer: &mut PixelBuffer, spinbox: &SpinBox, fonts: &FontManager, draw_cursor: bool) {
+    let style = spinbox.get_scaled_style();
+    draw_textbox(buffer, &spinbox.text, fonts, spinbox.text.active && draw_cursor);
+    for up in [true, false] {
+        let rect = spinbox.arrow_rect(up);
+        draw_border_box(buffer, &rect, &style);
+        fill_arrow(buffer, &rect, up, style.text_color);
+    }
+}
+
+// a triangle pointing up or down in the middle of a rect
+fn fill_arrow(buffer: &mut PixelBuffer, rect: &Rect, up: bool, color: Color) {
+    let height = std::cmp::max(2, std::cmp::min(rect.w, rect.h) / 4);
+    let top = rect.y + (rect.h - height) / 2;
+    let center = rect.x + rect.w / 2;
+    for row in 0..height {
+        let half = if up { row } else { height - 1 - row };
+        fill_rect(buffer, center - half, top + row, half * 2 + 1, 1, color);
+    }
+}
+
+// an antialiased disc, the coverage of each pixel is how far its center is
+// inside the edge
+fn fill_circle(buffer: &mut PixelBuffer, x: f32, y: f32, radius: f32, color: Color) {
+    if radius <= 0.0 {
+        return;
+    }
+    let left = (x - radius).floor() as i32;
+    let top = (y - radius).floor() as i32;
+    let size = (radius * 2.0).ceil() as i32 + 2;
+    let mut mask = vec![0u8; (size * size) as usize];
+    for row in 0..size {
+        for column in 0..size {
+            let dx = (left + column) as f32 + 0.5 - x;
+            let dy = (top + row) as f32 + 0.5 - y;
+            let coverage = (radius - (dx * dx + dy * dy).sqrt() + 0.5).clamp(0.0, 1.0);
+            mask[(row * size + column) as usize] = (coverage * 255.0).round() as u8;
+        }
+    }
+    let clip = Rect { x: buffer.left, y: buffer.top, w: buffer.width, h: buffer.height };
+    blend_mask(buffer, &mask, Rect { x: left, y: top, w: size, h: size }, clip, color);
+}
+
+// the text in its box, the box is sized to fit it
+pub fn draw_tooltip(buffer: &mut PixelBuffer, tooltip: &Tooltip, fonts: &FontManager) {
+    if let Some((text, rect)) = tooltip.shown() {
+        let style = tooltip.style.scaled(get_scale_factor());
+        draw_border_box(buffer, &rect, &style);
+        let left = rect.x + style.border_size.left + style.padding_size.left;
+        let width = rect.w - style.border_size.left - style.padding_size.left - style.border_size.right - style.padding_size.right;
+        fill_row_text(buffer, &rect, RowText { text, left, width, color: style.text_color, align: HorizontalAlign::Left }, fonts, &style);
+    }
+}
+
+// the text of a row, it starts at left and is cut off width pixels after
+struct RowText<'a> {
+    text: &'a str,
+    left: i32,
+    width: i32,
+    color: Color,
+    align: HorizontalAlign
+}
+
+// a line of text centered in a row, for menus and lists
+fn fill_row_text(buffer: &mut PixelBuffer, row: &Rect, line: RowText, fonts: &FontManager, style: &BoxStyle) {
+    let text: Vec<char> = line.text.chars().collect();
+    let run = TextRun {
+        color: line.color,
+        horizontal_align: line.align,
+        vertical_align: VerticalAlign::Center,
+        overflow: TextOverflow::Clip,
+        ..TextRun::new(&text, style)
+    };
+    fill_text(buffer, &run, Rect { x: line.left, y: row.y, w: line.width, h: row.h }, fonts, style);
+}
+
+// a ring just inside the bounds of the focused control
+pub fn draw_focus_ring(buffer: &mut PixelBuffer, bounds: &Rect, style: &BoxStyle) {
+    if style.focus_size > 0 {
+        draw_rect(buffer, bounds.x, bounds.y, bounds.w, bounds.h, BoxSize::single(style.focus_size), style.focus_color);
+    }
 }
 
 fn draw_border_box(mut buffer: &mut PixelBuffer, bounds: &Rect, style: &BoxStyle) {
@@ -70,22 +583,49 @@ fn draw_border_box(mut buffer: &mut PixelBuffer, bounds: &Rect, style: &BoxStyle
 }
 
 pub fn fill_rect(buffer: &mut PixelBuffer, left: i32, top: i32, width: i32, height: i32, color: Color) {
-    let right = left + width;
-    let bottom = top + height;
-    let stride = buffer.width;
-    for y in top..bottom {
-        for x in left..right {
-            if  y < buffer.height && x < buffer.width {
-                let offset = ((y * stride) + x) as usize;
-                let pixel = &mut buffer.pixels[offset];
-                pixel.red = color.red;
-                pixel.green = color.green;
-                pixel.blue = color.blue;
-            }
+    if let Some(clip) = clip_rect(buffer, left, top, width, height) {
+        for y in clip.y..clip.y + clip.h {
+            fill_row(buffer.row_mut(clip.x, y, clip.w), color);
         }
     }
 }
 
+// returns the part of the rect that lands inside the buffer, None when
+// nothing would be drawn
+pub fn clip_rect(buffer: &PixelBuffer, left: i32, top: i32, width: i32, height: i32) -> Option<Rect> {
+    let x0 = std::cmp::max(left, buffer.left);
+    let y0 = std::cmp::max(top, buffer.top);
+    let x1 = std::cmp::min(left + width, buffer.left + buffer.width);
+    let y1 = std::cmp::min(top + height, buffer.top + buffer.height);
+    if x1 <= x0 || y1 <= y0 {
+        None
+    }
+    else {
+        Some(Rect { x: x0, y: y0, w: x1 - x0, h: y1 - y0 })
+    }
+}
+
+// blends an 8 bit coverage mask (a glyph bitmap) in a solid color. the mask
+// covers the placement rect, row by row, and is clipped against the clip
+// rect and the buffer.
+pub fn blend_mask(buffer: &mut PixelBuffer, mask: &[u8], placement: Rect, clip: Rect, color: Color) {
+    let x0 = std::cmp::max(placement.x, clip.x);
+    let y0 = std::cmp::max(placement.y, clip.y);
+    let x1 = std::cmp::min(placement.x + placement.w, clip.x + clip.w);
+    let y1 = std::cmp::min(placement.y + placement.h, clip.y + clip.h);
+    let visible = match clip_rect(buffer, x0, y0, x1 - x0, y1 - y0) {
+        Some(r) => r,
+        None => return
+    };
+    for y in visible.y..visible.y + visible.h {
+        let mask_start = ((y - placement.y) * placement.w + (visible.x - placement.x)) as usize;
+        blend_coverage_row(
+            buffer.row_mut(visible.x, y, visible.w),
+            &mask[mask_start..mask_start + visible.w as usize],
+            color);
+    }
+}
+
 fn draw_rect(buffer: &mut PixelBuffer, left: i32, top: i32, width: i32, height: i32, line_width: BoxSize, color: Color) {
     fill_rect(buffer, left, top, width, line_width.top, color); // top
     fill_rect(buffer, left + width - line_width.right, top, line_width.right, height, color); // right
@@ -93,93 +633,226 @@ fn draw_rect(buffer: &mut PixelBuffer, left: i32, top: i32, width: i32, height:
     fill_rect(buffer, left, top, line_width.left, height, color); // left
 }
 
-fn alpha_blend_u8(c1: u8, c2 : u8, alpha: u8) -> u8 {
-    let c1 = c1 as i32;
-    let c2 = c2 as i32;
-    let alpha = alpha as i32;
-    let inv_alpha = 255 - alpha;
-    let result = ((c1 * alpha) + (c2 * inv_alpha));
-    return (result / 255) as u8;
-}
-
-fn fill_text(buffer: &mut PixelBuffer, text: &Vec::<char>,
-    left: i32, top: i32, width: i32, height: i32, 
-    font: &fontdue::Font, font_size: f32, scroll_offset_x: i32,
-    text_color: Color, highlight_color: Color, highlight_text_color: Color,
-    horizontal_align: HorizontalAlign, vertical_align: VerticalAlign,
-    cursor_index: usize, selection_index: usize, draw_cursor: bool) {
-
-    let buffer_stride = buffer.width;
-    let max_bottom = std::cmp::min(top + height, buffer.height);
-    let max_right = std::cmp::min(left + width, buffer.width);
-    let max_top = std::cmp::max(top, 0);
-    let max_left = std::cmp::max(left, 0);
-    let (font_height, _, _,_) = measure_string(&['W'], font, font_size);
-    let (_, text_width, _,_char_widths) = measure_string(&text, font, font_size);
-    let h_align_offset = calculate_h_align_offset(width, text_width, scroll_offset_x, horizontal_align);
-    let v_align_offset = calculate_v_align_offset(height, font_height, vertical_align);
-    let mut cursor_left = left + h_align_offset + scroll_offset_x;
+/*
+    draws the image scaled into the destination rect. pixels are blended
+    over the buffer using the image alpha.
+*/
+pub fn draw_image(buffer: &mut PixelBuffer, image: &Image, left: i32, top: i32, width: i32, height: i32, filter: ImageFilter) {
+    if width <= 0 || height <= 0 || image.width <= 0 || image.height <= 0 {
+        return;
+    }
+    let clip = match clip_rect(buffer, left, top, width, height) {
+        Some(r) => r,
+        None => return
+    };
+
+    // unscaled images can blend straight from the image rows
+    if width == image.width && height == image.height {
+        for y in clip.y..clip.y + clip.h {
+            let source_start = ((y - top) * image.width + (clip.x - left)) as usize;
+            blend_pixel_row(
+                buffer.row_mut(clip.x, y, clip.w),
+                &image.pixels[source_start..source_start + clip.w as usize]);
+        }
+        return;
+    }
+
+    let scale_x = image.width as f32 / width as f32;
+    let scale_y = image.height as f32 / height as f32;
+    let mut scaled_row = vec![Pixel::default(); clip.w as usize];
+    for y in clip.y..clip.y + clip.h {
+        for (i, source) in scaled_row.iter_mut().enumerate() {
+            let x = clip.x + i as i32;
+            *source = match filter {
+                ImageFilter::Nearest => {
+                    let src_x = std::cmp::min(((x - left) as f32 * scale_x) as i32, image.width - 1);
+                    let src_y = std::cmp::min(((y - top) as f32 * scale_y) as i32, image.height - 1);
+                    image.get_pixel(src_x, src_y)
+                },
+                ImageFilter::Bilinear => {
+                    // sample from pixel centers so scaling doesn't shift the image
+                    let src_x = ((x - left) as f32 + 0.5) * scale_x - 0.5;
+                    let src_y = ((y - top) as f32 + 0.5) * scale_y - 0.5;
+                    sample_bilinear(image, src_x, src_y)
+                }
+            };
+        }
+        blend_pixel_row(buffer.row_mut(clip.x, y, clip.w), &scaled_row);
+    }
+}
+
+// colors are weighted by alpha while interpolating so transparent pixels
+// don't bleed their (usually black) color into the edges of the image
+fn sample_bilinear(image: &Image, x: f32, y: f32) -> Pixel {
+    let x = x.max(0.0).min((image.width - 1) as f32);
+    let y = y.max(0.0).min((image.height - 1) as f32);
+    let x0 = x.floor() as i32;
+    let y0 = y.floor() as i32;
+    let x1 = std::cmp::min(x0 + 1, image.width - 1);
+    let y1 = std::cmp::min(y0 + 1, image.height - 1);
+    let fx = x - x0 as f32;
+    let fy = y - y0 as f32;
+
+    let samples = [
+        (image.get_pixel(x0, y0), (1.0 - fx) * (1.0 - fy)),
+        (image.get_pixel(x1, y0), fx * (1.0 - fy)),
+        (image.get_pixel(x0, y1), (1.0 - fx) * fy),
+        (image.get_pixel(x1, y1), fx * fy)];
+
+    let mut red = 0.0;
+    let mut green = 0.0;
+    let mut blue = 0.0;
+    let mut alpha = 0.0;
+    for (pixel, weight) in samples.iter() {
+        let a = pixel.alpha as f32 * weight;
+        red += pixel.red as f32 * a;
+        green += pixel.green as f32 * a;
+        blue += pixel.blue as f32 * a;
+        alpha += a;
+    }
+    if alpha <= 0.0 {
+        return Pixel::default();
+    }
+    Pixel::from_rgba(
+        (red / alpha + 0.5) as u8,
+        (green / alpha + 0.5) as u8,
+        (blue / alpha + 0.5) as u8,
+        (alpha + 0.5) as u8)
+}
+
+/*
+    a single line of text and how fill_text draws it. the color and
+    alignment start out as the style's and can be set apart from it. the
+    underline, caret and selection are char indexes into the text, a
+    selection_index of usize::MAX means nothing is selected.
+*/
+struct TextRun<'a> {
+    text: &'a [char],
+    scroll_offset_x: i32,
+    color: Color,
+    horizontal_align: HorizontalAlign,
+    vertical_align: VerticalAlign,
+    overflow: TextOverflow,
+    underline: Option<(usize, usize)>,
+    cursor_index: usize,
+    selection_index: usize,
+    draw_cursor: bool
+}
+
+impl<'a> TextRun<'a> {
+    // the text drawn the way the style says with no caret or selection
+    fn new(text: &'a [char], style: &BoxStyle) -> TextRun<'a> {
+        TextRun {
+            text,
+            scroll_offset_x: 0,
+            color: style.text_color,
+            horizontal_align: style.horizontal_align,
+            vertical_align: style.vertical_align,
+            overflow: style.text_overflow,
+            underline: None,
+            cursor_index: 0,
+            selection_index: usize::MAX,
+            draw_cursor: false
+        }
+    }
+}
+
+// draws the run inside rect with the style's font and highlight colors
+fn fill_text(buffer: &mut PixelBuffer, run: &TextRun, rect: Rect, fonts: &FontManager, style: &BoxStyle) {
+    let Rect { x: left, y: top, w: width, h: height } = rect;
+    let face = fonts.select_style(style);
+    let font_size = style.font_size;
+    let text_color = run.color;
+    let horizontal_align = run.horizontal_align;
+
+    // the shortened text doesn't line up with the caret and selection
+    // indexes so those are dropped, it's never scrolled either
+    let truncated = truncate_text(run.text, fonts, face, font_size, width, run.overflow);
+    let (text, scroll_offset_x, underline, cursor_index, selection_index, draw_cursor) = match &truncated {
+        Some(t) => (t.as_slice(), 0, None, 0, usize::MAX, false),
+        None => (run.text, run.scroll_offset_x, run.underline, run.cursor_index, run.selection_index, run.draw_cursor)
+    };
+
+    let max_right = left + width;
+    let max_left = left;
+    // the caret and selection reach a little above and below the text
+    let caret_margin = scale_i32(2, get_scale_factor());
+    let caret_width = std::cmp::max(1, scale_i32(2, get_scale_factor()));
+    let text_clip = Rect { x: left, y: top, w: width, h: height };
+    let (font_height, _, _,_) = measure_string(&['W'], fonts, face, font_size);
+    let shaped = shape_line(text, fonts, face, font_size, width, horizontal_align);
+    let h_align_offset = calculate_h_align_offset(width, shaped.width, scroll_offset_x, horizontal_align);
+    let v_align_offset = calculate_v_align_offset(height, font_height, run.vertical_align);
+    let text_left = left + h_align_offset + scroll_offset_x;
     let cursor_top = top + v_align_offset;
-    let mut text_char_index = 0;
-    let mut cursor_pos = cursor_left;
     let selection_start = std::cmp::min(cursor_index, selection_index);
-    let selection_end = std::cmp::max(cursor_index, selection_index);
-    for c in text {
-        let (font_metrics, font_bitmap) = font.rasterize(*c, font_size);
-        let buffer_top = cursor_top + font_height - font_metrics.height as i32 - font_metrics.ymin;
-        let buffer_bottom = buffer_top + font_metrics.height as i32;
-        let buffer_left = cursor_left;
-        let buffer_right = buffer_left + font_metrics.width as i32;
-        let mut font_bitmap_index = 0;
-        let mut color = text_color;
-        if  selection_index != usize::MAX &&
-            text_char_index >= selection_start &&
-            text_char_index < selection_end {
-            color = highlight_text_color;
-            let sel_left = std::cmp::max(cursor_left, max_left);
-            let sel_width = font_metrics.advance_width as i32;
-            let sel_right = std::cmp::min(cursor_left + sel_width, max_right);
-            let sel_width = sel_right - sel_left;
-            if sel_width > 0 && sel_right > 0 {
-                fill_rect(buffer, sel_left, cursor_top - 2, sel_width, font_height + 4, highlight_color);                
-            }
+    let selection_end = std::cmp::min(std::cmp::max(cursor_index, selection_index), text.len());
+    let has_selection = selection_index != usize::MAX && selection_start < selection_end;
+
+    if has_selection {
+        let sel_left = std::cmp::max(text_left + shaped.caret_positions[selection_start], max_left);
+        let sel_right = std::cmp::min(text_left + shaped.caret_positions[selection_end], max_right);
+        let sel_width = sel_right - sel_left;
+        if sel_width > 0 && sel_right > 0 {
+            fill_rect(buffer, sel_left, cursor_top - caret_margin, sel_width, font_height + caret_margin * 2, style.highlight_color);
         }
-        for buffer_y in buffer_top..buffer_bottom {
-            for buffer_x in buffer_left..buffer_right {
-                if  is_point_in_rect_a(buffer_x, buffer_y, max_left, max_top, max_right, max_bottom) {
-                    let buffer_index = (buffer_y * buffer_stride) + buffer_x;
-                    let buffer_pixel = &mut buffer.pixels[buffer_index as usize];
-                    let font_pixel = font_bitmap[font_bitmap_index];
-                    if font_pixel > 0 {
-                        buffer_pixel.red = alpha_blend_u8(color.red, buffer_pixel.red, font_pixel);
-                        buffer_pixel.green = alpha_blend_u8(color.green, buffer_pixel.green, font_pixel);
-                        buffer_pixel.blue = alpha_blend_u8(color.blue, buffer_pixel.blue, font_pixel);
-                    }
-                }
-                font_bitmap_index += 1;
-            }
+    }
+
+    for glyph in &shaped.glyphs {
+        let (font_metrics, font_bitmap) = fonts.font(glyph.font).font.rasterize_indexed(glyph.glyph_index, font_size);
+        let glyph_top = cursor_top + font_height - font_metrics.height as i32 - font_metrics.ymin;
+        // a ligature takes the selection color of its first char
+        let color = if has_selection && glyph.cluster >= selection_start && glyph.cluster < selection_end {
+            style.text_highlight_color
         }
-        cursor_left += font_metrics.advance_width as i32;
-        if cursor_index > text_char_index {
-            cursor_pos = cursor_left;
+        else {
+            text_color
+        };
+        let glyph_rect = Rect { x: text_left + glyph.x + font_metrics.xmin, y: glyph_top, w: font_metrics.width as i32, h: font_metrics.height as i32 };
+        blend_mask(buffer, &font_bitmap, glyph_rect, text_clip, color);
+    }
+
+    if let Some((start, end)) = underline {
+        let underline_left = std::cmp::max(text_left + shaped.caret_positions[start], max_left);
+        let underline_right = std::cmp::min(text_left + shaped.caret_positions[end], max_right);
+        let thickness = std::cmp::max(1, scale_i32(1, get_scale_factor()));
+        if underline_right > underline_left {
+            fill_rect(buffer, underline_left, cursor_top + font_height + thickness, underline_right - underline_left, thickness, text_color);
         }
-        text_char_index += 1;
     }
 
     if draw_cursor {
+        let cursor_index = std::cmp::min(cursor_index, text.len());
         fill_rect(buffer, 
-            cursor_pos,
-            cursor_top - 2,
-            2, // width
-            font_height + 4, // height
+            text_left + shaped.caret_positions[cursor_index],
+            cursor_top - caret_margin,
+            caret_width,
+            font_height + caret_margin * 2,
             text_color);
     }
 }
 
-fn calculate_h_align_offset(container_width: i32, text_width: i32, _scroll_offset_x: i32, align: HorizontalAlign) -> i32 {
+// shapes a line the way fill_text lays it out, justified lines are
+// stretched to the width
+pub fn shape_line(text: &[char], fonts: &FontManager, face: usize, font_size: f32, width: i32, align: HorizontalAlign) -> ShapedText {
+    let mut shaped = shape_text(text, fonts, face, font_size);
+    if align == HorizontalAlign::Justify {
+        justify(&mut shaped, text, width);
+    }
+    shaped
+}
+
+pub fn calculate_h_align_offset(container_width: i32, text_width: i32, _scroll_offset_x: i32, align: HorizontalAlign) -> i32 {
     match align {
-        HorizontalAlign::Left => 0,
-        //HorizontalAlign::Right => left, // I don't need this one yet so I'll wait on the implementation
+        HorizontalAlign::Left | HorizontalAlign::Justify => 0,
+        HorizontalAlign::Right => {
+            if text_width > container_width {
+                0
+            }
+            else {
+                container_width - text_width
+            }
+        },
         HorizontalAlign::Center => {
             if text_width > container_width {
                 0
@@ -206,24 +879,23 @@ fn calculate_v_align_offset(container_height: i32, text_height: i32, align: Vert
 }
 
 /*
-    return a tuple of height, width, baseline
+    return a tuple of height, width, baseline and the width each char adds
+    to the shaped string, so summing the first n widths gives the caret
+    position after n chars
 */
-pub fn measure_string(text: &[char], font: &fontdue::Font, font_size: f32) -> (i32, i32, i32, Vec<i32>) {
+pub fn measure_string(text: &[char], fonts: &FontManager, face: usize, font_size: f32) -> (i32, i32, i32, Vec<i32>) {
     let mut height: i32 = 0;
-    let mut width: i32 = 0;
     let mut ymin: i32 = 0;
-    let mut char_widths: Vec<i32> = Vec::<i32>::with_capacity(text.len());
-    for c in text {
-        let m = font.metrics(*c, font_size);
+    let shaped = shape_text(text, fonts, face, font_size);
+    for glyph in &shaped.glyphs {
+        let m = fonts.font(glyph.font).font.metrics_indexed(glyph.glyph_index, font_size);
         if height < m.height as i32 {
             height = m.height as i32;
         }
-        width += m.advance_width as i32;
-        char_widths.push(m.advance_width as i32);
         if ymin > m.ymin {
             ymin = m.ymin;
         }
     }
-    (height, width, ymin, char_widths)
+    let char_widths = shaped.caret_positions.windows(2).map(|w| w[1] - w[0]).collect();
+    (height, shaped.width, ymin, char_widths)
 }
-
@@ -0,0 +1,158 @@
+use crate::gui::Rect;
+use crate::gui::widget::Event;
+use crate::gui::widget::WidgetPath;
+use crate::gui::command::run_chosen_commands;
+
+/*
+    keyboard focus. one control at a time gets the keyboard, picked by a
+    click or by moving along the focus chain with tab and shift+tab. the
+    chain holds every control that can take focus in reading order, top to
+    bottom then left to right, and is rebuilt from the widget tree whenever
+    it's walked so controls can come and go freely.
+
+    the focus ring only shows after the keyboard moved focus, clicking a
+    text box shows its caret and nothing else, like :focus-visible on the
+    web.
+
+    a popup like the command palette takes focus while it's open. when the
+    focused widget stops taking focus the focus goes back to where it was
+    before.
+*/
+
+pub struct FocusManager {
+    pub focused: Option<WidgetPath>,
+    pub focus_visible: bool,
+    previous: Option<WidgetPath>
+}
+
+impl FocusManager {
+    pub const fn new() -> FocusManager {
+        FocusManager {
+            focused: None,
+            focus_visible: false,
+            previous: None
+        }
+    }
+
+    // the control after the focused one in the chain, wrapping around. with
+    // nothing focused tab starts at the first control and shift+tab at the
+    // last.
+    pub fn next_in_chain(&self, chain: &[WidgetPath], backwards: bool) -> Option<WidgetPath> {
+        if chain.is_empty() {
+            return None;
+        }
+        let position = self.focused.as_ref().and_then(|path| chain.iter().position(|c| c == path));
+        let index = match (position, backwards) {
+            (None, false) => 0,
+            (None, true) => chain.len() - 1,
+            (Some(i), false) => (i + 1) % chain.len(),
+            (Some(i), true) => (i + chain.len() - 1) % chain.len()
+        };
+        Some(chain[index].clone())
+    }
+}
+
+// orders controls by where they are on screen, rows first
+pub fn focus_chain<T: Clone>(controls: &[(T, Rect)]) -> Vec<T> {
+    let mut controls = controls.to_vec();
+    controls.sort_by_key(|(_, rect)| (rect.y, rect.x));
+    controls.into_iter().map(|(id, _)| id).collect()
+}
+
+// moves focus to a widget or takes it away from everything. text boxes
+// and text areas show their caret while they have it.
+pub fn set_focus(path: Option<WidgetPath>) {
+    let state = unsafe { &mut crate::APPLICATION_STATE };
+    state.widgets.walk_mut(&mut |p, node| node.widget.set_focused(path.as_deref() == Some(p)));
+    if state.focus.focused != path && state.focus.focused.is_some() {
+        state.focus.previous = state.focus.focused.take();
+    }
+    state.focus.focused = path;
+    crate::update_window();
+}
+
+// the focused widget closed or otherwise stopped taking focus, it goes
+// back to the one before
+pub fn restore_lost_focus() {
+    let state = unsafe { &mut crate::APPLICATION_STATE };
+    let lost = match &state.focus.focused {
+        Some(path) => state.widgets.get(path).and_then(|node| node.widget.focus_style()).is_none(),
+        None => false
+    };
+    if lost {
+        let previous = state.focus.previous.take();
+        set_focus(previous.filter(|path| state.widgets.get(path).is_some_and(|node| node.widget.focus_style().is_some())));
+    }
+}
+
+// tab and shift+tab
+pub fn focus_next(backwards: bool) {
+    let state = unsafe { &mut crate::APPLICATION_STATE };
+    let chain = focus_chain(&state.widgets.focusable());
+    let next = state.focus.next_in_chain(&chain, backwards);
+    set_focus(next);
+    state.focus.focus_visible = true;
+}
+
+// the widget that takes focus under a point, for giving focus on click
+pub fn control_at(x: i32, y: i32) -> Option<WidgetPath> {
+    let widgets = unsafe { &crate::APPLICATION_STATE.widgets };
+    widgets.focusable_ancestor(&widgets.hit_test(x, y)?)
+}
+
+// keys, text and edit commands go to the focused widget, true when it used
+// the event. commands it picked run after the focus is back where they
+// should go.
+pub fn send_to_focus(event: &Event) -> bool {
+    let state = unsafe { &mut crate::APPLICATION_STATE };
+    let used = match &state.focus.focused {
+        Some(path) => state.widgets.dispatch(path, event),
+        None => false
+    };
+    if used {
+        crate::update_window();
+    }
+    restore_lost_focus();
+    run_chosen_commands();
+    used
+}
+
+// the bounds of the focused control and its style when the focus ring
+// should be drawn
+pub fn focus_ring() -> Option<(Rect, crate::gui::style::BoxStyle)> {
+    let state = unsafe { &crate::APPLICATION_STATE };
+    if !state.focus.focus_visible {
+        return None;
+    }
+    let node = state.widgets.get(state.focus.focused.as_ref()?)?;
+    Some((node.widget.bounds(), node.widget.focus_style()?))
+}
+
+#[cfg(test)]
+mod focus_tests {
+    use super::*;
+
+    #[test]
+    fn test_chain_order_and_wrapping() {
+        let text_area = vec![0];
+        let button = vec![1, 0];
+        let text_box = vec![1, 1];
+        let chain = focus_chain(&[
+            (text_area.clone(), Rect { x: 0, y: 100, w: 50, h: 50 }),
+            (button.clone(), Rect { x: 200, y: 10, w: 50, h: 20 }),
+            (text_box.clone(), Rect { x: 10, y: 10, w: 100, h: 20 })
+        ]);
+        assert_eq!(chain, vec![text_box.clone(), button.clone(), text_area.clone()]);
+
+        let mut focus = FocusManager::new();
+        assert_eq!(focus.next_in_chain(&chain, false), Some(text_box.clone()));
+        assert_eq!(focus.next_in_chain(&chain, true), Some(text_area.clone()));
+        focus.focused = Some(text_area);
+        assert_eq!(focus.next_in_chain(&chain, false), Some(text_box.clone()));
+        assert_eq!(focus.next_in_chain(&chain, true), Some(button));
+        // a control that went away starts the chain over
+        focus.focused = Some(vec![5]);
+        assert_eq!(focus.next_in_chain(&chain, false), Some(text_box));
+        assert_eq!(focus.next_in_chain(&[], false), None);
+    }
+}
@@ -0,0 +1,214 @@
+
+/*
+    bmp decoder for uncompressed and bitfield bitmaps at 1, 4, 8, 16, 24
+    and 32 bits per pixel. decode_dib is shared with the ico decoder since
+    icons store the same header without the 14 byte file header, with a
+    doubled height and a 1 bit AND mask after the color data.
+*/
+
+use crate::gui::Pixel;
+use crate::gui::image::Image;
+use crate::gui::image::ImageError;
+
+const BI_RGB: u32 = 0;
+const BI_BITFIELDS: u32 = 3;
+const BI_ALPHABITFIELDS: u32 = 6;
+
+const FILE_HEADER_SIZE: usize = 14;
+
+pub fn decode_bmp(data: &[u8]) -> Result<Image, ImageError> {
+    if data.len() < FILE_HEADER_SIZE || &data[0..2] != b"BM" {
+        return Err(ImageError::UnsupportedFormat);
+    }
+    let pixel_offset = read_u32_le(data, 10) as usize;
+    if pixel_offset < FILE_HEADER_SIZE {
+        return Err(ImageError::Corrupt("bitmap pixel offset inside file header"));
+    }
+    decode_dib(&data[FILE_HEADER_SIZE..], Some(pixel_offset - FILE_HEADER_SIZE), false)
+}
+
+// pixel_offset is relative to the start of the dib header, when it's None
+// the pixels follow the header and color table directly like they do in icons
+pub fn decode_dib(data: &[u8], pixel_offset: Option<usize>, is_icon: bool) -> Result<Image, ImageError> {
+    if data.len() < 40 {
+        return Err(ImageError::Corrupt("bitmap header too short"));
+    }
+    let header_size = read_u32_le(data, 0) as usize;
+    if header_size < 40 || header_size > data.len() {
+        return Err(ImageError::Unsupported("bitmap header version"));
+    }
+    let width = read_i32_le(data, 4);
+    let mut height = read_i32_le(data, 8);
+    let bit_count = read_u16_le(data, 14) as u32;
+    let compression = read_u32_le(data, 16);
+    let colors_used = read_u32_le(data, 32) as usize;
+
+    // icons store the height of the color data plus the mask
+    if is_icon {
+        height /= 2;
+    }
+    let top_down = height < 0;
+    let height = height.checked_abs().ok_or(ImageError::Corrupt("bitmap height out of range"))?;
+    if width <= 0 || height == 0 {
+        return Err(ImageError::Corrupt("bitmap has zero size"));
+    }
+    if width as usize > super::MAX_DIMENSION || height as usize > super::MAX_DIMENSION {
+        return Err(ImageError::Unsupported("bitmap is too large"));
+    }
+
+    let mut masks = match bit_count {
+        16 => [0x7C00, 0x03E0, 0x001F, 0],
+        32 => [0x00FF_0000, 0x0000_FF00, 0x0000_00FF, 0xFF00_0000],
+        _ => [0, 0, 0, 0]
+    };
+    let mut table_offset = header_size;
+    match compression {
+        BI_RGB => { },
+        BI_BITFIELDS | BI_ALPHABITFIELDS => {
+            if bit_count != 16 && bit_count != 32 {
+                return Err(ImageError::Corrupt("bitfields need 16 or 32 bits per pixel"));
+            }
+            let mask_count = if compression == BI_ALPHABITFIELDS { 4 } else { 3 };
+            if header_size >= 40 + mask_count * 4 {
+                // v4 and v5 headers carry the masks inside the header
+                for (i, mask) in masks.iter_mut().take(mask_count).enumerate() {
+                    *mask = read_u32_le(data, 40 + i * 4);
+                }
+            }
+            else {
+                if data.len() < header_size + mask_count * 4 {
+                    return Err(ImageError::Corrupt("bitmap masks missing"));
+                }
+                for (i, mask) in masks.iter_mut().take(mask_count).enumerate() {
+                    *mask = read_u32_le(data, header_size + i * 4);
+                }
+                table_offset += mask_count * 4;
+            }
+        },
+        _ => return Err(ImageError::Unsupported("compressed bitmaps"))
+    }
+    if header_size >= 56 && compression == BI_BITFIELDS {
+        masks[3] = read_u32_le(data, 52);
+    }
+
+    let palette_size = match bit_count {
+        1 | 4 | 8 => if colors_used == 0 { 1usize << bit_count } else { colors_used },
+        16 | 24 | 32 => 0,
+        _ => return Err(ImageError::Unsupported("bitmap bit depth"))
+    };
+    if data.len() < table_offset + palette_size * 4 {
+        return Err(ImageError::Corrupt("bitmap color table too short"));
+    }
+    let palette: Vec<Pixel> = data[table_offset..table_offset + palette_size * 4]
+        .chunks_exact(4)
+        .map(|c| Pixel::from_rgba(c[2], c[1], c[0], 255))
+        .collect();
+
+    let pixel_start = match pixel_offset {
+        Some(offset) => offset,
+        None => table_offset + palette_size * 4
+    };
+    let row_size = (width as usize * bit_count as usize).div_ceil(32) * 4;
+    let color_size = row_size * height as usize;
+    if data.len() < pixel_start + color_size {
+        return Err(ImageError::Corrupt("bitmap pixel data too short"));
+    }
+    let pixels = &data[pixel_start..pixel_start + color_size];
+
+    let mut image = Image::new(width, height);
+    let mut has_alpha = false;
+    for row in 0..height as usize {
+        let y = if top_down { row } else { height as usize - 1 - row };
+        let src = &pixels[row * row_size..(row + 1) * row_size];
+        for x in 0..width as usize {
+            let pixel = match bit_count {
+                1 | 4 | 8 => {
+                    let bit = x * bit_count as usize;
+                    let shift = 8 - bit_count as usize - (bit % 8);
+                    let index = ((src[bit / 8] >> shift) as usize) & ((1 << bit_count) - 1);
+                    match palette.get(index) {
+                        Some(p) => *p,
+                        None => Pixel::from_rgba(0, 0, 0, 255)
+                    }
+                },
+                16 => {
+                    let value = read_u16_le(src, x * 2) as u32;
+                    masked_pixel(value, &masks)
+                },
+                24 => Pixel::from_rgba(src[x * 3 + 2], src[x * 3 + 1], src[x * 3], 255),
+                _ => {
+                    let value = read_u32_le(src, x * 4);
+                    masked_pixel(value, &masks)
+                }
+            };
+            if pixel.alpha != 0 {
+                has_alpha = true;
+            }
+            image.pixels[y * width as usize + x] = pixel;
+        }
+    }
+
+    // plenty of 32 bit bitmaps leave the x byte at zero, so only trust the
+    // alpha channel when something in it is set
+    let uses_alpha = bit_count == 32 && has_alpha;
+    if !uses_alpha {
+        for pixel in image.pixels.iter_mut() {
+            pixel.alpha = 255;
+        }
+    }
+
+    // the and mask marks transparent pixels for icons without an alpha channel
+    if is_icon && !uses_alpha {
+        let mask_row_size = (width as usize).div_ceil(32) * 4;
+        let mask_start = pixel_start + color_size;
+        if data.len() >= mask_start + mask_row_size * height as usize {
+            for row in 0..height as usize {
+                let y = if top_down { row } else { height as usize - 1 - row };
+                let src = &data[mask_start + row * mask_row_size..];
+                for x in 0..width as usize {
+                    if (src[x / 8] >> (7 - (x % 8))) & 1 == 1 {
+                        image.pixels[y * width as usize + x].alpha = 0;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(image)
+}
+
+fn masked_pixel(value: u32, masks: &[u32; 4]) -> Pixel {
+    let alpha = if masks[3] == 0 { 255 } else { extract_channel(value, masks[3]) };
+    Pixel::from_rgba(
+        extract_channel(value, masks[0]),
+        extract_channel(value, masks[1]),
+        extract_channel(value, masks[2]),
+        alpha)
+}
+
+// shifts the masked bits down and scales them up to 8 bits
+fn extract_channel(value: u32, mask: u32) -> u8 {
+    if mask == 0 {
+        return 0;
+    }
+    let shift = mask.trailing_zeros();
+    let bits = (mask >> shift).count_ones();
+    let channel = (value & mask) >> shift;
+    let max = (1u64 << bits) - 1;
+    (channel as u64 * 255 / max) as u8
+}
+
+pub fn read_u16_le(data: &[u8], offset: usize) -> u16 {
+    (data[offset] as u16) | ((data[offset + 1] as u16) << 8)
+}
+
+pub fn read_u32_le(data: &[u8], offset: usize) -> u32 {
+    (data[offset] as u32) |
+    ((data[offset + 1] as u32) << 8) |
+    ((data[offset + 2] as u32) << 16) |
+    ((data[offset + 3] as u32) << 24)
+}
+
+fn read_i32_le(data: &[u8], offset: usize) -> i32 {
+    read_u32_le(data, offset) as i32
+}
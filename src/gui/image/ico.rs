@@ -0,0 +1,109 @@
+
+/*
+    ico (and cur) decoder. an icon file is a directory of images at
+    different sizes, each stored either as a png or as a dib without the
+    bmp file header. decode_ico_for_size picks the entry that best fits the
+    size it's going to be drawn at so scaling has the least work to do.
+*/
+
+use crate::gui::image::Image;
+use crate::gui::image::ImageError;
+use crate::gui::image::bmp::decode_dib;
+use crate::gui::image::bmp::read_u16_le;
+use crate::gui::image::bmp::read_u32_le;
+use crate::gui::image::png::decode_png;
+use crate::gui::image::png::SIGNATURE as PNG_SIGNATURE;
+
+const DIRECTORY_SIZE: usize = 6;
+const ENTRY_SIZE: usize = 16;
+
+#[derive(Debug, Copy, Clone)]
+struct IconEntry {
+    width: u32,
+    height: u32,
+    bit_count: u16,
+    size: usize,
+    offset: usize
+}
+
+pub fn is_ico(data: &[u8]) -> bool {
+    data.len() >= DIRECTORY_SIZE &&
+    read_u16_le(data, 0) == 0 &&
+    (read_u16_le(data, 2) == 1 || read_u16_le(data, 2) == 2) &&
+    read_u16_le(data, 4) > 0
+}
+
+// decodes the largest image in the icon
+pub fn decode_ico(data: &[u8]) -> Result<Image, ImageError> {
+    decode_ico_for_size(data, u32::MAX)
+}
+
+// decodes the smallest image at least `size` pixels wide, or the largest
+// image when none are big enough. deeper color wins between equal sizes.
+pub fn decode_ico_for_size(data: &[u8], size: u32) -> Result<Image, ImageError> {
+    let entries = read_entries(data)?;
+    let mut best: Option<IconEntry> = None;
+    for entry in entries {
+        best = match best {
+            None => Some(entry),
+            Some(current) => {
+                let current_fits = current.width >= size;
+                let entry_fits = entry.width >= size;
+                let better = if entry.width == current.width {
+                    entry.bit_count > current.bit_count
+                }
+                else if entry_fits && current_fits {
+                    entry.width < current.width
+                }
+                else if entry_fits != current_fits {
+                    entry_fits
+                }
+                else {
+                    entry.width > current.width
+                };
+                if better { Some(entry) } else { Some(current) }
+            }
+        };
+    }
+    match best {
+        Some(entry) => decode_entry(data, &entry),
+        None => Err(ImageError::Corrupt("icon has no images"))
+    }
+}
+
+fn read_entries(data: &[u8]) -> Result<Vec<IconEntry>, ImageError> {
+    if !is_ico(data) {
+        return Err(ImageError::UnsupportedFormat);
+    }
+    let count = read_u16_le(data, 4) as usize;
+    if data.len() < DIRECTORY_SIZE + count * ENTRY_SIZE {
+        return Err(ImageError::Corrupt("icon directory too short"));
+    }
+    let mut entries = Vec::<IconEntry>::with_capacity(count);
+    for i in 0..count {
+        let e = &data[DIRECTORY_SIZE + i * ENTRY_SIZE..];
+        // a zero width or height byte means 256
+        let entry = IconEntry {
+            width: if e[0] == 0 { 256 } else { e[0] as u32 },
+            height: if e[1] == 0 { 256 } else { e[1] as u32 },
+            bit_count: read_u16_le(e, 6),
+            size: read_u32_le(e, 8) as usize,
+            offset: read_u32_le(e, 12) as usize
+        };
+        if entry.offset + entry.size > data.len() {
+            return Err(ImageError::Corrupt("icon entry runs past end of file"));
+        }
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+fn decode_entry(data: &[u8], entry: &IconEntry) -> Result<Image, ImageError> {
+    let bytes = &data[entry.offset..entry.offset + entry.size];
+    if bytes.len() >= PNG_SIGNATURE.len() && bytes[..PNG_SIGNATURE.len()] == PNG_SIGNATURE {
+        decode_png(bytes)
+    }
+    else {
+        decode_dib(bytes, None, true)
+    }
+}
@@ -0,0 +1,278 @@
+
+/*
+    a small inflate (RFC 1951) implementation so png decoding doesn't need
+    another crate. it decodes stored, fixed huffman and dynamic huffman
+    blocks into a single output vector. zlib_decompress strips the 2 byte
+    zlib header (RFC 1950) and ignores the adler32 trailer. the caller says
+    how much output it expects, a few bytes of input can inflate to
+    gigabytes so anything past the limit is an error.
+*/
+
+use crate::gui::image::ImageError;
+
+const MAX_BITS: usize = 15;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31,
+    35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2,
+    3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193,
+    257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6,
+    7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+// order the code length code lengths are stored in for dynamic blocks
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    bit_buffer: u32,
+    bit_count: u32
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> BitReader<'a> {
+        BitReader { data, position: 0, bit_buffer: 0, bit_count: 0 }
+    }
+
+    fn need(&mut self, count: u32) -> Result<(), ImageError> {
+        while self.bit_count < count {
+            if self.position >= self.data.len() {
+                return Err(ImageError::Corrupt("deflate stream ended early"));
+            }
+            self.bit_buffer |= (self.data[self.position] as u32) << self.bit_count;
+            self.position += 1;
+            self.bit_count += 8;
+        }
+        Ok(())
+    }
+
+    fn bits(&mut self, count: u32) -> Result<u32, ImageError> {
+        if count == 0 {
+            return Ok(0);
+        }
+        self.need(count)?;
+        let value = self.bit_buffer & ((1 << count) - 1);
+        self.bit_buffer >>= count;
+        self.bit_count -= count;
+        Ok(value)
+    }
+
+    // drop the remaining bits of the current byte, used by stored blocks
+    fn align_to_byte(&mut self) {
+        let drop = self.bit_count % 8;
+        self.bit_buffer >>= drop;
+        self.bit_count -= drop;
+    }
+}
+
+/*
+    canonical huffman table, decoded one bit at a time. counts[n] is the
+    number of codes of length n and symbols are sorted by code.
+*/
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Huffman, ImageError> {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        let mut offsets = [0u16; MAX_BITS + 2];
+        for bits in 1..=MAX_BITS {
+            offsets[bits + 1] = offsets[bits] + counts[bits];
+        }
+
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+        Ok(Huffman { counts, symbols })
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, ImageError> {
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+        for bits in 1..=MAX_BITS {
+            code |= reader.bits(1)? as i32;
+            let count = self.counts[bits] as i32;
+            if code - count < first {
+                return Ok(self.symbols[(index + (code - first)) as usize]);
+            }
+            index += count;
+            first += count;
+            first <<= 1;
+            code <<= 1;
+        }
+        Err(ImageError::Corrupt("invalid huffman code"))
+    }
+}
+
+pub fn zlib_decompress(data: &[u8], limit: usize) -> Result<Vec<u8>, ImageError> {
+    if data.len() < 2 {
+        return Err(ImageError::Corrupt("zlib stream too short"));
+    }
+    let cmf = data[0];
+    let flg = data[1];
+    if cmf & 0x0F != 8 || !((cmf as u16) << 8 | flg as u16).is_multiple_of(31) {
+        return Err(ImageError::Corrupt("bad zlib header"));
+    }
+    if flg & 0x20 != 0 {
+        return Err(ImageError::Unsupported("zlib preset dictionary"));
+    }
+    inflate(&data[2..], limit)
+}
+
+pub fn inflate(data: &[u8], limit: usize) -> Result<Vec<u8>, ImageError> {
+    let mut reader = BitReader::new(data);
+    let mut output = Vec::<u8>::with_capacity(std::cmp::min(limit, data.len() * 4));
+    loop {
+        let is_final = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => inflate_stored(&mut reader, &mut output, limit)?,
+            1 => {
+                let (literals, distances) = fixed_tables()?;
+                inflate_block(&mut reader, &mut output, &literals, &distances, limit)?;
+            },
+            2 => {
+                let (literals, distances) = dynamic_tables(&mut reader)?;
+                inflate_block(&mut reader, &mut output, &literals, &distances, limit)?;
+            },
+            _ => return Err(ImageError::Corrupt("invalid deflate block type"))
+        }
+        if is_final {
+            break;
+        }
+    }
+    Ok(output)
+}
+
+fn check_limit(output: &[u8], length: usize, limit: usize) -> Result<(), ImageError> {
+    if output.len() + length > limit {
+        return Err(ImageError::Corrupt("deflate output larger than expected"));
+    }
+    Ok(())
+}
+
+fn inflate_stored(reader: &mut BitReader, output: &mut Vec<u8>, limit: usize) -> Result<(), ImageError> {
+    reader.align_to_byte();
+    let length = reader.bits(16)?;
+    let inverse = reader.bits(16)?;
+    if length != !inverse & 0xFFFF {
+        return Err(ImageError::Corrupt("stored block length mismatch"));
+    }
+    // the bit buffer is empty after reading two full 16 bit values so
+    // the rest of the block can be copied straight from the input
+    let start = reader.position;
+    let end = start + length as usize;
+    if end > reader.data.len() {
+        return Err(ImageError::Corrupt("stored block ended early"));
+    }
+    check_limit(output, length as usize, limit)?;
+    output.extend_from_slice(&reader.data[start..end]);
+    reader.position = end;
+    Ok(())
+}
+
+fn fixed_tables() -> Result<(Huffman, Huffman), ImageError> {
+    let mut lengths = [0u8; 288];
+    for (symbol, length) in lengths.iter_mut().enumerate() {
+        *length = match symbol {
+            0..=143 => 8,
+            144..=255 => 9,
+            256..=279 => 7,
+            _ => 8
+        };
+    }
+    Ok((Huffman::new(&lengths)?, Huffman::new(&[5u8; 30])?))
+}
+
+fn dynamic_tables(reader: &mut BitReader) -> Result<(Huffman, Huffman), ImageError> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+
+    let mut code_lengths = [0u8; 19];
+    for i in 0..code_length_count {
+        code_lengths[CODE_LENGTH_ORDER[i]] = reader.bits(3)? as u8;
+    }
+    let code_length_table = Huffman::new(&code_lengths)?;
+
+    let mut lengths = vec![0u8; literal_count + distance_count];
+    let mut i = 0;
+    while i < lengths.len() {
+        let symbol = code_length_table.decode(reader)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                if i == 0 {
+                    return Err(ImageError::Corrupt("repeat with no previous length"));
+                }
+                (lengths[i - 1], 3 + reader.bits(2)? as usize)
+            },
+            17 => (0, 3 + reader.bits(3)? as usize),
+            18 => (0, 11 + reader.bits(7)? as usize),
+            _ => return Err(ImageError::Corrupt("invalid code length symbol"))
+        };
+        if i + repeat > lengths.len() {
+            return Err(ImageError::Corrupt("code lengths overflow"));
+        }
+        for _ in 0..repeat {
+            lengths[i] = value;
+            i += 1;
+        }
+    }
+
+    Ok((Huffman::new(&lengths[..literal_count])?, Huffman::new(&lengths[literal_count..])?))
+}
+
+fn inflate_block(reader: &mut BitReader, output: &mut Vec<u8>, literals: &Huffman, distances: &Huffman, limit: usize) -> Result<(), ImageError> {
+    loop {
+        let symbol = literals.decode(reader)? as usize;
+        if symbol < 256 {
+            check_limit(output, 1, limit)?;
+            output.push(symbol as u8);
+        }
+        else if symbol == 256 {
+            return Ok(());
+        }
+        else {
+            let symbol = symbol - 257;
+            if symbol >= LENGTH_BASE.len() {
+                return Err(ImageError::Corrupt("invalid length symbol"));
+            }
+            let length = LENGTH_BASE[symbol] as usize + reader.bits(LENGTH_EXTRA[symbol] as u32)? as usize;
+
+            let symbol = distances.decode(reader)? as usize;
+            if symbol >= DIST_BASE.len() {
+                return Err(ImageError::Corrupt("invalid distance symbol"));
+            }
+            let distance = DIST_BASE[symbol] as usize + reader.bits(DIST_EXTRA[symbol] as u32)? as usize;
+            if distance > output.len() {
+                return Err(ImageError::Corrupt("distance before start of output"));
+            }
+
+            check_limit(output, length, limit)?;
+            // copies can overlap the bytes they produce so go one at a time
+            let start = output.len() - distance;
+            for i in 0..length {
+                let byte = output[start + i];
+                output.push(byte);
+            }
+        }
+    }
+}
@@ -0,0 +1,213 @@
+
+pub mod bmp;
+pub mod ico;
+pub mod inflate;
+pub mod png;
+
+use crate::gui::Pixel;
+
+// guards against allocating gigabytes for a corrupt or hostile header
+pub const MAX_DIMENSION: usize = 16384;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ImageError {
+    UnsupportedFormat,
+    Unsupported(&'static str),
+    Corrupt(&'static str)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum ImageFilter {
+    Nearest,
+    #[default]
+    Bilinear
+}
+
+// pixels are stored top down in the same bgra layout as the PixelBuffer
+// with straight (not premultiplied) alpha
+pub struct Image {
+    pub width: i32,
+    pub height: i32,
+    pub pixels: Vec<Pixel>
+}
+
+impl Image {
+    pub fn new(width: i32, height: i32) -> Image {
+        Image {
+            width,
+            height,
+            pixels: vec![Pixel::default(); (width * height) as usize]
+        }
+    }
+
+    pub fn get_pixel(&self, x: i32, y: i32) -> Pixel {
+        self.pixels[(y * self.width + x) as usize]
+    }
+}
+
+// sniffs the format from the first few bytes and decodes the image.
+// icons decode their largest entry, use decode_icon to pick a size.
+pub fn decode_image(data: &[u8]) -> Result<Image, ImageError> {
+    if data.starts_with(&png::SIGNATURE) {
+        png::decode_png(data)
+    }
+    else if data.starts_with(b"BM") {
+        bmp::decode_bmp(data)
+    }
+    else if ico::is_ico(data) {
+        ico::decode_ico(data)
+    }
+    else {
+        Err(ImageError::UnsupportedFormat)
+    }
+}
+
+pub fn decode_icon(data: &[u8], size: u32) -> Result<Image, ImageError> {
+    if ico::is_ico(data) {
+        ico::decode_ico_for_size(data, size)
+    }
+    else {
+        decode_image(data)
+    }
+}
+
+#[cfg(test)]
+mod image_tests {
+    use super::*;
+
+    fn rgba(pixel: Pixel) -> (u8, u8, u8, u8) {
+        (pixel.red, pixel.green, pixel.blue, pixel.alpha)
+    }
+
+    // 2x2 rgba png: red, green / blue, transparent white
+    const PNG_RGBA_2X2: [u8; 76] = [
+        0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52,
+        0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x02, 0x08, 0x06, 0x00, 0x00, 0x00, 0x72, 0xb6, 0x0d,
+        0x24, 0x00, 0x00, 0x00, 0x13, 0x49, 0x44, 0x41, 0x54, 0x78, 0xda, 0x63, 0xf8, 0xcf, 0xc0, 0xf0,
+        0x1f, 0x0c, 0x81, 0x34, 0x08, 0x30, 0x00, 0x00, 0x48, 0xc9, 0x08, 0xf8, 0xc5, 0x34, 0xfd, 0x05,
+        0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82];
+
+    // zlib stream compressed with a dynamic huffman block
+    const ZLIB_DYNAMIC: [u8; 100] = [
+        0x78, 0xda, 0x1d, 0xcc, 0x41, 0x0e, 0xc2, 0x30, 0x0c, 0x44, 0xd1, 0xab, 0xcc, 0x01, 0xaa, 0x1e,
+        0xa3, 0x07, 0xe8, 0x8a, 0x6d, 0xd2, 0xb8, 0x30, 0x92, 0x71, 0x91, 0xed, 0x10, 0xb8, 0x3d, 0x11,
+        0xdb, 0xaf, 0xa7, 0xbf, 0x51, 0xe5, 0x06, 0x06, 0x0a, 0xb2, 0xd4, 0x2a, 0x0d, 0xe7, 0x2c, 0xa8,
+        0x7e, 0x8d, 0x10, 0xc7, 0x70, 0x66, 0x8a, 0x81, 0x86, 0xbd, 0x47, 0xae, 0xd8, 0xfe, 0xbe, 0x79,
+        0x19, 0x01, 0x79, 0x8b, 0x7f, 0xf1, 0xe2, 0x47, 0x14, 0xcc, 0x10, 0x3d, 0x97, 0x09, 0x0f, 0xed,
+        0x8d, 0x76, 0x07, 0x8f, 0xcb, 0xe6, 0xd6, 0x1a, 0xf2, 0xd1, 0x9f, 0xd5, 0x0a, 0x35, 0xd6, 0x1f,
+        0xb6, 0x75, 0x28, 0x66];
+
+    #[test]
+    fn test_inflate_dynamic_block() {
+        let text = inflate::zlib_decompress(&ZLIB_DYNAMIC, 1024).unwrap();
+        assert_eq!(String::from_utf8(text).unwrap(),
+            "FileX is a tabbed file browser written in Rust. FileX draws every pixel itself, including icons and thumbnails.");
+        // more output than the caller expects is an error, not a huge vector
+        assert_eq!(inflate::zlib_decompress(&ZLIB_DYNAMIC, 50).err(), Some(ImageError::Corrupt("deflate output larger than expected")));
+    }
+
+    #[test]
+    fn test_decode_png_rgba() {
+        let image = decode_image(&PNG_RGBA_2X2).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(rgba(image.get_pixel(0, 0)), (255, 0, 0, 255));
+        assert_eq!(rgba(image.get_pixel(1, 0)), (0, 255, 0, 255));
+        assert_eq!(rgba(image.get_pixel(0, 1)), (0, 0, 255, 255));
+        assert_eq!(rgba(image.get_pixel(1, 1)), (255, 255, 255, 0));
+    }
+
+    #[test]
+    fn test_decode_png_bad_crc() {
+        let mut data = PNG_RGBA_2X2;
+        data[20] ^= 0xFF;
+        assert_eq!(decode_image(&data).err(), Some(ImageError::Corrupt("png chunk crc mismatch")));
+    }
+
+    #[test]
+    fn test_decode_png_unknown_interlace() {
+        let mut data = PNG_RGBA_2X2;
+        data[28] = 2;
+        let crc = png::crc32(&data[12..29]);
+        data[29..33].copy_from_slice(&crc.to_be_bytes());
+        assert_eq!(decode_image(&data).err(), Some(ImageError::Unsupported("png interlace method")));
+    }
+
+    // 3x2 bottom up 24 bit bmp, rows are padded to 4 bytes
+    fn bmp_24_3x2() -> Vec<u8> {
+        let mut data = Vec::<u8>::new();
+        let row_size = 12;
+        let file_size = 14 + 40 + row_size * 2;
+        data.extend_from_slice(b"BM");
+        data.extend_from_slice(&(file_size as u32).to_le_bytes());
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        data.extend_from_slice(&54u32.to_le_bytes());
+        data.extend_from_slice(&40u32.to_le_bytes());
+        data.extend_from_slice(&3i32.to_le_bytes());
+        data.extend_from_slice(&2i32.to_le_bytes());
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&24u16.to_le_bytes());
+        data.extend_from_slice(&[0u8; 24]);
+        // bottom row: white, black, gray
+        data.extend_from_slice(&[255, 255, 255, 0, 0, 0, 128, 128, 128, 0, 0, 0]);
+        // top row: blue, green, red (stored bgr)
+        data.extend_from_slice(&[255, 0, 0, 0, 255, 0, 0, 0, 255, 0, 0, 0]);
+        data
+    }
+
+    #[test]
+    fn test_decode_bmp_24() {
+        let image = decode_image(&bmp_24_3x2()).unwrap();
+        assert_eq!((image.width, image.height), (3, 2));
+        assert_eq!(rgba(image.get_pixel(0, 0)), (0, 0, 255, 255));
+        assert_eq!(rgba(image.get_pixel(1, 0)), (0, 255, 0, 255));
+        assert_eq!(rgba(image.get_pixel(2, 0)), (255, 0, 0, 255));
+        assert_eq!(rgba(image.get_pixel(0, 1)), (255, 255, 255, 255));
+        assert_eq!(rgba(image.get_pixel(2, 1)), (128, 128, 128, 255));
+    }
+
+    #[test]
+    fn test_decode_bmp_height_out_of_range() {
+        let mut data = bmp_24_3x2();
+        data[22..26].copy_from_slice(&i32::MIN.to_le_bytes());
+        assert_eq!(decode_image(&data).err(), Some(ImageError::Corrupt("bitmap height out of range")));
+    }
+
+    // icon with a 1x1 dib entry and a 2x2 png entry
+    fn ico_two_entries() -> Vec<u8> {
+        let mut dib = Vec::<u8>::new();
+        dib.extend_from_slice(&40u32.to_le_bytes());
+        dib.extend_from_slice(&1i32.to_le_bytes());
+        dib.extend_from_slice(&2i32.to_le_bytes()); // doubled for the and mask
+        dib.extend_from_slice(&1u16.to_le_bytes());
+        dib.extend_from_slice(&32u16.to_le_bytes());
+        dib.extend_from_slice(&[0u8; 24]);
+        dib.extend_from_slice(&[10, 20, 30, 128]); // bgra
+        dib.extend_from_slice(&[0, 0, 0, 0]); // and mask row
+
+        let offset = 6 + 16 * 2;
+        let mut data = Vec::<u8>::new();
+        data.extend_from_slice(&[0, 0, 1, 0, 2, 0]);
+        data.extend_from_slice(&[1, 1, 0, 0, 1, 0, 32, 0]);
+        data.extend_from_slice(&(dib.len() as u32).to_le_bytes());
+        data.extend_from_slice(&(offset as u32).to_le_bytes());
+        data.extend_from_slice(&[2, 2, 0, 0, 1, 0, 32, 0]);
+        data.extend_from_slice(&(PNG_RGBA_2X2.len() as u32).to_le_bytes());
+        data.extend_from_slice(&((offset + dib.len()) as u32).to_le_bytes());
+        data.extend_from_slice(&dib);
+        data.extend_from_slice(&PNG_RGBA_2X2);
+        data
+    }
+
+    #[test]
+    fn test_decode_ico_picks_size() {
+        let data = ico_two_entries();
+
+        let small = decode_icon(&data, 1).unwrap();
+        assert_eq!((small.width, small.height), (1, 1));
+        assert_eq!(rgba(small.get_pixel(0, 0)), (30, 20, 10, 128));
+
+        let large = decode_icon(&data, 16).unwrap();
+        assert_eq!((large.width, large.height), (2, 2));
+        assert_eq!(rgba(large.get_pixel(1, 0)), (0, 255, 0, 255));
+    }
+}
@@ -0,0 +1,354 @@
+
+/*
+    png decoder. handles every color type and bit depth in the spec plus
+    adam7 interlacing and tRNS transparency. 16 bit samples are reduced to
+    8 bits since the back buffer only has 8 bits per channel anyway.
+*/
+
+use crate::gui::Pixel;
+use crate::gui::image::Image;
+use crate::gui::image::ImageError;
+use crate::gui::image::inflate::zlib_decompress;
+
+pub const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+// x start, y start, x step, y step for each of the 7 adam7 passes
+const ADAM7: [(usize, usize, usize, usize); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2)];
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum ColorType {
+    Gray,
+    Rgb,
+    Indexed,
+    GrayAlpha,
+    Rgba
+}
+
+impl ColorType {
+    fn from_u8(value: u8) -> Result<ColorType, ImageError> {
+        match value {
+            0 => Ok(ColorType::Gray),
+            2 => Ok(ColorType::Rgb),
+            3 => Ok(ColorType::Indexed),
+            4 => Ok(ColorType::GrayAlpha),
+            6 => Ok(ColorType::Rgba),
+            _ => Err(ImageError::Corrupt("invalid png color type"))
+        }
+    }
+
+    fn channels(&self) -> usize {
+        match self {
+            ColorType::Gray | ColorType::Indexed => 1,
+            ColorType::GrayAlpha => 2,
+            ColorType::Rgb => 3,
+            ColorType::Rgba => 4
+        }
+    }
+}
+
+struct Header {
+    width: usize,
+    height: usize,
+    bit_depth: u8,
+    color_type: ColorType,
+    interlaced: bool
+}
+
+impl Header {
+    fn bits_per_pixel(&self) -> usize {
+        self.color_type.channels() * self.bit_depth as usize
+    }
+
+    // filters work on whole bytes so sub pixel depths round up to 1
+    fn filter_stride(&self) -> usize {
+        std::cmp::max(1, self.bits_per_pixel() / 8)
+    }
+
+    fn row_bytes(&self, width: usize) -> usize {
+        (width * self.bits_per_pixel()).div_ceil(8)
+    }
+
+    // the filtered rows of a pass, each with its filter type byte
+    fn pass_size(&self, width: usize, height: usize) -> usize {
+        (self.row_bytes(width) + 1) * height
+    }
+
+    // the size of the whole image data once it's inflated
+    fn data_size(&self) -> usize {
+        if !self.interlaced {
+            return self.pass_size(self.width, self.height);
+        }
+        ADAM7.iter()
+            .filter(|(x_start, y_start, _, _)| *x_start < self.width && *y_start < self.height)
+            .map(|(x_start, y_start, x_step, y_step)| {
+                self.pass_size((self.width - x_start).div_ceil(*x_step), (self.height - y_start).div_ceil(*y_step))
+            })
+            .sum()
+    }
+}
+
+pub fn decode_png(data: &[u8]) -> Result<Image, ImageError> {
+    if data.len() < SIGNATURE.len() || data[..SIGNATURE.len()] != SIGNATURE {
+        return Err(ImageError::UnsupportedFormat);
+    }
+
+    let mut header: Option<Header> = None;
+    let mut palette: Vec<[u8; 3]> = Vec::new();
+    let mut transparency: Vec<u8> = Vec::new();
+    let mut compressed: Vec<u8> = Vec::new();
+
+    let mut offset = SIGNATURE.len();
+    loop {
+        if offset + 8 > data.len() {
+            return Err(ImageError::Corrupt("png ended before IEND"));
+        }
+        let length = read_u32_be(data, offset) as usize;
+        let chunk_type = &data[offset + 4..offset + 8];
+        let chunk_start = offset + 8;
+        let chunk_end = chunk_start + length;
+        if chunk_end + 4 > data.len() {
+            return Err(ImageError::Corrupt("png chunk runs past end of file"));
+        }
+        let chunk = &data[chunk_start..chunk_end];
+        let expected_crc = read_u32_be(data, chunk_end);
+        if crc32(&data[offset + 4..chunk_end]) != expected_crc {
+            return Err(ImageError::Corrupt("png chunk crc mismatch"));
+        }
+
+        match chunk_type {
+            b"IHDR" => header = Some(read_header(chunk)?),
+            b"PLTE" => {
+                palette = chunk.chunks_exact(3).map(|c| [c[0], c[1], c[2]]).collect();
+            },
+            b"tRNS" => transparency = chunk.to_vec(),
+            b"IDAT" => compressed.extend_from_slice(chunk),
+            b"IEND" => break,
+            _ => {
+                // bit 5 of the first byte marks ancillary chunks which are safe to skip
+                if chunk_type[0] & 0x20 == 0 {
+                    return Err(ImageError::Unsupported("unknown critical png chunk"));
+                }
+            }
+        }
+        offset = chunk_end + 4;
+    }
+
+    let header = match header {
+        Some(h) => h,
+        None => return Err(ImageError::Corrupt("png is missing IHDR"))
+    };
+    if header.color_type == ColorType::Indexed && palette.is_empty() {
+        return Err(ImageError::Corrupt("indexed png is missing PLTE"));
+    }
+
+    let raw = zlib_decompress(&compressed, header.data_size())?;
+    let mut image = Image::new(header.width as i32, header.height as i32);
+
+    if header.interlaced {
+        let mut position = 0;
+        for &(x_start, y_start, x_step, y_step) in ADAM7.iter() {
+            if x_start >= header.width || y_start >= header.height {
+                continue;
+            }
+            let pass_width = (header.width - x_start).div_ceil(x_step);
+            let pass_height = (header.height - y_start).div_ceil(y_step);
+            let pass_size = header.pass_size(pass_width, pass_height);
+            if position + pass_size > raw.len() {
+                return Err(ImageError::Corrupt("png image data too short"));
+            }
+            let rows = unfilter(&header, &raw[position..position + pass_size], pass_width, pass_height)?;
+            position += pass_size;
+            for (pass_y, row) in rows.iter().enumerate() {
+                for pass_x in 0..pass_width {
+                    let x = x_start + pass_x * x_step;
+                    let y = y_start + pass_y * y_step;
+                    image.pixels[y * header.width + x] = read_pixel(&header, row, pass_x, &palette, &transparency);
+                }
+            }
+        }
+    }
+    else {
+        let size = header.data_size();
+        if size > raw.len() {
+            return Err(ImageError::Corrupt("png image data too short"));
+        }
+        let rows = unfilter(&header, &raw[..size], header.width, header.height)?;
+        for (y, row) in rows.iter().enumerate() {
+            for x in 0..header.width {
+                image.pixels[y * header.width + x] = read_pixel(&header, row, x, &palette, &transparency);
+            }
+        }
+    }
+
+    Ok(image)
+}
+
+fn read_header(chunk: &[u8]) -> Result<Header, ImageError> {
+    if chunk.len() != 13 {
+        return Err(ImageError::Corrupt("bad IHDR length"));
+    }
+    let header = Header {
+        width: read_u32_be(chunk, 0) as usize,
+        height: read_u32_be(chunk, 4) as usize,
+        bit_depth: chunk[8],
+        color_type: ColorType::from_u8(chunk[9])?,
+        interlaced: match chunk[12] {
+            0 => false,
+            1 => true,
+            _ => return Err(ImageError::Unsupported("png interlace method"))
+        }
+    };
+    if header.width == 0 || header.height == 0 {
+        return Err(ImageError::Corrupt("png has zero size"));
+    }
+    if header.width > super::MAX_DIMENSION || header.height > super::MAX_DIMENSION {
+        return Err(ImageError::Unsupported("png is too large"));
+    }
+    let valid_depth = match header.color_type {
+        ColorType::Gray => [1, 2, 4, 8, 16].contains(&header.bit_depth),
+        ColorType::Indexed => [1, 2, 4, 8].contains(&header.bit_depth),
+        _ => [8, 16].contains(&header.bit_depth)
+    };
+    if !valid_depth {
+        return Err(ImageError::Corrupt("invalid png bit depth"));
+    }
+    if chunk[10] != 0 || chunk[11] != 0 {
+        return Err(ImageError::Unsupported("png compression or filter method"));
+    }
+    Ok(header)
+}
+
+// reverses the per scanline filters, returns the rows without the filter byte
+fn unfilter(header: &Header, data: &[u8], width: usize, height: usize) -> Result<Vec<Vec<u8>>, ImageError> {
+    let row_bytes = header.row_bytes(width);
+    let stride = header.filter_stride();
+    let mut rows: Vec<Vec<u8>> = Vec::with_capacity(height);
+    let mut previous = vec![0u8; row_bytes];
+    for y in 0..height {
+        let start = y * (row_bytes + 1);
+        let filter = data[start];
+        let mut row = data[start + 1..start + 1 + row_bytes].to_vec();
+        for i in 0..row_bytes {
+            let left = if i >= stride { row[i - stride] } else { 0 };
+            let up = previous[i];
+            let up_left = if i >= stride { previous[i - stride] } else { 0 };
+            row[i] = row[i].wrapping_add(match filter {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                _ => return Err(ImageError::Corrupt("invalid png filter type"))
+            });
+        }
+        previous.copy_from_slice(&row);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+    if pa <= pb && pa <= pc { a } else if pb <= pc { b } else { c }
+}
+
+// returns the raw sample value, 16 bit samples come back as 16 bit values
+fn read_sample(row: &[u8], bit_depth: u8, index: usize) -> u16 {
+    match bit_depth {
+        16 => ((row[index * 2] as u16) << 8) | row[index * 2 + 1] as u16,
+        8 => row[index] as u16,
+        _ => {
+            let bit = index * bit_depth as usize;
+            let shift = 8 - bit_depth as usize - (bit % 8);
+            let mask = (1u16 << bit_depth) - 1;
+            ((row[bit / 8] as u16) >> shift) & mask
+        }
+    }
+}
+
+// scales a sample of any bit depth into 0..=255
+fn to_u8(sample: u16, bit_depth: u8) -> u8 {
+    match bit_depth {
+        16 => (sample >> 8) as u8,
+        8 => sample as u8,
+        _ => (sample as u32 * 255 / ((1u32 << bit_depth) - 1)) as u8
+    }
+}
+
+fn read_pixel(header: &Header, row: &[u8], x: usize, palette: &[[u8; 3]], transparency: &[u8]) -> Pixel {
+    let depth = header.bit_depth;
+    let channels = header.color_type.channels();
+    let sample = |channel: usize| read_sample(row, depth, x * channels + channel);
+    match header.color_type {
+        ColorType::Gray => {
+            let gray = sample(0);
+            let value = to_u8(gray, depth);
+            let alpha = if transparency.len() >= 2 && read_u16_be(transparency, 0) == gray { 0 } else { 255 };
+            Pixel::from_rgba(value, value, value, alpha)
+        },
+        ColorType::Rgb => {
+            let (r, g, b) = (sample(0), sample(1), sample(2));
+            let alpha = if transparency.len() >= 6 &&
+                read_u16_be(transparency, 0) == r &&
+                read_u16_be(transparency, 2) == g &&
+                read_u16_be(transparency, 4) == b { 0 } else { 255 };
+            Pixel::from_rgba(to_u8(r, depth), to_u8(g, depth), to_u8(b, depth), alpha)
+        },
+        ColorType::Indexed => {
+            let index = sample(0) as usize;
+            let color = match palette.get(index) {
+                Some(c) => *c,
+                None => [0, 0, 0]
+            };
+            let alpha = match transparency.get(index) {
+                Some(a) => *a,
+                None => 255
+            };
+            Pixel::from_rgba(color[0], color[1], color[2], alpha)
+        },
+        ColorType::GrayAlpha => {
+            let value = to_u8(sample(0), depth);
+            Pixel::from_rgba(value, value, value, to_u8(sample(1), depth))
+        },
+        ColorType::Rgba => {
+            Pixel::from_rgba(
+                to_u8(sample(0), depth),
+                to_u8(sample(1), depth),
+                to_u8(sample(2), depth),
+                to_u8(sample(3), depth))
+        }
+    }
+}
+
+fn read_u32_be(data: &[u8], offset: usize) -> u32 {
+    ((data[offset] as u32) << 24) |
+    ((data[offset + 1] as u32) << 16) |
+    ((data[offset + 2] as u32) << 8) |
+    (data[offset + 3] as u32)
+}
+
+fn read_u16_be(data: &[u8], offset: usize) -> u16 {
+    ((data[offset] as u16) << 8) | data[offset + 1] as u16
+}
+
+pub(super) fn crc32(data: &[u8]) -> u32 {
+    let mut crc: u32 = 0xFFFF_FFFF;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (!(crc & 1)).wrapping_add(1);
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
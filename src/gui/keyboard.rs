@@ -1,58 +1,309 @@
+use crate::gui::Rect;
+use crate::gui::focus::send_to_focus;
+use crate::gui::widget::Event;
+use crate::gui::focus::focus_next;
+use crate::gui::keymap::KeyStroke;
+use crate::gui::keymap::key_stroke;
+use crate::gui::menu::menu_key;
+use crate::gui::menubar::menu_bar_key;
+use crate::gui::tooltip::hide_tooltip;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
 pub struct KeyboardModifiers {
     pub ctrl: bool,
     pub alt: bool,
-    pub shift: bool
+    pub shift: bool,
+    // the windows key, command on a mac
+    pub super_key: bool
 }
 
-pub enum KeyboardInput {
+/*
+    a key going down or up. every backend turns its native key messages
+    into these so nothing past the platform layer knows about virtual key
+    codes or scan codes.
+
+    code is the physical key, named after what's on it on a us keyboard,
+    so it stays the same key whatever the layout. key is what the key means
+    with the current layout and num lock: the z key on a german layout has
+    code Letter('Y') and key Char('Z'), numpad 1 is Char('1') or End. text
+    typed with keys doesn't come from here but as TextInput, a key and the
+    text it makes aren't the same thing once dead keys and IMEs are around.
+*/
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct KeyEvent {
+    pub code: KeyCode,
+    pub key: Key,
+    pub modifiers: KeyboardModifiers,
+    pub state: KeyState,
+    // held down long enough to auto repeat
+    pub repeat: bool
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum KeyState {
+    Pressed,
+    Released
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum KeyCode {
+    Letter(char),
+    Digit(u8),
+    F(u8),
+    Numpad(u8),
+    NumpadAdd,
+    NumpadSubtract,
+    NumpadMultiply,
+    NumpadDivide,
+    NumpadDecimal,
+    NumpadEnter,
+    Escape,
+    Enter,
+    Tab,
+    Space,
+    Backspace,
+    Delete,
+    Insert,
+    ArrowLeft,
+    ArrowUp,
+    ArrowRight,
+    ArrowDown,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    ShiftLeft,
+    ShiftRight,
+    ControlLeft,
+    ControlRight,
+    AltLeft,
+    AltRight,
+    SuperLeft,
+    SuperRight,
+    CapsLock,
+    NumLock,
+    ScrollLock,
+    PrintScreen,
+    Pause,
+    ContextMenu,
+    Minus,
+    Equal,
+    BracketLeft,
+    BracketRight,
+    Backslash,
+    Semicolon,
+    Quote,
+    Backquote,
+    Comma,
+    Period,
+    Slash,
+    // the extra key next to left shift on european keyboards
+    IntlBackslash,
+    Unknown(u32)
+}
+
+// keys are named by what they do with the current layout, letters always
+// upper case
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Key {
     Char(char),
+    F(u8),
     Escape,
+    Enter,
+    Tab,
+    Space,
     Back,
     Delete,
+    Insert,
+    Left,
+    Up,
+    Right,
+    Down,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Shift,
     Ctrl,
-    Ctrl_A,
-    Ctrl_C,
-    Ctrl_V(Option<String>),
-    Ctrl_X,
-    Ctrl_Y,
-    Ctrl_Z,
     Alt,
-    Shift,
+    Super,
     CapsLock,
-    ArrowLeft(KeyboardModifiers),
-    ArrowUp(KeyboardModifiers),
-    ArrowRight(KeyboardModifiers),
-    ArrowDown(KeyboardModifiers),
-    Home(KeyboardModifiers),
-    End(KeyboardModifiers)
+    NumLock,
+    ScrollLock,
+    PrintScreen,
+    Pause,
+    ContextMenu,
+    Unidentified
+}
+
+impl Key {
+    pub fn is_modifier(&self) -> bool {
+        matches!(self, Key::Shift | Key::Ctrl | Key::Alt | Key::Super)
+    }
+
+    pub fn from_name(name: &str) -> Option<Key> {
+        let lower = name.to_lowercase();
+        let key = match lower.as_str() {
+            "esc" | "escape" => Key::Escape,
+            "enter" | "return" => Key::Enter,
+            "tab" => Key::Tab,
+            "space" => Key::Space,
+            "back" | "backspace" => Key::Back,
+            "del" | "delete" => Key::Delete,
+            "ins" | "insert" => Key::Insert,
+            "left" => Key::Left,
+            "up" => Key::Up,
+            "right" => Key::Right,
+            "down" => Key::Down,
+            "home" => Key::Home,
+            "end" => Key::End,
+            "pgup" | "pageup" => Key::PageUp,
+            "pgdn" | "pagedown" => Key::PageDown,
+            "pause" => Key::Pause,
+            "printscreen" => Key::PrintScreen,
+            "menu" | "contextmenu" => Key::ContextMenu,
+            "plus" => Key::Char('+'),
+            _ => {
+                let mut chars = lower.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Key::Char(c.to_uppercase().next().unwrap_or(c)),
+                    (Some('f'), Some(_)) => {
+                        let n: u8 = lower[1..].parse().ok()?;
+                        if !(1..=24).contains(&n) {
+                            return None;
+                        }
+                        Key::F(n)
+                    },
+                    _ => return None
+                }
+            }
+        };
+        Some(key)
+    }
+}
+
+impl std::fmt::Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Key::Char(c) => write!(f, "{}", c),
+            Key::F(n) => write!(f, "F{}", n),
+            other => write!(f, "{:?}", other)
+        }
+    }
+}
+
+// what the edit commands ask of the focused control
+pub enum EditCommand {
+    SelectAll,
+    Copy,
+    Cut,
+    Paste(Option<String>),
+    Undo,
+    Redo
+}
+
+/*
+    text from the platform's input method. dead keys and IMEs send the text
+    being composed as a preedit, each one replaces the last and it's shown
+    underlined at the cursor without being part of the text. a commit
+    inserts the finished text and ends the composition, PreeditEnd drops it
+    when the composition is cancelled.
+*/
+pub enum TextInput {
+    Preedit { text: String, cursor: usize },
+    Commit(String),
+    PreeditEnd
 }
 
-pub fn keyboard_keydown(keytype: KeyboardInput) {
-    let textboxes = unsafe { &mut crate::APPLICATION_STATE.textboxes };
+pub fn text_input(event: TextInput) {
     crate::update_window();
-    for textbox in textboxes {
-        if textbox.active {
-            match keytype {
-                KeyboardInput::Char(c) => textbox.insert_char(c),
-                KeyboardInput::Escape => { },
-                KeyboardInput::Back => textbox.delete_back(),
-                KeyboardInput::Delete => textbox.delete(),
-                KeyboardInput::Ctrl_A => textbox.select_all(),
-                KeyboardInput::Ctrl_C => textbox.copy_to_clipboard(),
-                KeyboardInput::Ctrl_V(text) => textbox.insert_text(text),
-                KeyboardInput::Ctrl_X => textbox.cut_to_clipboard(),
-                KeyboardInput::ArrowLeft(modifiers) => textbox.arrow_left(modifiers),
-                KeyboardInput::ArrowUp(_modifiers) => { },
-                KeyboardInput::ArrowRight(modifiers) => textbox.arrow_right(modifiers),
-                KeyboardInput::ArrowDown(_modifiers) => { },
-                KeyboardInput::Home(modifiers) => textbox.home(modifiers),
-                KeyboardInput::End(modifiers) => textbox.end(modifiers),
-                _ => { }
+    send_to_focus(&Event::Text(event));
+}
+
+// where the input method should put its candidate window, the caret of the
+// focused text box in window coordinates. None when nothing focused takes
+// text, the input method's own window should handle it then
+pub fn text_input_caret_rect() -> Option<Rect> {
+    let state = unsafe { &crate::APPLICATION_STATE };
+    state.widgets.get(state.focus.focused.as_ref()?)?.widget.caret_rect()
+}
+
+/*
+    windows sends chars outside the basic multilingual plane as two
+    WM_CHAR messages, one per utf-16 surrogate. this holds on to the high
+    surrogate until the low one arrives.
+*/
+#[derive(Default)]
+pub struct Utf16Decoder {
+    high_surrogate: Option<u16>
+}
+
+impl Utf16Decoder {
+    pub const fn new() -> Utf16Decoder {
+        Utf16Decoder { high_surrogate: None }
+    }
+
+    // the char finished by this unit, None while waiting for the low
+    // surrogate or when the unit can't be decoded
+    pub fn push(&mut self, unit: u16) -> Option<char> {
+        match unit {
+            0xD800..=0xDBFF => {
+                self.high_surrogate = Some(unit);
+                None
+            },
+            0xDC00..=0xDFFF => {
+                let high = self.high_surrogate.take()?;
+                std::char::decode_utf16([high, unit].iter().cloned()).next()?.ok()
+            },
+            _ => {
+                self.high_surrogate = None;
+                std::char::from_u32(unit as u32)
             }
-            break;
         }
     }
 }
 
+// keys go through the keymap first and then to whichever control has
+// focus, tab moves the focus itself. an open menu gets them before all of
+// that and the menu bar's f10 and alt keys after it. true when something
+// used the key.
+pub fn keyboard_event(event: KeyEvent) -> bool {
+    hide_tooltip();
+    if menu_key(event) || menu_bar_key(event) {
+        return true;
+    }
+    if event.state == KeyState::Pressed && key_stroke(KeyStroke { key: event.key, modifiers: event.modifiers }) {
+        return true;
+    }
+    if event.state == KeyState::Pressed && event.key == Key::Tab {
+        focus_next(event.modifiers.shift);
+        return true;
+    }
+    send_to_focus(&Event::Key(event))
+}
+
+// the edit commands, for the focused text box or text area
+pub fn edit_command(command: EditCommand) {
+    send_to_focus(&Event::Edit(command));
+    crate::update_window();
+}
+
+
+#[cfg(test)]
+mod keyboard_tests {
+    use super::*;
+
+    #[test]
+    fn test_utf16_decoder() {
+        let mut decoder = Utf16Decoder::new();
+        assert_eq!(decoder.push('a' as u16), Some('a'));
+        // U+1F600 is D83D DE00
+        assert_eq!(decoder.push(0xD83D), None);
+        assert_eq!(decoder.push(0xDE00), Some('\u{1F600}'));
+        // a lone low surrogate or a high one followed by something else
+        assert_eq!(decoder.push(0xDE00), None);
+        assert_eq!(decoder.push(0xD83D), None);
+        assert_eq!(decoder.push('b' as u16), Some('b'));
+        assert_eq!(decoder.push(0xDE00), None);
+    }
+}
@@ -0,0 +1,346 @@
+use std::fmt;
+use std::path::PathBuf;
+use crate::gui::keyboard::KeyboardModifiers;
+use crate::gui::keyboard::Key;
+use crate::gui::command::run_command;
+use crate::gui::config_path;
+
+/*
+    key bindings. a binding maps a chord, one or more key strokes pressed
+    one after another like ctrl+k ctrl+b, to the name of a command. the
+    defaults below are read first and then the user's file on top of them,
+    both in the same format, one binding per line:
+
+        # comments start with a hash
+        ctrl+k ctrl+b = view.toggle_line_numbers
+        alt+shift+f12 = edit.select_all
+        ctrl+z =
+
+    a binding without a command removes whatever was bound to the chord.
+    modifiers are ctrl, alt, shift and super (also win, cmd or meta), keys
+    are single chars, f1 to f24 or the names in Key::from_name. case
+    doesn't matter anywhere.
+
+    while a chord is half way typed the keymap swallows keys until it
+    either completes or can't match anything anymore.
+*/
+
+pub const DEFAULT_KEYMAP: &str = "\
+ctrl+a = edit.select_all
+ctrl+c = edit.copy
+ctrl+insert = edit.copy
+ctrl+v = edit.paste
+shift+insert = edit.paste
+ctrl+x = edit.cut
+ctrl+z = edit.undo
+ctrl+y = edit.redo
+ctrl+shift+z = edit.redo
+ctrl+k ctrl+l = view.toggle_line_numbers
+alt+up = files.parent
+ctrl+shift+p = view.command_palette
+";
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct KeyStroke {
+    pub key: Key,
+    pub modifiers: KeyboardModifiers
+}
+
+impl KeyStroke {
+    // ctrl+shift+k, the key comes last. ctrl++ is ctrl and the plus key.
+    pub fn parse(text: &str) -> Option<KeyStroke> {
+        let (mods, key) = if let Some(mods) = text.strip_suffix("++") {
+            (mods, "+")
+        } else {
+            match text.rfind('+') {
+                Some(i) => (&text[..i], &text[i + 1..]),
+                None => ("", text)
+            }
+        };
+        let mut modifiers = KeyboardModifiers::default();
+        for name in mods.split('+').filter(|m| !m.is_empty()) {
+            match name.to_lowercase().as_str() {
+                "ctrl" | "control" => modifiers.ctrl = true,
+                "alt" => modifiers.alt = true,
+                "shift" => modifiers.shift = true,
+                "super" | "win" | "cmd" | "meta" => modifiers.super_key = true,
+                _ => return None
+            }
+        }
+        Some(KeyStroke { key: Key::from_name(key)?, modifiers })
+    }
+}
+
+impl fmt::Display for KeyStroke {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.modifiers.ctrl { write!(f, "Ctrl+")?; }
+        if self.modifiers.alt { write!(f, "Alt+")?; }
+        if self.modifiers.shift { write!(f, "Shift+")?; }
+        if self.modifiers.super_key { write!(f, "Super+")?; }
+        write!(f, "{}", self.key)
+    }
+}
+
+pub fn parse_chord(text: &str) -> Option<Vec<KeyStroke>> {
+    let chord: Option<Vec<KeyStroke>> = text.split_whitespace().map(KeyStroke::parse).collect();
+    chord.filter(|c| !c.is_empty())
+}
+
+// Ctrl+K Ctrl+B, for showing next to menu items
+pub fn chord_to_string(chord: &[KeyStroke]) -> String {
+    let strokes: Vec<String> = chord.iter().map(|s| s.to_string()).collect();
+    strokes.join(" ")
+}
+
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub chord: Vec<KeyStroke>,
+    // None unbinds the chord
+    pub command: Option<String>,
+    // from the user's file rather than the defaults
+    pub user: bool
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Conflict {
+    // the same chord bound twice in one file, the later binding wins
+    Duplicate { chord: Vec<KeyStroke>, first: String, second: String },
+    // a chord that's the start of a longer one never runs, the keymap
+    // waits for the rest of the longer chord instead
+    Prefix { chord: Vec<KeyStroke>, command: String, longer: Vec<KeyStroke> }
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Conflict::Duplicate { chord, first, second } =>
+                write!(f, "{} is bound to both {} and {}, using {}", chord_to_string(chord), first, second, second),
+            Conflict::Prefix { chord, command, longer } =>
+                write!(f, "{} ({}) never runs because it starts {}", chord_to_string(chord), command, chord_to_string(longer))
+        }
+    }
+}
+
+pub enum KeyPress {
+    Command(String),
+    // the first strokes of a chord, waiting for more
+    Pending,
+    // a chord was being typed and this key doesn't continue it
+    Cancelled,
+    Unbound
+}
+
+pub struct Keymap {
+    // in the order they were read, later bindings of a chord win
+    bindings: Vec<Binding>,
+    pending: Vec<KeyStroke>
+}
+
+impl Keymap {
+    pub const fn new() -> Keymap {
+        Keymap { bindings: Vec::new(), pending: Vec::new() }
+    }
+
+    // reads bindings in the format described at the top, returns a message
+    // for each line that couldn't be read
+    pub fn load(&mut self, text: &str, user: bool) -> Vec<String> {
+        let mut errors = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (keys, command) = match line.split_once('=') {
+                Some(parts) => parts,
+                None => {
+                    errors.push(format!("line {}: expected keys = command", number + 1));
+                    continue;
+                }
+            };
+            let chord = match parse_chord(keys) {
+                Some(chord) => chord,
+                None => {
+                    errors.push(format!("line {}: can't read the keys '{}'", number + 1, keys.trim()));
+                    continue;
+                }
+            };
+            let command = command.trim();
+            let command = if command.is_empty() { None } else { Some(command.to_string()) };
+            self.bindings.push(Binding { chord, command, user });
+        }
+        errors
+    }
+
+    // the binding in effect for every chord that's bound to something
+    pub fn bindings(&self) -> Vec<&Binding> {
+        let mut effective: Vec<&Binding> = Vec::new();
+        for binding in self.bindings.iter().rev() {
+            if !effective.iter().any(|b| b.chord == binding.chord) {
+                effective.push(binding);
+            }
+        }
+        effective.reverse();
+        effective.retain(|b| b.command.is_some());
+        effective
+    }
+
+    pub fn chord_for(&self, command: &str) -> Option<Vec<KeyStroke>> {
+        self.bindings().into_iter()
+            .find(|b| b.command.as_deref() == Some(command))
+            .map(|b| b.chord.clone())
+    }
+
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        let mut conflicts = Vec::new();
+        for (i, first) in self.bindings.iter().enumerate() {
+            let second = self.bindings[i + 1..].iter()
+                .find(|b| b.chord == first.chord && b.user == first.user)
+                .and_then(|b| b.command.as_ref());
+            if let (Some(a), Some(b)) = (&first.command, second) {
+                if a != b {
+                    conflicts.push(Conflict::Duplicate { chord: first.chord.clone(), first: a.clone(), second: b.clone() });
+                }
+            }
+        }
+        let effective = self.bindings();
+        for binding in &effective {
+            let longer = effective.iter()
+                .find(|b| b.chord.len() > binding.chord.len() && b.chord.starts_with(&binding.chord));
+            if let (Some(longer), Some(command)) = (longer, &binding.command) {
+                conflicts.push(Conflict::Prefix { chord: binding.chord.clone(), command: command.clone(), longer: longer.chord.clone() });
+            }
+        }
+        conflicts
+    }
+
+    // feeds one key stroke in, a command comes out once a chord is complete
+    pub fn press(&mut self, stroke: KeyStroke) -> KeyPress {
+        let was_pending = !self.pending.is_empty();
+        self.pending.push(stroke);
+        let effective = self.bindings();
+        let continues = effective.iter()
+            .any(|b| b.chord.len() > self.pending.len() && b.chord.starts_with(&self.pending));
+        if continues {
+            return KeyPress::Pending;
+        }
+        let command = effective.iter()
+            .find(|b| b.chord == self.pending)
+            .and_then(|b| b.command.clone());
+        self.pending.clear();
+        match (command, was_pending) {
+            (Some(command), _) => KeyPress::Command(command),
+            (None, true) => KeyPress::Cancelled,
+            (None, false) => KeyPress::Unbound
+        }
+    }
+}
+
+pub fn user_keymap_path() -> Option<PathBuf> {
+    config_path("keys.conf")
+}
+
+// the defaults and then the user's file. problems are printed and the rest
+// of the keymap still works, a typo shouldn't stop the application.
+pub fn load_keymap() {
+    let state = unsafe { &mut crate::APPLICATION_STATE };
+    // loading again starts over, for picking up changes to the file
+    state.keymap = Keymap::new();
+    let mut problems = state.keymap.load(DEFAULT_KEYMAP, false);
+    if let Some(path) = user_keymap_path() {
+        if let Ok(text) = std::fs::read_to_string(&path) {
+            let errors = state.keymap.load(&text, true);
+            problems.extend(errors.into_iter().map(|e| format!("{}: {}", path.display(), e)));
+        }
+    }
+    problems.extend(state.keymap.conflicts().iter().map(|c| c.to_string()));
+    for binding in state.keymap.bindings() {
+        if let Some(command) = &binding.command {
+            if state.commands.get(command).is_none() {
+                problems.push(format!("{} is bound to {} which isn't a command", chord_to_string(&binding.chord), command));
+            }
+        }
+    }
+    for problem in problems {
+        eprintln!("keys: {}", problem);
+    }
+}
+
+// every key press goes here before anything else sees it, true when the
+// keymap used the key
+pub fn key_stroke(stroke: KeyStroke) -> bool {
+    // shift going down on its own doesn't end a chord
+    if stroke.key.is_modifier() || stroke.key == Key::Unidentified {
+        return false;
+    }
+    let keymap = unsafe { &mut crate::APPLICATION_STATE.keymap };
+    match keymap.press(stroke) {
+        KeyPress::Command(command) => {
+            run_command(&command);
+            true
+        },
+        KeyPress::Pending | KeyPress::Cancelled => true,
+        KeyPress::Unbound => false
+    }
+}
+
+#[cfg(test)]
+mod keymap_tests {
+    use super::*;
+
+    fn stroke(text: &str) -> KeyStroke {
+        KeyStroke::parse(text).unwrap()
+    }
+
+    fn command(press: KeyPress) -> Option<String> {
+        match press {
+            KeyPress::Command(command) => Some(command),
+            _ => None
+        }
+    }
+
+    #[test]
+    fn test_parse_key_strokes() {
+        let s = stroke("Ctrl+Shift+k");
+        assert_eq!(s.key, Key::Char('K'));
+        assert!(s.modifiers.ctrl && s.modifiers.shift && !s.modifiers.alt && !s.modifiers.super_key);
+        assert_eq!(stroke("win+alt+F12").to_string(), "Alt+Super+F12");
+        assert_eq!(stroke("ctrl++").key, Key::Char('+'));
+        assert_eq!(stroke("pgdn").key, Key::PageDown);
+        assert!(KeyStroke::parse("hyper+a").is_none());
+        assert!(KeyStroke::parse("ctrl+f25").is_none());
+        assert_eq!(chord_to_string(&parse_chord("ctrl+k  ctrl+b").unwrap()), "Ctrl+K Ctrl+B");
+    }
+
+    #[test]
+    fn test_chords_and_overrides() {
+        let mut keymap = Keymap::new();
+        assert!(keymap.load(DEFAULT_KEYMAP, false).is_empty());
+        let errors = keymap.load("# mine\nctrl+z = edit.redo\nctrl+y =\nctrl+k ctrl+b = view.sidebar\nctrl+q\nctrl+bogus = x\n", true);
+        assert_eq!(errors.len(), 2);
+
+        assert_eq!(command(keymap.press(stroke("ctrl+z"))).as_deref(), Some("edit.redo"));
+        assert!(matches!(keymap.press(stroke("ctrl+y")), KeyPress::Unbound));
+        assert!(matches!(keymap.press(stroke("ctrl+k")), KeyPress::Pending));
+        assert_eq!(command(keymap.press(stroke("ctrl+b"))).as_deref(), Some("view.sidebar"));
+        assert!(matches!(keymap.press(stroke("ctrl+k")), KeyPress::Pending));
+        assert!(matches!(keymap.press(stroke("x")), KeyPress::Cancelled));
+        assert_eq!(command(keymap.press(stroke("ctrl+c"))).as_deref(), Some("edit.copy"));
+        assert_eq!(keymap.chord_for("view.toggle_line_numbers"), parse_chord("ctrl+k ctrl+l"));
+        // overriding a default isn't a conflict
+        assert!(keymap.conflicts().is_empty());
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let mut keymap = Keymap::new();
+        keymap.load("ctrl+k = a\nctrl+k ctrl+b = b\nf5 = c\nf5 = d\n", true);
+        let conflicts = keymap.conflicts();
+        assert_eq!(conflicts.len(), 2);
+        assert!(conflicts.contains(&Conflict::Duplicate { chord: parse_chord("f5").unwrap(), first: "c".to_string(), second: "d".to_string() }));
+        assert!(conflicts.contains(&Conflict::Prefix {
+            chord: parse_chord("ctrl+k").unwrap(),
+            command: "a".to_string(),
+            longer: parse_chord("ctrl+k ctrl+b").unwrap()
+        }));
+    }
+}
@@ -0,0 +1,353 @@
+use crate::gui::Bounds;
+use crate::gui::BoundsField;
+use crate::gui::Rect;
+use crate::gui::scale_i32;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Direction {
+    Row,
+    Column
+}
+
+// how a node places its children
+pub enum Arrange {
+    // every child on top of the node, each by its own Place
+    Overlay,
+    // one after another with the spacing between them in logical pixels
+    Stack(Direction, i32)
+}
+
+// distances in logical pixels from the parent's edges. with both edges of an
+// axis the node stretches between them, with one it keeps its size and
+// sticks to that edge, with neither it is centered.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Anchors {
+    pub left: Option<i32>,
+    pub top: Option<i32>,
+    pub right: Option<i32>,
+    pub bottom: Option<i32>
+}
+
+// where a node sits inside an overlay, a stack ignores it
+pub enum Place {
+    Fill,
+    Bounds(Bounds),
+    Anchors(Anchors)
+}
+
+/*
+    a tree of boxes that turns the window size into a rect for every named
+    node. stacks share their length between the children like flexbox, each
+    child starts at its width or height and the space left over is handed out
+    by grow, or taken back by shrink when there isn't enough. the other axis
+    stretches unless the child has a size for it. sizes are INT logical
+    pixels or FLOAT fractions of the parent, like Bounds.
+*/
+pub struct LayoutNode {
+    // the name the rect is looked up by, empty for containers nobody asks
+    // about
+    pub name: &'static str,
+    pub place: Place,
+    pub arrange: Arrange,
+    // logical pixels on every side, between the node and its children
+    pub padding: i32,
+    pub width: Option<BoundsField>,
+    pub height: Option<BoundsField>,
+    // logical pixels, the minimum wins when they disagree
+    pub min_width: i32,
+    pub min_height: i32,
+    pub max_width: Option<i32>,
+    pub max_height: Option<i32>,
+    pub grow: f32,
+    pub shrink: f32,
+    pub children: Vec<LayoutNode>
+}
+
+impl LayoutNode {
+    pub fn new(name: &'static str) -> LayoutNode {
+        LayoutNode {
+            name,
+            place: Place::Fill,
+            arrange: Arrange::Overlay,
+            padding: 0,
+            width: None,
+            height: None,
+            min_width: 0,
+            min_height: 0,
+            max_width: None,
+            max_height: None,
+            grow: 0.0,
+            shrink: 1.0,
+            children: Vec::new()
+        }
+    }
+
+    pub fn row(name: &'static str, spacing: i32, children: Vec<LayoutNode>) -> LayoutNode {
+        LayoutNode { arrange: Arrange::Stack(Direction::Row, spacing), children, ..LayoutNode::new(name) }
+    }
+
+    pub fn column(name: &'static str, spacing: i32, children: Vec<LayoutNode>) -> LayoutNode {
+        LayoutNode { arrange: Arrange::Stack(Direction::Column, spacing), children, ..LayoutNode::new(name) }
+    }
+
+    pub fn overlay(name: &'static str, children: Vec<LayoutNode>) -> LayoutNode {
+        LayoutNode { children, ..LayoutNode::new(name) }
+    }
+
+    pub fn width(self, width: BoundsField) -> LayoutNode {
+        LayoutNode { width: Some(width), ..self }
+    }
+
+    pub fn height(self, height: BoundsField) -> LayoutNode {
+        LayoutNode { height: Some(height), ..self }
+    }
+
+    pub fn grow(self, grow: f32) -> LayoutNode {
+        LayoutNode { grow, ..self }
+    }
+
+    pub fn shrink(self, shrink: f32) -> LayoutNode {
+        LayoutNode { shrink, ..self }
+    }
+
+    pub fn padding(self, padding: i32) -> LayoutNode {
+        LayoutNode { padding, ..self }
+    }
+
+    pub fn place(self, place: Place) -> LayoutNode {
+        LayoutNode { place, ..self }
+    }
+
+    pub fn min_size(self, min_width: i32, min_height: i32) -> LayoutNode {
+        LayoutNode { min_width, min_height, ..self }
+    }
+
+    pub fn max_size(self, max_width: Option<i32>, max_height: Option<i32>) -> LayoutNode {
+        LayoutNode { max_width, max_height, ..self }
+    }
+
+    // the rects of every named node when this one fills rect
+    pub fn layout(&self, rect: Rect, scale: f32) -> Layout {
+        let mut layout = Layout { rects: Vec::new() };
+        self.layout_into(rect, scale, &mut layout);
+        layout
+    }
+
+    fn layout_into(&self, rect: Rect, scale: f32, layout: &mut Layout) {
+        if !self.name.is_empty() {
+            layout.rects.push((self.name, rect));
+        }
+        let padding = scale_i32(self.padding, scale);
+        let inner = Rect {
+            x: rect.x + padding,
+            y: rect.y + padding,
+            w: std::cmp::max(0, rect.w - padding * 2),
+            h: std::cmp::max(0, rect.h - padding * 2)
+        };
+        match self.arrange {
+            Arrange::Overlay => {
+                for child in &self.children {
+                    child.layout_into(child.placed_rect(inner, scale), scale, layout);
+                }
+            },
+            Arrange::Stack(direction, spacing) => self.layout_stack(inner, direction, scale_i32(spacing, scale), scale, layout)
+        }
+    }
+
+    fn clamp(&self, direction: Direction, size: i32, scale: f32) -> i32 {
+        let (min, max) = match direction {
+            Direction::Row => (self.min_width, self.max_width),
+            Direction::Column => (self.min_height, self.max_height)
+        };
+        let size = match max {
+            Some(max) => std::cmp::min(size, scale_i32(max, scale)),
+            None => size
+        };
+        std::cmp::max(size, scale_i32(min, scale))
+    }
+
+    // the size asked for along one axis of a parent that long
+    fn preferred(&self, direction: Direction, length: i32, scale: f32) -> Option<i32> {
+        let size = match direction {
+            Direction::Row => self.width,
+            Direction::Column => self.height
+        };
+        size.map(|size| match size {
+            BoundsField::INT(a) => scale_i32(a, scale),
+            BoundsField::FLOAT(a) => (a * length as f32) as i32
+        })
+    }
+
+    fn placed_rect(&self, inner: Rect, scale: f32) -> Rect {
+        let rect = match self.place {
+            Place::Fill => inner,
+            Place::Bounds(bounds) => {
+                let rect = bounds.get_rect(inner.w, inner.h, scale);
+                Rect { x: inner.x + rect.x, y: inner.y + rect.y, ..rect }
+            },
+            Place::Anchors(anchors) => {
+                let offset = |a: Option<i32>| a.map(|a| scale_i32(a, scale));
+                let (x, w) = self.anchor(Direction::Row, offset(anchors.left), offset(anchors.right), inner.x, inner.w, scale);
+                let (y, h) = self.anchor(Direction::Column, offset(anchors.top), offset(anchors.bottom), inner.y, inner.h, scale);
+                return Rect { x, y, w, h };
+            }
+        };
+        Rect { w: self.clamp(Direction::Row, rect.w, scale), h: self.clamp(Direction::Column, rect.h, scale), ..rect }
+    }
+
+    // where the node starts and how long it is along one axis
+    fn anchor(&self, direction: Direction, start: Option<i32>, end: Option<i32>, from: i32, length: i32, scale: f32) -> (i32, i32) {
+        let preferred = self.preferred(direction, length, scale);
+        let size = match (start, end) {
+            (Some(start), Some(end)) => length - start - end,
+            (Some(start), None) => preferred.unwrap_or(length - start),
+            (None, Some(end)) => preferred.unwrap_or(length - end),
+            (None, None) => preferred.unwrap_or(length)
+        };
+        let size = self.clamp(direction, size, scale);
+        match (start, end) {
+            (Some(start), _) => (from + start, size),
+            (None, Some(end)) => (from + length - end - size, size),
+            (None, None) => (from + (length - size) / 2, size)
+        }
+    }
+
+    fn layout_stack(&self, inner: Rect, direction: Direction, spacing: i32, scale: f32, layout: &mut Layout) {
+        let count = self.children.len();
+        if count == 0 {
+            return;
+        }
+        let (start, length, cross_start, cross_length) = match direction {
+            Direction::Row => (inner.x, inner.w, inner.y, inner.h),
+            Direction::Column => (inner.y, inner.h, inner.x, inner.w)
+        };
+        let available = std::cmp::max(0, length - spacing * (count as i32 - 1)) as f32;
+        let basis: Vec<f32> = self.children.iter()
+            .map(|child| child.clamp(direction, child.preferred(direction, length, scale).unwrap_or(0), scale) as f32)
+            .collect();
+        let sizes = self.flex(direction, &basis, available, scale);
+
+        // rounding the running total instead of each size leaves no gaps
+        let mut position = start as f32;
+        let cross_direction = match direction { Direction::Row => Direction::Column, Direction::Column => Direction::Row };
+        for (child, size) in self.children.iter().zip(sizes) {
+            let main = position.round() as i32;
+            let main_size = (position + size).round() as i32 - main;
+            position += size + spacing as f32;
+            let cross = child.preferred(cross_direction, cross_length, scale).unwrap_or(cross_length);
+            let cross = child.clamp(cross_direction, cross, scale);
+            let rect = match direction {
+                Direction::Row => Rect { x: main, y: cross_start, w: main_size, h: cross },
+                Direction::Column => Rect { x: cross_start, y: main, w: cross, h: main_size }
+            };
+            child.layout_into(rect, scale, layout);
+        }
+    }
+
+    // hands the free space out by grow, or takes the missing space back by
+    // shrink times the starting size. a child that hits its min or max keeps
+    // it and the others share what it couldn't take.
+    fn flex(&self, direction: Direction, basis: &[f32], available: f32, scale: f32) -> Vec<f32> {
+        let mut sizes = basis.to_vec();
+        let mut frozen = vec![false; sizes.len()];
+        loop {
+            let free = available - sizes.iter().sum::<f32>();
+            if free.abs() < 0.5 {
+                break;
+            }
+            let weight = |i: usize| if free > 0.0 { self.children[i].grow } else { self.children[i].shrink * basis[i] };
+            let total: f32 = (0..sizes.len()).filter(|i| !frozen[*i]).map(weight).sum();
+            if total <= 0.0 {
+                break;
+            }
+            let mut clamped = false;
+            for i in 0..sizes.len() {
+                if frozen[i] || weight(i) <= 0.0 {
+                    continue;
+                }
+                let wanted = sizes[i] + free * weight(i) / total;
+                let size = self.children[i].clamp(direction, wanted.round() as i32, scale) as f32;
+                if (size - wanted).abs() >= 0.5 {
+                    sizes[i] = size;
+                    frozen[i] = true;
+                    clamped = true;
+                }
+                else {
+                    sizes[i] = wanted;
+                }
+            }
+            if !clamped {
+                break;
+            }
+        }
+        sizes.iter().map(|s| s.max(0.0)).collect()
+    }
+}
+
+// the rects a layout came out with, by name
+pub struct Layout {
+    rects: Vec<(&'static str, Rect)>
+}
+
+impl Layout {
+    pub fn get(&self, name: &str) -> Option<Rect> {
+        self.rects.iter().find(|(n, _)| *n == name).map(|(_, rect)| *rect)
+    }
+}
+
+#[cfg(test)]
+mod layout_tests {
+    use super::*;
+
+    fn assert_rect(layout: &Layout, name: &str, x: i32, y: i32, w: i32, h: i32) {
+        let rect = layout.get(name).unwrap();
+        assert_eq!((rect.x, rect.y, rect.w, rect.h), (x, y, w, h), "{}", name);
+    }
+
+    #[test]
+    fn test_stack_grow_and_shrink() {
+        let root = LayoutNode::column("", 0, vec![
+            LayoutNode::new("toolbar").height(BoundsField::INT(30)),
+            LayoutNode::row("main", 10, vec![
+                LayoutNode::new("tree").width(BoundsField::INT(200)).min_size(150, 0),
+                LayoutNode::new("list").grow(1.0),
+                LayoutNode::new("preview").grow(1.0).max_size(Some(100), None)
+            ]).grow(1.0),
+            LayoutNode::new("status").height(BoundsField::INT(20))
+        ]);
+        let layout = root.layout(Rect { x: 0, y: 0, w: 1000, h: 600 }, 1.0);
+        assert_rect(&layout, "toolbar", 0, 0, 1000, 30);
+        assert_rect(&layout, "main", 0, 30, 1000, 550);
+        // the preview stops at its max and the list takes the rest
+        assert_rect(&layout, "tree", 0, 30, 200, 550);
+        assert_rect(&layout, "list", 210, 30, 680, 550);
+        assert_rect(&layout, "preview", 900, 30, 100, 550);
+        assert_rect(&layout, "status", 0, 580, 1000, 20);
+
+        // too narrow, the tree shrinks down to its minimum
+        let layout = root.layout(Rect { x: 0, y: 0, w: 100, h: 600 }, 1.0);
+        assert_rect(&layout, "tree", 0, 30, 150, 550);
+
+        // everything doubles at twice the scale
+        let layout = root.layout(Rect { x: 0, y: 0, w: 2000, h: 1200 }, 2.0);
+        assert_rect(&layout, "toolbar", 0, 0, 2000, 60);
+        assert_rect(&layout, "tree", 0, 60, 400, 1100);
+    }
+
+    #[test]
+    fn test_overlay_anchors_and_bounds() {
+        let root = LayoutNode::overlay("", vec![
+            LayoutNode::new("stretched").place(Place::Anchors(Anchors { left: Some(10), right: Some(10), top: Some(5), bottom: Some(5) })),
+            LayoutNode::new("corner").width(BoundsField::INT(50)).height(BoundsField::INT(20))
+                .place(Place::Anchors(Anchors { right: Some(10), bottom: Some(10), ..Anchors::default() })),
+            LayoutNode::new("centered").width(BoundsField::FLOAT(0.5)).height(BoundsField::INT(40))
+                .place(Place::Anchors(Anchors::default())),
+            LayoutNode::new("bounds").place(Place::Bounds(Bounds::variable_horizontal(0.5, 10, 0.5, 30)))
+        ]).padding(10);
+        let layout = root.layout(Rect { x: 100, y: 100, w: 420, h: 220 }, 1.0);
+        assert_rect(&layout, "stretched", 120, 115, 380, 190);
+        assert_rect(&layout, "corner", 450, 280, 50, 20);
+        assert_rect(&layout, "centered", 210, 190, 200, 40);
+        assert_rect(&layout, "bounds", 310, 120, 200, 30);
+    }
+}
@@ -0,0 +1,212 @@
+use crate::gui::is_point_in_rect;
+use crate::gui::style::BoxStyle;
+use crate::gui::style::ScrollbarStyle;
+use crate::gui::Rect;
+use crate::gui::Bounds;
+use crate::gui::control::Control;
+use crate::gui::scrollview::ScrollView;
+use crate::gui::text::line::truncate_text;
+use crate::gui::scale_i32;
+use crate::gui::get_scale_factor;
+use crate::gui::text::manager::FontManager;
+use crate::gui::widget::Widget;
+use crate::gui::widget::Event;
+use crate::gui::mouse::MouseButton;
+use crate::gui::keyboard::Key;
+use crate::gui::keyboard::KeyState;
+use crate::gui::draw::draw_listview;
+use crate::gui::PixelBuffer;
+use std::time::Instant;
+
+/*
+    a column of items in a scroll view, one of them selected. the file
+    list uses it. moving the selection with the keyboard keeps the selected
+    item in view.
+*/
+pub struct ListView {
+    pub items: Vec<String>,
+    pub selected: Option<usize>,
+    pub scroll: ScrollView,
+    // logical pixels
+    pub item_height: i32,
+    pub style: BoxStyle,
+    pub style_selected: BoxStyle
+}
+
+impl ListView {
+    pub fn new(bounds: Bounds, item_height: i32, style: BoxStyle, style_selected: BoxStyle) -> ListView {
+        ListView {
+            items: Vec::new(),
+            selected: None,
+            scroll: ScrollView::new(bounds, ScrollbarStyle::default()),
+            item_height,
+            style,
+            style_selected
+        }
+    }
+
+    pub fn update_bounds_rect(&mut self, width: i32, height: i32, scale: f32) {
+        self.scroll.update_bounds_rect(width, height, scale);
+        self.update_content_size();
+    }
+
+    pub fn set_bounds_rect(&mut self, rect: Rect) {
+        self.scroll.set_bounds_rect(rect);
+        self.update_content_size();
+    }
+
+    pub fn set_items(&mut self, items: Vec<String>) {
+        self.items = items;
+        self.selected = None;
+        self.scroll.scroll_to(0.0, 0.0);
+        self.update_content_size();
+    }
+
+    fn update_content_size(&mut self) {
+        let height = self.scaled_item_height() * self.items.len() as i32;
+        self.scroll.set_content_size(self.scroll.bounds_rect.w, height);
+    }
+
+    pub fn scaled_item_height(&self) -> i32 {
+        scale_i32(self.item_height, get_scale_factor())
+    }
+
+    // where an item is in content coordinates, before scrolling
+    pub fn item_rect(&self, index: usize) -> Rect {
+        let height = self.scaled_item_height();
+        Rect { x: 0, y: index as i32 * height, w: self.scroll.bounds_rect.w, h: height }
+    }
+
+    // the item under a point in window coordinates
+    pub fn item_at(&self, x: i32, y: i32) -> Option<usize> {
+        if !is_point_in_rect(x, y, self.scroll.bounds_rect) {
+            return None;
+        }
+        let content_y = y - self.scroll.bounds_rect.y + self.scroll.scroll_y.round() as i32;
+        let index = (content_y / self.scaled_item_height()) as usize;
+        if index < self.items.len() { Some(index) } else { None }
+    }
+
+    pub fn select(&mut self, index: usize) {
+        if self.items.is_empty() {
+            return;
+        }
+        let index = std::cmp::min(index, self.items.len() - 1);
+        self.selected = Some(index);
+        self.scroll.scroll_into_view(self.item_rect(index));
+    }
+
+    // moves the selection by some items, nothing selected yet starts at the
+    // top
+    pub fn move_selection(&mut self, by: i32) {
+        let index = match self.selected {
+            Some(i) => std::cmp::max(0, i as i32 + by) as usize,
+            None => 0
+        };
+        self.select(index);
+    }
+
+    pub fn items_per_page(&self) -> i32 {
+        std::cmp::max(1, self.scroll.bounds_rect.h / self.scaled_item_height())
+    }
+
+    // the whole name of the item under the mouse when it's drawn shortened
+    pub fn get_tooltip(&self, x: i32, y: i32) -> Option<String> {
+        let index = self.item_at(x, y)?;
+        let fonts = unsafe { &crate::APPLICATION_STATE.fonts };
+        let style = if self.selected == Some(index) { self.style_selected.scaled(get_scale_factor()) } else { self.get_scaled_style() };
+        let text: Vec<char> = self.items[index].chars().collect();
+        let box_width = self.scroll.bounds_rect.w - style.border_size.left - style.padding_size.left - style.border_size.right - style.padding_size.right;
+        truncate_text(&text, fonts, fonts.select_style(&style), style.font_size, box_width, style.text_overflow)
+            .map(|_| self.items[index].clone())
+    }
+
+    pub fn left_mouse_button_down(&mut self, mouse_x: i32, mouse_y: i32) {
+        if self.scroll.left_mouse_button_down(mouse_x, mouse_y) {
+            return;
+        }
+        if let Some(index) = self.item_at(mouse_x, mouse_y) {
+            self.select(index);
+        }
+    }
+}
+
+impl Widget for ListView {
+    fn bounds(&self) -> Rect { self.scroll.bounds_rect }
+    fn set_bounds_rect(&mut self, rect: Rect) { ListView::set_bounds_rect(self, rect) }
+
+    fn draw(&self, buffer: &mut PixelBuffer, fonts: &FontManager, _draw_cursor: bool) {
+        draw_listview(buffer, self, fonts);
+    }
+
+    fn event(&mut self, event: &Event) -> bool {
+        match event {
+            Event::MouseDown { button: MouseButton::Left, x, y, .. } => self.left_mouse_button_down(*x, *y),
+            // the end of a thumb drag
+            Event::MouseUp { button: MouseButton::Left, .. } if self.scroll.is_dragging() => self.scroll.left_mouse_button_up(),
+            // dragging a thumb and the scrollbars showing up when the mouse
+            // comes in
+            Event::MouseMove { x, y } => return self.scroll.mouse_move(*x, *y),
+            Event::Wheel { lines_x, lines_y, .. } => self.scroll.wheel(*lines_x, *lines_y),
+            Event::Key(key) if key.state == KeyState::Pressed => {
+                let page = self.items_per_page();
+                match key.key {
+                    Key::Up => self.move_selection(-1),
+                    Key::Down => self.move_selection(1),
+                    Key::PageUp => self.move_selection(-page),
+                    Key::PageDown => self.move_selection(page),
+                    Key::Home => self.select(0),
+                    Key::End => self.select(usize::MAX),
+                    _ => return false
+                }
+            },
+            _ => return false
+        }
+        true
+    }
+
+    fn tooltip(&self, x: i32, y: i32) -> Option<String> { self.get_tooltip(x, y) }
+    fn focus_style(&self) -> Option<BoxStyle> { Some(self.get_scaled_style()) }
+    // smooth scrolling
+    fn animate(&mut self, now: Instant) -> bool { self.scroll.step(now) }
+    // scrollbars hide a while after the last scroll
+    fn timer_tick(&mut self, now: Instant) -> bool { self.scroll.update_visibility(now) }
+}
+
+impl Control for ListView {
+    fn get_bounds(&self) -> Rect { self.scroll.bounds_rect }
+    fn get_hot(&self) -> bool { self.scroll.hot }
+    fn set_hot(&mut self, hit: bool) { self.scroll.hot = hit }
+
+    fn get_style(&self) -> &BoxStyle {
+        &self.style
+    }
+}
+
+#[cfg(test)]
+mod listview_tests {
+    use super::*;
+
+    #[test]
+    fn test_selection_stays_in_view() {
+        let mut list = ListView::new(Bounds::int(0, 0, 100, 100), 20, BoxStyle::default(), BoxStyle::default());
+        list.scroll.style.smooth = false;
+        list.update_bounds_rect(1000, 1000, 1.0);
+        list.set_items((0..50).map(|i| format!("file {}", i)).collect());
+        assert_eq!(list.items_per_page(), 5);
+
+        list.move_selection(1);
+        assert_eq!(list.selected, Some(0));
+        list.move_selection(7);
+        assert_eq!(list.selected, Some(7));
+        // item 7 ends at 160, so the view scrolled until its bottom shows
+        assert_eq!(list.scroll.scroll_y, 60.0);
+        assert_eq!(list.item_at(10, 5), Some(3));
+        list.select(usize::MAX);
+        assert_eq!(list.selected, Some(49));
+        assert_eq!(list.scroll.scroll_y, 900.0);
+        list.move_selection(-100);
+        assert_eq!(list.selected, Some(0));
+        assert_eq!(list.scroll.scroll_y, 0.0);
+    }
+}
@@ -0,0 +1,504 @@
+use crate::gui::is_point_in_rect;
+use crate::gui::style::BoxStyle;
+use crate::gui::color::Color;
+use crate::gui::color::DarkTheme;
+use crate::gui::Rect;
+use crate::gui::scale_i32;
+use crate::gui::get_scale_factor;
+use crate::gui::command::run_chosen_commands;
+use crate::gui::text::manager::FontManager;
+use crate::gui::widget::Widget;
+use crate::gui::widget::Event;
+use crate::gui::widget::WidgetPath;
+use crate::gui::widget::WidgetTree;
+use crate::gui::widget::WidgetNode;
+use crate::gui::menubar::MenuBar;
+use crate::gui::keyboard::Key;
+use crate::gui::keyboard::KeyEvent;
+use crate::gui::keyboard::KeyState;
+use crate::gui::draw::draw_menu;
+use crate::gui::draw::measure_string;
+use crate::gui::PixelBuffer;
+
+/*
+    popup menus, the context menus of the file list. an item runs a command
+    by name like a key binding does so the menu never knows what its items
+    do. the command runs once the event that chose it is done with, commands
+    change the widgets and the menu is one of them.
+
+    while it's open the menu covers the whole window. a click outside it
+    closes it without going on to whatever is under it, and it gets every
+    key before the keymap. submenus open to the right of their item, or to
+    the left when there's no room.
+*/
+
+// logical pixels, the columns of a row: the check mark, the title, the
+// accelerator and the submenu arrow
+pub const CHECK_WIDTH: i32 = 24;
+pub const ACCELERATOR_GAP: i32 = 32;
+pub const ARROW_WIDTH: i32 = 20;
+
+pub struct MenuItem {
+    pub title: &'static str,
+    pub command: Option<&'static str>,
+    // None when the item can't be checked at all
+    pub checked: Option<bool>,
+    pub enabled: bool,
+    // the key binding of the command, only shown
+    pub accelerator: Option<String>,
+    pub separator: bool,
+    pub submenu: Vec<MenuItem>
+}
+
+impl MenuItem {
+    pub fn command(title: &'static str, command: &'static str) -> MenuItem {
+        MenuItem { title, command: Some(command), checked: None, enabled: true, accelerator: None, separator: false, submenu: Vec::new() }
+    }
+
+    pub fn submenu(title: &'static str, items: Vec<MenuItem>) -> MenuItem {
+        MenuItem { title, command: None, checked: None, enabled: true, accelerator: None, separator: false, submenu: items }
+    }
+
+    pub fn separator() -> MenuItem {
+        MenuItem { title: "", command: None, checked: None, enabled: false, accelerator: None, separator: true, submenu: Vec::new() }
+    }
+
+    pub fn checked(mut self, checked: bool) -> MenuItem {
+        self.checked = Some(checked);
+        self
+    }
+
+    pub fn enabled(mut self, enabled: bool) -> MenuItem {
+        self.enabled = enabled;
+        self
+    }
+
+    pub fn accelerator(mut self, accelerator: Option<String>) -> MenuItem {
+        self.accelerator = accelerator;
+        self
+    }
+
+    // separators and disabled items are skipped by the keyboard and never hot
+    pub fn selectable(&self) -> bool {
+        !self.separator && self.enabled
+    }
+}
+
+// the menu and each open submenu, with where it is and its hot item
+struct Level {
+    rect: Rect,
+    hot: Option<usize>
+}
+
+pub struct Menu {
+    pub style: BoxStyle,
+    pub disabled_color: Color,
+    // logical pixels
+    pub item_height: i32,
+    pub separator_height: i32,
+    items: Vec<MenuItem>,
+    levels: Vec<Level>,
+    window: Rect,
+    chosen: Option<&'static str>
+}
+
+impl Menu {
+    pub fn new(style: BoxStyle) -> Menu {
+        Menu {
+            style,
+            disabled_color: DarkTheme::TEXT_DISABLED,
+            item_height: 24,
+            separator_height: 9,
+            items: Vec::new(),
+            levels: Vec::new(),
+            window: Rect::default(),
+            chosen: None
+        }
+    }
+
+    // opens at a point in window coordinates, window is the space it has to
+    // stay inside
+    pub fn open(&mut self, items: Vec<MenuItem>, x: i32, y: i32, window: Rect) {
+        self.items = items;
+        self.window = window;
+        self.chosen = None;
+        self.levels.clear();
+        let (w, h) = self.level_size(&self.items);
+        let rect = self.place(x, y, w, h, x);
+        self.levels.push(Level { rect, hot: None });
+        crate::update_window();
+    }
+
+    pub fn close(&mut self) {
+        self.levels.clear();
+        crate::update_window();
+    }
+
+    pub fn is_open(&self) -> bool {
+        !self.levels.is_empty()
+    }
+
+    // the command picked since it opened, once
+    pub fn take_chosen(&mut self) -> Option<&'static str> {
+        self.chosen.take()
+    }
+
+    // the first item hot, for menus opened from the keyboard
+    pub fn select_first(&mut self) {
+        let level = self.levels.len() - 1;
+        self.levels[level].hot = None;
+        self.step(1);
+    }
+
+    pub fn level_count(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn level_rect(&self, level: usize) -> Rect {
+        self.levels[level].rect
+    }
+
+    pub fn hot(&self, level: usize) -> Option<usize> {
+        self.levels.get(level).and_then(|l| l.hot)
+    }
+
+    // each level shows the submenu of the hot item of the one before
+    pub fn level_items(&self, level: usize) -> &[MenuItem] {
+        let mut items = &self.items[..];
+        for open in &self.levels[..level] {
+            items = match open.hot {
+                Some(i) => &items[i].submenu,
+                None => &[]
+            };
+        }
+        items
+    }
+
+    fn scaled(&self, value: i32) -> i32 {
+        scale_i32(value, get_scale_factor())
+    }
+
+    fn row_height(&self, item: &MenuItem) -> i32 {
+        self.scaled(if item.separator { self.separator_height } else { self.item_height })
+    }
+
+    fn level_size(&self, items: &[MenuItem]) -> (i32, i32) {
+        let fonts = unsafe { &crate::APPLICATION_STATE.fonts };
+        let style = self.style.scaled(get_scale_factor());
+        let face = fonts.select_style(&style);
+        let width = |text: &str| measure_string(&text.chars().collect::<Vec<char>>(), fonts, face, style.font_size).1;
+        let titles = items.iter().map(|i| width(i.title)).max().unwrap_or(0);
+        let accelerators = items.iter().filter_map(|i| i.accelerator.as_deref()).map(width).max();
+        let border = style.border_size.left + style.border_size.right;
+        let w = self.scaled(CHECK_WIDTH) + titles + accelerators.map_or(0, |a| self.scaled(ACCELERATOR_GAP) + a) + self.scaled(ARROW_WIDTH) + border;
+        let h = items.iter().map(|i| self.row_height(i)).sum::<i32>() + style.border_size.top + style.border_size.bottom;
+        (w, h)
+    }
+
+    // keeps a popup inside the window, when it doesn't fit to the right it
+    // goes left of flip_x instead
+    fn place(&self, x: i32, y: i32, w: i32, h: i32, flip_x: i32) -> Rect {
+        let right = self.window.x + self.window.w;
+        let bottom = self.window.y + self.window.h;
+        let x = if x + w > right { flip_x - w } else { x };
+        let y = if y + h > bottom { bottom - h } else { y };
+        Rect { x: std::cmp::max(self.window.x, x), y: std::cmp::max(self.window.y, y), w, h }
+    }
+
+    pub fn item_rect(&self, level: usize, index: usize) -> Rect {
+        let style = self.style.scaled(get_scale_factor());
+        let rect = self.levels[level].rect;
+        let items = self.level_items(level);
+        let top = items[..index].iter().map(|i| self.row_height(i)).sum::<i32>();
+        Rect {
+            x: rect.x + style.border_size.left,
+            y: rect.y + style.border_size.top + top,
+            w: rect.w - style.border_size.left - style.border_size.right,
+            h: self.row_height(&items[index])
+        }
+    }
+
+    // the deepest level under a point, submenus are on top
+    fn level_at(&self, x: i32, y: i32) -> Option<usize> {
+        (0..self.levels.len()).rev().find(|l| is_point_in_rect(x, y, self.levels[*l].rect))
+    }
+
+    fn index_at(&self, level: usize, x: i32, y: i32) -> Option<usize> {
+        (0..self.level_items(level).len()).find(|i| is_point_in_rect(x, y, self.item_rect(level, *i)))
+    }
+
+    // makes an item hot and opens its submenu, closing any deeper ones
+    fn set_hot(&mut self, level: usize, index: Option<usize>) {
+        self.levels.truncate(level + 1);
+        self.levels[level].hot = index.filter(|i| self.level_items(level)[*i].selectable());
+        self.open_submenu(level);
+    }
+
+    fn open_submenu(&mut self, level: usize) -> bool {
+        let index = match self.levels[level].hot {
+            Some(index) if !self.level_items(level)[index].submenu.is_empty() => index,
+            _ => return false
+        };
+        if self.levels.len() > level + 1 {
+            return true;
+        }
+        let parent = self.levels[level].rect;
+        let row = self.item_rect(level, index);
+        let (w, h) = self.level_size(&self.level_items(level)[index].submenu);
+        let top = self.style.scaled(get_scale_factor()).border_size.top;
+        let rect = self.place(parent.x + parent.w, row.y - top, w, h, parent.x);
+        self.levels.push(Level { rect, hot: None });
+        true
+    }
+
+    // the next item the keyboard can land on in the deepest level, wrapping
+    fn step(&mut self, by: i32) {
+        let level = self.levels.len() - 1;
+        let count = self.level_items(level).len() as i32;
+        let mut index = match self.levels[level].hot {
+            Some(i) => i as i32,
+            None if by > 0 => -1,
+            None => count
+        };
+        for _ in 0..count {
+            index = (index + by).rem_euclid(count);
+            if self.level_items(level)[index as usize].selectable() {
+                self.levels[level].hot = Some(index as usize);
+                return;
+            }
+        }
+    }
+
+    // runs an item, or opens its submenu with the first item hot when it
+    // came from the keyboard
+    fn activate(&mut self, level: usize, index: usize, from_keyboard: bool) {
+        let item = &self.level_items(level)[index];
+        if !item.selectable() {
+            return;
+        }
+        let command = item.command;
+        self.set_hot(level, Some(index));
+        if self.levels.len() > level + 1 {
+            if from_keyboard {
+                self.step(1);
+            }
+        }
+        else if command.is_some() {
+            self.chosen = command;
+            self.close();
+        }
+    }
+
+    fn key(&mut self, key: KeyEvent) {
+        let level = self.levels.len() - 1;
+        let hot = self.levels[level].hot;
+        match key.key {
+            Key::Up => self.step(-1),
+            Key::Down => self.step(1),
+            Key::Home => self.select_first(),
+            Key::End => {
+                self.levels[level].hot = None;
+                self.step(-1);
+            },
+            Key::Right if self.open_submenu(level) => self.step(1),
+            Key::Left | Key::Escape if level > 0 => { self.levels.pop(); },
+            Key::Escape => self.close(),
+            Key::Enter | Key::Space => {
+                if let Some(index) = hot {
+                    self.activate(level, index, true);
+                }
+            },
+            // the next item starting with the letter
+            Key::Char(c) => {
+                let items = self.level_items(level);
+                let count = items.len();
+                let start = hot.map_or(0, |i| i + 1);
+                let found = (0..count).map(|i| (start + i) % count).find(|i| {
+                    items[*i].selectable() && items[*i].title.chars().next().is_some_and(|t| t.to_uppercase().eq(c.to_uppercase()))
+                });
+                if found.is_some() {
+                    self.levels[level].hot = found;
+                }
+            },
+            _ => { }
+        }
+    }
+}
+
+impl Widget for Menu {
+    // all of the window while it's open so every click comes here first
+    fn bounds(&self) -> Rect {
+        if self.is_open() {
+            Rect { x: self.window.x - 1, y: self.window.y - 1, w: self.window.w + 2, h: self.window.h + 2 }
+        }
+        else {
+            Rect::default()
+        }
+    }
+
+    // it places itself where it's opened
+    fn set_bounds_rect(&mut self, _rect: Rect) { }
+
+    fn draw(&self, buffer: &mut PixelBuffer, fonts: &FontManager, _draw_cursor: bool) {
+        if self.is_open() {
+            draw_menu(buffer, self, fonts);
+        }
+    }
+
+    fn event(&mut self, event: &Event) -> bool {
+        if !self.is_open() {
+            return false;
+        }
+        match event {
+            // the hot item follows the mouse, leaving the menu keeps it
+            Event::MouseMove { x, y } => {
+                let level = match self.level_at(*x, *y) {
+                    Some(level) => level,
+                    None => return false
+                };
+                let index = self.index_at(level, *x, *y).filter(|i| self.level_items(level)[*i].selectable());
+                if self.levels[level].hot == index {
+                    return false;
+                }
+                self.set_hot(level, index);
+            },
+            Event::MouseDown { x, y, .. } if self.level_at(*x, *y).is_none() => self.close(),
+            Event::MouseUp { x, y, .. } => {
+                let item = self.level_at(*x, *y).and_then(|level| Some((level, self.index_at(level, *x, *y)?)));
+                if let Some((level, index)) = item {
+                    self.activate(level, index, false);
+                }
+            },
+            Event::Key(key) if key.state == KeyState::Pressed => self.key(*key),
+            _ => { }
+        }
+        true
+    }
+
+    fn take_command(&mut self) -> Option<&'static str> { self.take_chosen() }
+}
+
+// menus are on their own or under a menu bar
+fn menu_of(node: &WidgetNode) -> Option<&Menu> {
+    match node.downcast_ref::<MenuBar>() {
+        Some(bar) => Some(&bar.menu),
+        None => node.downcast_ref::<Menu>()
+    }
+}
+
+// where the open menu is in the tree, if one is
+fn open_menu(widgets: &WidgetTree) -> Option<WidgetPath> {
+    let mut open = None;
+    widgets.walk(&mut |path, node| {
+        if menu_of(node).is_some_and(|menu| menu.is_open()) {
+            open = Some(path.to_vec());
+        }
+    });
+    open
+}
+
+pub fn is_menu_open() -> bool {
+    open_menu(unsafe { &crate::APPLICATION_STATE.widgets }).is_some()
+}
+
+// an open menu takes every key until it closes, true when it did
+pub fn menu_key(event: KeyEvent) -> bool {
+    let widgets = unsafe { &mut crate::APPLICATION_STATE.widgets };
+    let path = match open_menu(widgets) {
+        Some(path) => path,
+        None => return false
+    };
+    widgets.dispatch(&path, &Event::Key(event));
+    crate::update_window();
+    run_chosen_commands();
+    true
+}
+
+#[cfg(test)]
+mod menu_tests {
+    use super::*;
+    use crate::gui::text::font::Font;
+    use crate::gui::keyboard::{KeyCode, KeyboardModifiers};
+
+    fn create_menu_for_test() -> Menu {
+        if unsafe { crate::APPLICATION_STATE.fonts.len() } == 0 {
+            let font = include_bytes!("../../fonts/OpenSans-Regular.ttf") as &[u8];
+            let font = Font::from_bytes(font).unwrap();
+            unsafe { crate::APPLICATION_STATE.fonts.add_font(font); }
+        }
+        let mut style = BoxStyle::default();
+        style.font_size = 16.0;
+        style.border_size = crate::gui::style::BoxSize::single(1);
+        let mut menu = Menu::new(style);
+        menu.open(vec![
+            MenuItem::command("Open", "files.open"),
+            MenuItem::command("Open in New Tab", "files.open_in_new_tab").enabled(false),
+            MenuItem::separator(),
+            MenuItem::submenu("Sort By", vec![
+                MenuItem::command("Name", "files.sort_by_name").checked(true),
+                MenuItem::command("Folders First", "files.folders_first").checked(false)
+            ]),
+            MenuItem::command("Delete", "files.delete").accelerator(Some("Del".to_string()))
+        ], 100, 100, Rect { x: 0, y: 0, w: 800, h: 600 });
+        menu
+    }
+
+    fn press(menu: &mut Menu, key: Key) {
+        let code = KeyCode::Unknown(0);
+        menu.event(&Event::Key(KeyEvent { code, key, modifiers: KeyboardModifiers::default(), state: KeyState::Pressed, repeat: false }));
+    }
+
+    #[test]
+    fn test_keyboard_skips_separators_and_disabled() {
+        let mut menu = create_menu_for_test();
+        press(&mut menu, Key::Down);
+        assert_eq!(menu.hot(0), Some(0));
+        press(&mut menu, Key::Down);
+        assert_eq!(menu.hot(0), Some(3));
+        press(&mut menu, Key::Up);
+        assert_eq!(menu.hot(0), Some(0));
+        // wraps around to the bottom
+        press(&mut menu, Key::Up);
+        assert_eq!(menu.hot(0), Some(4));
+        press(&mut menu, Key::Char('S'));
+        assert_eq!(menu.hot(0), Some(3));
+
+        press(&mut menu, Key::Right);
+        assert_eq!(menu.level_count(), 2);
+        assert_eq!(menu.hot(1), Some(0));
+        let (parent, submenu) = (menu.level_rect(0), menu.level_rect(1));
+        assert_eq!(submenu.x, parent.x + parent.w);
+        assert_eq!(submenu.y, menu.item_rect(0, 3).y - 1);
+        press(&mut menu, Key::Left);
+        assert_eq!(menu.level_count(), 1);
+
+        press(&mut menu, Key::Enter);
+        press(&mut menu, Key::Down);
+        press(&mut menu, Key::Enter);
+        assert!(!menu.is_open());
+        assert_eq!(menu.take_chosen(), Some("files.folders_first"));
+        assert_eq!(menu.take_chosen(), None);
+    }
+
+    #[test]
+    fn test_mouse_opens_submenus_and_clicks_outside_close() {
+        let mut menu = create_menu_for_test();
+        let row = menu.item_rect(0, 3);
+        menu.event(&Event::MouseMove { x: row.x + 5, y: row.y + 5 });
+        assert_eq!(menu.level_count(), 2);
+        // the disabled item doesn't light up and closes the submenu
+        let disabled = menu.item_rect(0, 1);
+        menu.event(&Event::MouseMove { x: disabled.x + 5, y: disabled.y + 5 });
+        assert_eq!((menu.hot(0), menu.level_count()), (None, 1));
+        menu.event(&Event::MouseUp { button: crate::gui::mouse::MouseButton::Left, x: disabled.x + 5, y: disabled.y + 5 });
+        assert!(menu.is_open());
+
+        // near the right edge the menu opens to the left of the mouse
+        menu.open(vec![MenuItem::command("Open", "files.open")], 790, 590, Rect { x: 0, y: 0, w: 800, h: 600 });
+        let rect = menu.level_rect(0);
+        assert_eq!((rect.x + rect.w, rect.y + rect.h), (790, 600));
+        menu.event(&Event::MouseDown { button: crate::gui::mouse::MouseButton::Left, x: 10, y: 10, clicks: 1 });
+        assert!(!menu.is_open());
+        assert_eq!(menu.take_chosen(), None);
+    }
+}
@@ -0,0 +1,242 @@
+use crate::gui::is_point_in_rect;
+use crate::gui::style::BoxStyle;
+use crate::gui::Rect;
+use crate::gui::scale_i32;
+use crate::gui::get_scale_factor;
+use crate::gui::menu::Menu;
+use crate::gui::menu::MenuItem;
+use crate::gui::text::manager::FontManager;
+use crate::gui::widget::Widget;
+use crate::gui::widget::Event;
+use crate::gui::mouse::MouseButton;
+use crate::gui::keyboard::Key;
+use crate::gui::keyboard::KeyEvent;
+use crate::gui::keyboard::KeyState;
+use crate::gui::keyboard::KeyboardModifiers;
+use crate::gui::draw::draw_menu_bar;
+use crate::gui::draw::measure_string;
+use crate::gui::PixelBuffer;
+
+// builds a menu's items each time it opens
+pub type MenuBuilder = fn() -> Vec<MenuItem>;
+
+/*
+    the row of menu titles along the top of the window. each title builds
+    its items when it opens so they show what's enabled, checked and bound
+    right now. a click on a title, f10 or alt and the title's first letter
+    opens its menu. while one is open moving the mouse over another title
+    or pressing left and right at the top of the menu switches to the
+    next one.
+*/
+pub struct MenuBar {
+    pub bounds_rect: Rect,
+    pub style: BoxStyle,
+    // logical pixels either side of a title
+    pub title_padding: i32,
+    pub menus: Vec<(&'static str, MenuBuilder)>,
+    pub menu: Menu,
+    // what the menus have to stay inside
+    pub window: Rect,
+    hot: Option<usize>,
+    open: Option<usize>
+}
+
+impl MenuBar {
+    pub fn new(style: BoxStyle, menu_style: BoxStyle, menus: Vec<(&'static str, MenuBuilder)>) -> MenuBar {
+        MenuBar {
+            bounds_rect: Rect::default(),
+            style,
+            title_padding: 8,
+            menus,
+            menu: Menu::new(menu_style),
+            window: Rect::default(),
+            hot: None,
+            open: None
+        }
+    }
+
+    pub fn title_rect(&self, index: usize) -> Rect {
+        let fonts = unsafe { &crate::APPLICATION_STATE.fonts };
+        let style = self.style.scaled(get_scale_factor());
+        let face = fonts.select_style(&style);
+        let padding = scale_i32(self.title_padding, get_scale_factor());
+        let width = |title: &str| measure_string(&title.chars().collect::<Vec<char>>(), fonts, face, style.font_size).1 + padding * 2;
+        let x = self.menus[..index].iter().map(|(title, _)| width(title)).sum::<i32>();
+        Rect { x: self.bounds_rect.x + x, y: self.bounds_rect.y, w: width(self.menus[index].0), h: self.bounds_rect.h }
+    }
+
+    fn title_at(&self, x: i32, y: i32) -> Option<usize> {
+        (0..self.menus.len()).find(|i| is_point_in_rect(x, y, self.title_rect(*i)))
+    }
+
+    // the title that's lit up, the open one wins over the one under the mouse
+    pub fn highlighted(&self) -> Option<usize> {
+        self.open.or(self.hot)
+    }
+
+    pub fn open_menu(&mut self, index: usize) {
+        let rect = self.title_rect(index);
+        self.menu.open((self.menus[index].1)(), rect.x, rect.y + rect.h, self.window);
+        self.open = Some(index);
+    }
+
+    pub fn open_index(&self) -> Option<usize> {
+        self.open
+    }
+
+    // the menu before or after the open one, wrapping around
+    fn switch_menu(&mut self, by: i32) {
+        if let Some(open) = self.open {
+            let count = self.menus.len() as i32;
+            self.open_menu((open as i32 + by).rem_euclid(count) as usize);
+            self.menu.select_first();
+        }
+    }
+
+    // the first menu whose title starts with a letter
+    pub fn menu_for_letter(&self, c: char) -> Option<usize> {
+        self.menus.iter().position(|(title, _)| title.chars().next().is_some_and(|t| t.to_uppercase().eq(c.to_uppercase())))
+    }
+
+    fn key(&mut self, key: KeyEvent) {
+        let level = self.menu.level_count() - 1;
+        let opens_submenu = self.menu.hot(level).is_some_and(|i| !self.menu.level_items(level)[i].submenu.is_empty());
+        match key.key {
+            Key::Left if level == 0 => self.switch_menu(-1),
+            Key::Right if !opens_submenu => self.switch_menu(1),
+            _ => { self.menu.event(&Event::Key(key)); }
+        }
+    }
+}
+
+impl Widget for MenuBar {
+    // the window while a menu is open, it's modal
+    fn bounds(&self) -> Rect {
+        if self.menu.is_open() { self.menu.bounds() } else { self.bounds_rect }
+    }
+
+    fn set_bounds_rect(&mut self, rect: Rect) { self.bounds_rect = rect }
+
+    fn draw(&self, buffer: &mut PixelBuffer, fonts: &FontManager, draw_cursor: bool) {
+        draw_menu_bar(buffer, self, fonts);
+        self.menu.draw(buffer, fonts, draw_cursor);
+    }
+
+    fn event(&mut self, event: &Event) -> bool {
+        let used = match event {
+            Event::MouseMove { x, y } => {
+                let hot = self.title_at(*x, *y);
+                let changed = hot != self.hot;
+                self.hot = hot;
+                match (hot, self.open) {
+                    (Some(hot), Some(open)) if hot != open => {
+                        self.open_menu(hot);
+                        true
+                    },
+                    (_, Some(_)) => self.menu.event(event) || changed,
+                    (_, None) => changed
+                }
+            },
+            Event::MouseDown { button: MouseButton::Left, x, y, .. } if is_point_in_rect(*x, *y, self.bounds_rect) => {
+                match self.title_at(*x, *y) {
+                    Some(index) if self.open != Some(index) => self.open_menu(index),
+                    _ => self.menu.close()
+                }
+                true
+            },
+            Event::Key(key) if self.menu.is_open() && key.state == KeyState::Pressed => {
+                self.key(*key);
+                true
+            },
+            _ if self.menu.is_open() => self.menu.event(event),
+            _ => false
+        };
+        if !self.menu.is_open() {
+            self.open = None;
+        }
+        used
+    }
+
+    fn take_command(&mut self) -> Option<&'static str> { self.menu.take_chosen() }
+}
+
+// f10 opens the first menu and alt with a letter the menu it starts, true
+// when a menu opened
+pub fn menu_bar_key(event: KeyEvent) -> bool {
+    if event.state != KeyState::Pressed {
+        return false;
+    }
+    let alt = KeyboardModifiers { alt: true, ..KeyboardModifiers::default() };
+    let widgets = unsafe { &mut crate::APPLICATION_STATE.widgets };
+    let bar = match widgets.all_mut::<MenuBar>().into_iter().next() {
+        Some(bar) => bar,
+        None => return false
+    };
+    let index = match event.key {
+        Key::F(10) if event.modifiers == KeyboardModifiers::default() => Some(0),
+        Key::Char(c) if event.modifiers == alt => bar.menu_for_letter(c),
+        _ => None
+    };
+    match index {
+        Some(index) if index < bar.menus.len() => {
+            bar.open_menu(index);
+            bar.menu.select_first();
+            true
+        },
+        _ => false
+    }
+}
+
+#[cfg(test)]
+mod menubar_tests {
+    use super::*;
+    use crate::gui::text::font::Font;
+    use crate::gui::keyboard::KeyCode;
+
+    fn file_menu() -> Vec<MenuItem> {
+        vec![MenuItem::command("Open", "files.open"), MenuItem::command("Exit", "app.exit")]
+    }
+
+    fn view_menu() -> Vec<MenuItem> {
+        vec![MenuItem::submenu("Sort", vec![MenuItem::command("Folders First", "files.folders_first")])]
+    }
+
+    fn press(bar: &mut MenuBar, key: Key) {
+        let code = KeyCode::Unknown(0);
+        bar.event(&Event::Key(KeyEvent { code, key, modifiers: KeyboardModifiers::default(), state: KeyState::Pressed, repeat: false }));
+    }
+
+    #[test]
+    fn test_switching_menus() {
+        if unsafe { crate::APPLICATION_STATE.fonts.len() } == 0 {
+            let font = include_bytes!("../../fonts/OpenSans-Regular.ttf") as &[u8];
+            unsafe { crate::APPLICATION_STATE.fonts.add_font(Font::from_bytes(font).unwrap()); }
+        }
+        let mut bar = MenuBar::new(BoxStyle::default(), BoxStyle::default(), vec![("File", file_menu), ("View", view_menu)]);
+        bar.window = Rect { x: 0, y: 0, w: 800, h: 600 };
+        bar.set_bounds_rect(Rect { x: 0, y: 0, w: 800, h: 30 });
+        assert_eq!(bar.menu_for_letter('v'), Some(1));
+
+        let view = bar.title_rect(1);
+        assert_eq!(view.x, bar.title_rect(0).w);
+        bar.event(&Event::MouseDown { button: MouseButton::Left, x: view.x + 5, y: 10, clicks: 1 });
+        assert_eq!(bar.open_index(), Some(1));
+        assert_eq!(bar.menu.level_rect(0).y, 30);
+        // the mouse sliding over to file opens it instead
+        bar.event(&Event::MouseMove { x: 5, y: 10 });
+        assert_eq!(bar.open_index(), Some(0));
+
+        // left at the top wraps around to view, right goes into its submenu
+        // and then on to file
+        press(&mut bar, Key::Left);
+        assert_eq!((bar.open_index(), bar.menu.hot(0)), (Some(1), Some(0)));
+        press(&mut bar, Key::Right);
+        assert_eq!((bar.open_index(), bar.menu.level_count()), (Some(1), 2));
+        press(&mut bar, Key::Right);
+        assert_eq!(bar.open_index(), Some(0));
+        press(&mut bar, Key::Down);
+        press(&mut bar, Key::Enter);
+        assert_eq!(bar.open_index(), None);
+        assert_eq!(bar.take_command(), Some("app.exit"));
+    }
+}
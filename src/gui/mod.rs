@@ -3,20 +3,47 @@
 #![allow(dead_code)]
 
 pub mod button;
+pub mod checkbox;
+pub mod combobox;
+pub mod command;
 pub mod draw;
+pub mod focus;
+pub mod image;
+pub mod textarea;
 pub mod textbox;
 pub mod color;
 pub mod control;
 pub mod keyboard;
+pub mod keymap;
+pub mod layout;
+pub mod listview;
+pub mod menu;
+pub mod menubar;
 pub mod mouse;
+pub mod palette;
+pub mod radio;
+pub mod raster;
+pub mod scrollview;
+pub mod slider;
+pub mod spinbox;
+pub mod splitter;
 pub mod style;
-//pub mod view;
+pub mod tabbar;
+pub mod text;
+pub mod toggle;
+pub mod tiles;
+pub mod tooltip;
+pub mod widget;
+#[cfg(test)]
+mod benchmarks;
 
 pub enum Cursor {
     NotSet,
     Arrow,
     IBeam,
-    Hand
+    Hand,
+    // the left right arrows over a splitter
+    ResizeHorizontal
 }
 
 #[repr(C, align(4))]
@@ -37,6 +64,15 @@ impl Pixel {
             alpha: 0
         }
     }
+
+    pub const fn from_rgba(red: u8, green: u8, blue: u8, alpha: u8) -> Pixel {
+        Pixel {
+            blue,
+            green,
+            red,
+            alpha
+        }
+    }
 }
 
 
@@ -71,12 +107,14 @@ pub struct Bounds {
 }
 
 impl Bounds {
-    pub fn get_rect(&self, width: i32, height: i32) -> Rect {
+    // INT fields are logical pixels and get multiplied by the scale factor,
+    // FLOAT fields are fractions of the window which is already physical
+    pub fn get_rect(&self, width: i32, height: i32, scale: f32) -> Rect {
         Rect {
-            x: match self.x { BoundsField::INT(a) => a, BoundsField::FLOAT(a) => (a * width as f32) as i32 },
-            y: match self.y { BoundsField::INT(a) => a, BoundsField::FLOAT(a) => (a * height as f32) as i32 },
-            w: match self.w { BoundsField::INT(a) => a, BoundsField::FLOAT(a) => (a * width as f32) as i32 },
-            h: match self.h { BoundsField::INT(a) => a, BoundsField::FLOAT(a) => (a * height as f32) as i32 }
+            x: match self.x { BoundsField::INT(a) => scale_i32(a, scale), BoundsField::FLOAT(a) => (a * width as f32) as i32 },
+            y: match self.y { BoundsField::INT(a) => scale_i32(a, scale), BoundsField::FLOAT(a) => (a * height as f32) as i32 },
+            w: match self.w { BoundsField::INT(a) => scale_i32(a, scale), BoundsField::FLOAT(a) => (a * width as f32) as i32 },
+            h: match self.h { BoundsField::INT(a) => scale_i32(a, scale), BoundsField::FLOAT(a) => (a * height as f32) as i32 }
         }
     }
 
@@ -99,13 +137,67 @@ impl Bounds {
     }
 }
 
+// left and top are where the first pixel sits in window coordinates. the
+// back buffer is always at 0, 0 but tiles rendered on their own start
+// wherever the tile is so every draw function can keep using window
+// coordinates.
 pub struct PixelBuffer {
     pub pixels: Vec<Pixel>,
+    pub left: i32,
+    pub top: i32,
     pub width: i32,
     pub height: i32
 }
 
+impl PixelBuffer {
+    pub fn new(left: i32, top: i32, width: i32, height: i32) -> PixelBuffer {
+        PixelBuffer {
+            pixels: vec![Pixel::default(); (width * height) as usize],
+            left,
+            top,
+            width,
+            height
+        }
+    }
+
+    pub fn get_rect(&self) -> Rect {
+        Rect { x: self.left, y: self.top, w: self.width, h: self.height }
+    }
+
+    // the pixels from x to x + w on row y, in window coordinates. the caller
+    // is expected to have clipped the span to the buffer already.
+    pub fn row_mut(&mut self, x: i32, y: i32, w: i32) -> &mut [Pixel] {
+        let start = ((y - self.top) * self.width + (x - self.left)) as usize;
+        &mut self.pixels[start..start + w as usize]
+    }
+
+    pub fn row(&self, x: i32, y: i32, w: i32) -> &[Pixel] {
+        let start = ((y - self.top) * self.width + (x - self.left)) as usize;
+        &self.pixels[start..start + w as usize]
+    }
+}
+
+
+
+// where the application keeps a settings file, %APPDATA%\filex on windows
+// and ~/.config/filex elsewhere
+pub fn config_path(file: &str) -> Option<std::path::PathBuf> {
+    use std::path::PathBuf;
+    let config = std::env::var_os("APPDATA").map(PathBuf::from)
+        .or_else(|| std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config.join("filex").join(file))
+}
+
+// converts logical pixels to physical pixels
+pub fn scale_i32(value: i32, scale: f32) -> i32 {
+    (value as f32 * scale).round() as i32
+}
 
+// the scale factor of the monitor the window is on, 1.0 is 96 dpi
+pub fn get_scale_factor() -> f32 {
+    unsafe { crate::APPLICATION_STATE.scale_factor }
+}
 
 pub fn is_point_in_rect_a(x:i32, y:i32, left:i32, top:i32, right:i32, bottom:i32) -> bool {
     x > left && x < right && y > top && y < bottom
@@ -1,70 +1,199 @@
 
 use crate::gui::Cursor;
-use crate::gui::control::Control;
-//use crate::gui::view::ViewBehavior;
+use crate::gui::focus::control_at;
+use crate::gui::focus::set_focus;
+use crate::gui::focus::restore_lost_focus;
+use crate::gui::menu::is_menu_open;
+use crate::gui::command::run_chosen_commands;
+use crate::gui::tooltip::hide_tooltip;
+use crate::gui::tooltip::tooltip_mouse_move;
+use crate::gui::widget::Event;
+use crate::gui::widget::WidgetPath;
+use crate::gui::keyboard::KeyboardModifiers;
+use std::time::Duration;
+use std::time::Instant;
 
-pub fn handle_left_mouse_button_down(mouse_x: i32, mouse_y: i32) {
-    let buttons = unsafe { &mut crate::APPLICATION_STATE.buttons };
-    let textboxes = unsafe { &mut crate::APPLICATION_STATE.textboxes };
-    for button in buttons {
-        button.left_mouse_button_down(mouse_x, mouse_y);
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    // the thumb buttons
+    Back,
+    Forward
+}
+
+/*
+    counts clicks so a double click is a press that comes soon enough after
+    the last one, with the same button and close enough to where it was.
+    the platform sets the interval and distance from the system settings,
+    they start out at the windows defaults.
+*/
+pub struct ClickCounter {
+    pub interval: Duration,
+    pub distance: i32,
+    last: Option<(MouseButton, Instant, i32, i32)>,
+    count: u32
+}
+
+impl ClickCounter {
+    pub const fn new() -> ClickCounter {
+        ClickCounter {
+            interval: Duration::from_millis(500),
+            distance: 2,
+            last: None,
+            count: 0
+        }
     }
-    for textbox in textboxes {
-        textbox.left_mouse_button_down(mouse_x, mouse_y);
+
+    // 1 for a single click, 2 for a double click and so on
+    pub fn press(&mut self, button: MouseButton, x: i32, y: i32, time: Instant) -> u32 {
+        let follows = match self.last {
+            Some((last_button, last_time, last_x, last_y)) => {
+                last_button == button
+                    && time.saturating_duration_since(last_time) <= self.interval
+                    && (x - last_x).abs() <= self.distance
+                    && (y - last_y).abs() <= self.distance
+            },
+            None => false
+        };
+        self.count = if follows { self.count + 1 } else { 1 };
+        self.last = Some((button, time, x, y));
+        self.count
     }
-    crate::update_window();
-    crate::CURSOR_TOGGLE.store(true, std::sync::atomic::Ordering::Relaxed);
 }
 
-pub fn handle_left_mouse_button_up(mouse_x: i32, mouse_y: i32) {
-    let buttons = unsafe { &mut crate::APPLICATION_STATE.buttons };
-    //let textboxes = &mut crate::APPLICATION_STATE.textboxes;
-    for button in buttons {
-        button.left_mouse_button_up(mouse_x, mouse_y);
+pub struct MouseState {
+    pub clicks: ClickCounter,
+    // buttons pressed inside the window and not released yet, with where
+    // the mouse was last so they can be let go if the window loses capture
+    pressed: Vec<MouseButton>,
+    last_x: i32,
+    last_y: i32,
+    // the widget a button went down on gets the mouse until every button
+    // is back up, so a drag keeps going outside it
+    captured: Option<WidgetPath>
+}
+
+impl MouseState {
+    pub const fn new() -> MouseState {
+        MouseState { clicks: ClickCounter::new(), pressed: Vec::new(), last_x: 0, last_y: 0, captured: None }
     }
-    // for textbox in textboxes {
-    //     let hit = is_point_in_rect(mouse_x, mouse_y, textbox.get_bounds());
-    //     handle_textbox_mouse_up(textbox, hit);
-    // }
 }
 
-pub fn handle_mouse_move(mouse_x: i32, mouse_y: i32) -> Cursor {
-    let mut is_button_hot = false;
-    let mut is_textbox_hot = false;
+pub fn handle_mouse_button_down(button: MouseButton, mouse_x: i32, mouse_y: i32) {
+    let state = unsafe { &mut crate::APPLICATION_STATE };
+    let clicks = state.mouse.clicks.press(button, mouse_x, mouse_y, Instant::now());
+    hide_tooltip();
+    if !state.mouse.pressed.contains(&button) {
+        state.mouse.pressed.push(button);
+    }
+    // a click focuses whatever is under it, or nothing, without the ring. a
+    // right click does too for the menu that comes with it. a click that
+    // closes a menu leaves the focus alone.
+    if (button == MouseButton::Left || button == MouseButton::Right) && !is_menu_open() {
+        set_focus(control_at(mouse_x, mouse_y));
+        state.focus.focus_visible = false;
+    }
+    let target = match state.mouse.captured.clone() {
+        Some(path) => Some(path),
+        None => state.widgets.hit_test(mouse_x, mouse_y)
+    };
+    if let Some(path) = target {
+        state.widgets.dispatch(&path, &Event::MouseDown { button, x: mouse_x, y: mouse_y, clicks });
+        state.mouse.captured = Some(path);
+    }
+    restore_lost_focus();
+    run_chosen_commands();
+    crate::update_window();
+    if button == MouseButton::Left {
+        crate::CURSOR_TOGGLE.store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+}
 
-    let buttons = unsafe { &mut crate::APPLICATION_STATE.buttons };
-    for button in buttons {
-        let (hot_changed, is_hot) = button.hit_check(mouse_x, mouse_y);
-        if hot_changed { crate::update_window() }
-        if is_hot { is_button_hot = true }
+pub fn handle_mouse_button_up(button: MouseButton, mouse_x: i32, mouse_y: i32) {
+    let state = unsafe { &mut crate::APPLICATION_STATE };
+    state.mouse.pressed.retain(|b| *b != button);
+    let target = match state.mouse.captured.clone() {
+        Some(path) => Some(path),
+        None => state.widgets.hit_test(mouse_x, mouse_y)
+    };
+    if state.mouse.pressed.is_empty() {
+        state.mouse.captured = None;
     }
+    if let Some(path) = target {
+        if state.widgets.dispatch(&path, &Event::MouseUp { button, x: mouse_x, y: mouse_y }) {
+            crate::update_window();
+        }
+    }
+    run_chosen_commands();
+}
 
-    let textboxes = unsafe { &mut crate::APPLICATION_STATE.textboxes };
-    for textbox in textboxes {
-        let (hot_changed, is_hot) = textbox.hit_check(mouse_x, mouse_y);
-        if hot_changed { crate::update_window() }
-        if is_hot { is_textbox_hot = true }
+// another window took the mouse in the middle of a drag, every button
+// still down goes up where the mouse was last seen
+pub fn handle_mouse_capture_lost() {
+    let mouse = unsafe { &mut crate::APPLICATION_STATE.mouse };
+    let pressed = std::mem::take(&mut mouse.pressed);
+    for button in pressed {
+        handle_mouse_button_up(button, mouse.last_x, mouse.last_y);
     }
+}
 
-    // let views =  unsafe { &mut crate::views };
-    // for view in views {
-    //     match view.behavior {
-    //         ViewBehavior::None => { },
-    //         ViewBehavior::Button => { 
-    //             view.mouse_move(mouse_x, mouse_y);
-    //         }
-    //     }
-    // }
+// the wheel in lines, positive y is away from the user and positive x to
+// the right. shift turns the vertical wheel sideways like most editors.
+// whatever is under the mouse scrolls whether it has focus or not.
+pub fn handle_mouse_wheel(mouse_x: i32, mouse_y: i32, lines_x: f32, lines_y: f32, modifiers: KeyboardModifiers) {
+    let (lines_x, lines_y) = if modifiers.shift && lines_x == 0.0 { (-lines_y, 0.0) } else { (lines_x, lines_y) };
+    let widgets = unsafe { &mut crate::APPLICATION_STATE.widgets };
+    hide_tooltip();
+    if let Some(path) = widgets.hit_test(mouse_x, mouse_y) {
+        if widgets.dispatch(&path, &Event::Wheel { x: mouse_x, y: mouse_y, lines_x, lines_y }) {
+            crate::invalidate_rect(widgets.get(&path).map(|node| node.widget.bounds()).unwrap_or_default());
+        }
+    }
+}
 
+// every widget hears about the move, the ones that change are painted
+// again, and the tooltip waits on what's under the mouse now. the cursor
+// is the captured widget's during a drag, otherwise the one under the
+// mouse.
+pub fn handle_mouse_move(mouse_x: i32, mouse_y: i32) -> Cursor {
+    let state = unsafe { &mut crate::APPLICATION_STATE };
+    state.mouse.last_x = mouse_x;
+    state.mouse.last_y = mouse_y;
+    let event = Event::MouseMove { x: mouse_x, y: mouse_y };
+    state.widgets.walk_mut(&mut |_, node| {
+        if node.widget.event(&event) {
+            crate::invalidate_rect(node.widget.bounds());
+        }
+    });
+    tooltip_mouse_move(mouse_x, mouse_y, state.mouse.captured.is_some());
+    let target = match state.mouse.captured.clone() {
+        Some(path) => Some(path),
+        None => state.widgets.hit_test(mouse_x, mouse_y)
+    };
+    target.and_then(|path| state.widgets.get(&path)).map_or(Cursor::Arrow, |node| node.widget.cursor())
+}
 
-    if is_button_hot { 
-        Cursor::Hand 
-    }
-    else if is_textbox_hot { 
-        Cursor::IBeam
-    }
-    else { 
-        Cursor::Arrow
+#[cfg(test)]
+mod mouse_tests {
+    use super::*;
+
+    #[test]
+    fn test_click_counting() {
+        let mut counter = ClickCounter::new();
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        assert_eq!(counter.press(MouseButton::Left, 10, 10, at(0)), 1);
+        assert_eq!(counter.press(MouseButton::Left, 11, 10, at(200)), 2);
+        assert_eq!(counter.press(MouseButton::Left, 11, 11, at(400)), 3);
+        // too slow
+        assert_eq!(counter.press(MouseButton::Left, 11, 11, at(1000)), 1);
+        // moved too far
+        assert_eq!(counter.press(MouseButton::Left, 30, 11, at(1100)), 1);
+        // another button
+        assert_eq!(counter.press(MouseButton::Right, 30, 11, at(1200)), 1);
+        counter.interval = Duration::from_millis(100);
+        assert_eq!(counter.press(MouseButton::Right, 30, 11, at(1350)), 1);
     }
 }
-
@@ -0,0 +1,356 @@
+use crate::gui::is_point_in_rect;
+use crate::gui::style::BoxStyle;
+use crate::gui::Rect;
+use crate::gui::scale_i32;
+use crate::gui::get_scale_factor;
+use crate::gui::textbox::TextBox;
+use crate::gui::text::manager::FontManager;
+use crate::gui::widget::Widget;
+use crate::gui::widget::Event;
+use crate::gui::keyboard::Key;
+use crate::gui::keyboard::KeyState;
+use crate::gui::draw::draw_palette;
+use crate::gui::PixelBuffer;
+use std::time::Instant;
+
+/*
+    every command in a list under a search box, with its key binding. typing
+    narrows the list down with a fuzzy match on the title or the name of the
+    command, enter or a click runs the selected one. it has the focus while
+    it's open and closes when it loses it, the focus goes back to where it
+    was so the command runs there.
+*/
+
+pub struct PaletteEntry {
+    pub command: &'static str,
+    pub title: &'static str,
+    pub keys: Option<String>
+}
+
+pub struct CommandPalette {
+    pub style: BoxStyle,
+    pub style_selected: BoxStyle,
+    pub query: TextBox,
+    // logical pixels, the width and how far down the window it sits
+    pub width: i32,
+    pub top: i32,
+    pub item_height: i32,
+    pub visible_items: usize,
+    entries: Vec<PaletteEntry>,
+    // indexes into the entries, best match first
+    matches: Vec<usize>,
+    selected: usize,
+    first: usize,
+    window: Rect,
+    open: bool,
+    chosen: Option<&'static str>
+}
+
+// how well a query matches a text, None when its chars aren't all in there
+// in order. a char at the start of a word or right after the last one
+// scores more so "tln" finds Toggle Line Numbers before anything else.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let word_start = |i: usize| i == 0 || !text[i - 1].is_alphanumeric();
+    let mut score = 0;
+    let mut from = 0;
+    for q in query.chars().flat_map(char::to_lowercase).filter(|c| !c.is_whitespace()) {
+        // the very next char, then the start of a word, then anywhere
+        let i = if from > 0 && text.get(from) == Some(&q) {
+            from
+        }
+        else {
+            (from..text.len()).find(|i| text[*i] == q && word_start(*i))
+                .or_else(|| (from..text.len()).find(|i| text[*i] == q))?
+        };
+        score += 1;
+        if word_start(i) {
+            score += 8;
+        }
+        if from > 0 && i == from {
+            score += 4;
+        }
+        score -= std::cmp::min(3, (i - from) as i32);
+        from = i + 1;
+    }
+    Some(score)
+}
+
+impl CommandPalette {
+    pub fn new(style: BoxStyle, style_selected: BoxStyle, query_style: BoxStyle) -> CommandPalette {
+        CommandPalette {
+            style,
+            style_selected,
+            query: TextBox::new("Type a command", query_style),
+            width: 500,
+            top: 40,
+            item_height: 26,
+            visible_items: 12,
+            entries: Vec::new(),
+            matches: Vec::new(),
+            selected: 0,
+            first: 0,
+            window: Rect::default(),
+            open: false,
+            chosen: None
+        }
+    }
+
+    pub fn open(&mut self, entries: Vec<PaletteEntry>, window: Rect) {
+        self.entries = entries;
+        self.window = window;
+        self.open = true;
+        self.chosen = None;
+        self.query.set_text("");
+        self.query.set_bounds_rect(self.query_rect());
+        self.update_matches();
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+        self.query.active = false;
+        crate::update_window();
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn take_chosen(&mut self) -> Option<&'static str> {
+        self.chosen.take()
+    }
+
+    // the entries that match, best first, and which one is selected
+    pub fn matches(&self) -> impl Iterator<Item = &PaletteEntry> {
+        self.matches.iter().map(move |i| &self.entries[*i])
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn first_visible(&self) -> usize {
+        self.first
+    }
+
+    fn update_matches(&mut self) {
+        let query: String = self.query.text.iter().collect();
+        let mut scored: Vec<(i32, usize)> = self.entries.iter().enumerate()
+            .filter_map(|(i, entry)| {
+                let score = std::cmp::max(fuzzy_score(&query, entry.title), fuzzy_score(&query, entry.command))?;
+                Some((score, i))
+            })
+            .collect();
+        let entries = &self.entries;
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| entries[a.1].title.cmp(entries[b.1].title)));
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.selected = 0;
+        self.first = 0;
+        crate::update_window();
+    }
+
+    fn scaled(&self, value: i32) -> i32 {
+        scale_i32(value, get_scale_factor())
+    }
+
+    fn rows(&self) -> usize {
+        std::cmp::min(self.visible_items, self.matches.len())
+    }
+
+    // centered near the top of the window, as tall as the matches it shows
+    pub fn palette_rect(&self) -> Rect {
+        let style = self.style.scaled(get_scale_factor());
+        let w = std::cmp::min(self.scaled(self.width), self.window.w - self.scaled(20));
+        let h = self.scaled(self.item_height) * (self.rows() as i32 + 1) + style.border_size.top + style.border_size.bottom;
+        Rect { x: self.window.x + (self.window.w - w) / 2, y: self.window.y + self.scaled(self.top), w, h }
+    }
+
+    fn query_rect(&self) -> Rect {
+        let style = self.style.scaled(get_scale_factor());
+        let rect = self.palette_rect();
+        Rect {
+            x: rect.x + style.border_size.left,
+            y: rect.y + style.border_size.top,
+            w: rect.w - style.border_size.left - style.border_size.right,
+            h: self.scaled(self.item_height)
+        }
+    }
+
+    // a row under the search box, counted from the first one showing
+    pub fn row_rect(&self, row: usize) -> Rect {
+        let query = self.query_rect();
+        let height = self.scaled(self.item_height);
+        Rect { x: query.x, y: query.y + height * (row as i32 + 1), w: query.w, h: height }
+    }
+
+    fn row_at(&self, x: i32, y: i32) -> Option<usize> {
+        (0..self.rows()).find(|row| is_point_in_rect(x, y, self.row_rect(*row))).map(|row| self.first + row)
+    }
+
+    fn select(&mut self, index: i32) {
+        if self.matches.is_empty() {
+            return;
+        }
+        self.selected = index.clamp(0, self.matches.len() as i32 - 1) as usize;
+        let visible = self.rows();
+        if self.selected < self.first {
+            self.first = self.selected;
+        }
+        else if self.selected >= self.first + visible {
+            self.first = self.selected + 1 - visible;
+        }
+    }
+
+    fn choose(&mut self, index: usize) {
+        if let Some(entry) = self.matches.get(index) {
+            self.chosen = Some(self.entries[*entry].command);
+            self.close();
+        }
+    }
+
+    // the search box gets what the palette doesn't use itself
+    fn send_to_query(&mut self, event: &Event) -> bool {
+        let before = self.query.text.clone();
+        let used = self.query.event(event);
+        if self.query.text != before {
+            self.update_matches();
+        }
+        used
+    }
+}
+
+impl Widget for CommandPalette {
+    fn bounds(&self) -> Rect {
+        if self.open { self.palette_rect() } else { Rect::default() }
+    }
+
+    // it places itself in the window it's opened in
+    fn set_bounds_rect(&mut self, _rect: Rect) { }
+
+    fn draw(&self, buffer: &mut PixelBuffer, fonts: &FontManager, draw_cursor: bool) {
+        if self.open {
+            draw_palette(buffer, self, fonts, draw_cursor);
+        }
+    }
+
+    fn event(&mut self, event: &Event) -> bool {
+        if !self.open {
+            return false;
+        }
+        match event {
+            Event::Key(key) if key.state == KeyState::Pressed => {
+                let page = self.visible_items as i32;
+                let selected = self.selected as i32;
+                match key.key {
+                    Key::Up => self.select(selected - 1),
+                    Key::Down => self.select(selected + 1),
+                    Key::PageUp => self.select(selected - page),
+                    Key::PageDown => self.select(selected + page),
+                    Key::Enter => self.choose(self.selected),
+                    Key::Escape => self.close(),
+                    _ => return self.send_to_query(event)
+                }
+                crate::update_window();
+                true
+            },
+            // hovering selects like the keyboard does
+            Event::MouseMove { x, y } => {
+                let hot = self.query.event(event);
+                match self.row_at(*x, *y) {
+                    Some(row) if row != self.selected => {
+                        self.selected = row;
+                        true
+                    },
+                    _ => hot
+                }
+            },
+            Event::MouseDown { x, y, .. } => {
+                match self.row_at(*x, *y) {
+                    Some(row) => self.choose(row),
+                    None => { self.send_to_query(event); }
+                }
+                true
+            },
+            Event::Wheel { lines_y, .. } => {
+                let last = self.matches.len().saturating_sub(self.rows());
+                self.first = (self.first as i32 - lines_y.round() as i32).clamp(0, last as i32) as usize;
+                true
+            },
+            _ => self.send_to_query(event)
+        }
+    }
+
+    fn focus_style(&self) -> Option<BoxStyle> {
+        if self.open { Some(self.style.scaled(get_scale_factor())) } else { None }
+    }
+
+    // closes when something else gets the focus
+    fn set_focused(&mut self, focused: bool) {
+        self.query.set_focused(focused);
+        if !focused && self.open {
+            self.close();
+        }
+    }
+
+    fn caret_rect(&self) -> Option<Rect> {
+        if self.open { Widget::caret_rect(&self.query) } else { None }
+    }
+
+    fn timer_tick(&mut self, now: Instant) -> bool { self.open && self.query.timer_tick(now) }
+    fn take_command(&mut self) -> Option<&'static str> { self.take_chosen() }
+}
+
+#[cfg(test)]
+mod palette_tests {
+    use super::*;
+    use crate::gui::text::font::Font;
+    use crate::gui::keyboard::{KeyCode, KeyEvent, KeyboardModifiers, TextInput};
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert_eq!(fuzzy_score("", "Copy"), Some(0));
+        assert!(fuzzy_score("xyz", "Copy").is_none());
+        assert!(fuzzy_score("pc", "Copy").is_none());
+        // word starts beat letters in the middle of words
+        assert!(fuzzy_score("tln", "Toggle Line Numbers") > fuzzy_score("tln", "Select All Lines Now"));
+        // and runs beat letters spread out
+        assert!(fuzzy_score("cop", "Copy") > fuzzy_score("cop", "Circle Loop"));
+        assert!(fuzzy_score("PASTE", "edit.paste").is_some());
+    }
+
+    #[test]
+    fn test_typing_filters_and_enter_chooses() {
+        if unsafe { crate::APPLICATION_STATE.fonts.len() } == 0 {
+            let font = include_bytes!("../../fonts/OpenSans-Regular.ttf") as &[u8];
+            unsafe { crate::APPLICATION_STATE.fonts.add_font(Font::from_bytes(font).unwrap()); }
+        }
+        let entry = |command, title| PaletteEntry { command, title, keys: None };
+        let mut palette = CommandPalette::new(BoxStyle::default(), BoxStyle::default(), BoxStyle::default());
+        palette.open(vec![
+            entry("edit.copy", "Copy"),
+            entry("edit.paste", "Paste"),
+            entry("view.toggle_line_numbers", "Toggle Line Numbers"),
+            entry("files.open", "Open")
+        ], Rect { x: 0, y: 0, w: 800, h: 600 });
+        palette.set_focused(true);
+        assert_eq!(palette.matches().count(), 4);
+        assert_eq!(palette.matches().next().unwrap().title, "Copy");
+
+        palette.event(&Event::Text(TextInput::Commit("p".to_string())));
+        let titles: Vec<&str> = palette.matches().map(|e| e.title).collect();
+        assert_eq!(titles, ["Paste", "Open", "Copy"]);
+        let key = |key| Event::Key(KeyEvent { code: KeyCode::Unknown(0), key, modifiers: KeyboardModifiers::default(), state: KeyState::Pressed, repeat: false });
+        palette.event(&key(Key::Down));
+        palette.event(&key(Key::Down));
+        palette.event(&key(Key::Down));
+        assert_eq!(palette.selected(), 2);
+        palette.event(&key(Key::Back));
+        assert_eq!((palette.matches().count(), palette.selected()), (4, 0));
+
+        palette.event(&Event::Text(TextInput::Commit("tln".to_string())));
+        palette.event(&key(Key::Enter));
+        assert!(!palette.is_open());
+        assert!(palette.focus_style().is_none());
+        assert_eq!(palette.take_command(), Some("view.toggle_line_numbers"));
+    }
+}
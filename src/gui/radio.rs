@@ -0,0 +1,160 @@
+use crate::gui::is_point_in_rect;
+use crate::gui::style::BoxStyle;
+use crate::gui::style::StateStyles;
+use crate::gui::Rect;
+use crate::gui::scale_i32;
+use crate::gui::get_scale_factor;
+use crate::gui::control::Control;
+use crate::gui::control::ControlState;
+use crate::gui::text::manager::FontManager;
+use crate::gui::widget::Widget;
+use crate::gui::widget::Event;
+use crate::gui::mouse::MouseButton;
+use crate::gui::keyboard::Key;
+use crate::gui::keyboard::KeyState;
+use crate::gui::draw::draw_radio_group;
+use crate::gui::Cursor;
+use crate::gui::PixelBuffer;
+
+/*
+    options stacked in a column where exactly one is picked. the group
+    takes the focus as a whole, the arrows pick the option before or after
+    the picked one and wrap around like windows does. a click picks the
+    option under it. it raises Event::Changed when another one is picked.
+*/
+pub struct RadioGroup {
+    pub options: Vec<&'static str>,
+    pub selected: usize,
+    pub bounds: Rect,
+    // logical pixels
+    pub item_height: i32,
+    pub state: ControlState,
+    pub styles: StateStyles,
+    // the option the mouse went down on
+    pressed: Option<usize>,
+    changed: bool
+}
+
+impl RadioGroup {
+    pub fn new(options: Vec<&'static str>, selected: usize, styles: StateStyles) -> RadioGroup {
+        RadioGroup {
+            options,
+            selected,
+            bounds: Rect::default(),
+            item_height: 24,
+            state: ControlState::default(),
+            styles,
+            pressed: None,
+            changed: false
+        }
+    }
+
+    pub fn option_rect(&self, index: usize) -> Rect {
+        let height = scale_i32(self.item_height, get_scale_factor());
+        Rect { x: self.bounds.x, y: self.bounds.y + height * index as i32, w: self.bounds.w, h: height }
+    }
+
+    pub fn option_at(&self, x: i32, y: i32) -> Option<usize> {
+        (0..self.options.len()).find(|i| is_point_in_rect(x, y, self.option_rect(*i)))
+    }
+
+    pub fn select(&mut self, index: usize) {
+        if index < self.options.len() && index != self.selected {
+            self.selected = index;
+            self.changed = true;
+        }
+    }
+
+    fn step(&mut self, by: i32) {
+        let count = self.options.len() as i32;
+        if count > 0 {
+            self.select((self.selected as i32 + by).rem_euclid(count) as usize);
+        }
+    }
+}
+
+impl Widget for RadioGroup {
+    fn bounds(&self) -> Rect { self.bounds }
+    fn set_bounds_rect(&mut self, rect: Rect) { self.bounds = rect }
+
+    fn draw(&self, buffer: &mut PixelBuffer, fonts: &FontManager, _draw_cursor: bool) {
+        draw_radio_group(buffer, self, fonts);
+    }
+
+    fn event(&mut self, event: &Event) -> bool {
+        if self.state.disabled {
+            return false;
+        }
+        match event {
+            Event::MouseMove { x, y } => return self.hit_check(*x, *y).0,
+            Event::MouseDown { button: MouseButton::Left, x, y, .. } => {
+                self.pressed = self.option_at(*x, *y);
+                self.state.active = self.pressed.is_some();
+            },
+            Event::MouseUp { button: MouseButton::Left, x, y } => {
+                self.state.active = false;
+                match self.pressed.take() {
+                    Some(index) if self.option_at(*x, *y) == Some(index) => self.select(index),
+                    _ => { }
+                }
+            },
+            Event::Key(key) if key.state == KeyState::Pressed => match key.key {
+                Key::Up | Key::Left => self.step(-1),
+                Key::Down | Key::Right => self.step(1),
+                Key::Home => self.select(0),
+                Key::End => self.select(self.options.len().saturating_sub(1)),
+                _ => return false
+            },
+            _ => return false
+        }
+        true
+    }
+
+    fn raised(&mut self) -> Option<Event> {
+        if std::mem::take(&mut self.changed) { Some(Event::Changed) } else { None }
+    }
+
+    fn cursor(&self) -> Cursor { Cursor::Hand }
+
+    fn focus_style(&self) -> Option<BoxStyle> {
+        if self.state.disabled { None } else { Some(self.get_scaled_style()) }
+    }
+
+    fn set_focused(&mut self, focused: bool) { self.state.focused = focused }
+}
+
+impl Control for RadioGroup {
+    fn get_bounds(&self) -> Rect { self.bounds }
+    fn get_hot(&self) -> bool { self.state.hot }
+    fn set_hot(&mut self, hit: bool) { self.state.hot = hit }
+    fn get_style(&self) -> &BoxStyle { self.styles.select(&self.state) }
+}
+
+#[cfg(test)]
+mod radio_tests {
+    use super::*;
+    use crate::gui::keyboard::{KeyCode, KeyEvent, KeyboardModifiers};
+
+    #[test]
+    fn test_click_and_arrows_pick() {
+        let mut group = RadioGroup::new(vec!["Name", "Size", "Date"], 0, StateStyles::control_default());
+        group.set_bounds_rect(Rect { x: 0, y: 0, w: 200, h: 72 });
+        let y = group.option_rect(2).y + 5;
+        group.event(&Event::MouseDown { button: MouseButton::Left, x: 10, y, clicks: 1 });
+        group.event(&Event::MouseUp { button: MouseButton::Left, x: 10, y });
+        assert_eq!(group.selected, 2);
+        assert!(matches!(group.raised(), Some(Event::Changed)));
+
+        let key = |key| Event::Key(KeyEvent { code: KeyCode::Unknown(0), key, modifiers: KeyboardModifiers::default(), state: KeyState::Pressed, repeat: false });
+        group.event(&key(Key::Down));
+        assert_eq!(group.selected, 0);
+        group.event(&key(Key::Left));
+        assert_eq!(group.selected, 2);
+        group.event(&key(Key::Home));
+        assert_eq!(group.selected, 0);
+        group.raised();
+        // picking the one that's picked isn't a change
+        group.event(&key(Key::Home));
+        assert!(group.raised().is_none());
+    }
+}
@@ -0,0 +1,200 @@
+
+/*
+    row kernels used by draw.rs. every function here works on one already
+    clipped row so the callers do the bounds checks once per rect instead
+    of once per pixel. the blend kernels have an sse2 version on x86_64
+    that produces exactly the same bytes as the scalar version.
+*/
+
+use crate::gui::Pixel;
+use crate::gui::color::Color;
+
+pub fn fill_row(row: &mut [Pixel], color: Color) {
+    row.fill(Pixel::from_rgba(color.red, color.green, color.blue, 255));
+}
+
+// blends a solid color into the row using an 8 bit coverage mask, this is
+// what glyph bitmaps from fontdue are. the destination alpha is left alone.
+pub fn blend_coverage_row(row: &mut [Pixel], coverage: &[u8], color: Color) {
+    let count = std::cmp::min(row.len(), coverage.len());
+    let row = &mut row[..count];
+    let coverage = &coverage[..count];
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        let simd_count = count - (count % 4);
+        unsafe { sse2::blend_coverage_row(&mut row[..simd_count], &coverage[..simd_count], color) };
+        blend_coverage_row_scalar(&mut row[simd_count..], &coverage[simd_count..], color);
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    blend_coverage_row_scalar(row, coverage, color);
+}
+
+// blends straight alpha source pixels over the row
+pub fn blend_pixel_row(row: &mut [Pixel], source: &[Pixel]) {
+    let count = std::cmp::min(row.len(), source.len());
+    let row = &mut row[..count];
+    let source = &source[..count];
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        let simd_count = count - (count % 4);
+        unsafe { sse2::blend_pixel_row(&mut row[..simd_count], &source[..simd_count]) };
+        blend_pixel_row_scalar(&mut row[simd_count..], &source[simd_count..]);
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    blend_pixel_row_scalar(row, source);
+}
+
+pub fn blend_coverage_row_scalar(row: &mut [Pixel], coverage: &[u8], color: Color) {
+    for (pixel, &alpha) in row.iter_mut().zip(coverage.iter()) {
+        if alpha > 0 {
+            pixel.red = blend_u8(color.red, pixel.red, alpha);
+            pixel.green = blend_u8(color.green, pixel.green, alpha);
+            pixel.blue = blend_u8(color.blue, pixel.blue, alpha);
+        }
+    }
+}
+
+pub fn blend_pixel_row_scalar(row: &mut [Pixel], source: &[Pixel]) {
+    for (pixel, src) in row.iter_mut().zip(source.iter()) {
+        if src.alpha > 0 {
+            pixel.red = blend_u8(src.red, pixel.red, src.alpha);
+            pixel.green = blend_u8(src.green, pixel.green, src.alpha);
+            pixel.blue = blend_u8(src.blue, pixel.blue, src.alpha);
+        }
+    }
+}
+
+// c1 over c2 at alpha out of 255, (c1 * a + c2 * (255 - a)) / 255 rounded
+// down. the simd versions below have to give the same bytes.
+pub fn blend_u8(c1: u8, c2: u8, alpha: u8) -> u8 {
+    let alpha = alpha as u32;
+    ((c1 as u32 * alpha + c2 as u32 * (255 - alpha)) / 255) as u8
+}
+
+#[cfg(target_arch = "x86_64")]
+mod sse2 {
+    // sse2 is part of the x86_64 baseline so these don't need runtime detection
+    use std::arch::x86_64::*;
+    use crate::gui::Pixel;
+    use crate::gui::color::Color;
+
+    // exact x / 255 for x in 0..=65025, (x + 1 + (x >> 8)) >> 8
+    #[inline(always)]
+    unsafe fn div_255(x: __m128i) -> __m128i {
+        let x = _mm_add_epi16(x, _mm_add_epi16(_mm_set1_epi16(1), _mm_srli_epi16(x, 8)));
+        _mm_srli_epi16(x, 8)
+    }
+
+    // blends 2 pixels held as 8 x u16 lanes, alpha holds the alpha for each lane
+    #[inline(always)]
+    unsafe fn blend_lanes(source: __m128i, destination: __m128i, alpha: __m128i) -> __m128i {
+        let inverse = _mm_sub_epi16(_mm_set1_epi16(255), alpha);
+        let sum = _mm_add_epi16(_mm_mullo_epi16(source, alpha), _mm_mullo_epi16(destination, inverse));
+        div_255(sum)
+    }
+
+    // the alpha byte of every pixel, used to keep destination alpha unchanged
+    #[inline(always)]
+    unsafe fn alpha_byte_mask() -> __m128i {
+        _mm_set1_epi32(0xFF00_0000u32 as i32)
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn blend_coverage_row(row: &mut [Pixel], coverage: &[u8], color: Color) {
+        let zero = _mm_setzero_si128();
+        let color_pixel = _mm_set1_epi32(
+            ((color.red as i32) << 16) | ((color.green as i32) << 8) | (color.blue as i32));
+        let color_lanes = _mm_unpacklo_epi8(color_pixel, zero);
+        let keep_alpha = alpha_byte_mask();
+        let row_ptr = row.as_mut_ptr() as *mut __m128i;
+        for i in 0..row.len() / 4 {
+            let c = &coverage[i * 4..i * 4 + 4];
+            if c[0] == 0 && c[1] == 0 && c[2] == 0 && c[3] == 0 {
+                continue;
+            }
+            let destination = _mm_loadu_si128(row_ptr.add(i));
+            // spread each coverage byte across the 4 channel lanes of its pixel
+            let alpha_lo = _mm_set_epi16(
+                c[1] as i16, c[1] as i16, c[1] as i16, c[1] as i16,
+                c[0] as i16, c[0] as i16, c[0] as i16, c[0] as i16);
+            let alpha_hi = _mm_set_epi16(
+                c[3] as i16, c[3] as i16, c[3] as i16, c[3] as i16,
+                c[2] as i16, c[2] as i16, c[2] as i16, c[2] as i16);
+            let lo = blend_lanes(color_lanes, _mm_unpacklo_epi8(destination, zero), alpha_lo);
+            let hi = blend_lanes(color_lanes, _mm_unpackhi_epi8(destination, zero), alpha_hi);
+            let blended = _mm_packus_epi16(lo, hi);
+            let result = _mm_or_si128(
+                _mm_andnot_si128(keep_alpha, blended),
+                _mm_and_si128(keep_alpha, destination));
+            _mm_storeu_si128(row_ptr.add(i), result);
+        }
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn blend_pixel_row(row: &mut [Pixel], source: &[Pixel]) {
+        let zero = _mm_setzero_si128();
+        let keep_alpha = alpha_byte_mask();
+        let row_ptr = row.as_mut_ptr() as *mut __m128i;
+        let source_ptr = source.as_ptr() as *const __m128i;
+        for i in 0..row.len() / 4 {
+            let src = _mm_loadu_si128(source_ptr.add(i));
+            let alphas = _mm_and_si128(src, keep_alpha);
+            if _mm_movemask_epi8(_mm_cmpeq_epi32(alphas, zero)) == 0xFFFF {
+                continue;
+            }
+            let destination = _mm_loadu_si128(row_ptr.add(i));
+            // move each alpha into the low byte and copy it across the pixel
+            let alpha = _mm_srli_epi32(alphas, 24);
+            let alpha = _mm_or_si128(alpha, _mm_slli_epi32(alpha, 16));
+            let alpha_lo = _mm_unpacklo_epi32(alpha, alpha);
+            let alpha_hi = _mm_unpackhi_epi32(alpha, alpha);
+            let lo = blend_lanes(_mm_unpacklo_epi8(src, zero), _mm_unpacklo_epi8(destination, zero), alpha_lo);
+            let hi = blend_lanes(_mm_unpackhi_epi8(src, zero), _mm_unpackhi_epi8(destination, zero), alpha_hi);
+            let blended = _mm_packus_epi16(lo, hi);
+            let result = _mm_or_si128(
+                _mm_andnot_si128(keep_alpha, blended),
+                _mm_and_si128(keep_alpha, destination));
+            _mm_storeu_si128(row_ptr.add(i), result);
+        }
+    }
+}
+
+#[cfg(test)]
+mod raster_tests {
+    use super::*;
+
+    fn test_row(count: usize) -> Vec<Pixel> {
+        (0..count).map(|i| Pixel::from_rgba((i * 7) as u8, (i * 13) as u8, (i * 29) as u8, (i * 3) as u8)).collect()
+    }
+
+    fn bytes(row: &[Pixel]) -> Vec<(u8, u8, u8, u8)> {
+        row.iter().map(|p| (p.red, p.green, p.blue, p.alpha)).collect()
+    }
+
+    #[test]
+    fn test_blend_coverage_row_matches_scalar() {
+        let color = Color::from_rgb(200, 100, 50);
+        let coverage: Vec<u8> = (0..259).map(|i| (i * 37 % 256) as u8).collect();
+        let mut fast = test_row(259);
+        let mut scalar = test_row(259);
+        blend_coverage_row(&mut fast, &coverage, color);
+        blend_coverage_row_scalar(&mut scalar, &coverage, color);
+        assert_eq!(bytes(&fast), bytes(&scalar));
+    }
+
+    #[test]
+    fn test_blend_pixel_row_matches_scalar() {
+        let source: Vec<Pixel> = (0..259)
+            .map(|i| Pixel::from_rgba((i * 11) as u8, (i * 5) as u8, (i * 17) as u8, (i * 37 % 256) as u8))
+            .collect();
+        let mut fast = test_row(259);
+        let mut scalar = test_row(259);
+        blend_pixel_row(&mut fast, &source);
+        blend_pixel_row_scalar(&mut scalar, &source);
+        assert_eq!(bytes(&fast), bytes(&scalar));
+    }
+}
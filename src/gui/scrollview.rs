@@ -0,0 +1,361 @@
+use crate::gui::is_point_in_rect;
+use crate::gui::style::ScrollbarStyle;
+use crate::gui::Rect;
+use crate::gui::Bounds;
+use std::time::Duration;
+use std::time::Instant;
+
+/*
+    a window onto content bigger than itself. the owner draws the content
+    shifted by offset() and clipped to the view and tells the view how big
+    the content is, the view takes care of the scrollbars, the wheel and
+    keeping whatever the owner asks for in view.
+
+    the scrollbars sit on top of the content along the right and bottom
+    edges and hide themselves when the mouse isn't over the view and it
+    hasn't scrolled for a while. scrolling with the wheel, the keyboard or
+    a track click glides to the new position, dragging the thumb follows
+    the mouse exactly.
+*/
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Axis {
+    Horizontal,
+    Vertical
+}
+
+// how long smooth scrolling takes to cover most of the distance
+const SMOOTH_TIME_CONSTANT: f32 = 0.05;
+
+pub struct ScrollView {
+    pub bounds: Bounds,
+    pub bounds_rect: Rect,
+    // physical pixels
+    pub content_width: i32,
+    pub content_height: i32,
+    // where the content is scrolled to, fractional while gliding
+    pub scroll_x: f32,
+    pub scroll_y: f32,
+    target_x: f32,
+    target_y: f32,
+    pub hot: bool,
+    // the thumb under the mouse
+    pub hot_thumb: Option<Axis>,
+    // the thumb being dragged and where in it the mouse grabbed it
+    drag: Option<(Axis, i32)>,
+    pub scrollbars_visible: bool,
+    last_scrolled: Option<Instant>,
+    last_step: Option<Instant>,
+    pub style: ScrollbarStyle
+}
+
+impl ScrollView {
+    pub fn new(bounds: Bounds, style: ScrollbarStyle) -> ScrollView {
+        ScrollView {
+            bounds,
+            bounds_rect: Rect::default(),
+            content_width: 0,
+            content_height: 0,
+            scroll_x: 0.0,
+            scroll_y: 0.0,
+            target_x: 0.0,
+            target_y: 0.0,
+            hot: false,
+            hot_thumb: None,
+            drag: None,
+            scrollbars_visible: false,
+            last_scrolled: None,
+            last_step: None,
+            style
+        }
+    }
+
+    pub fn update_bounds_rect(&mut self, width: i32, height: i32, scale: f32) {
+        self.set_bounds_rect(self.bounds.get_rect(width, height, scale));
+    }
+
+    pub fn set_bounds_rect(&mut self, rect: Rect) {
+        self.bounds_rect = rect;
+        self.clamp();
+    }
+
+    pub fn set_content_size(&mut self, width: i32, height: i32) {
+        self.content_width = width;
+        self.content_height = height;
+        self.clamp();
+    }
+
+    pub fn get_scaled_style(&self) -> ScrollbarStyle {
+        self.style.scaled(crate::gui::get_scale_factor())
+    }
+
+    pub fn max_scroll(&self) -> (f32, f32) {
+        (std::cmp::max(0, self.content_width - self.bounds_rect.w) as f32,
+         std::cmp::max(0, self.content_height - self.bounds_rect.h) as f32)
+    }
+
+    // how far to shift the content when drawing it, negative or 0
+    pub fn offset(&self) -> (i32, i32) {
+        (-self.scroll_x.round() as i32, -self.scroll_y.round() as i32)
+    }
+
+    fn clamp(&mut self) {
+        let (max_x, max_y) = self.max_scroll();
+        self.target_x = self.target_x.clamp(0.0, max_x);
+        self.target_y = self.target_y.clamp(0.0, max_y);
+        self.scroll_x = self.scroll_x.clamp(0.0, max_x);
+        self.scroll_y = self.scroll_y.clamp(0.0, max_y);
+    }
+
+    pub fn scroll_to(&mut self, x: f32, y: f32) {
+        self.target_x = x;
+        self.target_y = y;
+        if !self.style.smooth {
+            self.scroll_x = x;
+            self.scroll_y = y;
+        }
+        self.clamp();
+        self.last_scrolled = Some(Instant::now());
+        self.scrollbars_visible = self.has_scrollbar(Axis::Horizontal) || self.has_scrollbar(Axis::Vertical);
+    }
+
+    // relative to where a scroll in progress is heading, so quick wheel
+    // turns add up instead of starting over from wherever the glide is
+    pub fn scroll_by(&mut self, dx: f32, dy: f32) {
+        self.scroll_to(self.target_x + dx, self.target_y + dy);
+    }
+
+    // scrolls as little as possible to show a rect given in content
+    // coordinates, for keeping the selection visible
+    pub fn scroll_into_view(&mut self, rect: Rect) {
+        let view = self.bounds_rect;
+        let mut x = self.target_x;
+        let mut y = self.target_y;
+        if (rect.x + rect.w) as f32 > x + view.w as f32 {
+            x = (rect.x + rect.w - view.w) as f32;
+        }
+        if (rect.x as f32) < x {
+            x = rect.x as f32;
+        }
+        if (rect.y + rect.h) as f32 > y + view.h as f32 {
+            y = (rect.y + rect.h - view.h) as f32;
+        }
+        if (rect.y as f32) < y {
+            y = rect.y as f32;
+        }
+        if x != self.target_x || y != self.target_y {
+            self.scroll_to(x, y);
+        }
+    }
+
+    // positive lines_y is the wheel turned away from the user, which
+    // scrolls up
+    pub fn wheel(&mut self, lines_x: f32, lines_y: f32) {
+        let line_size = self.get_scaled_style().line_size as f32;
+        self.scroll_by(lines_x * line_size, -lines_y * line_size);
+    }
+
+    pub fn page(&mut self, axis: Axis, pages: f32) {
+        match axis {
+            Axis::Horizontal => self.scroll_by(pages * self.bounds_rect.w as f32, 0.0),
+            Axis::Vertical => self.scroll_by(0.0, pages * self.bounds_rect.h as f32)
+        }
+    }
+
+    pub fn has_scrollbar(&self, axis: Axis) -> bool {
+        match axis {
+            Axis::Horizontal => self.content_width > self.bounds_rect.w,
+            Axis::Vertical => self.content_height > self.bounds_rect.h
+        }
+    }
+
+    // the track along the edge, shortened so the two don't overlap in the
+    // corner when both are there
+    pub fn scrollbar_rect(&self, axis: Axis) -> Option<Rect> {
+        if !self.has_scrollbar(axis) {
+            return None;
+        }
+        let size = self.get_scaled_style().size;
+        let r = self.bounds_rect;
+        let corner = |other: Axis| if self.has_scrollbar(other) { size } else { 0 };
+        Some(match axis {
+            Axis::Vertical => Rect { x: r.x + r.w - size, y: r.y, w: size, h: r.h - corner(Axis::Horizontal) },
+            Axis::Horizontal => Rect { x: r.x, y: r.y + r.h - size, w: r.w - corner(Axis::Vertical), h: size }
+        })
+    }
+
+    // as long as the view is compared to the content, and never too small
+    // to grab
+    pub fn thumb_rect(&self, axis: Axis) -> Option<Rect> {
+        let track = self.scrollbar_rect(axis)?;
+        let style = self.get_scaled_style();
+        let (track_length, view, content, scroll, max) = match axis {
+            Axis::Vertical => (track.h, self.bounds_rect.h, self.content_height, self.scroll_y, self.max_scroll().1),
+            Axis::Horizontal => (track.w, self.bounds_rect.w, self.content_width, self.scroll_x, self.max_scroll().0)
+        };
+        let length = std::cmp::min(track_length, std::cmp::max(style.min_thumb_size, track_length * view / content));
+        let position = if max > 0.0 { ((track_length - length) as f32 * scroll / max).round() as i32 } else { 0 };
+        Some(match axis {
+            Axis::Vertical => Rect { x: track.x, y: track.y + position, w: track.w, h: length },
+            Axis::Horizontal => Rect { x: track.x + position, y: track.y, w: length, h: track.h }
+        })
+    }
+
+    fn scrollbar_at(&self, x: i32, y: i32) -> Option<Axis> {
+        [Axis::Vertical, Axis::Horizontal].iter().copied()
+            .find(|axis| self.scrollbar_rect(*axis).is_some_and(|r| is_point_in_rect(x, y, r)))
+    }
+
+    // true when the click was on a scrollbar, anything else is for the
+    // content. the thumb starts a drag, the track pages towards the click.
+    pub fn left_mouse_button_down(&mut self, x: i32, y: i32) -> bool {
+        let axis = match self.scrollbar_at(x, y) {
+            Some(axis) if self.scrollbars_visible => axis,
+            _ => return false
+        };
+        let thumb = match self.thumb_rect(axis) {
+            Some(thumb) => thumb,
+            None => return false
+        };
+        if is_point_in_rect(x, y, thumb) {
+            let grab = match axis { Axis::Vertical => y - thumb.y, Axis::Horizontal => x - thumb.x };
+            self.drag = Some((axis, grab));
+        }
+        else {
+            let before = match axis { Axis::Vertical => y < thumb.y, Axis::Horizontal => x < thumb.x };
+            self.page(axis, if before { -1.0 } else { 1.0 });
+        }
+        true
+    }
+
+    pub fn left_mouse_button_up(&mut self) {
+        self.drag = None;
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    // follows a thumb drag and keeps track of what's hot, true when
+    // something needs to be drawn again
+    pub fn mouse_move(&mut self, x: i32, y: i32) -> bool {
+        let was = (self.hot, self.hot_thumb, self.scroll_x, self.scroll_y, self.scrollbars_visible);
+        self.hot = is_point_in_rect(x, y, self.bounds_rect);
+        self.hot_thumb = self.scrollbar_at(x, y)
+            .filter(|axis| self.thumb_rect(*axis).is_some_and(|t| is_point_in_rect(x, y, t)));
+        if let Some((axis, grab)) = self.drag {
+            if let (Some(track), Some(thumb)) = (self.scrollbar_rect(axis), self.thumb_rect(axis)) {
+                let (max_x, max_y) = self.max_scroll();
+                let (position, room, max) = match axis {
+                    Axis::Vertical => (y - grab - track.y, track.h - thumb.h, max_y),
+                    Axis::Horizontal => (x - grab - track.x, track.w - thumb.w, max_x)
+                };
+                let scroll = if room > 0 { position as f32 / room as f32 * max } else { 0.0 };
+                match axis {
+                    Axis::Vertical => { self.target_y = scroll; self.scroll_y = scroll; },
+                    Axis::Horizontal => { self.target_x = scroll; self.scroll_x = scroll; }
+                }
+                self.clamp();
+                self.last_scrolled = Some(Instant::now());
+            }
+        }
+        self.update_visibility(Instant::now());
+        was != (self.hot, self.hot_thumb, self.scroll_x, self.scroll_y, self.scrollbars_visible)
+    }
+
+    // moves a glide along, true when the content moved. the distance left
+    // shrinks the same amount each second whatever the frame rate is.
+    pub fn step(&mut self, now: Instant) -> bool {
+        let dt = self.last_step.map_or(0.016, |last| now.saturating_duration_since(last).as_secs_f32());
+        let moving = self.scroll_x != self.target_x || self.scroll_y != self.target_y;
+        self.last_step = if moving { Some(now) } else { None };
+        if !moving {
+            return false;
+        }
+        let factor = 1.0 - (-dt / SMOOTH_TIME_CONSTANT).exp();
+        self.scroll_x += (self.target_x - self.scroll_x) * factor;
+        self.scroll_y += (self.target_y - self.scroll_y) * factor;
+        if (self.target_x - self.scroll_x).abs() < 0.5 && (self.target_y - self.scroll_y).abs() < 0.5 {
+            self.scroll_x = self.target_x;
+            self.scroll_y = self.target_y;
+        }
+        true
+    }
+
+    // the scrollbars show while the mouse is over the view or a thumb is
+    // being dragged and for a while after the last scroll. true when that
+    // changed.
+    pub fn update_visibility(&mut self, now: Instant) -> bool {
+        let recently = self.last_scrolled
+            .is_some_and(|t| now.saturating_duration_since(t) < Duration::from_millis(self.style.auto_hide_ms));
+        let overflows = self.has_scrollbar(Axis::Horizontal) || self.has_scrollbar(Axis::Vertical);
+        let always = self.style.auto_hide_ms == 0;
+        let visible = overflows && (always || self.hot || self.drag.is_some() || recently);
+        let changed = visible != self.scrollbars_visible;
+        self.scrollbars_visible = visible;
+        changed
+    }
+}
+
+#[cfg(test)]
+mod scrollview_tests {
+    use super::*;
+
+    fn create_scrollview_for_test() -> ScrollView {
+        let mut view = ScrollView::new(Bounds::int(0, 0, 100, 200), ScrollbarStyle { smooth: false, ..ScrollbarStyle::default() });
+        view.bounds_rect = Rect { x: 0, y: 0, w: 100, h: 200 };
+        view.set_content_size(100, 1000);
+        view
+    }
+
+    #[test]
+    fn test_thumb_and_clamping() {
+        let mut view = create_scrollview_for_test();
+        assert!(view.has_scrollbar(Axis::Vertical));
+        assert!(!view.has_scrollbar(Axis::Horizontal));
+        let size = view.style.size;
+        // a fifth of the content shows so the thumb is a fifth of the track
+        let thumb = view.thumb_rect(Axis::Vertical).unwrap();
+        assert_eq!((thumb.x, thumb.y, thumb.w, thumb.h), (100 - size, 0, size, 40));
+        view.scroll_to(0.0, 5000.0);
+        assert_eq!(view.scroll_y, 800.0);
+        assert_eq!(view.thumb_rect(Axis::Vertical).unwrap().y, 160);
+        assert_eq!(view.offset(), (0, -800));
+        view.wheel(0.0, 1.0);
+        assert_eq!(view.scroll_y, 800.0 - view.style.line_size as f32);
+    }
+
+    #[test]
+    fn test_scroll_into_view() {
+        let mut view = create_scrollview_for_test();
+        view.scroll_into_view(Rect { x: 0, y: 100, w: 100, h: 20 });
+        assert_eq!(view.scroll_y, 0.0);
+        view.scroll_into_view(Rect { x: 0, y: 500, w: 100, h: 20 });
+        assert_eq!(view.scroll_y, 320.0);
+        view.scroll_into_view(Rect { x: 0, y: 300, w: 100, h: 20 });
+        assert_eq!(view.scroll_y, 300.0);
+    }
+
+    #[test]
+    fn test_track_click_and_smooth_step() {
+        let mut view = create_scrollview_for_test();
+        view.style.smooth = true;
+        view.update_visibility(Instant::now());
+        view.hot = true;
+        view.update_visibility(Instant::now());
+        assert!(view.scrollbars_visible);
+        // below the thumb pages down, the glide gets there over a few frames
+        assert!(view.left_mouse_button_down(95, 150));
+        assert_eq!(view.scroll_y, 0.0);
+        let start = Instant::now();
+        let mut frames = 0;
+        while view.step(start + Duration::from_millis(16 * frames)) {
+            frames += 1;
+            assert!(frames < 100);
+        }
+        assert_eq!(view.scroll_y, 200.0);
+        assert!(frames > 2);
+        // clicks on the content aren't for the scroll view
+        assert!(!view.left_mouse_button_down(20, 150));
+    }
+}
@@ -0,0 +1,192 @@
+use crate::gui::is_point_in_rect;
+use crate::gui::style::BoxStyle;
+use crate::gui::style::StateStyles;
+use crate::gui::Rect;
+use crate::gui::scale_i32;
+use crate::gui::get_scale_factor;
+use crate::gui::control::Control;
+use crate::gui::control::ControlState;
+use crate::gui::control::INDICATOR_SIZE;
+use crate::gui::text::manager::FontManager;
+use crate::gui::widget::Widget;
+use crate::gui::widget::Event;
+use crate::gui::mouse::MouseButton;
+use crate::gui::keyboard::Key;
+use crate::gui::keyboard::KeyState;
+use crate::gui::draw::draw_slider;
+use crate::gui::PixelBuffer;
+
+/*
+    a value between min and max picked by dragging a thumb along a track.
+    the value is always a whole number of steps from min. a click on the
+    track jumps the thumb there and keeps dragging it. the arrows and the
+    wheel while it has focus move it a step, page up and down ten steps
+    and home and end to the ends. it raises Event::Changed whenever the
+    value moves.
+*/
+pub struct Slider {
+    pub value: f32,
+    pub min: f32,
+    pub max: f32,
+    pub step: f32,
+    pub bounds: Rect,
+    pub state: ControlState,
+    pub styles: StateStyles,
+    changed: bool
+}
+
+impl Slider {
+    pub fn new(value: f32, min: f32, max: f32, step: f32, styles: StateStyles) -> Slider {
+        let mut slider = Slider { value: min, min, max, step, bounds: Rect::default(), state: ControlState::default(), styles, changed: false };
+        slider.value = slider.snap(value);
+        slider
+    }
+
+    // the nearest step inside the range
+    fn snap(&self, value: f32) -> f32 {
+        let value = value.clamp(self.min, self.max);
+        if self.step > 0.0 {
+            (self.min + ((value - self.min) / self.step).round() * self.step).clamp(self.min, self.max)
+        }
+        else {
+            value
+        }
+    }
+
+    pub fn set_value(&mut self, value: f32) {
+        let value = self.snap(value);
+        if value != self.value {
+            self.value = value;
+            self.changed = true;
+        }
+    }
+
+    fn step_by(&mut self, steps: f32) {
+        self.set_value(self.value + self.step * steps);
+    }
+
+    // the thumb's radius, the track runs between the centers of the thumb
+    // at either end
+    pub fn thumb_radius(&self) -> i32 {
+        scale_i32(INDICATOR_SIZE, get_scale_factor()) / 2
+    }
+
+    pub fn track_rect(&self) -> Rect {
+        let style = self.get_scaled_style();
+        let radius = self.thumb_radius();
+        let left = self.bounds.x + style.padding_size.left + radius;
+        let right = self.bounds.x + self.bounds.w - style.padding_size.right - radius;
+        let height = std::cmp::max(1, scale_i32(4, get_scale_factor()));
+        Rect { x: left, y: self.bounds.y + (self.bounds.h - height) / 2, w: std::cmp::max(0, right - left), h: height }
+    }
+
+    // where the thumb's center is along the track
+    pub fn thumb_x(&self) -> i32 {
+        let track = self.track_rect();
+        let range = self.max - self.min;
+        let fraction = if range > 0.0 { (self.value - self.min) / range } else { 0.0 };
+        track.x + (track.w as f32 * fraction).round() as i32
+    }
+
+    fn value_at(&self, x: i32) -> f32 {
+        let track = self.track_rect();
+        let fraction = if track.w > 0 { (x - track.x) as f32 / track.w as f32 } else { 0.0 };
+        self.min + (self.max - self.min) * fraction
+    }
+}
+
+impl Widget for Slider {
+    fn bounds(&self) -> Rect { self.bounds }
+    fn set_bounds_rect(&mut self, rect: Rect) { self.bounds = rect }
+
+    fn draw(&self, buffer: &mut PixelBuffer, fonts: &FontManager, _draw_cursor: bool) {
+        draw_slider(buffer, self, fonts);
+    }
+
+    fn event(&mut self, event: &Event) -> bool {
+        if self.state.disabled {
+            return false;
+        }
+        match event {
+            // the mouse is captured while dragging so the thumb follows it
+            // outside the slider too
+            Event::MouseMove { x, y } => {
+                let hot = self.hit_check(*x, *y).0;
+                if !self.state.active {
+                    return hot;
+                }
+                self.set_value(self.value_at(*x));
+            },
+            Event::MouseDown { button: MouseButton::Left, x, y, .. } if is_point_in_rect(*x, *y, self.bounds) => {
+                self.state.active = true;
+                self.set_value(self.value_at(*x));
+            },
+            Event::MouseUp { button: MouseButton::Left, .. } => self.state.active = false,
+            Event::Wheel { lines_y, .. } if self.state.focused && *lines_y != 0.0 => self.step_by(lines_y.signum()),
+            Event::Key(key) if key.state == KeyState::Pressed => match key.key {
+                Key::Left | Key::Down => self.step_by(-1.0),
+                Key::Right | Key::Up => self.step_by(1.0),
+                Key::PageDown => self.step_by(-10.0),
+                Key::PageUp => self.step_by(10.0),
+                Key::Home => self.set_value(self.min),
+                Key::End => self.set_value(self.max),
+                _ => return false
+            },
+            _ => return false
+        }
+        true
+    }
+
+    fn raised(&mut self) -> Option<Event> {
+        if std::mem::take(&mut self.changed) { Some(Event::Changed) } else { None }
+    }
+
+    fn focus_style(&self) -> Option<BoxStyle> {
+        if self.state.disabled { None } else { Some(self.get_scaled_style()) }
+    }
+
+    fn set_focused(&mut self, focused: bool) { self.state.focused = focused }
+}
+
+impl Control for Slider {
+    fn get_bounds(&self) -> Rect { self.bounds }
+    fn get_hot(&self) -> bool { self.state.hot }
+    fn set_hot(&mut self, hit: bool) { self.state.hot = hit }
+    fn get_style(&self) -> &BoxStyle { self.styles.select(&self.state) }
+}
+
+#[cfg(test)]
+mod slider_tests {
+    use super::*;
+    use crate::gui::keyboard::{KeyCode, KeyEvent, KeyboardModifiers};
+
+    #[test]
+    fn test_drag_and_keys_snap_to_steps() {
+        let mut slider = Slider::new(33.0, 0.0, 100.0, 5.0, StateStyles::control_default());
+        assert_eq!(slider.value, 35.0);
+        slider.set_bounds_rect(Rect { x: 0, y: 0, w: 220, h: 30 });
+        let track = slider.track_rect();
+
+        // down at the middle of the track and dragged past its end
+        slider.event(&Event::MouseDown { button: MouseButton::Left, x: track.x + track.w / 2, y: 15, clicks: 1 });
+        assert_eq!(slider.value, 50.0);
+        assert!(matches!(slider.raised(), Some(Event::Changed)));
+        slider.event(&Event::MouseMove { x: 1000, y: 200 });
+        assert_eq!(slider.value, 100.0);
+        assert_eq!(slider.thumb_x(), track.x + track.w);
+        slider.event(&Event::MouseUp { button: MouseButton::Left, x: 1000, y: 200 });
+        slider.event(&Event::MouseMove { x: track.x, y: 15 });
+        assert_eq!(slider.value, 100.0);
+
+        let key = |key| Event::Key(KeyEvent { code: KeyCode::Unknown(0), key, modifiers: KeyboardModifiers::default(), state: KeyState::Pressed, repeat: false });
+        slider.event(&key(Key::Left));
+        assert_eq!(slider.value, 95.0);
+        slider.event(&key(Key::PageDown));
+        assert_eq!(slider.value, 45.0);
+        slider.event(&key(Key::Home));
+        assert_eq!(slider.value, 0.0);
+        slider.raised();
+        slider.event(&key(Key::Down));
+        assert!(slider.raised().is_none());
+    }
+}
@@ -0,0 +1,238 @@
+use crate::gui::is_point_in_rect;
+use crate::gui::style::BoxStyle;
+use crate::gui::style::StateStyles;
+use crate::gui::Rect;
+use crate::gui::scale_i32;
+use crate::gui::get_scale_factor;
+use crate::gui::control::Control;
+use crate::gui::control::ControlState;
+use crate::gui::textbox::TextBox;
+use crate::gui::text::manager::FontManager;
+use crate::gui::widget::Widget;
+use crate::gui::widget::Event;
+use crate::gui::mouse::MouseButton;
+use crate::gui::keyboard::Key;
+use crate::gui::keyboard::KeyState;
+use crate::gui::keyboard::TextInput;
+use crate::gui::draw::draw_spinbox;
+use crate::gui::Cursor;
+use crate::gui::PixelBuffer;
+use std::time::Instant;
+
+/*
+    a whole number typed into a text box or stepped with the arrows beside
+    it. only digits and a minus sign can be typed, the number is read back
+    on enter, a step or when the focus leaves and kept between min and max,
+    escape puts back the last one. up and down and the wheel step it, page
+    up and down ten steps. it raises Event::Changed when the number
+    changes.
+*/
+pub struct SpinBox {
+    pub value: i32,
+    pub min: i32,
+    pub max: i32,
+    pub step: i32,
+    pub text: TextBox,
+    pub bounds: Rect,
+    // logical pixels, the column the arrows are in
+    pub arrows_width: i32,
+    pub state: ControlState,
+    pub styles: StateStyles,
+    changed: bool
+}
+
+impl SpinBox {
+    pub fn new(value: i32, min: i32, max: i32, step: i32, styles: StateStyles) -> SpinBox {
+        let mut spinbox = SpinBox {
+            value: value.clamp(min, max),
+            min,
+            max,
+            step,
+            text: TextBox::new("", styles.normal),
+            bounds: Rect::default(),
+            arrows_width: 20,
+            state: ControlState::default(),
+            styles,
+            changed: false
+        };
+        spinbox.text.set_text(&spinbox.value.to_string());
+        spinbox
+    }
+
+    pub fn set_value(&mut self, value: i32) {
+        let value = value.clamp(self.min, self.max);
+        if value != self.value {
+            self.value = value;
+            self.changed = true;
+        }
+        self.text.set_text(&self.value.to_string());
+    }
+
+    // reads the typed number, anything that isn't one puts the last one back
+    pub fn commit(&mut self) {
+        let typed: String = self.text.text.iter().collect();
+        match typed.trim().parse::<i32>() {
+            Ok(value) => self.set_value(value),
+            Err(_) => self.set_value(self.value)
+        }
+    }
+
+    fn step_by(&mut self, steps: i32) {
+        self.commit();
+        self.set_value(self.value.saturating_add(self.step.saturating_mul(steps)));
+    }
+
+    fn arrows_rect(&self) -> Rect {
+        let width = scale_i32(self.arrows_width, get_scale_factor());
+        Rect { x: self.bounds.x + self.bounds.w - width, y: self.bounds.y, w: width, h: self.bounds.h }
+    }
+
+    // the arrow up on top and down under it
+    pub fn arrow_rect(&self, up: bool) -> Rect {
+        let arrows = self.arrows_rect();
+        let half = arrows.h / 2;
+        if up { Rect { h: half, ..arrows } } else { Rect { y: arrows.y + half, h: arrows.h - half, ..arrows } }
+    }
+
+    // the text box takes the state's style so it's drawn like the rest
+    fn update_style(&mut self) {
+        self.text.style = *self.styles.select(&self.state);
+    }
+
+    fn send_to_text(&mut self, event: &Event) -> bool {
+        match event {
+            Event::Text(TextInput::Commit(text)) => {
+                let number: String = text.chars().filter(|c| c.is_ascii_digit() || *c == '-').collect();
+                self.text.event(&Event::Text(TextInput::Commit(number)))
+            },
+            _ => self.text.event(event)
+        }
+    }
+}
+
+impl Widget for SpinBox {
+    fn bounds(&self) -> Rect { self.bounds }
+
+    fn set_bounds_rect(&mut self, rect: Rect) {
+        self.bounds = rect;
+        let arrows = self.arrows_rect();
+        self.text.set_bounds_rect(Rect { w: arrows.x - rect.x, ..rect });
+    }
+
+    fn draw(&self, buffer: &mut PixelBuffer, fonts: &FontManager, draw_cursor: bool) {
+        draw_spinbox(buffer, self, fonts, draw_cursor);
+    }
+
+    fn event(&mut self, event: &Event) -> bool {
+        if self.state.disabled {
+            return false;
+        }
+        let used = match event {
+            Event::MouseMove { x, y } => {
+                let hot = self.hit_check(*x, *y).0;
+                self.text.event(event) || hot
+            },
+            Event::MouseDown { button: MouseButton::Left, x, y, .. } if is_point_in_rect(*x, *y, self.arrows_rect()) => {
+                self.state.active = true;
+                self.step_by(if is_point_in_rect(*x, *y, self.arrow_rect(true)) { 1 } else { -1 });
+                true
+            },
+            Event::MouseUp { button: MouseButton::Left, .. } if self.state.active => {
+                self.state.active = false;
+                true
+            },
+            Event::Wheel { lines_y, .. } if self.state.focused && *lines_y != 0.0 => {
+                self.step_by(lines_y.signum() as i32);
+                true
+            },
+            Event::Key(key) if key.state == KeyState::Pressed => match key.key {
+                Key::Up => { self.step_by(1); true },
+                Key::Down => { self.step_by(-1); true },
+                Key::PageUp => { self.step_by(10); true },
+                Key::PageDown => { self.step_by(-10); true },
+                Key::Enter => { self.commit(); true },
+                Key::Escape => { self.set_value(self.value); true },
+                _ => self.send_to_text(event)
+            },
+            _ => self.send_to_text(event)
+        };
+        self.update_style();
+        used
+    }
+
+    fn raised(&mut self) -> Option<Event> {
+        if std::mem::take(&mut self.changed) { Some(Event::Changed) } else { None }
+    }
+
+    fn cursor(&self) -> Cursor { Cursor::IBeam }
+
+    fn focus_style(&self) -> Option<BoxStyle> {
+        if self.state.disabled { None } else { Some(self.get_scaled_style()) }
+    }
+
+    // the typed number counts once the focus leaves
+    fn set_focused(&mut self, focused: bool) {
+        self.state.focused = focused;
+        self.text.set_focused(focused);
+        if !focused {
+            self.commit();
+        }
+        self.update_style();
+    }
+
+    fn caret_rect(&self) -> Option<Rect> { Widget::caret_rect(&self.text) }
+    fn timer_tick(&mut self, now: Instant) -> bool { self.text.timer_tick(now) }
+}
+
+impl Control for SpinBox {
+    fn get_bounds(&self) -> Rect { self.bounds }
+    fn get_hot(&self) -> bool { self.state.hot }
+    fn set_hot(&mut self, hit: bool) { self.state.hot = hit }
+    fn get_style(&self) -> &BoxStyle { self.styles.select(&self.state) }
+}
+
+#[cfg(test)]
+mod spinbox_tests {
+    use super::*;
+    use crate::gui::text::font::Font;
+    use crate::gui::keyboard::{KeyCode, KeyEvent, KeyboardModifiers};
+
+    #[test]
+    fn test_typing_stepping_and_limits() {
+        if unsafe { crate::APPLICATION_STATE.fonts.len() } == 0 {
+            let font = include_bytes!("../../fonts/OpenSans-Regular.ttf") as &[u8];
+            unsafe { crate::APPLICATION_STATE.fonts.add_font(Font::from_bytes(font).unwrap()); }
+        }
+        let mut spinbox = SpinBox::new(12, 6, 72, 2, StateStyles::control_default());
+        spinbox.set_bounds_rect(Rect { x: 0, y: 0, w: 120, h: 30 });
+        spinbox.set_focused(true);
+        let key = |key| Event::Key(KeyEvent { code: KeyCode::Unknown(0), key, modifiers: KeyboardModifiers::default(), state: KeyState::Pressed, repeat: false });
+        let text = |spinbox: &SpinBox| spinbox.text.text.iter().collect::<String>();
+
+        spinbox.event(&key(Key::Up));
+        assert_eq!((spinbox.value, text(&spinbox)), (14, "14".to_string()));
+        assert!(matches!(spinbox.raised(), Some(Event::Changed)));
+        let down = spinbox.arrow_rect(false);
+        spinbox.event(&Event::MouseDown { button: MouseButton::Left, x: down.x + 5, y: down.y + 5, clicks: 1 });
+        spinbox.event(&Event::MouseUp { button: MouseButton::Left, x: down.x + 5, y: down.y + 5 });
+        assert_eq!(spinbox.value, 12);
+
+        // letters don't go in, the number is read on enter and kept in range
+        spinbox.event(&Event::Text(TextInput::Commit("0px".to_string())));
+        assert_eq!(text(&spinbox), "120");
+        spinbox.event(&key(Key::Enter));
+        assert_eq!((spinbox.value, text(&spinbox)), (72, "72".to_string()));
+
+        spinbox.event(&key(Key::Back));
+        spinbox.event(&key(Key::Escape));
+        assert_eq!(text(&spinbox), "72");
+        spinbox.raised();
+        spinbox.event(&key(Key::PageUp));
+        assert!(spinbox.raised().is_none());
+        spinbox.event(&key(Key::Back));
+        spinbox.event(&key(Key::Back));
+        spinbox.event(&Event::Text(TextInput::Commit("-5".to_string())));
+        spinbox.set_focused(false);
+        assert_eq!(spinbox.value, 6);
+    }
+}
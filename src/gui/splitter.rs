@@ -0,0 +1,309 @@
+use crate::gui::is_point_in_rect;
+use crate::gui::color::Color;
+use crate::gui::color::DarkTheme;
+use crate::gui::Rect;
+use crate::gui::scale_i32;
+use crate::gui::text::manager::FontManager;
+use crate::gui::widget::Widget;
+use crate::gui::widget::Event;
+use crate::gui::mouse::MouseButton;
+use crate::gui::draw::draw_splitter;
+use crate::gui::Cursor;
+use crate::gui::PixelBuffer;
+
+/*
+    a vertical bar between two panes that can be dragged sideways. where it
+    sits is a fraction of the window so it keeps its place when the window
+    resizes, but never closer to its neighbours than the panes' minimum
+    sizes. a double click collapses the pane on one side and another double
+    click brings it back. the application lays the panes out around it.
+*/
+pub struct Splitter {
+    // the key it is saved under in the layout file
+    pub name: &'static str,
+    // fraction of the window width where the bar starts
+    pub ratio: f32,
+    // logical pixels
+    pub min_before: i32,
+    pub min_after: i32,
+    pub max_before: Option<i32>,
+    pub thickness: i32,
+    // which pane a double click hides
+    pub collapse_after: bool,
+    pub collapsed: bool,
+    pub color: Color,
+    pub hot_color: Color,
+    pub hot: bool,
+    // physical pixels, the space between the neighbouring splitters or the
+    // window edges, and how far down it goes
+    start: i32,
+    end: i32,
+    top: i32,
+    window_width: i32,
+    height: i32,
+    scale: f32,
+    // where in the bar it was grabbed
+    drag: Option<i32>
+}
+
+impl Splitter {
+    pub fn new(name: &'static str, ratio: f32, collapse_after: bool) -> Splitter {
+        Splitter {
+            name,
+            ratio,
+            min_before: 100,
+            min_after: 100,
+            max_before: None,
+            thickness: 4,
+            collapse_after,
+            collapsed: false,
+            color: DarkTheme::BACKGROUND_LIGHT,
+            hot_color: DarkTheme::FOCUS,
+            hot: false,
+            start: 0,
+            end: 0,
+            top: 0,
+            window_width: 0,
+            height: 0,
+            scale: 1.0,
+            drag: None
+        }
+    }
+
+    // area is the space it can move in, the ratio is of the whole window
+    pub fn update_bounds_rect(&mut self, area: Rect, window_width: i32, scale: f32) {
+        self.start = area.x;
+        self.end = area.x + area.w;
+        self.top = area.y;
+        self.height = area.h;
+        self.window_width = window_width;
+        self.scale = scale;
+    }
+
+    pub fn scaled_thickness(&self) -> i32 {
+        scale_i32(self.thickness, self.scale)
+    }
+
+    // the furthest left and right the bar can go, when the space is too
+    // small for both minimums the pane before wins
+    fn limits(&self) -> (i32, i32) {
+        let low = self.start + scale_i32(self.min_before, self.scale);
+        let mut high = self.end - scale_i32(self.min_after, self.scale) - self.scaled_thickness();
+        if let Some(max) = self.max_before {
+            high = std::cmp::min(high, self.start + scale_i32(max, self.scale));
+        }
+        (low, std::cmp::max(low, high))
+    }
+
+    // where the bar starts in window coordinates
+    pub fn position(&self) -> i32 {
+        if self.collapsed {
+            return if self.collapse_after { self.end - self.scaled_thickness() } else { self.start };
+        }
+        let (low, high) = self.limits();
+        let x = (self.ratio * self.window_width as f32).round() as i32;
+        x.clamp(low, high)
+    }
+
+    pub fn get_rect(&self) -> Rect {
+        Rect { x: self.position(), y: self.top, w: self.scaled_thickness(), h: self.height }
+    }
+
+    // a few pixels either side of the bar grab it too, it is thin
+    pub fn hit_rect(&self) -> Rect {
+        let slop = scale_i32(3, self.scale);
+        let rect = self.get_rect();
+        Rect { x: rect.x - slop - 1, y: rect.y - 1, w: rect.w + slop * 2 + 2, h: rect.h + 2 }
+    }
+
+    fn hit(&self, x: i32, y: i32) -> bool {
+        is_point_in_rect(x, y, self.hit_rect())
+    }
+
+    pub fn toggle_collapsed(&mut self) {
+        self.collapsed = !self.collapsed;
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    // true when the splitter took the click
+    pub fn left_mouse_button_down(&mut self, x: i32, y: i32, clicks: u32) -> bool {
+        if !self.hit(x, y) {
+            return false;
+        }
+        if clicks == 2 {
+            self.drag = None;
+            self.toggle_collapsed();
+            return true;
+        }
+        // dragging a collapsed bar opens the pane from where the bar is
+        let position = self.position();
+        if self.collapsed {
+            self.collapsed = false;
+            self.ratio = position as f32 / std::cmp::max(1, self.window_width) as f32;
+        }
+        self.drag = Some(x - position);
+        true
+    }
+
+    // true when the splitter moved or changed how it looks
+    pub fn mouse_move(&mut self, x: i32, y: i32) -> bool {
+        let hot = self.drag.is_some() || self.hit(x, y);
+        let hot_changed = hot != self.hot;
+        self.hot = hot;
+        match self.drag {
+            Some(grab) => {
+                let before = self.position();
+                let (low, high) = self.limits();
+                let position = (x - grab).clamp(low, high);
+                self.ratio = position as f32 / std::cmp::max(1, self.window_width) as f32;
+                hot_changed || position != before
+            },
+            None => hot_changed
+        }
+    }
+
+    // true when a drag ended
+    pub fn left_mouse_button_up(&mut self) -> bool {
+        self.drag.take().is_some()
+    }
+}
+
+impl Widget for Splitter {
+    fn bounds(&self) -> Rect { self.hit_rect() }
+    // it places itself between its neighbours, see update_bounds_rect
+    fn set_bounds_rect(&mut self, _rect: Rect) { }
+
+    fn draw(&self, buffer: &mut PixelBuffer, _fonts: &FontManager, _draw_cursor: bool) {
+        draw_splitter(buffer, self);
+    }
+
+    // the panes move with it, the application lays them out again before
+    // the next paint and saves where it ended up
+    fn event(&mut self, event: &Event) -> bool {
+        match event {
+            Event::MouseDown { button: MouseButton::Left, x, y, clicks } => {
+                if !self.left_mouse_button_down(*x, *y, *clicks) {
+                    return false;
+                }
+                // a double click collapsed or opened a pane
+                crate::request_layout(!self.is_dragging());
+                true
+            },
+            Event::MouseUp { button: MouseButton::Left, .. } => {
+                let ended = self.left_mouse_button_up();
+                if ended {
+                    crate::request_layout(true);
+                }
+                ended
+            },
+            Event::MouseMove { x, y } => {
+                let changed = self.mouse_move(*x, *y);
+                if changed {
+                    crate::request_layout(false);
+                }
+                changed
+            },
+            _ => false
+        }
+    }
+
+    fn cursor(&self) -> Cursor { Cursor::ResizeHorizontal }
+}
+
+// the layout file has a line per splitter with its ratio and another when
+// it is collapsed, like
+//     sidebar = 0.2
+//     preview.collapsed = true
+pub fn save_splitters(splitters: &[&Splitter]) -> String {
+    let mut text = String::new();
+    for splitter in splitters {
+        text.push_str(&format!("{} = {}\n", splitter.name, splitter.ratio));
+        if splitter.collapsed {
+            text.push_str(&format!("{}.collapsed = true\n", splitter.name));
+        }
+    }
+    text
+}
+
+// anything it doesn't understand is skipped, the defaults are fine
+pub fn load_splitters(splitters: &mut [&mut Splitter], text: &str) {
+    for line in text.lines() {
+        let mut parts = line.splitn(2, '=');
+        let (key, value) = match (parts.next(), parts.next()) {
+            (Some(key), Some(value)) => (key.trim(), value.trim()),
+            _ => continue
+        };
+        let (name, collapsed) = match key.strip_suffix(".collapsed") {
+            Some(name) => (name, true),
+            None => (key, false)
+        };
+        if let Some(splitter) = splitters.iter_mut().find(|s| s.name == name) {
+            if collapsed {
+                splitter.collapsed = value == "true";
+            }
+            else if let Ok(ratio) = value.parse::<f32>() {
+                if (0.0..=1.0).contains(&ratio) {
+                    splitter.ratio = ratio;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod splitter_tests {
+    use super::*;
+
+    #[test]
+    fn test_dragging_stays_within_limits() {
+        let mut splitter = Splitter::new("sidebar", 0.2, false);
+        splitter.max_before = Some(500);
+        splitter.update_bounds_rect(Rect { x: 0, y: 0, w: 1000, h: 600 }, 1000, 1.0);
+        assert_eq!(splitter.position(), 200);
+
+        assert!(splitter.left_mouse_button_down(201, 300, 1));
+        assert!(splitter.mouse_move(351, 300));
+        assert_eq!(splitter.position(), 350);
+        splitter.mouse_move(20, 300);
+        assert_eq!(splitter.position(), 100);
+        splitter.mouse_move(900, 300);
+        assert_eq!(splitter.position(), 500);
+        assert!(splitter.left_mouse_button_up());
+        assert!(!splitter.is_dragging());
+        assert_eq!(splitter.ratio, 0.5);
+
+        // a neighbour coming closer keeps the minimum after it
+        splitter.update_bounds_rect(Rect { x: 0, y: 0, w: 300, h: 600 }, 1000, 1.0);
+        assert_eq!(splitter.position(), 196);
+    }
+
+    #[test]
+    fn test_double_click_collapses() {
+        let mut splitter = Splitter::new("preview", 0.6, true);
+        splitter.update_bounds_rect(Rect { x: 200, y: 0, w: 800, h: 600 }, 1000, 1.0);
+        assert!(!splitter.left_mouse_button_down(100, 300, 2));
+        assert!(splitter.left_mouse_button_down(601, 300, 2));
+        assert!(splitter.collapsed);
+        assert_eq!(splitter.position(), 996);
+        assert!(splitter.left_mouse_button_down(997, 300, 2));
+        assert_eq!(splitter.position(), 600);
+    }
+
+    #[test]
+    fn test_layout_round_trip() {
+        let mut splitters = [Splitter::new("sidebar", 0.2, false), Splitter::new("preview", 0.6, true)];
+        splitters[0].ratio = 0.25;
+        splitters[1].collapsed = true;
+        let text = save_splitters(&splitters.iter().collect::<Vec<_>>());
+
+        let mut loaded = [Splitter::new("sidebar", 0.2, false), Splitter::new("preview", 0.6, true)];
+        load_splitters(&mut loaded.iter_mut().collect::<Vec<_>>(), &format!("{}unknown = 0.3\nsidebar.collapsed = yes\npreview = 7\n", text));
+        assert_eq!(loaded[0].ratio, 0.25);
+        assert!(!loaded[0].collapsed);
+        assert_eq!(loaded[1].ratio, 0.6);
+        assert!(loaded[1].collapsed);
+    }
+}
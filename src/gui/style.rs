@@ -1,28 +1,53 @@
 
 use crate::gui::color::Color;
+use crate::gui::color::DarkTheme;
+use crate::gui::scale_i32;
+use crate::gui::control::ControlState;
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
 pub enum HorizontalAlign {
+    #[default]
     Left,
-    //Right,
-    Center
+    Right,
+    Center,
+    // stretches the spaces so a line that fits reaches both edges
+    Justify
 }
 
-impl Default for HorizontalAlign {
-    fn default() -> HorizontalAlign { HorizontalAlign::Left }
+// what happens to text wider than its box
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum TextOverflow {
+    #[default]
+    Clip,
+    // name_of_the_f…
+    EllipsisEnd,
+    // name_of…le.txt, keeps the extension of file names visible
+    EllipsisMiddle
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Default)]
 pub enum VerticalAlign {
+    #[default]
     Center,
     Bottom,
     Top
 }
 
-impl Default for VerticalAlign {
-    fn default() -> VerticalAlign { VerticalAlign::Center }
+// css style font weights, 400 is regular and 700 bold
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct FontWeight(pub u16);
+
+impl FontWeight {
+    pub const REGULAR: FontWeight = FontWeight(400);
+    pub const BOLD: FontWeight = FontWeight(700);
 }
 
+impl Default for FontWeight {
+    fn default() -> FontWeight { FontWeight::REGULAR }
+}
+
+pub const DEFAULT_FONT_FAMILY: &str = "Open Sans";
+
 #[derive(Debug, Copy, Clone, Default)]
 pub struct BoxSize {
     pub left:i32,
@@ -40,6 +65,15 @@ impl BoxSize {
             bottom: s
         }
     }
+
+    pub fn scaled(&self, scale: f32) -> BoxSize {
+        BoxSize {
+            left: scale_i32(self.left, scale),
+            top: scale_i32(self.top, scale),
+            right: scale_i32(self.right, scale),
+            bottom: scale_i32(self.bottom, scale)
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone, Default)]
@@ -51,10 +85,22 @@ pub struct BoxStyle {
     pub text_color: Color,
     pub highlight_color: Color,
     pub text_highlight_color: Color,
+    // the face is picked from the loaded fonts, see FontManager::select
+    pub font_family: &'static str,
+    pub font_weight: FontWeight,
+    pub italic: bool,
     pub font_size: f32,
     pub vertical_align: VerticalAlign,
-    pub horizontal_align: HorizontalAlign
-
+    pub horizontal_align: HorizontalAlign,
+    pub text_overflow: TextOverflow,
+    // multi-line text, line_height multiplies the font's own line height
+    // and line_spacing adds pixels between lines
+    pub word_wrap: bool,
+    pub line_height: f32,
+    pub line_spacing: i32,
+    // the ring drawn around the control when the keyboard moved focus to it
+    pub focus_color: Color,
+    pub focus_size: i32
 }
 
 impl BoxStyle {
@@ -67,9 +113,18 @@ impl BoxStyle {
             text_color: Color::RED,
             highlight_color: Color::DARK_RED,
             text_highlight_color: Color::WHITE,
+            font_family: DEFAULT_FONT_FAMILY,
+            font_weight: FontWeight::REGULAR,
+            italic: false,
             font_size: 30.0,
             vertical_align: VerticalAlign::Center,
-            horizontal_align: HorizontalAlign::Left
+            horizontal_align: HorizontalAlign::Left,
+            text_overflow: TextOverflow::Clip,
+            word_wrap: false,
+            line_height: 1.0,
+            line_spacing: 0,
+            focus_color: Color::DARK_RED,
+            focus_size: 2
         }
     }
     pub const fn button_default() -> BoxStyle {
@@ -91,5 +146,106 @@ impl BoxStyle {
         let style = BoxStyle::default();
         style
     }
+
+    // styles are written in logical pixels, this converts the sizes to
+    // physical pixels for drawing and measuring
+    pub fn scaled(&self, scale: f32) -> BoxStyle {
+        BoxStyle {
+            border_size: self.border_size.scaled(scale),
+            padding_size: self.padding_size.scaled(scale),
+            font_size: self.font_size * scale,
+            line_spacing: scale_i32(self.line_spacing, scale),
+            focus_size: scale_i32(self.focus_size, scale),
+            ..*self
+        }
+    }
+}
+
+/*
+    an input control's style in each of its states. disabled wins over the
+    rest, then pressed, then under the mouse, then focused.
+*/
+#[derive(Debug, Copy, Clone)]
+pub struct StateStyles {
+    pub normal: BoxStyle,
+    pub hot: BoxStyle,
+    pub active: BoxStyle,
+    pub focused: BoxStyle,
+    pub disabled: BoxStyle
+}
+
+impl StateStyles {
+    // the same style in every state
+    pub const fn new(style: BoxStyle) -> StateStyles {
+        StateStyles { normal: style, hot: style, active: style, focused: style, disabled: style }
+    }
+
+    pub const fn control_default() -> StateStyles {
+        let mut styles = StateStyles::new(BoxStyle::default());
+        styles.hot = BoxStyle::button_default_hot();
+        styles.hot.horizontal_align = HorizontalAlign::Left;
+        styles.active = BoxStyle::button_default_active();
+        styles.active.horizontal_align = HorizontalAlign::Left;
+        styles.disabled.text_color = Color::LIGHT_GRAY;
+        styles.disabled.border_color = Color::LIGHT_GRAY;
+        styles
+    }
+
+    pub fn select(&self, state: &ControlState) -> &BoxStyle {
+        if state.disabled {
+            &self.disabled
+        }
+        else if state.active {
+            &self.active
+        }
+        else if state.hot {
+            &self.hot
+        }
+        else if state.focused {
+            &self.focused
+        }
+        else {
+            &self.normal
+        }
+    }
 }
 
+// the scrollbars of a scroll view, sizes in logical pixels
+#[derive(Debug, Copy, Clone)]
+pub struct ScrollbarStyle {
+    pub track_color: Color,
+    pub thumb_color: Color,
+    pub thumb_hot_color: Color,
+    pub size: i32,
+    pub min_thumb_size: i32,
+    // how far a line of the mouse wheel scrolls
+    pub line_size: i32,
+    // the scrollbars hide this long after the last scroll once the mouse
+    // has left the view, 0 always shows them
+    pub auto_hide_ms: u64,
+    pub smooth: bool
+}
+
+impl ScrollbarStyle {
+    pub const fn default() -> ScrollbarStyle {
+        ScrollbarStyle {
+            track_color: DarkTheme::SCROLL_BACKGROUND,
+            thumb_color: DarkTheme::SCROLL_FOREGROUND,
+            thumb_hot_color: Color::from_rgb(0x6f, 0x6f, 0x6f),
+            size: 10,
+            min_thumb_size: 20,
+            line_size: 20,
+            auto_hide_ms: 1000,
+            smooth: true
+        }
+    }
+
+    pub fn scaled(&self, scale: f32) -> ScrollbarStyle {
+        ScrollbarStyle {
+            size: scale_i32(self.size, scale),
+            min_thumb_size: scale_i32(self.min_thumb_size, scale),
+            line_size: scale_i32(self.line_size, scale),
+            ..*self
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use crate::gui::is_point_in_rect;
+use crate::gui::style::BoxStyle;
+use crate::gui::Rect;
+use crate::gui::scale_i32;
+use crate::gui::get_scale_factor;
+use crate::gui::text::manager::FontManager;
+use crate::gui::widget::Widget;
+use crate::gui::widget::Event;
+use crate::gui::mouse::MouseButton;
+use crate::gui::draw::draw_tab_bar;
+use crate::gui::PixelBuffer;
+
+/*
+    a row of tabs, one per open folder. the tabs share the width until
+    they reach tab_width, the active one is drawn in its own style. a
+    click on another tab makes it active and raises Changed, what the tab
+    shows is up to the application.
+*/
+pub struct TabBar {
+    pub bounds_rect: Rect,
+    pub style: BoxStyle,
+    pub active_style: BoxStyle,
+    // logical pixels, the widest a tab gets
+    pub tab_width: i32,
+    pub titles: Vec<String>,
+    pub active: usize,
+    changed: bool
+}
+
+impl TabBar {
+    pub fn new(style: BoxStyle, active_style: BoxStyle) -> TabBar {
+        TabBar {
+            bounds_rect: Rect::default(),
+            style,
+            active_style,
+            tab_width: 200,
+            titles: Vec::new(),
+            active: 0,
+            changed: false
+        }
+    }
+
+    pub fn tab_rect(&self, index: usize) -> Rect {
+        let count = std::cmp::max(1, self.titles.len() as i32);
+        let width = std::cmp::min(scale_i32(self.tab_width, get_scale_factor()), self.bounds_rect.w / count);
+        Rect { x: self.bounds_rect.x + width * index as i32, y: self.bounds_rect.y, w: width, h: self.bounds_rect.h }
+    }
+
+    pub fn tab_at(&self, x: i32, y: i32) -> Option<usize> {
+        (0..self.titles.len()).find(|i| is_point_in_rect(x, y, self.tab_rect(*i)))
+    }
+}
+
+impl Widget for TabBar {
+    fn bounds(&self) -> Rect { self.bounds_rect }
+    fn set_bounds_rect(&mut self, rect: Rect) { self.bounds_rect = rect }
+
+    fn draw(&self, buffer: &mut PixelBuffer, fonts: &FontManager, _draw_cursor: bool) {
+        draw_tab_bar(buffer, self, fonts);
+    }
+
+    fn event(&mut self, event: &Event) -> bool {
+        match event {
+            Event::MouseDown { button: MouseButton::Left, x, y, .. } => match self.tab_at(*x, *y) {
+                Some(index) if index != self.active => {
+                    self.active = index;
+                    self.changed = true;
+                    true
+                },
+                _ => false
+            },
+            _ => false
+        }
+    }
+
+    fn raised(&mut self) -> Option<Event> {
+        if std::mem::take(&mut self.changed) { Some(Event::Changed) } else { None }
+    }
+
+    // the whole title when it's shortened
+    fn tooltip(&self, x: i32, y: i32) -> Option<String> {
+        self.tab_at(x, y).map(|i| self.titles[i].clone())
+    }
+}
+
+#[cfg(test)]
+mod tabbar_tests {
+    use super::*;
+
+    #[test]
+    fn test_click_switches_tab() {
+        let mut bar = TabBar::new(BoxStyle::default(), BoxStyle::default());
+        bar.titles = vec!["src".to_string(), "docs".to_string(), "fonts".to_string()];
+        bar.set_bounds_rect(Rect { x: 0, y: 0, w: 300, h: 30 });
+        // too many to fit at full width so they share it
+        assert_eq!(bar.tab_rect(1).x, 100);
+        assert_eq!(bar.tab_at(250, 10), Some(2));
+
+        assert!(bar.event(&Event::MouseDown { button: MouseButton::Left, x: 150, y: 10, clicks: 1 }));
+        assert_eq!(bar.active, 1);
+        assert!(matches!(bar.raised(), Some(Event::Changed)));
+        assert!(bar.raised().is_none());
+        // the active tab again changes nothing
+        assert!(!bar.event(&Event::MouseDown { button: MouseButton::Left, x: 150, y: 10, clicks: 1 }));
+    }
+}
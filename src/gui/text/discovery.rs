@@ -0,0 +1,249 @@
+
+/*
+    finds the fonts installed on the system. the font directories for the
+    platform are scanned recursively for font files, on linux the <dir>
+    entries of the fontconfig config are scanned too the way fontconfig
+    itself builds its cache. nothing is parsed here, the files are only
+    loaded once the fallback chain needs them.
+*/
+
+use std::path::Path;
+use std::path::PathBuf;
+
+const FONT_EXTENSIONS: [&str; 4] = ["ttf", "otf", "ttc", "otc"];
+
+// deep enough for /usr/share/fonts/truetype/<vendor>/<family> layouts and
+// stops symlink loops from going on forever
+const MAX_SCAN_DEPTH: usize = 6;
+
+const FONTCONFIG_FILE: &str = "/etc/fonts/fonts.conf";
+
+/*
+    file names of fonts worth falling back to, in the order they're tried.
+    each group covers scripts the ones before it don't, the names are
+    matched without case so the same list works on every platform.
+*/
+pub const FALLBACK_PREFERENCE: &[&str] = &[
+    // latin, greek, cyrillic and lots of symbols
+    "segoeui.ttf",
+    "dejavusans.ttf",
+    "notosans-regular.ttf",
+    "liberationsans-regular.ttf",
+    "arial.ttf",
+    "helvetica.ttc",
+    // symbols and emoji, outlines only since glyphs are rasterized as coverage
+    "seguisym.ttf",
+    "seguiemj.ttf",
+    "notosanssymbols-regular.ttf",
+    "notosanssymbols2-regular.ttf",
+    "symbola.ttf",
+    "apple symbols.ttf",
+    // chinese, japanese and korean
+    "msyh.ttc",
+    "msjh.ttc",
+    "yugothm.ttc",
+    "meiryo.ttc",
+    "malgun.ttf",
+    "simsun.ttc",
+    "notosanscjk-regular.ttc",
+    "notosanscjksc-regular.otf",
+    "notosanscjkjp-regular.otf",
+    "notosanscjkkr-regular.otf",
+    "wqy-microhei.ttc",
+    "wqy-zenhei.ttc",
+    "droidsansfallbackfull.ttf",
+    "droidsansfallback.ttf",
+    "pingfang.ttc",
+    "hiragino sans gb.ttc",
+    "applesdgothicneo.ttc",
+    // indic, arabic, hebrew, african scripts
+    "nirmala.ttf",
+    "ebrima.ttf",
+    "notosansarabic-regular.ttf",
+    "notosanshebrew-regular.ttf",
+    // huge last resort
+    "arialuni.ttf"
+];
+
+pub fn system_font_directories() -> Vec<PathBuf> {
+    let mut directories = Vec::<PathBuf>::new();
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+
+    if cfg!(windows) {
+        let windows_directory = std::env::var_os("WINDIR").unwrap_or_else(|| "C:\\Windows".into());
+        directories.push(PathBuf::from(windows_directory).join("Fonts"));
+        // fonts installed for the current user only
+        if let Some(local) = std::env::var_os("LOCALAPPDATA") {
+            directories.push(PathBuf::from(local).join("Microsoft\\Windows\\Fonts"));
+        }
+    }
+    else if cfg!(target_os = "macos") {
+        directories.push(PathBuf::from("/System/Library/Fonts"));
+        directories.push(PathBuf::from("/Library/Fonts"));
+        if let Some(home) = &home {
+            directories.push(home.join("Library/Fonts"));
+        }
+    }
+    else {
+        let data_home = match std::env::var_os("XDG_DATA_HOME") {
+            Some(d) => Some(PathBuf::from(d)),
+            None => home.as_ref().map(|h| h.join(".local/share"))
+        };
+        if let Ok(config) = std::fs::read_to_string(FONTCONFIG_FILE) {
+            directories.extend(fontconfig_directories(&config, home.as_deref(), data_home.as_deref()));
+        }
+        directories.push(PathBuf::from("/usr/share/fonts"));
+        directories.push(PathBuf::from("/usr/local/share/fonts"));
+        if let Some(data_home) = &data_home {
+            directories.push(data_home.join("fonts"));
+        }
+        if let Some(home) = &home {
+            directories.push(home.join(".fonts"));
+        }
+    }
+
+    let mut unique = Vec::<PathBuf>::new();
+    for directory in directories {
+        if !unique.contains(&directory) {
+            unique.push(directory);
+        }
+    }
+    unique
+}
+
+/*
+    pulls the <dir> entries out of a fontconfig config. a leading ~ is the
+    home directory and prefix="xdg" is relative to the xdg data home, the
+    same rules fontconfig uses. <include> files aren't followed.
+*/
+pub fn fontconfig_directories(config: &str, home: Option<&Path>, data_home: Option<&Path>) -> Vec<PathBuf> {
+    let mut directories = Vec::<PathBuf>::new();
+    let mut rest = config;
+    while let Some(start) = rest.find("<dir") {
+        rest = &rest[start + 4..];
+        let tag_end = match rest.find('>') {
+            Some(i) => i,
+            None => break
+        };
+        let attributes = &rest[..tag_end];
+        rest = &rest[tag_end + 1..];
+        // <directory> or a self closing <dir/> aren't what we're after
+        if attributes.ends_with('/') || !(attributes.is_empty() || attributes.starts_with(' ')) {
+            continue;
+        }
+        let value_end = match rest.find("</dir>") {
+            Some(i) => i,
+            None => break
+        };
+        let value = rest[..value_end].trim();
+        rest = &rest[value_end..];
+
+        let directory = if attributes.contains("prefix=\"xdg\"") {
+            data_home.map(|d| d.join(value))
+        }
+        else if let Some(path) = value.strip_prefix('~') {
+            home.map(|h| h.join(path.trim_start_matches('/')))
+        }
+        else {
+            Some(PathBuf::from(value))
+        };
+        if let Some(directory) = directory {
+            directories.push(directory);
+        }
+    }
+    directories
+}
+
+// every font file in the directories and their subdirectories
+pub fn scan_font_files(directories: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files = Vec::<PathBuf>::new();
+    for directory in directories {
+        scan_directory(directory, 0, &mut files);
+    }
+    files
+}
+
+fn scan_directory(directory: &Path, depth: usize, files: &mut Vec<PathBuf>) {
+    if depth > MAX_SCAN_DEPTH {
+        return;
+    }
+    let entries = match std::fs::read_dir(directory) {
+        Ok(e) => e,
+        Err(_) => return
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            scan_directory(&path, depth + 1, files);
+        }
+        else if is_font_file(&path) {
+            files.push(path);
+        }
+    }
+}
+
+fn is_font_file(path: &Path) -> bool {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(extension) => FONT_EXTENSIONS.iter().any(|e| extension.eq_ignore_ascii_case(e)),
+        None => false
+    }
+}
+
+// picks the fonts in the preference list out of the discovered files, in
+// preference order. when a name is installed in more than one place the
+// first one found wins.
+pub fn fallback_chain(files: &[PathBuf]) -> Vec<PathBuf> {
+    let mut chain = Vec::<PathBuf>::new();
+    for name in FALLBACK_PREFERENCE.iter() {
+        let found = files.iter().find(|file| {
+            match file.file_name().and_then(|n| n.to_str()) {
+                Some(file_name) => file_name.eq_ignore_ascii_case(name),
+                None => false
+            }
+        });
+        if let Some(file) = found {
+            chain.push(file.clone());
+        }
+    }
+    chain
+}
+
+#[cfg(test)]
+mod discovery_tests {
+    use super::*;
+
+    #[test]
+    fn test_fontconfig_directories() {
+        let config = r#"
+            <fontconfig>
+                <dir>/usr/share/fonts</dir>
+                <dir prefix="xdg">fonts</dir>
+                <dir>~/.fonts</dir>
+                <directory>not a dir</directory>
+                <cachedir>/var/cache/fontconfig</cachedir>
+            </fontconfig>"#;
+        let directories = fontconfig_directories(config, Some(Path::new("/home/a")), Some(Path::new("/home/a/.local/share")));
+        assert_eq!(directories, vec![
+            PathBuf::from("/usr/share/fonts"),
+            PathBuf::from("/home/a/.local/share/fonts"),
+            PathBuf::from("/home/a/.fonts")]);
+    }
+
+    #[test]
+    fn test_scan_and_fallback_order() {
+        let root = std::env::temp_dir().join(format!("filex_font_scan_{}", std::process::id()));
+        let nested = root.join("truetype").join("noto");
+        std::fs::create_dir_all(&nested).unwrap();
+        for path in [root.join("MSYH.TTC"), root.join("readme.txt"), nested.join("NotoSans-Regular.ttf")].iter() {
+            std::fs::write(path, b"").unwrap();
+        }
+
+        let mut files = scan_font_files(std::slice::from_ref(&root));
+        files.sort();
+        assert_eq!(files.len(), 2);
+        let chain = fallback_chain(&files);
+        assert_eq!(chain, vec![nested.join("NotoSans-Regular.ttf"), root.join("MSYH.TTC")]);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
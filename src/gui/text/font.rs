@@ -0,0 +1,45 @@
+
+use crate::gui::text::opentype::FontInfo;
+use crate::gui::text::opentype::ShapingTables;
+
+// a fontdue font plus the opentype tables it doesn't read itself
+pub struct Font {
+    pub font: fontdue::Font,
+    pub info: FontInfo,
+    pub tables: ShapingTables
+}
+
+impl Font {
+    pub fn from_bytes(data: &[u8]) -> Result<Font, &'static str> {
+        Font::from_collection(data, 0)
+    }
+
+    // loads one font out of a .ttc collection, index 0 for plain font files
+    pub fn from_collection(data: &[u8], collection_index: u32) -> Result<Font, &'static str> {
+        let settings = fontdue::FontSettings { collection_index, ..fontdue::FontSettings::default() };
+        let font = fontdue::Font::from_bytes(data, settings)?;
+        Ok(Font {
+            font,
+            info: FontInfo::parse(data, collection_index),
+            tables: ShapingTables::parse(data, collection_index)
+        })
+    }
+
+    pub fn has_glyph(&self, c: char) -> bool {
+        self.font.lookup_glyph_index(c) != 0
+    }
+
+    // kerning between two glyphs in pixels. GPOS is used when the font has
+    // it, otherwise fontdue reads the old style kern table.
+    pub fn kerning(&self, first: u16, second: u16, font_size: f32) -> f32 {
+        if self.tables.has_kerning() {
+            match self.tables.kerning(first, second) {
+                Some(units) => units as f32 * font_size / self.font.units_per_em(),
+                None => 0.0
+            }
+        }
+        else {
+            self.font.horizontal_kern_indexed(first, second, font_size).unwrap_or(0.0)
+        }
+    }
+}
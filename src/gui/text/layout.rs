@@ -0,0 +1,331 @@
+
+/*
+    multi-line text layout. the text is split into paragraphs at newlines
+    and each paragraph is wrapped at the line break opportunities from
+    linebreak.rs. a word that's wider than the wrap width on its own is
+    broken between grapheme clusters so nothing sticks out of the box.
+    spaces at the end of a wrapped line hang past the wrap width and don't
+    count towards its width, so they don't throw off right or centered
+    alignment.
+
+    every line is shaped on its own so ligatures and kerning never cross a
+    line break. positions in the layout are relative to the top left of
+    the text block.
+*/
+
+use crate::gui::style::HorizontalAlign;
+use crate::gui::text::linebreak::line_breaks;
+use crate::gui::text::linebreak::Break;
+use crate::gui::text::line::justify;
+use crate::gui::text::manager::FontManager;
+use crate::gui::text::segment::grapheme_boundaries;
+use crate::gui::text::shape::shape_text;
+use crate::gui::text::shape::ShapedText;
+
+pub struct LayoutLine {
+    // the chars of the text on this line, the newline that ended the
+    // line isn't included
+    pub start: usize,
+    pub end: usize,
+    pub shaped: ShapedText,
+    // left of the line after alignment and top of the line box
+    pub x: i32,
+    pub top: i32,
+    pub baseline: i32,
+    // width without the trailing spaces
+    pub width: i32
+}
+
+pub struct TextLayout {
+    pub lines: Vec<LayoutLine>,
+    pub width: i32,
+    pub height: i32,
+    pub line_height: i32,
+    // grapheme cluster boundaries of the text, hit testing only returns
+    // these
+    pub graphemes: Vec<bool>
+}
+
+pub struct LayoutOptions {
+    // None lays every paragraph out on one line
+    pub wrap_width: Option<i32>,
+    pub align: HorizontalAlign,
+    // multiplier of the font's line height
+    pub line_height: f32,
+    // extra pixels between lines
+    pub line_spacing: i32
+}
+
+impl Default for LayoutOptions {
+    fn default() -> LayoutOptions {
+        LayoutOptions {
+            wrap_width: None,
+            align: HorizontalAlign::Left,
+            line_height: 1.0,
+            line_spacing: 0
+        }
+    }
+}
+
+impl TextLayout {
+    // the line a caret index is on. an index where a line wraps belongs to
+    // the start of the next line, like in every text editor
+    pub fn line_of_index(&self, index: usize) -> usize {
+        self.lines.iter().rposition(|line| line.start <= index).unwrap_or(0)
+    }
+
+    // x and the line of the caret before text[index]
+    pub fn caret_position(&self, index: usize) -> (i32, usize) {
+        let line_index = self.line_of_index(index);
+        let line = &self.lines[line_index];
+        let offset = std::cmp::min(index.saturating_sub(line.start), line.end - line.start);
+        (line.x + line.shaped.caret_positions[offset], line_index)
+    }
+
+    // the caret index closest to a point relative to the text block
+    pub fn index_at_point(&self, x: i32, y: i32) -> usize {
+        let line_index = match self.lines.iter().position(|line| y < line.top + self.line_height) {
+            Some(i) => i,
+            None => self.lines.len() - 1
+        };
+        self.index_at_x(line_index, x)
+    }
+
+    // the caret index on a line closest to x
+    pub fn index_at_x(&self, line_index: usize, x: i32) -> usize {
+        let line = &self.lines[line_index];
+        let boundaries = &self.graphemes[line.start..=line.end];
+        let mut index = line.start + line.shaped.caret_index_at_boundary(x - line.x, boundaries);
+        // a caret after the last char of a wrapped line would show at the
+        // start of the next line, keep it on this line
+        let wrapped = self.lines.get(line_index + 1).is_some_and(|next| next.start == line.end);
+        if wrapped && index == line.end && line.end > line.start {
+            index = (line.start..line.end).rev().find(|i| self.graphemes[*i]).unwrap_or(line.start);
+        }
+        index
+    }
+}
+
+pub fn layout_text(text: &[char], fonts: &FontManager, face: usize, font_size: f32, options: &LayoutOptions) -> TextLayout {
+    let metrics = fonts.font(face).font.horizontal_line_metrics(font_size);
+    let (ascent, natural_height) = match metrics {
+        Some(m) => (m.ascent, m.new_line_size),
+        None => (font_size, font_size * 1.2)
+    };
+    let line_height = std::cmp::max(1, (natural_height * options.line_height).round() as i32 + options.line_spacing);
+    // extra line height is shared above and below the text like css does
+    let half_leading = ((line_height - options.line_spacing) as f32 - natural_height) / 2.0;
+    let baseline_offset = (half_leading + ascent).round() as i32;
+
+    let breaks = line_breaks(text);
+    let mut lines = Vec::<LayoutLine>::new();
+    let mut start = 0;
+    loop {
+        let (end, next) = next_line(text, &breaks, start, fonts, face, font_size, options.wrap_width);
+        let mandatory = breaks[next] == Break::Mandatory;
+        let shaped = shape_text(&text[start..end], fonts, face, font_size);
+        let visible = trim_trailing_spaces(text, start, end);
+        let width = shaped.caret_positions[visible - start];
+        lines.push(LayoutLine { start, end, shaped, x: 0, top: 0, baseline: 0, width });
+        // the last paragraph ends at the end of the text, a newline at the
+        // very end leaves an empty line after it for the caret
+        if next >= text.len() {
+            if next > end && ends_with_newline(text) {
+                let shaped = shape_text(&[], fonts, face, font_size);
+                lines.push(LayoutLine { start: text.len(), end: text.len(), shaped, x: 0, top: 0, baseline: 0, width: 0 });
+            }
+            break;
+        }
+        if mandatory || options.align != HorizontalAlign::Justify {
+            start = next;
+            continue;
+        }
+        // wrapped lines of justified paragraphs are stretched to the edges
+        if let (Some(wrap_width), Some(line)) = (options.wrap_width, lines.last_mut()) {
+            justify(&mut line.shaped, &text[line.start..visible], wrap_width);
+            line.width = wrap_width;
+        }
+        start = next;
+    }
+
+    let width = lines.iter().map(|l| l.width).max().unwrap_or(0);
+    let align_width = options.wrap_width.unwrap_or(width);
+    for (i, line) in lines.iter_mut().enumerate() {
+        line.top = i as i32 * line_height;
+        line.baseline = line.top + baseline_offset;
+        line.x = match options.align {
+            HorizontalAlign::Left | HorizontalAlign::Justify => 0,
+            HorizontalAlign::Right => std::cmp::max(0, align_width - line.width),
+            HorizontalAlign::Center => std::cmp::max(0, (align_width - line.width) / 2)
+        };
+    }
+    let height = lines.len() as i32 * line_height;
+    TextLayout { lines, width, height, line_height, graphemes: grapheme_boundaries(text) }
+}
+
+/*
+    finds where the line starting at start ends. returns the end of the
+    chars drawn on the line and where the next line starts, they differ by
+    the newline chars when the line ends at a newline.
+*/
+fn next_line(text: &[char], breaks: &[Break], start: usize, fonts: &FontManager, face: usize, font_size: f32, wrap_width: Option<i32>) -> (usize, usize) {
+    // the end of the paragraph
+    let mut paragraph_end = text.len();
+    for (i, b) in breaks.iter().enumerate().skip(start + 1) {
+        if *b == Break::Mandatory {
+            paragraph_end = i;
+            break;
+        }
+    }
+    let drawn_end = trim_newline(text, start, paragraph_end);
+    let wrap_width = match wrap_width {
+        Some(w) => w,
+        None => return (drawn_end, paragraph_end)
+    };
+
+    // shaping the whole paragraph gives the width of every prefix
+    let shaped = shape_text(&text[start..drawn_end], fonts, face, font_size);
+    let fits = |end: usize| shaped.caret_positions[trim_trailing_spaces(text, start, end) - start] <= wrap_width;
+    if fits(drawn_end) {
+        return (drawn_end, paragraph_end);
+    }
+
+    let mut best = None;
+    for (i, b) in breaks.iter().enumerate().take(drawn_end).skip(start + 1) {
+        if *b == Break::Allowed {
+            if fits(i) {
+                best = Some(i);
+            }
+            else {
+                break;
+            }
+        }
+    }
+    match best {
+        Some(end) => (end, end),
+        None => {
+            // a single word wider than the line, break it where it stops
+            // fitting but only between grapheme clusters so an accent never
+            // ends up on the next line, and always move forward at least
+            // one whole cluster
+            let graphemes = grapheme_boundaries(&text[start..drawn_end]);
+            let next_cluster = |from: usize| (from + 1..graphemes.len()).find(|i| graphemes[*i]).unwrap_or(graphemes.len() - 1);
+            let mut end = next_cluster(0);
+            while end < drawn_end - start && shaped.caret_positions[next_cluster(end)] <= wrap_width {
+                end = next_cluster(end);
+            }
+            (start + end, start + end)
+        }
+    }
+}
+
+fn trim_trailing_spaces(text: &[char], start: usize, end: usize) -> usize {
+    let mut end = end;
+    while end > start && text[end - 1] == ' ' {
+        end -= 1;
+    }
+    end
+}
+
+fn trim_newline(text: &[char], start: usize, end: usize) -> usize {
+    let mut end = end;
+    if end > start && matches!(text[end - 1], '\n' | '\u{0B}' | '\u{0C}' | '\u{85}' | '\u{2028}' | '\u{2029}') {
+        end -= 1;
+    }
+    if end > start && text[end - 1] == '\r' {
+        end -= 1;
+    }
+    end
+}
+
+fn ends_with_newline(text: &[char]) -> bool {
+    match text.last() {
+        Some(c) => trim_newline(text, text.len() - 1, text.len()) != text.len() || *c == '\r',
+        None => false
+    }
+}
+
+#[cfg(test)]
+mod layout_tests {
+    use super::*;
+    use crate::gui::text::font::Font;
+
+    fn load_fonts() -> FontManager {
+        let font = include_bytes!("../../../fonts/OpenSans-Regular.ttf") as &[u8];
+        let mut fonts = FontManager::new();
+        fonts.add_font(Font::from_bytes(font).unwrap());
+        fonts
+    }
+
+    fn line_texts(text: &[char], layout: &TextLayout) -> Vec<String> {
+        layout.lines.iter().map(|l| text[l.start..l.end].iter().collect()).collect()
+    }
+
+    #[test]
+    fn test_wrap_and_newlines() {
+        let fonts = load_fonts();
+        let text: Vec<char> = "the quick brown fox\njumps over\r\n\nthe lazy dog".chars().collect();
+        let word = shape_text(&"quick brown".chars().collect::<Vec<char>>(), &fonts, 0, 20.0).width;
+        let options = LayoutOptions { wrap_width: Some(word + 2), ..LayoutOptions::default() };
+        let layout = layout_text(&text, &fonts, 0, 20.0, &options);
+        assert_eq!(line_texts(&text, &layout), vec!["the quick ", "brown fox", "jumps over", "", "the lazy dog"]);
+        assert!(layout.lines.iter().all(|l| l.width <= word + 2));
+        assert_eq!(layout.height, layout.line_height * 5);
+        assert_eq!(layout.lines[2].top, layout.line_height * 2);
+
+        // unwrapped only breaks at newlines
+        let layout = layout_text(&text, &fonts, 0, 20.0, &LayoutOptions::default());
+        assert_eq!(layout.lines.len(), 4);
+    }
+
+    #[test]
+    fn test_long_word_and_trailing_newline() {
+        let fonts = load_fonts();
+        let text: Vec<char> = "abcdefghijklmnop\n".chars().collect();
+        let options = LayoutOptions { wrap_width: Some(40), ..LayoutOptions::default() };
+        let layout = layout_text(&text, &fonts, 0, 20.0, &options);
+        assert!(layout.lines.len() > 3);
+        assert!(layout.lines.iter().all(|l| l.width <= 40));
+        // every char is on exactly one line and the newline leaves an empty last line
+        assert_eq!(layout.lines.last().unwrap().start, text.len());
+        assert_eq!(layout.lines[layout.lines.len() - 2].end, text.len() - 1);
+    }
+
+    #[test]
+    fn test_long_word_breaks_between_clusters() {
+        let fonts = load_fonts();
+        // every e carries a combining acute that has to stay with it
+        let text: Vec<char> = "e\u{301}".repeat(12).chars().collect();
+        let options = LayoutOptions { wrap_width: Some(30), ..LayoutOptions::default() };
+        let layout = layout_text(&text, &fonts, 0, 20.0, &options);
+        assert!(layout.lines.len() > 1);
+        assert!(layout.lines.iter().all(|l| l.end > l.start && l.start % 2 == 0 && l.end % 2 == 0));
+        assert_eq!(layout.lines.last().unwrap().end, text.len());
+
+        // a cluster wider than the line still goes on a line of its own
+        let options = LayoutOptions { wrap_width: Some(1), ..LayoutOptions::default() };
+        let layout = layout_text(&text, &fonts, 0, 20.0, &options);
+        assert_eq!(layout.lines.len(), 12);
+        assert!(layout.lines.iter().all(|l| l.end - l.start == 2));
+    }
+
+    #[test]
+    fn test_caret_mapping_and_alignment() {
+        let fonts = load_fonts();
+        let text: Vec<char> = "one two three four".chars().collect();
+        let word = shape_text(&"one two".chars().collect::<Vec<char>>(), &fonts, 0, 20.0).width;
+        let options = LayoutOptions { wrap_width: Some(word + 5), align: HorizontalAlign::Right, line_height: 1.5, line_spacing: 3 };
+        let layout = layout_text(&text, &fonts, 0, 20.0, &options);
+        assert_eq!(line_texts(&text, &layout), vec!["one two ", "three ", "four"]);
+        // right aligned lines end at the wrap width
+        assert!(layout.lines.iter().all(|l| l.x + l.width == word + 5));
+
+        for index in 0..=text.len() {
+            let (x, line) = layout.caret_position(index);
+            assert_eq!(layout.index_at_point(x, layout.lines[line].top + 1), index);
+        }
+        // clicking past the end of a wrapped line stays on that line
+        assert_eq!(layout.index_at_point(10_000, 1), 7);
+        assert_eq!(layout.index_at_point(-5, 10_000), 14);
+    }
+}
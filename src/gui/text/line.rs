@@ -0,0 +1,210 @@
+
+/*
+    single line layout on top of shaping: justification and ellipsis
+    truncation. both work from the caret positions of the shaped text so
+    kerning and ligatures are accounted for.
+*/
+
+use crate::gui::style::TextOverflow;
+use crate::gui::text::manager::FontManager;
+use crate::gui::text::segment::grapheme_boundaries;
+use crate::gui::text::shape::shape_text;
+use crate::gui::text::shape::ShapedText;
+
+pub const ELLIPSIS: char = '\u{2026}';
+
+/*
+    spreads the space left over in width between the spaces of the text.
+    leading and trailing spaces don't stretch, text without spaces or that
+    doesn't fit is left alone.
+*/
+pub fn justify(shaped: &mut ShapedText, text: &[char], width: i32) {
+    let extra = width - shaped.width;
+    let first = text.iter().position(|c| *c != ' ');
+    let last = text.iter().rposition(|c| *c != ' ');
+    let (first, last) = match (first, last) {
+        (Some(f), Some(l)) => (f, l),
+        _ => return
+    };
+    let gaps: Vec<usize> = (first..last).filter(|i| text[*i] == ' ').collect();
+    if extra <= 0 || gaps.is_empty() {
+        return;
+    }
+
+    // how far everything from char i onwards moves, the remainder goes to
+    // the first gaps a pixel each
+    let per_gap = extra / gaps.len() as i32;
+    let remainder = extra as usize % gaps.len();
+    let mut shifts = vec![0; text.len() + 1];
+    let mut shift = 0;
+    let mut gap = 0;
+    for (i, s) in shifts.iter_mut().enumerate() {
+        *s = shift;
+        if gap < gaps.len() && gaps[gap] == i {
+            shift += per_gap + if gap < remainder { 1 } else { 0 };
+            gap += 1;
+        }
+    }
+
+    for glyph in shaped.glyphs.iter_mut() {
+        glyph.x += shifts[glyph.cluster];
+    }
+    for (position, s) in shaped.caret_positions.iter_mut().zip(shifts.iter()) {
+        *position += s;
+    }
+    shaped.width = width;
+}
+
+/*
+    the text to show when it's wider than width and the overflow mode is an
+    ellipsis, None when the text fits or is clipped. the middle ellipsis
+    keeps the file extension visible when there is room for it:
+
+        very_long_file_name.txt -> very_long_fi…me.txt
+*/
+pub fn truncate_text(text: &[char], fonts: &FontManager, face: usize, font_size: f32, width: i32, overflow: TextOverflow) -> Option<Vec<char>> {
+    if overflow == TextOverflow::Clip {
+        return None;
+    }
+    let shaped = shape_text(text, fonts, face, font_size);
+    if shaped.width <= width {
+        return None;
+    }
+    let ellipsis_width = shape_text(&[ELLIPSIS], fonts, face, font_size).width;
+    let available = width - ellipsis_width;
+    let carets = &shaped.caret_positions;
+    let total = shaped.width;
+    let count = text.len();
+
+    // head is text[..head] and tail is text[tail..]
+    let (mut head, mut tail) = match overflow {
+        TextOverflow::EllipsisEnd => {
+            (carets.iter().rposition(|x| *x <= available).unwrap_or(0), count)
+        },
+        _ => {
+            let extension_width = match text.iter().rposition(|c| *c == '.') {
+                Some(dot) if dot > 0 => total - carets[dot],
+                _ => 0
+            };
+            let tail_width = if extension_width <= available * 2 / 3 {
+                std::cmp::max(available / 2, extension_width)
+            }
+            else {
+                available / 2
+            };
+            let tail = carets.iter().position(|x| total - *x <= tail_width).unwrap_or(count);
+            let head = carets.iter().rposition(|x| *x + (total - carets[tail]) <= available).unwrap_or(0);
+            (std::cmp::min(head, tail), tail)
+        }
+    };
+
+    // never cut a cluster in half, an accent or half an emoji sequence
+    // left next to the ellipsis would draw on the wrong thing
+    let graphemes = grapheme_boundaries(text);
+    let prev_cluster = |i: usize| (0..i).rev().find(|j| graphemes[*j]).unwrap_or(0);
+    let next_cluster = |i: usize| (i + 1..=count).find(|j| graphemes[*j]).unwrap_or(count);
+    if !graphemes[head] {
+        head = prev_cluster(head);
+    }
+    if !graphemes[tail] {
+        tail = next_cluster(tail);
+    }
+
+    // kerning across the cut can make the result a pixel or two wider than
+    // the sum of its parts, trim until it really fits
+    loop {
+        let mut result = Vec::<char>::with_capacity(head + 1 + count - tail);
+        result.extend_from_slice(&text[..head]);
+        result.push(ELLIPSIS);
+        result.extend_from_slice(&text[tail..]);
+        if shape_text(&result, fonts, face, font_size).width <= width || (head == 0 && tail == count) {
+            return Some(result);
+        }
+        if head > 0 && (tail == count || head >= count - tail) {
+            head = prev_cluster(head);
+        }
+        else {
+            tail = next_cluster(tail);
+        }
+    }
+}
+
+#[cfg(test)]
+mod line_tests {
+    use super::*;
+    use crate::gui::text::font::Font;
+
+    fn load_fonts() -> FontManager {
+        let font = include_bytes!("../../../fonts/OpenSans-Regular.ttf") as &[u8];
+        let mut fonts = FontManager::new();
+        fonts.add_font(Font::from_bytes(font).unwrap());
+        fonts
+    }
+
+    fn chars(text: &str) -> Vec<char> {
+        text.chars().collect()
+    }
+
+    fn width(fonts: &FontManager, text: &[char]) -> i32 {
+        shape_text(text, fonts, 0, 20.0).width
+    }
+
+    #[test]
+    fn test_justify() {
+        let fonts = load_fonts();
+        let text = chars("a b  c");
+        let mut shaped = shape_text(&text, &fonts, 0, 20.0);
+        let natural = shaped.caret_positions.clone();
+        justify(&mut shaped, &text, natural[6] + 10);
+        assert_eq!(shaped.width, natural[6] + 10);
+        // 3 gaps share 10 pixels as 4, 3, 3
+        assert_eq!(shaped.caret_positions[1], natural[1]);
+        assert_eq!(shaped.caret_positions[2], natural[2] + 4);
+        assert_eq!(shaped.caret_positions[4], natural[4] + 7);
+        assert_eq!(shaped.caret_positions[5], natural[5] + 10);
+        assert_eq!(shaped.glyphs[5].x, natural[5] + 10);
+    }
+
+    #[test]
+    fn test_truncate_end_and_middle() {
+        let fonts = load_fonts();
+        let text = chars("very_long_file_name.txt");
+        let full = width(&fonts, &text);
+        assert!(truncate_text(&text, &fonts, 0, 20.0, full, TextOverflow::EllipsisEnd).is_none());
+        assert!(truncate_text(&text, &fonts, 0, 20.0, full / 2, TextOverflow::Clip).is_none());
+
+        let end = truncate_text(&text, &fonts, 0, 20.0, full / 2, TextOverflow::EllipsisEnd).unwrap();
+        assert!(width(&fonts, &end) <= full / 2);
+        assert_eq!(*end.last().unwrap(), ELLIPSIS);
+        assert!(text.starts_with(&end[..end.len() - 1]));
+
+        let middle = truncate_text(&text, &fonts, 0, 20.0, full * 2 / 3, TextOverflow::EllipsisMiddle).unwrap();
+        let middle: String = middle.iter().collect();
+        assert!(width(&fonts, &chars(&middle)) <= full * 2 / 3);
+        assert!(middle.starts_with("very_lo"));
+        assert!(middle.ends_with(".txt"));
+        assert!(middle.contains(ELLIPSIS));
+
+        // nothing fits but the ellipsis
+        let tiny = truncate_text(&text, &fonts, 0, 20.0, 1, TextOverflow::EllipsisMiddle).unwrap();
+        assert_eq!(tiny, vec![ELLIPSIS]);
+    }
+
+    #[test]
+    fn test_truncate_keeps_clusters_whole() {
+        let fonts = load_fonts();
+        // every e carries a combining acute, the cut can only fall on an
+        // even index
+        let text = chars(&"e\u{301}".repeat(16));
+        let full = width(&fonts, &text);
+        for overflow in [TextOverflow::EllipsisEnd, TextOverflow::EllipsisMiddle] {
+            for w in (1..full).step_by(3) {
+                let result = truncate_text(&text, &fonts, 0, 20.0, w, overflow).unwrap();
+                let dot = result.iter().position(|c| *c == ELLIPSIS).unwrap();
+                assert_eq!(dot % 2, 0);
+                assert_eq!((result.len() - dot - 1) % 2, 0);
+                assert!(result.get(dot + 1) != Some(&'\u{301}'));
+            }
+        }
+    }
+}
@@ -0,0 +1,296 @@
+
+/*
+    line break opportunities following the unicode line breaking algorithm
+    (UAX #14). the pair rules LB4 to LB31 are implemented as written but
+    the class of each char comes from a small hand made table instead of
+    the full LineBreak.txt, so rare punctuation ends up as AL and south
+    east asian scripts that need a dictionary break like letters.
+*/
+
+use crate::gui::text::unicode::is_combining_mark;
+use crate::gui::text::unicode::is_extended_pictographic;
+use crate::gui::text::unicode::is_ideographic;
+use crate::gui::text::unicode::is_regional_indicator;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Break {
+    None,
+    Allowed,
+    // after a newline, the line has to end here
+    Mandatory
+}
+
+// the line break classes from UAX #14 that the table below produces
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
+enum Class {
+    BK, CR, LF, NL, SP, ZW, GL, WJ, CM, ZWJ,
+    OP, CL, CP, QU, EX, IS, NS, SY,
+    HY, BA, BB, B2, IN, PR, PO, NU,
+    AL, ID, EB, EM, RI
+}
+
+fn class_of(c: char) -> Class {
+    match c {
+        '\u{0B}' | '\u{0C}' | '\u{2028}' | '\u{2029}' => Class::BK,
+        '\r' => Class::CR,
+        '\n' => Class::LF,
+        '\u{85}' => Class::NL,
+        ' ' => Class::SP,
+        '\u{200B}' => Class::ZW,
+        '\u{A0}' | '\u{202F}' | '\u{2007}' | '\u{034F}' | '\u{2011}' | '\u{0F0C}' => Class::GL,
+        '\u{2060}' | '\u{FEFF}' => Class::WJ,
+        '\u{200D}' => Class::ZWJ,
+        '(' | '[' | '{' | '\u{A1}' | '\u{BF}' | '\u{201A}' | '\u{201E}' |
+        '\u{3008}' | '\u{300A}' | '\u{300C}' | '\u{300E}' | '\u{3010}' | '\u{3014}' | '\u{3016}' |
+        '\u{3018}' | '\u{301A}' | '\u{FF08}' | '\u{FF3B}' | '\u{FF5B}' => Class::OP,
+        ')' | ']' | '\u{FF09}' | '\u{FF3D}' => Class::CP,
+        '}' | '\u{3001}' | '\u{3002}' | '\u{3009}' | '\u{300B}' | '\u{300D}' | '\u{300F}' |
+        '\u{3011}' | '\u{3015}' | '\u{3017}' | '\u{3019}' | '\u{301B}' | '\u{FF0C}' | '\u{FF0E}' |
+        '\u{FF5D}' | '\u{FF61}' | '\u{FF63}' | '\u{FF64}' => Class::CL,
+        '"' | '\'' | '\u{AB}' | '\u{BB}' | '\u{2018}' | '\u{2019}' | '\u{201B}' | '\u{201C}' |
+        '\u{201D}' | '\u{201F}' |
+        '\u{2039}' | '\u{203A}' => Class::QU,
+        '!' | '?' | '\u{203C}' | '\u{2047}' | '\u{2048}' | '\u{2049}' | '\u{FF01}' | '\u{FF1F}' => Class::EX,
+        ',' | '.' | ':' | ';' | '\u{37E}' | '\u{589}' | '\u{60C}' | '\u{60D}' | '\u{2044}' |
+        '\u{FE10}' | '\u{FE13}' | '\u{FE14}' => Class::IS,
+        '/' => Class::SY,
+        '-' => Class::HY,
+        '\t' | '\u{AD}' | '\u{2010}' | '\u{2012}' | '\u{2013}' | '|' | '\u{1680}' |
+        '\u{2000}'..='\u{2006}' | '\u{2008}'..='\u{200A}' | '\u{205F}' => Class::BA,
+        '\u{B4}' | '\u{2C8}' | '\u{2CC}' | '\u{2DF}' => Class::BB,
+        '\u{2014}' | '\u{2E3A}' | '\u{2E3B}' => Class::B2,
+        '\u{2024}'..='\u{2026}' | '\u{22EF}' | '\u{FE19}' => Class::IN,
+        '$' | '+' | '\\' | '\u{A3}' | '\u{A5}' | '\u{B1}' | '\u{20A0}'..='\u{20A6}' |
+        '\u{20A8}'..='\u{20BF}' | '\u{2116}' | '\u{2212}' | '\u{2213}' | '\u{FFE1}' | '\u{FFE5}' | '\u{FFE6}' => Class::PR,
+        '%' | '\u{A2}' | '\u{B0}' | '\u{2030}'..='\u{2037}' | '\u{2103}' | '\u{2109}' | '\u{20A7}' |
+        '\u{FF05}' | '\u{FFE0}' => Class::PO,
+        '0'..='9' | '\u{660}'..='\u{669}' | '\u{6F0}'..='\u{6F9}' | '\u{966}'..='\u{96F}' => Class::NU,
+        // small kana, iteration marks and the prolonged sound mark can't
+        // start a line in japanese
+        '\u{3005}' | '\u{303B}' | '\u{309B}'..='\u{309E}' | '\u{30A0}' | '\u{30FB}'..='\u{30FE}' |
+        '\u{3041}' | '\u{3043}' | '\u{3045}' | '\u{3047}' | '\u{3049}' | '\u{3063}' | '\u{3083}' |
+        '\u{3085}' | '\u{3087}' | '\u{308E}' | '\u{3095}' | '\u{3096}' | '\u{30A1}' | '\u{30A3}' |
+        '\u{30A5}' | '\u{30A7}' | '\u{30A9}' | '\u{30C3}' | '\u{30E3}' | '\u{30E5}' | '\u{30E7}' |
+        '\u{30EE}' | '\u{30F5}' | '\u{30F6}' | '\u{FF67}'..='\u{FF70}' | '\u{203D}' | '\u{301C}' => Class::NS,
+        '\u{1F3FB}'..='\u{1F3FF}' => Class::EM,
+        '\u{261D}' | '\u{26F9}' | '\u{270A}'..='\u{270D}' | '\u{1F385}' | '\u{1F3C2}'..='\u{1F3C4}' |
+        '\u{1F3C7}' | '\u{1F3CA}'..='\u{1F3CC}' | '\u{1F442}' | '\u{1F443}' | '\u{1F446}'..='\u{1F450}' |
+        '\u{1F466}'..='\u{1F478}' | '\u{1F47C}' | '\u{1F481}'..='\u{1F483}' | '\u{1F485}'..='\u{1F487}' |
+        '\u{1F4AA}' | '\u{1F574}' | '\u{1F575}' | '\u{1F57A}' | '\u{1F590}' | '\u{1F595}' | '\u{1F596}' |
+        '\u{1F645}'..='\u{1F647}' | '\u{1F64B}'..='\u{1F64F}' | '\u{1F6A3}' | '\u{1F6B4}'..='\u{1F6B6}' |
+        '\u{1F6C0}' | '\u{1F6CC}' | '\u{1F90C}' | '\u{1F90F}' | '\u{1F918}'..='\u{1F91F}' | '\u{1F926}' |
+        '\u{1F930}'..='\u{1F939}' | '\u{1F93C}'..='\u{1F93E}' | '\u{1F977}' | '\u{1F9B5}' | '\u{1F9B6}' |
+        '\u{1F9B8}' | '\u{1F9B9}' | '\u{1F9BB}' | '\u{1F9CD}'..='\u{1F9CF}' | '\u{1F9D1}'..='\u{1F9DD}' => Class::EB,
+        _ => {
+            if is_regional_indicator(c) {
+                Class::RI
+            }
+            else if is_combining_mark(c) {
+                Class::CM
+            }
+            else if is_ideographic(c) || is_extended_pictographic(c) {
+                Class::ID
+            }
+            else {
+                Class::AL
+            }
+        }
+    }
+}
+
+/*
+    the break before each char, index 0 is always None and the extra entry
+    at the end of the text is always Mandatory. a break at i means a line
+    can end after text[i - 1].
+*/
+pub fn line_breaks(text: &[char]) -> Vec<Break> {
+    let mut breaks = vec![Break::None; text.len() + 1];
+    if text.is_empty() {
+        breaks[0] = Break::Mandatory;
+        return breaks;
+    }
+    breaks[text.len()] = Break::Mandatory;
+
+    // LB9 and LB10, marks take the class of the char they attach to,
+    // marks with nothing to attach to act as letters
+    let original: Vec<Class> = text.iter().map(|c| class_of(*c)).collect();
+    let mut classes = original.clone();
+    let mut attached = vec![false; text.len()];
+    for i in 0..text.len() {
+        if original[i] == Class::CM || original[i] == Class::ZWJ {
+            let base = if i > 0 { Some(classes[i - 1]) } else { None };
+            match base {
+                Some(Class::BK) | Some(Class::CR) | Some(Class::LF) | Some(Class::NL) |
+                Some(Class::SP) | Some(Class::ZW) | None => classes[i] = Class::AL,
+                Some(base) => {
+                    classes[i] = base;
+                    attached[i] = true;
+                }
+            }
+        }
+    }
+
+    // the class before the run of spaces ending at i - 1, used by the rules
+    // that look through spaces
+    let mut before_spaces = classes[0];
+    let mut regional_count = if classes[0] == Class::RI { 1 } else { 0 };
+    for i in 1..text.len() {
+        let before = classes[i - 1];
+        let after = classes[i];
+        if before != Class::SP {
+            before_spaces = before;
+        }
+        breaks[i] = pair_break(before, after, before_spaces, original[i - 1], attached[i], regional_count);
+        // marks attached to a regional indicator don't count towards the pair
+        if !attached[i] {
+            regional_count = if after == Class::RI { regional_count + 1 } else { 0 };
+        }
+    }
+    breaks
+}
+
+fn pair_break(before: Class, after: Class, before_spaces: Class, original_before: Class, after_attached: bool, regional_count: usize) -> Break {
+    use Class::*;
+
+    // LB4, LB5 hard line breaks
+    match before {
+        BK | LF | NL => return Break::Mandatory,
+        CR => return if after == LF { Break::None } else { Break::Mandatory },
+        _ => { }
+    }
+    // LB6, LB7 no break before hard breaks, spaces or zero width space
+    if matches!(after, BK | CR | LF | NL | SP | ZW) {
+        return Break::None;
+    }
+    // LB8 break after zero width space even after spaces
+    if before_spaces == ZW {
+        return Break::Allowed;
+    }
+    // LB8a, LB9 don't split joiners and marks from their base
+    if original_before == ZWJ || after_attached {
+        return Break::None;
+    }
+    // LB11 word joiner
+    if before == WJ || after == WJ {
+        return Break::None;
+    }
+    // LB12, LB12a non breaking glue
+    if before == GL || (after == GL && !matches!(before, SP | BA | HY)) {
+        return Break::None;
+    }
+    // LB13 closing punctuation sticks to what's before it
+    if matches!(after, CL | CP | EX | IS | SY) {
+        return Break::None;
+    }
+    // LB14 to LB17 look through spaces
+    if before_spaces == OP {
+        return Break::None;
+    }
+    if before_spaces == QU && after == OP {
+        return Break::None;
+    }
+    if matches!(before_spaces, CL | CP) && after == NS {
+        return Break::None;
+    }
+    if before_spaces == B2 && after == B2 {
+        return Break::None;
+    }
+    // LB18 break after spaces
+    if before == SP {
+        return Break::Allowed;
+    }
+    // LB19 quotes
+    if before == QU || after == QU {
+        return Break::None;
+    }
+    // LB21 hyphens and small kana stick to what's before them
+    if matches!(after, BA | HY | NS) || before == BB {
+        return Break::None;
+    }
+    // LB22 ellipsis
+    if after == IN {
+        return Break::None;
+    }
+    // LB23 to LB25 letters, numbers and the signs around them
+    let keep = match (before, after) {
+        (AL, NU) | (NU, AL) => true,
+        (PR, ID) | (PR, EB) | (PR, EM) | (ID, PO) | (EB, PO) | (EM, PO) => true,
+        (PR, AL) | (PO, AL) | (AL, PR) | (AL, PO) => true,
+        (CL, PO) | (CP, PO) | (CL, PR) | (CP, PR) | (NU, PO) | (NU, PR) => true,
+        (PO, OP) | (PO, NU) | (PR, OP) | (PR, NU) | (HY, NU) | (IS, NU) | (NU, NU) | (SY, NU) => true,
+        // LB28, LB29 letters and letters after infix punctuation
+        (AL, AL) | (IS, AL) => true,
+        // LB30 letters and numbers next to parentheses
+        (AL, OP) | (NU, OP) | (CP, AL) | (CP, NU) => true,
+        // LB30b emoji modifiers
+        (EB, EM) => true,
+        _ => false
+    };
+    if keep {
+        return Break::None;
+    }
+    // LB30a flags are pairs of regional indicators
+    if before == RI && after == RI && regional_count % 2 == 1 {
+        return Break::None;
+    }
+    // LB31
+    Break::Allowed
+}
+
+#[cfg(test)]
+mod linebreak_tests {
+    use super::*;
+
+    // the text split at every allowed or mandatory break
+    fn segments(text: &str) -> Vec<String> {
+        let chars: Vec<char> = text.chars().collect();
+        let breaks = line_breaks(&chars);
+        let mut result = Vec::new();
+        let mut start = 0;
+        for i in 1..=chars.len() {
+            if breaks[i] != Break::None {
+                result.push(chars[start..i].iter().collect());
+                start = i;
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn test_words_and_punctuation() {
+        assert_eq!(segments("hello world"), vec!["hello ", "world"]);
+        assert_eq!(segments("one, two (three)!"), vec!["one, ", "two ", "(three)!"]);
+        assert_eq!(segments("file-name_v2.txt"), vec!["file-", "name_v2.txt"]);
+        assert_eq!(segments("\"quoted\" $10.50 50%"), vec!["\"quoted\" ", "$10.50 ", "50%"]);
+        assert_eq!(segments("a/b"), vec!["a/", "b"]);
+    }
+
+    #[test]
+    fn test_newlines_are_mandatory() {
+        let chars: Vec<char> = "a\r\nb\nc".chars().collect();
+        let breaks = line_breaks(&chars);
+        assert_eq!(breaks[2], Break::None);
+        assert_eq!(breaks[3], Break::Mandatory);
+        assert_eq!(breaks[5], Break::Mandatory);
+        assert_eq!(breaks[6], Break::Mandatory);
+    }
+
+    #[test]
+    fn test_ideographs_marks_and_emoji() {
+        // every ideograph can break but not before the closing punctuation
+        assert_eq!(segments("日本語。"), vec!["日", "本", "語。"]);
+        // small kana can't start a line
+        assert_eq!(segments("キャ"), vec!["キャ"]);
+        // combining accents stay with their letter
+        assert_eq!(segments("e\u{301} x"), vec!["e\u{301} ", "x"]);
+        // zwj sequences and skin tones stay together
+        assert_eq!(segments("\u{1F468}\u{200D}\u{1F469}\u{1F44D}\u{1F3FD}"), vec!["\u{1F468}\u{200D}\u{1F469}", "\u{1F44D}\u{1F3FD}"]);
+        // flags are pairs of regional indicators
+        assert_eq!(segments("\u{1F1EF}\u{1F1F5}\u{1F1FA}\u{1F1F8}"), vec!["\u{1F1EF}\u{1F1F5}", "\u{1F1FA}\u{1F1F8}"]);
+        // explicit zero width space
+        assert_eq!(segments("long\u{200B}word"), vec!["long\u{200B}", "word"]);
+        // non breaking space
+        assert_eq!(segments("10\u{A0}km"), vec!["10\u{A0}km"]);
+    }
+}
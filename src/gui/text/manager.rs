@@ -0,0 +1,221 @@
+
+/*
+    owns every font the ui draws with, picks the face for a style and the
+    font for each char.
+
+    the fonts added with add_font are loaded up front, the first one is the
+    primary font. select finds the loaded face that best matches a family,
+    weight and italic, chars are looked up in that face first and then in
+    the other loaded fonts. after them comes the fallback chain of system fonts which
+    are only read from disk the first time a char isn't covered by any font
+    before them, a cjk font can be 20mb so loading them all at startup
+    isn't an option. a char no font covers is drawn with the primary font
+    which shows its missing glyph box.
+
+    font indexes handed out by font_for_char stay valid for the life of the
+    manager, loaded fonts come first and fallbacks after them in chain order.
+*/
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::OnceLock;
+use crate::gui::text::font::Font;
+use crate::gui::style::BoxStyle;
+use crate::gui::text::discovery::system_font_directories;
+use crate::gui::text::discovery::scan_font_files;
+use crate::gui::text::discovery::fallback_chain;
+
+struct Fallback {
+    path: PathBuf,
+    // None once loading failed so a broken file is only tried once
+    font: OnceLock<Option<Font>>
+}
+
+pub struct FontManager {
+    fonts: Vec<Font>,
+    fallbacks: Vec<Fallback>,
+    // chars that needed the fallback chain and the font that covers them,
+    // drawing happens on the tile threads so this sits behind a lock
+    coverage: Mutex<BTreeMap<char, usize>>
+}
+
+impl FontManager {
+    pub const fn new() -> FontManager {
+        FontManager {
+            fonts: Vec::new(),
+            fallbacks: Vec::new(),
+            coverage: Mutex::new(BTreeMap::new())
+        }
+    }
+
+    // adding a font moves the fallback indexes along so the coverage cache
+    // is thrown away
+    pub fn add_font(&mut self, font: Font) {
+        self.fonts.push(font);
+        self.clear_coverage();
+    }
+
+    pub fn add_fallback(&mut self, path: PathBuf) {
+        self.fallbacks.push(Fallback { path, font: OnceLock::new() });
+        self.clear_coverage();
+    }
+
+    // scans the system font directories and adds the preferred fonts that
+    // are installed to the end of the fallback chain
+    pub fn discover_system_fonts(&mut self) {
+        let files = scan_font_files(&system_font_directories());
+        for path in fallback_chain(&files) {
+            self.add_fallback(path);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.fonts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fonts.is_empty()
+    }
+
+    pub fn primary(&self) -> &Font {
+        &self.fonts[0]
+    }
+
+    // the font at an index from font_for_char
+    pub fn font(&self, index: usize) -> &Font {
+        if index < self.fonts.len() {
+            return &self.fonts[index];
+        }
+        match self.fallbacks.get(index - self.fonts.len()).and_then(|f| f.font.get()) {
+            Some(Some(font)) => font,
+            _ => self.primary()
+        }
+    }
+
+    /*
+        the loaded font closest to the requested style, like css font
+        matching. the family has to match, when nothing in the family is
+        loaded the primary font's family is used instead. then the italic
+        has to match if it can, then the closest weight wins with the
+        heavier one winning ties.
+    */
+    pub fn select(&self, family: &str, weight: u16, italic: bool) -> usize {
+        let family = if self.fonts.iter().any(|f| f.info.family.eq_ignore_ascii_case(family)) {
+            family
+        }
+        else {
+            &self.primary().info.family
+        };
+        let mut best = 0;
+        let mut best_score = u32::MAX;
+        for (i, font) in self.fonts.iter().enumerate() {
+            if !font.info.family.eq_ignore_ascii_case(family) {
+                continue;
+            }
+            let italic_score = if font.info.italic == italic { 0 } else { 10_000 };
+            let lighter = if font.info.weight < weight { 1 } else { 0 };
+            let score = italic_score + (font.info.weight as i32 - weight as i32).unsigned_abs() * 2 + lighter;
+            if score < best_score {
+                best = i;
+                best_score = score;
+            }
+        }
+        best
+    }
+
+    pub fn select_style(&self, style: &BoxStyle) -> usize {
+        self.select(style.font_family, style.font_weight.0, style.italic)
+    }
+
+    // the font to draw c with when the text is set in the face from select
+    pub fn font_for_char(&self, c: char, face: usize) -> usize {
+        if self.fonts[face].has_glyph(c) {
+            return face;
+        }
+        if let Some(index) = self.fonts.iter().position(|font| font.has_glyph(c)) {
+            return index;
+        }
+        if let Some(index) = self.coverage.lock().unwrap().get(&c) {
+            return *index;
+        }
+
+        // control chars and the like aren't in any font, don't load the
+        // whole chain looking for them
+        let mut found = 0;
+        if !c.is_control() {
+            for (i, fallback) in self.fallbacks.iter().enumerate() {
+                let font = fallback.font.get_or_init(|| load_font_file(&fallback.path));
+                if let Some(font) = font {
+                    if font.has_glyph(c) {
+                        found = self.fonts.len() + i;
+                        break;
+                    }
+                }
+            }
+        }
+        self.coverage.lock().unwrap().insert(c, found);
+        found
+    }
+
+    fn clear_coverage(&mut self) {
+        self.coverage.get_mut().unwrap().clear();
+    }
+}
+
+fn load_font_file(path: &Path) -> Option<Font> {
+    let data = std::fs::read(path).ok()?;
+    Font::from_bytes(&data).ok()
+}
+
+#[cfg(test)]
+mod manager_tests {
+    use super::*;
+
+    fn load_font() -> Font {
+        let font = include_bytes!("../../../fonts/OpenSans-Regular.ttf") as &[u8];
+        Font::from_bytes(font).unwrap()
+    }
+
+    #[test]
+    fn test_fallback_chain() {
+        let broken = std::env::temp_dir().join(format!("filex_broken_font_{}.ttf", std::process::id()));
+        std::fs::write(&broken, b"not a font").unwrap();
+
+        let mut fonts = FontManager::new();
+        fonts.add_font(load_font());
+        fonts.add_fallback(broken.clone());
+        fonts.add_fallback(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fonts/OpenSans-Bold.ttf"));
+
+        assert_eq!(fonts.font_for_char('a', 0), 0);
+        // neither open sans has cjk so it falls back to the primary font
+        assert_eq!(fonts.font_for_char('\u{4E2D}', 0), 0);
+        // every fallback got tried, the broken one only once
+        assert!(matches!(fonts.fallbacks[0].font.get(), Some(None)));
+        assert!(matches!(fonts.fallbacks[1].font.get(), Some(Some(_))));
+        assert!(std::ptr::eq(fonts.font(1), fonts.primary()));
+        assert!(!std::ptr::eq(fonts.font(2), fonts.primary()));
+        std::fs::remove_file(&broken).unwrap();
+    }
+
+    #[test]
+    fn test_select_face() {
+        let bold = include_bytes!("../../../fonts/OpenSans-Bold.ttf") as &[u8];
+        let mut fonts = FontManager::new();
+        fonts.add_font(load_font());
+        fonts.add_font(Font::from_bytes(bold).unwrap());
+        assert_eq!(fonts.primary().info.family, "Open Sans");
+        assert_eq!(fonts.primary().info.weight, 400);
+        assert_eq!(fonts.font(1).info.weight, 700);
+
+        assert_eq!(fonts.select("Open Sans", 400, false), 0);
+        assert_eq!(fonts.select("open sans", 700, false), 1);
+        // semibold rounds up, light rounds down
+        assert_eq!(fonts.select("Open Sans", 600, false), 1);
+        assert_eq!(fonts.select("Open Sans", 300, false), 0);
+        // no italic loaded and an unknown family still give the closest weight
+        assert_eq!(fonts.select("Open Sans", 700, true), 1);
+        assert_eq!(fonts.select("Comic Sans", 800, false), 1);
+    }
+}
@@ -0,0 +1,10 @@
+pub mod discovery;
+pub mod font;
+pub mod layout;
+pub mod line;
+pub mod linebreak;
+pub mod manager;
+pub mod opentype;
+pub mod segment;
+pub mod shape;
+pub mod unicode;
@@ -0,0 +1,528 @@
+
+/*
+    just enough of the opentype tables to pick and shape fonts. the name and
+    OS/2 tables give the family, weight and style of a font. the GSUB and
+    GPOS tables are used for latin shaping:
+    ligature substitution (GSUB lookup type 4) for the liga and clig
+    features and pair positioning (GPOS lookup type 2) for the kern
+    feature. extension lookups are followed. script and language systems
+    are ignored and every lookup a feature points at is used, lookup flags
+    (skipping marks) are ignored too since the ui only ever shapes short
+    runs of latin text.
+
+    malformed tables are never an error, whatever can't be read is dropped
+    and shaping falls back to plain advances.
+*/
+
+use std::collections::HashMap;
+
+const LIGATURE_FEATURES: [&[u8; 4]; 2] = [b"liga", b"clig"];
+const KERNING_FEATURES: [&[u8; 4]; 1] = [b"kern"];
+
+const GSUB_LIGATURE: u16 = 4;
+const GSUB_EXTENSION: u16 = 7;
+const GPOS_PAIR: u16 = 2;
+const GPOS_EXTENSION: u16 = 9;
+
+// value record format bits
+const X_ADVANCE: u16 = 0x0004;
+
+// name ids, the typographic family groups every weight under one name
+// where the older family name has "Open Sans Light" and the like
+const NAME_FAMILY: u16 = 1;
+const NAME_TYPOGRAPHIC_FAMILY: u16 = 16;
+const ENGLISH_US: u16 = 0x0409;
+
+// OS/2 fsSelection and head macStyle bits
+const FS_ITALIC: u16 = 0x0001;
+const FS_OBLIQUE: u16 = 0x0200;
+const MAC_STYLE_BOLD: u16 = 0x0001;
+const MAC_STYLE_ITALIC: u16 = 0x0002;
+
+pub const WEIGHT_REGULAR: u16 = 400;
+pub const WEIGHT_BOLD: u16 = 700;
+
+// family name and style of a font, used to pick fonts by style
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontInfo {
+    pub family: String,
+    // 100 to 900, 400 is regular and 700 bold
+    pub weight: u16,
+    pub italic: bool
+}
+
+pub struct Ligature {
+    // the glyphs after the first one
+    pub components: Vec<u16>,
+    pub glyph: u16
+}
+
+// the shaping data pulled out of a font file
+#[derive(Default)]
+pub struct ShapingTables {
+    // keyed by the first glyph, in the order the font lists them
+    pub ligatures: HashMap<u16, Vec<Ligature>>,
+    pub pair_adjustments: Vec<PairAdjustment>
+}
+
+pub enum PairAdjustment {
+    Glyphs(HashMap<(u16, u16), i16>),
+    Classes {
+        coverage: Coverage,
+        first_classes: ClassDef,
+        second_classes: ClassDef,
+        second_class_count: usize,
+        // x advance of the first glyph for every class pair
+        values: Vec<i16>
+    }
+}
+
+// glyph ranges with the coverage index of their first glyph
+pub struct Coverage(Vec<(u16, u16, u16)>);
+
+// glyph ranges and their class, glyphs that aren't listed are class 0
+pub struct ClassDef(Vec<(u16, u16, u16)>);
+
+impl ShapingTables {
+    // collection_index picks the font inside a .ttc, it's ignored for
+    // single font files
+    pub fn parse(font_data: &[u8], collection_index: u32) -> ShapingTables {
+        let mut tables = ShapingTables::default();
+        let data = Reader(font_data);
+        let directory = match font_directory(data, collection_index) {
+            Some(d) => d,
+            None => return tables
+        };
+        if let Some(gsub) = find_table(data, directory, b"GSUB") {
+            for (lookup_type, subtable) in feature_subtables(gsub, &LIGATURE_FEATURES, GSUB_EXTENSION) {
+                if lookup_type == GSUB_LIGATURE {
+                    read_ligatures(subtable, &mut tables.ligatures);
+                }
+            }
+        }
+        if let Some(gpos) = find_table(data, directory, b"GPOS") {
+            for (lookup_type, subtable) in feature_subtables(gpos, &KERNING_FEATURES, GPOS_EXTENSION) {
+                if lookup_type == GPOS_PAIR {
+                    if let Some(adjustment) = read_pair_adjustment(subtable) {
+                        tables.pair_adjustments.push(adjustment);
+                    }
+                }
+            }
+        }
+        tables
+    }
+
+    pub fn has_kerning(&self) -> bool {
+        !self.pair_adjustments.is_empty()
+    }
+
+    // x advance adjustment in font units, the first subtable that covers
+    // the first glyph wins like the spec says
+    pub fn kerning(&self, first: u16, second: u16) -> Option<i16> {
+        for adjustment in &self.pair_adjustments {
+            match adjustment {
+                PairAdjustment::Glyphs(pairs) => {
+                    if let Some(value) = pairs.get(&(first, second)) {
+                        return Some(*value);
+                    }
+                },
+                PairAdjustment::Classes { coverage, first_classes, second_classes, second_class_count, values } => {
+                    if coverage.index(first).is_some() {
+                        let class1 = first_classes.class(first) as usize;
+                        let class2 = second_classes.class(second) as usize;
+                        if let Some(value) = values.get(class1 * second_class_count + class2) {
+                            return Some(*value);
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+impl FontInfo {
+    // fonts missing the tables come back as a regular weight font with no
+    // family name
+    pub fn parse(font_data: &[u8], collection_index: u32) -> FontInfo {
+        let mut info = FontInfo { family: String::new(), weight: WEIGHT_REGULAR, italic: false };
+        let data = Reader(font_data);
+        let directory = match font_directory(data, collection_index) {
+            Some(d) => d,
+            None => return info
+        };
+        if let Some(name) = find_table(data, directory, b"name") {
+            info.family = read_family_name(name).unwrap_or_default();
+        }
+        if let Some(os2) = find_table(data, directory, b"OS/2") {
+            if let Some(weight) = os2.u16(4) {
+                if (1..=1000).contains(&weight) {
+                    info.weight = weight;
+                }
+            }
+            if let Some(selection) = os2.u16(62) {
+                info.italic = selection & (FS_ITALIC | FS_OBLIQUE) != 0;
+            }
+        }
+        else if let Some(head) = find_table(data, directory, b"head") {
+            let style = head.u16(44).unwrap_or(0);
+            info.italic = style & MAC_STYLE_ITALIC != 0;
+            if style & MAC_STYLE_BOLD != 0 {
+                info.weight = WEIGHT_BOLD;
+            }
+        }
+        info
+    }
+}
+
+// the typographic family when there is one, english windows names are
+// preferred over mac roman names
+fn read_family_name(name: Reader) -> Option<String> {
+    let count = name.u16(2)? as usize;
+    let strings = name.u16(4)? as usize;
+    let mut best: Option<(u32, String)> = None;
+    for i in 0..count {
+        let record = 6 + i * 12;
+        let platform = name.u16(record)?;
+        let encoding = name.u16(record + 2)?;
+        let language = name.u16(record + 4)?;
+        let name_id = name.u16(record + 6)?;
+        let length = name.u16(record + 8)? as usize;
+        let offset = strings + name.u16(record + 10)? as usize;
+        let bytes = match name.0.get(offset..offset + length) {
+            Some(b) => b,
+            None => continue
+        };
+        let id_score = match name_id {
+            NAME_TYPOGRAPHIC_FAMILY => 4,
+            NAME_FAMILY => 0,
+            _ => continue
+        };
+        let (platform_score, text) = match (platform, encoding) {
+            (3, 0) | (3, 1) | (0, _) => {
+                let units: Vec<u16> = bytes.chunks_exact(2).map(|c| ((c[0] as u16) << 8) | c[1] as u16).collect();
+                let score = if platform == 3 && language == ENGLISH_US { 2 } else { 1 };
+                (score, String::from_utf16_lossy(&units))
+            },
+            // mac roman, only the ascii part is decoded
+            (1, 0) => (0, bytes.iter().map(|&b| if b < 0x80 { b as char } else { '?' }).collect()),
+            _ => continue
+        };
+        let score = id_score + platform_score;
+        if best.as_ref().is_none_or(|(best_score, _)| score > *best_score) {
+            best = Some((score, text));
+        }
+    }
+    best.map(|(_, text)| text)
+}
+
+impl Coverage {
+    pub fn index(&self, glyph: u16) -> Option<u16> {
+        self.0.iter()
+            .find(|(start, end, _)| glyph >= *start && glyph <= *end)
+            .and_then(|(start, _, index)| index.checked_add(glyph - start))
+    }
+}
+
+impl ClassDef {
+    pub fn class(&self, glyph: u16) -> u16 {
+        match self.0.iter().find(|(start, end, _)| glyph >= *start && glyph <= *end) {
+            Some((_, _, class)) => *class,
+            None => 0
+        }
+    }
+}
+
+// bounds checked big endian reads, out of range reads give None
+#[derive(Copy, Clone)]
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn u16(&self, offset: usize) -> Option<u16> {
+        let bytes = self.0.get(offset..offset + 2)?;
+        Some(((bytes[0] as u16) << 8) | bytes[1] as u16)
+    }
+
+    fn u32(&self, offset: usize) -> Option<u32> {
+        Some(((self.u16(offset)? as u32) << 16) | self.u16(offset + 2)? as u32)
+    }
+
+    fn at(&self, offset: usize) -> Option<Reader<'a>> {
+        self.0.get(offset..).map(Reader)
+    }
+}
+
+// offset of the table directory, collections list one per font
+fn font_directory(data: Reader, collection_index: u32) -> Option<usize> {
+    if data.0.get(0..4)? == b"ttcf" {
+        if collection_index >= data.u32(8)? {
+            return None;
+        }
+        Some(data.u32(12 + collection_index as usize * 4)? as usize)
+    }
+    else {
+        Some(0)
+    }
+}
+
+// table offsets are from the start of the file even inside collections
+fn find_table<'a>(data: Reader<'a>, directory: usize, tag: &[u8; 4]) -> Option<Reader<'a>> {
+    let count = data.u16(directory + 4)? as usize;
+    for i in 0..count {
+        let record = directory + 12 + i * 16;
+        if data.0.get(record..record + 4)? == tag {
+            let offset = data.u32(record + 8)? as usize;
+            let length = data.u32(record + 12)? as usize;
+            return data.0.get(offset..offset + length).map(Reader);
+        }
+    }
+    None
+}
+
+/*
+    returns the lookup type and data of every subtable used by the
+    features with the given tags. extension subtables are unwrapped so the
+    caller only sees the real lookup types.
+*/
+fn feature_subtables<'a>(table: Reader<'a>, features: &[&[u8; 4]], extension_type: u16) -> Vec<(u16, Reader<'a>)> {
+    let mut subtables = Vec::new();
+    let (feature_list, lookup_list) = match (table.u16(6), table.u16(8)) {
+        (Some(f), Some(l)) => (f as usize, l as usize),
+        _ => return subtables
+    };
+
+    // the same lookup is usually listed once per script, only use it once
+    let mut lookup_indices = Vec::<u16>::new();
+    let feature_count = table.u16(feature_list).unwrap_or(0) as usize;
+    for i in 0..feature_count {
+        let record = feature_list + 2 + i * 6;
+        let tag = match table.0.get(record..record + 4) {
+            Some(t) => t,
+            None => break
+        };
+        if !features.iter().any(|f| &f[..] == tag) {
+            continue;
+        }
+        let feature = match table.u16(record + 4) {
+            Some(offset) => feature_list + offset as usize,
+            None => break
+        };
+        let index_count = table.u16(feature + 2).unwrap_or(0) as usize;
+        for j in 0..index_count {
+            if let Some(index) = table.u16(feature + 4 + j * 2) {
+                if !lookup_indices.contains(&index) {
+                    lookup_indices.push(index);
+                }
+            }
+        }
+    }
+    lookup_indices.sort();
+
+    for index in lookup_indices {
+        let lookup = match table.u16(lookup_list + 2 + index as usize * 2) {
+            Some(offset) => lookup_list + offset as usize,
+            None => continue
+        };
+        let lookup_type = table.u16(lookup).unwrap_or(0);
+        let subtable_count = table.u16(lookup + 4).unwrap_or(0) as usize;
+        for i in 0..subtable_count {
+            let subtable = table.u16(lookup + 6 + i * 2)
+                .and_then(|offset| table.at(lookup + offset as usize));
+            let subtable = subtable.and_then(|subtable| {
+                if lookup_type == extension_type {
+                    let real_type = subtable.u16(2)?;
+                    let offset = subtable.u32(4)? as usize;
+                    Some((real_type, subtable.at(offset)?))
+                }
+                else {
+                    Some((lookup_type, subtable))
+                }
+            });
+            if let Some(subtable) = subtable {
+                subtables.push(subtable);
+            }
+        }
+    }
+    subtables
+}
+
+fn read_coverage(data: Reader) -> Option<Coverage> {
+    let mut ranges = Vec::new();
+    match data.u16(0)? {
+        1 => {
+            let count = data.u16(2)? as usize;
+            for i in 0..count {
+                let glyph = data.u16(4 + i * 2)?;
+                ranges.push((glyph, glyph, i as u16));
+            }
+        },
+        2 => {
+            let count = data.u16(2)? as usize;
+            for i in 0..count {
+                let record = 4 + i * 6;
+                ranges.push((data.u16(record)?, data.u16(record + 2)?, data.u16(record + 4)?));
+            }
+        },
+        _ => return None
+    }
+    Some(Coverage(ranges))
+}
+
+fn read_class_def(data: Reader) -> Option<ClassDef> {
+    let mut ranges = Vec::new();
+    match data.u16(0)? {
+        1 => {
+            let start = data.u16(2)?;
+            let count = data.u16(4)? as usize;
+            for i in 0..count {
+                let glyph = start.checked_add(i as u16)?;
+                ranges.push((glyph, glyph, data.u16(6 + i * 2)?));
+            }
+        },
+        2 => {
+            let count = data.u16(2)? as usize;
+            for i in 0..count {
+                let record = 4 + i * 6;
+                ranges.push((data.u16(record)?, data.u16(record + 2)?, data.u16(record + 4)?));
+            }
+        },
+        _ => return None
+    }
+    Some(ClassDef(ranges))
+}
+
+// coverage glyphs in coverage index order
+fn coverage_glyphs(coverage: &Coverage) -> Vec<u16> {
+    let mut glyphs = Vec::new();
+    for &(start, end, index) in &coverage.0 {
+        for glyph in start..=end {
+            // a range running past the last index is broken, the rest of
+            // it is dropped
+            let i = match index.checked_add(glyph - start) {
+                Some(i) => i as usize,
+                None => break
+            };
+            if glyphs.len() <= i {
+                glyphs.resize(i + 1, 0);
+            }
+            glyphs[i] = glyph;
+        }
+    }
+    glyphs
+}
+
+fn read_ligatures(subtable: Reader, ligatures: &mut HashMap<u16, Vec<Ligature>>) -> Option<()> {
+    if subtable.u16(0)? != 1 {
+        return None;
+    }
+    let coverage = read_coverage(subtable.at(subtable.u16(2)? as usize)?)?;
+    let set_count = subtable.u16(4)? as usize;
+    for (i, first) in coverage_glyphs(&coverage).into_iter().enumerate().take(set_count) {
+        let set = subtable.at(subtable.u16(6 + i * 2)? as usize)?;
+        let count = set.u16(0)? as usize;
+        for j in 0..count {
+            let ligature = set.at(set.u16(2 + j * 2)? as usize)?;
+            let glyph = ligature.u16(0)?;
+            let component_count = ligature.u16(2)? as usize;
+            let components = (1..component_count)
+                .map(|k| ligature.u16(4 + (k - 1) * 2))
+                .collect::<Option<Vec<u16>>>()?;
+            ligatures.entry(first).or_default().push(Ligature { components, glyph });
+        }
+    }
+    Some(())
+}
+
+// size of a value record and where its x advance is, if it has one
+fn value_record_layout(format: u16) -> (usize, Option<usize>) {
+    let size = format.count_ones() as usize * 2;
+    if format & X_ADVANCE == 0 {
+        (size, None)
+    }
+    else {
+        (size, Some((format & (X_ADVANCE - 1)).count_ones() as usize * 2))
+    }
+}
+
+fn read_pair_adjustment(subtable: Reader) -> Option<PairAdjustment> {
+    let format = subtable.u16(0)?;
+    let coverage = read_coverage(subtable.at(subtable.u16(2)? as usize)?)?;
+    let (size1, advance1) = value_record_layout(subtable.u16(4)?);
+    let (size2, _) = value_record_layout(subtable.u16(6)?);
+    // only the first glyph's advance matters for horizontal kerning
+    let advance1 = advance1?;
+
+    match format {
+        1 => {
+            let mut pairs = HashMap::new();
+            let set_count = subtable.u16(8)? as usize;
+            for (i, first) in coverage_glyphs(&coverage).into_iter().enumerate().take(set_count) {
+                let set = subtable.at(subtable.u16(10 + i * 2)? as usize)?;
+                let count = set.u16(0)? as usize;
+                let record_size = 2 + size1 + size2;
+                for j in 0..count {
+                    let record = 2 + j * record_size;
+                    let second = set.u16(record)?;
+                    let value = set.u16(record + 2 + advance1)? as i16;
+                    pairs.entry((first, second)).or_insert(value);
+                }
+            }
+            Some(PairAdjustment::Glyphs(pairs))
+        },
+        2 => {
+            let first_classes = read_class_def(subtable.at(subtable.u16(8)? as usize)?)?;
+            let second_classes = read_class_def(subtable.at(subtable.u16(10)? as usize)?)?;
+            let first_class_count = subtable.u16(12)? as usize;
+            let second_class_count = subtable.u16(14)? as usize;
+            let record_size = size1 + size2;
+            // the counts come straight from the file, a matrix that runs
+            // past the end of the subtable is broken and skipped before
+            // anything is allocated for it
+            let count = first_class_count * second_class_count;
+            if count.checked_mul(record_size)?.checked_add(16)? > subtable.0.len() {
+                return None;
+            }
+            let mut values = Vec::with_capacity(count);
+            for i in 0..count {
+                values.push(subtable.u16(16 + i * record_size + advance1)? as i16);
+            }
+            Some(PairAdjustment::Classes { coverage, first_classes, second_classes, second_class_count, values })
+        },
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod opentype_tests {
+    use super::*;
+
+    #[test]
+    fn test_coverage_index_overflow() {
+        // the second range's indexes would run past u16::MAX
+        let coverage = Coverage(vec![(10, 12, 0), (100, 110, 65530)]);
+        assert_eq!(coverage.index(11), Some(1));
+        assert_eq!(coverage.index(105), Some(65535));
+        assert_eq!(coverage.index(106), None);
+        let glyphs = coverage_glyphs(&coverage);
+        assert_eq!(glyphs.len(), 65536);
+        assert_eq!((glyphs[2], glyphs[65535]), (12, 105));
+    }
+
+    #[test]
+    fn test_truncated_class_pair_subtable() {
+        // PairPos format 2 with x advances for the first glyph, asking for
+        // a 1000 by 1000 class matrix with only one record after it
+        let mut subtable: Vec<u16> = vec![2, 18, X_ADVANCE, 0, 24, 30, 1000, 1000, 0];
+        // coverage of glyph 5 and empty class defs
+        subtable.extend_from_slice(&[1, 1, 5, 1, 0, 0, 1, 0, 0]);
+        let bytes: Vec<u8> = subtable.iter().flat_map(|x| x.to_be_bytes()).collect();
+        assert!(read_pair_adjustment(Reader(&bytes)).is_none());
+
+        // the same subtable with a matrix that fits is read
+        let mut fits = subtable.clone();
+        fits[6] = 1;
+        fits[7] = 1;
+        let bytes: Vec<u8> = fits.iter().flat_map(|x| x.to_be_bytes()).collect();
+        assert!(matches!(read_pair_adjustment(Reader(&bytes)), Some(PairAdjustment::Classes { values, .. }) if values.len() == 1));
+    }
+}
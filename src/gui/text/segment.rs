@@ -0,0 +1,342 @@
+
+/*
+    grapheme cluster and word boundaries following unicode text
+    segmentation (UAX #29). editing moves the caret and deletes by grapheme
+    clusters so an accent typed as a combining mark or an emoji built from
+    several chars with zero width joiners behaves like a single char.
+    ctrl+left and right jump between words found by the word rules.
+
+    like linebreak.rs the properties come from the tables in unicode.rs
+    rather than the full unicode data files.
+*/
+
+use crate::gui::text::unicode::is_combining_mark;
+use crate::gui::text::unicode::is_extended_pictographic;
+use crate::gui::text::unicode::is_ideographic;
+use crate::gui::text::unicode::is_regional_indicator;
+
+const ZWJ: char = '\u{200D}';
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
+enum GraphemeClass {
+    CR,
+    LF,
+    Control,
+    Extend,
+    ZWJ,
+    RegionalIndicator,
+    // hangul jamo and syllables
+    L,
+    V,
+    T,
+    LV,
+    LVT,
+    Pictographic,
+    Other
+}
+
+fn grapheme_class(c: char) -> GraphemeClass {
+    match c {
+        '\r' => GraphemeClass::CR,
+        '\n' => GraphemeClass::LF,
+        ZWJ => GraphemeClass::ZWJ,
+        '\u{200C}' => GraphemeClass::Extend,
+        '\u{1100}'..='\u{115F}' | '\u{A960}'..='\u{A97C}' => GraphemeClass::L,
+        '\u{1160}'..='\u{11A7}' | '\u{D7B0}'..='\u{D7C6}' => GraphemeClass::V,
+        '\u{11A8}'..='\u{11FF}' | '\u{D7CB}'..='\u{D7FB}' => GraphemeClass::T,
+        '\u{AC00}'..='\u{D7A3}' => {
+            // every 28th syllable has no final consonant
+            match (c as u32 - 0xAC00) % 28 {
+                0 => GraphemeClass::LV,
+                _ => GraphemeClass::LVT
+            }
+        },
+        '\u{200B}' | '\u{2028}' | '\u{2029}' | '\u{FEFF}' => GraphemeClass::Control,
+        _ if c.is_control() => GraphemeClass::Control,
+        _ if is_combining_mark(c) => GraphemeClass::Extend,
+        _ if is_regional_indicator(c) => GraphemeClass::RegionalIndicator,
+        _ if is_extended_pictographic(c) => GraphemeClass::Pictographic,
+        _ => GraphemeClass::Other
+    }
+}
+
+/*
+    whether there is a grapheme cluster boundary before each char, the
+    result has text.len() + 1 entries and the start and end are always
+    boundaries
+*/
+pub fn grapheme_boundaries(text: &[char]) -> Vec<bool> {
+    use GraphemeClass::*;
+    let mut boundaries = vec![true; text.len() + 1];
+    let classes: Vec<GraphemeClass> = text.iter().map(|c| grapheme_class(*c)).collect();
+    // regional indicators before the current char, flags are pairs of them
+    let mut regional_count = 0;
+    for i in 1..text.len() {
+        let (before, after) = (classes[i - 1], classes[i]);
+        regional_count = if before == RegionalIndicator { regional_count + 1 } else { 0 };
+        boundaries[i] = match (before, after) {
+            (CR, LF) => false,
+            (CR, _) | (LF, _) | (Control, _) => true,
+            (_, CR) | (_, LF) | (_, Control) => true,
+            (L, L) | (L, V) | (L, LV) | (L, LVT) => false,
+            (LV, V) | (LV, T) | (V, V) | (V, T) => false,
+            (LVT, T) | (T, T) => false,
+            (_, Extend) | (_, ZWJ) => false,
+            (ZWJ, Pictographic) => {
+                // GB11, an emoji and its marks joined to the next emoji
+                let mut j = i - 1;
+                while j > 0 && classes[j - 1] == Extend {
+                    j -= 1;
+                }
+                !(j > 0 && classes[j - 1] == Pictographic)
+            },
+            (RegionalIndicator, RegionalIndicator) => regional_count % 2 == 0,
+            _ => true
+        };
+    }
+    boundaries
+}
+
+// the end of the grapheme cluster starting at or containing index
+pub fn next_grapheme_boundary(text: &[char], index: usize) -> usize {
+    let boundaries = grapheme_boundaries(text);
+    (index + 1..=text.len()).find(|i| boundaries[*i]).unwrap_or(text.len())
+}
+
+// the start of the grapheme cluster before index
+pub fn prev_grapheme_boundary(text: &[char], index: usize) -> usize {
+    let boundaries = grapheme_boundaries(text);
+    let index = std::cmp::min(index, text.len());
+    (0..index).rev().find(|i| boundaries[*i]).unwrap_or(0)
+}
+
+// index moved back to the start of the cluster it's in, for caret indexes
+// that come from hit testing
+pub fn grapheme_start(text: &[char], index: usize) -> usize {
+    let boundaries = grapheme_boundaries(text);
+    let index = std::cmp::min(index, text.len());
+    (0..=index).rev().find(|i| boundaries[*i]).unwrap_or(0)
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
+enum WordClass {
+    CR,
+    LF,
+    Newline,
+    Extend,
+    ZWJ,
+    Format,
+    RegionalIndicator,
+    Katakana,
+    Letter,
+    SingleQuote,
+    DoubleQuote,
+    MidNumLet,
+    MidLetter,
+    MidNum,
+    Numeric,
+    ExtendNumLet,
+    Space,
+    Pictographic,
+    Other
+}
+
+fn word_class(c: char) -> WordClass {
+    match c {
+        '\r' => WordClass::CR,
+        '\n' => WordClass::LF,
+        '\u{0B}' | '\u{0C}' | '\u{85}' | '\u{2028}' | '\u{2029}' => WordClass::Newline,
+        ZWJ => WordClass::ZWJ,
+        '\u{200C}' => WordClass::Extend,
+        '\u{AD}' | '\u{200E}' | '\u{200F}' | '\u{2060}'..='\u{2064}' | '\u{FEFF}' => WordClass::Format,
+        '\'' => WordClass::SingleQuote,
+        '"' => WordClass::DoubleQuote,
+        '.' | '\u{2018}' | '\u{2019}' | '\u{2024}' | '\u{FE52}' | '\u{FF07}' | '\u{FF0E}' => WordClass::MidNumLet,
+        ':' | '\u{B7}' | '\u{387}' | '\u{5F4}' | '\u{2027}' | '\u{FE13}' | '\u{FE55}' | '\u{FF1A}' => WordClass::MidLetter,
+        ',' | ';' | '\u{37E}' | '\u{589}' | '\u{60C}' | '\u{60D}' | '\u{66C}' | '\u{7F8}' | '\u{2044}' |
+        '\u{FE10}' | '\u{FE14}' | '\u{FE50}' | '\u{FE54}' | '\u{FF0C}' | '\u{FF1B}' => WordClass::MidNum,
+        '_' | '\u{203F}' | '\u{2040}' | '\u{2054}' | '\u{FE33}' | '\u{FE34}' | '\u{FE4D}'..='\u{FE4F}' | '\u{FF3F}' => WordClass::ExtendNumLet,
+        ' ' | '\u{1680}' | '\u{2000}'..='\u{2006}' | '\u{2008}'..='\u{200A}' | '\u{205F}' | '\u{3000}' => WordClass::Space,
+        '\u{30A1}'..='\u{30FA}' | '\u{30FC}'..='\u{30FF}' | '\u{31F0}'..='\u{31FF}' | '\u{FF66}'..='\u{FF9D}' => WordClass::Katakana,
+        // hangul is written with spaces between words so it's treated
+        // like letters, the other wide scripts break between every char
+        '\u{1100}'..='\u{11FF}' | '\u{3130}'..='\u{318F}' | '\u{AC00}'..='\u{D7FB}' => WordClass::Letter,
+        _ if is_combining_mark(c) => WordClass::Extend,
+        _ if is_regional_indicator(c) => WordClass::RegionalIndicator,
+        _ if c.is_numeric() && !is_ideographic(c) => WordClass::Numeric,
+        _ if c.is_alphabetic() && !is_ideographic(c) => WordClass::Letter,
+        _ if is_extended_pictographic(c) => WordClass::Pictographic,
+        _ => WordClass::Other
+    }
+}
+
+/*
+    whether there is a word boundary before each char, text.len() + 1
+    entries like grapheme_boundaries. a word here is anything between two
+    boundaries, so runs of spaces and single punctuation marks are words
+    too.
+*/
+pub fn word_boundaries(text: &[char]) -> Vec<bool> {
+    use WordClass::*;
+    let classes: Vec<WordClass> = text.iter().map(|c| word_class(*c)).collect();
+    let ignored = |c: WordClass| matches!(c, Extend | Format | ZWJ);
+    let letter = |c: WordClass| c == Letter;
+    let mid_letter = |c: WordClass| matches!(c, MidLetter | MidNumLet | SingleQuote);
+    let mid_num = |c: WordClass| matches!(c, MidNum | MidNumLet | SingleQuote);
+    // WB4, marks and format chars take the class of the char they follow
+    let previous = |i: usize| (0..i).rev().find(|j| !ignored(classes[*j]));
+    let next = |i: usize| (i + 1..text.len()).find(|j| !ignored(classes[*j]));
+
+    let mut boundaries = vec![true; text.len() + 1];
+    for i in 1..text.len() {
+        let (raw_before, after) = (classes[i - 1], classes[i]);
+        boundaries[i] = match (raw_before, after) {
+            (CR, LF) => false,
+            (CR, _) | (LF, _) | (Newline, _) => true,
+            (_, CR) | (_, LF) | (_, Newline) => true,
+            (ZWJ, Pictographic) => false,
+            (Space, Space) => false,
+            (_, Extend) | (_, Format) | (_, ZWJ) => false,
+            _ => {
+                let p = previous(i);
+                let before = p.map_or(Other, |j| classes[j]);
+                let before_before = p.and_then(previous).map_or(Other, |j| classes[j]);
+                let after_after = next(i).map_or(Other, |j| classes[j]);
+                match (before, after) {
+                    (Letter, Letter) => false,
+                    (Letter, m) if mid_letter(m) && letter(after_after) => false,
+                    (m, Letter) if mid_letter(m) && letter(before_before) => false,
+                    (Numeric, Numeric) | (Letter, Numeric) | (Numeric, Letter) => false,
+                    (m, Numeric) if mid_num(m) && before_before == Numeric => false,
+                    (Numeric, m) if mid_num(m) && after_after == Numeric => false,
+                    (Katakana, Katakana) => false,
+                    (Letter, ExtendNumLet) | (Numeric, ExtendNumLet) | (Katakana, ExtendNumLet) | (ExtendNumLet, ExtendNumLet) => false,
+                    (ExtendNumLet, Letter) | (ExtendNumLet, Numeric) | (ExtendNumLet, Katakana) => false,
+                    (RegionalIndicator, RegionalIndicator) => {
+                        // flags are pairs, count the indicators before
+                        let mut count = 0;
+                        let mut j = p;
+                        while let Some(k) = j.filter(|k| classes[*k] == RegionalIndicator) {
+                            count += 1;
+                            j = previous(k);
+                        }
+                        count % 2 == 0
+                    },
+                    _ => true
+                }
+            }
+        };
+    }
+    boundaries
+}
+
+fn is_space_word(text: &[char], start: usize, end: usize) -> bool {
+    text[start..end].iter().all(|c| c.is_whitespace())
+}
+
+// ctrl+right, the start of the next word skipping over spaces
+pub fn next_word_start(text: &[char], index: usize) -> usize {
+    let boundaries = word_boundaries(text);
+    let next = |i: usize| (i + 1..=text.len()).find(|j| boundaries[*j]).unwrap_or(text.len());
+    let mut i = next(std::cmp::min(index, text.len()));
+    while i < text.len() && is_space_word(text, i, next(i)) {
+        i = next(i);
+    }
+    i
+}
+
+// ctrl+left, the start of the word before the caret skipping over spaces
+pub fn prev_word_start(text: &[char], index: usize) -> usize {
+    let boundaries = word_boundaries(text);
+    let prev = |i: usize| (0..i).rev().find(|j| boundaries[*j]).unwrap_or(0);
+    let index = std::cmp::min(index, text.len());
+    let mut i = prev(index);
+    while i > 0 && is_space_word(text, i, index) {
+        i = prev(i);
+    }
+    i
+}
+
+// the word under a double click, the one the index is in or, at the very
+// end of the text, the one before it
+pub fn word_at(text: &[char], index: usize) -> (usize, usize) {
+    if text.is_empty() {
+        return (0, 0);
+    }
+    let boundaries = word_boundaries(text);
+    let index = std::cmp::min(index, text.len() - 1);
+    let start = (0..=index).rev().find(|i| boundaries[*i]).unwrap_or(0);
+    let end = (index + 1..=text.len()).find(|i| boundaries[*i]).unwrap_or(text.len());
+    (start, end)
+}
+
+#[cfg(test)]
+mod segment_tests {
+    use super::*;
+
+    fn split(text: &str, boundaries: fn(&[char]) -> Vec<bool>) -> Vec<String> {
+        let chars: Vec<char> = text.chars().collect();
+        let boundaries = boundaries(&chars);
+        let mut parts = vec![];
+        let mut start = 0;
+        for end in 1..=chars.len() {
+            if boundaries[end] {
+                parts.push(chars[start..end].iter().collect::<String>());
+                start = end;
+            }
+        }
+        parts
+    }
+
+    #[test]
+    fn test_grapheme_clusters() {
+        // e with a combining acute, a family joined with zwj, two flags,
+        // a thumbs up with a skin tone, crlf and a hangul syllable in jamo
+        let text = "e\u{301}\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}\u{1F1E9}\u{1F1EA}\u{1F1EB}\u{1F1F7}\u{1F44D}\u{1F3FD}\r\n\u{1100}\u{1161}\u{11A8}x";
+        assert_eq!(split(text, grapheme_boundaries), vec![
+            "e\u{301}", "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}", "\u{1F1E9}\u{1F1EA}", "\u{1F1EB}\u{1F1F7}",
+            "\u{1F44D}\u{1F3FD}", "\r\n", "\u{1100}\u{1161}\u{11A8}", "x"]);
+        // a joiner after a letter doesn't glue the next emoji on
+        assert_eq!(split("a\u{200D}\u{1F600}", grapheme_boundaries), vec!["a\u{200D}", "\u{1F600}"]);
+
+        let chars: Vec<char> = text.chars().collect();
+        assert_eq!(next_grapheme_boundary(&chars, 0), 2);
+        assert_eq!(next_grapheme_boundary(&chars, 2), 7);
+        assert_eq!(prev_grapheme_boundary(&chars, 7), 2);
+        assert_eq!(grapheme_start(&chars, 4), 2);
+        assert_eq!(grapheme_start(&chars, 7), 7);
+    }
+
+    #[test]
+    fn test_words() {
+        assert_eq!(split("can't stop 3.14, foo_bar.txt!", word_boundaries),
+            vec!["can't", " ", "stop", " ", "3.14", ",", " ", "foo_bar.txt", "!"]);
+        assert_eq!(split("caf\u{E9}\u{301}  \u{65E5}\u{672C}\u{30AB}\u{30BF}\u{30AB}\u{30CA}", word_boundaries),
+            vec!["caf\u{E9}\u{301}", "  ", "\u{65E5}", "\u{672C}", "\u{30AB}\u{30BF}\u{30AB}\u{30CA}"]);
+    }
+
+    #[test]
+    fn test_word_jumps() {
+        let text: Vec<char> = "hello,  wide world".chars().collect();
+        assert_eq!(next_word_start(&text, 0), 5);
+        assert_eq!(next_word_start(&text, 5), 8);
+        assert_eq!(next_word_start(&text, 9), 13);
+        assert_eq!(next_word_start(&text, 13), 18);
+        assert_eq!(prev_word_start(&text, 18), 13);
+        assert_eq!(prev_word_start(&text, 13), 8);
+        assert_eq!(prev_word_start(&text, 8), 5);
+        assert_eq!(prev_word_start(&text, 3), 0);
+    }
+
+    #[test]
+    fn test_word_at() {
+        let text: Vec<char> = "open foo_bar.txt now".chars().collect();
+        assert_eq!(word_at(&text, 0), (0, 4));
+        assert_eq!(word_at(&text, 7), (5, 16));
+        assert_eq!(word_at(&text, 4), (4, 5));
+        assert_eq!(word_at(&text, 20), (17, 20));
+        assert_eq!(word_at(&[], 0), (0, 0));
+    }
+}
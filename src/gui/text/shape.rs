@@ -0,0 +1,184 @@
+
+/*
+    turns a run of chars into positioned glyphs. chars are mapped to glyphs,
+    ligatures are substituted and the advances are adjusted by kerning.
+    positions are kept as f32 while laying out and only rounded at the end
+    so long strings don't drift the way summing truncated advances did.
+
+    text is set in a face picked with FontManager::select, chars the face
+    doesn't have are drawn with the first font that has them. ligatures and kerning only apply between glyphs from the same font.
+
+    every char gets a caret position. a ligature has one glyph for several
+    chars so its advance is split evenly between them, that way the caret
+    can still sit between the f and the i of an fi ligature.
+*/
+
+use crate::gui::text::manager::FontManager;
+
+pub struct ShapedGlyph {
+    // index of the font in the FontManager
+    pub font: usize,
+    pub glyph_index: u16,
+    // pen position from the start of the run
+    pub x: i32,
+    // index of the first char this glyph was made from and how many chars
+    // it covers, more than 1 for ligatures
+    pub cluster: usize,
+    pub char_count: usize
+}
+
+pub struct ShapedText {
+    pub glyphs: Vec<ShapedGlyph>,
+    // one entry per char plus one for the end of the text
+    pub caret_positions: Vec<i32>,
+    pub width: i32
+}
+
+impl ShapedText {
+    // the caret index closest to x, x is relative to the start of the run
+    pub fn caret_index_at(&self, x: i32) -> usize {
+        let mut best = 0;
+        for (i, position) in self.caret_positions.iter().enumerate() {
+            if (position - x).abs() < (self.caret_positions[best] - x).abs() {
+                best = i;
+            }
+        }
+        best
+    }
+
+    // like caret_index_at but only lands on indexes where boundaries is
+    // true, so a click never puts the caret inside a grapheme cluster
+    pub fn caret_index_at_boundary(&self, x: i32, boundaries: &[bool]) -> usize {
+        let mut best = 0;
+        for (i, position) in self.caret_positions.iter().enumerate() {
+            if boundaries[i] && (position - x).abs() < (self.caret_positions[best] - x).abs() {
+                best = i;
+            }
+        }
+        best
+    }
+}
+
+// a glyph while shaping, the font, glyph, first char and char count
+#[derive(Copy, Clone)]
+struct Glyph {
+    font: usize,
+    index: u16,
+    cluster: usize,
+    char_count: usize
+}
+
+pub fn shape_text(text: &[char], fonts: &FontManager, face: usize, font_size: f32) -> ShapedText {
+    let mut glyphs: Vec<Glyph> = text.iter()
+        .enumerate()
+        .map(|(i, c)| {
+            let font = fonts.font_for_char(*c, face);
+            Glyph { font, index: fonts.font(font).font.lookup_glyph_index(*c), cluster: i, char_count: 1 }
+        })
+        .collect();
+    substitute_ligatures(&mut glyphs, fonts);
+
+    let mut shaped = Vec::<ShapedGlyph>::with_capacity(glyphs.len());
+    let mut caret_positions = Vec::<i32>::with_capacity(text.len() + 1);
+    let mut pen = 0.0f32;
+    for (i, glyph) in glyphs.iter().enumerate() {
+        let font = fonts.font(glyph.font);
+        let mut advance = font.font.metrics_indexed(glyph.index, font_size).advance_width;
+        if let Some(next) = glyphs.get(i + 1) {
+            if next.font == glyph.font {
+                advance += font.kerning(glyph.index, next.index, font_size);
+            }
+        }
+        shaped.push(ShapedGlyph {
+            font: glyph.font,
+            glyph_index: glyph.index,
+            x: pen.round() as i32,
+            cluster: glyph.cluster,
+            char_count: glyph.char_count
+        });
+        for part in 0..glyph.char_count {
+            caret_positions.push((pen + advance * part as f32 / glyph.char_count as f32).round() as i32);
+        }
+        pen += advance;
+    }
+    let width = pen.round() as i32;
+    caret_positions.push(width);
+
+    ShapedText { glyphs: shaped, caret_positions, width }
+}
+
+// replaces glyph sequences with their ligature, the first ligature listed
+// for a glyph that matches wins
+fn substitute_ligatures(glyphs: &mut Vec<Glyph>, fonts: &FontManager) {
+    let mut i = 0;
+    while i < glyphs.len() {
+        let first = glyphs[i];
+        let font = fonts.font(first.font);
+        if let Some(ligatures) = font.tables.ligatures.get(&first.index) {
+            let rest = &glyphs[i + 1..];
+            let matched = ligatures.iter().find(|ligature| {
+                rest.len() >= ligature.components.len() &&
+                    ligature.components.iter().zip(rest.iter()).all(|(c, g)| *c == g.index && g.font == first.font)
+            });
+            if let Some(ligature) = matched {
+                let count = ligature.components.len();
+                let char_count = glyphs[i..=i + count].iter().map(|g| g.char_count).sum();
+                glyphs[i] = Glyph { index: ligature.glyph, char_count, ..first };
+                glyphs.drain(i + 1..=i + count);
+                // the new glyph can start another ligature so look again
+                continue;
+            }
+        }
+        i += 1;
+    }
+}
+
+#[cfg(test)]
+mod shape_tests {
+    use super::*;
+    use crate::gui::text::font::Font;
+
+    fn load_fonts() -> FontManager {
+        let font = include_bytes!("../../../fonts/OpenSans-Regular.ttf") as &[u8];
+        let mut fonts = FontManager::new();
+        fonts.add_font(Font::from_bytes(font).unwrap());
+        fonts
+    }
+
+    fn chars(text: &str) -> Vec<char> {
+        text.chars().collect()
+    }
+
+    #[test]
+    fn test_kerning_pairs() {
+        let fonts = load_fonts();
+        let font = &fonts.primary().font;
+        let shaped = shape_text(&chars("AV"), &fonts, 0, 40.0);
+        let unkerned = font.metrics('A', 40.0).advance_width + font.metrics('V', 40.0).advance_width;
+        assert!((shaped.width as f32) < unkerned - 1.0);
+        assert!(shaped.glyphs[1].x < font.metrics('A', 40.0).advance_width as i32);
+    }
+
+    #[test]
+    fn test_ligature_keeps_caret_positions() {
+        let fonts = load_fonts();
+        let text = chars("file");
+        let shaped = shape_text(&text, &fonts, 0, 30.0);
+        // fi becomes one glyph but every char still has a caret position
+        assert_eq!(shaped.glyphs.len(), 3);
+        assert_eq!(shaped.glyphs[0].char_count, 2);
+        assert_eq!(shaped.glyphs[1].cluster, 2);
+        assert_eq!(shaped.caret_positions.len(), text.len() + 1);
+        assert!(shaped.caret_positions.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(*shaped.caret_positions.last().unwrap(), shaped.width);
+    }
+
+    #[test]
+    fn test_caret_index_at() {
+        let fonts = load_fonts();
+        let shaped = shape_text(&chars("1234"), &fonts, 0, 20.0);
+        assert_eq!(shaped.caret_index_at(-10), 0);
+        assert_eq!(shaped.caret_index_at(shaped.caret_positions[2] + 1), 2);
+        assert_eq!(shaped.caret_index_at(1000), 4);
+    }
+}
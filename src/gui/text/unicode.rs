@@ -0,0 +1,100 @@
+
+/*
+    the few unicode character properties the text code needs, as range
+    tables covering the scripts people actually name files in. these are
+    close to the unicode data files rather than exact copies of them, a
+    char that's missed falls back to behaving like a latin letter.
+*/
+
+// nonspacing, spacing and enclosing marks (general category M), these
+// attach to the char before them
+const COMBINING_MARKS: &[(u32, u32)] = &[
+    (0x0300, 0x036F), (0x0483, 0x0489), (0x0591, 0x05BD), (0x05BF, 0x05BF),
+    (0x05C1, 0x05C2), (0x05C4, 0x05C5), (0x05C7, 0x05C7), (0x0610, 0x061A),
+    (0x064B, 0x065F), (0x0670, 0x0670), (0x06D6, 0x06DC), (0x06DF, 0x06E4),
+    (0x06E7, 0x06E8), (0x06EA, 0x06ED), (0x0711, 0x0711), (0x0730, 0x074A),
+    (0x07A6, 0x07B0), (0x0816, 0x082D), (0x0859, 0x085B), (0x08D3, 0x0903),
+    (0x093A, 0x093C), (0x093E, 0x094F), (0x0951, 0x0957), (0x0962, 0x0963),
+    (0x0981, 0x0983), (0x09BC, 0x09BC), (0x09BE, 0x09CD), (0x09D7, 0x09D7),
+    (0x09E2, 0x09E3), (0x0A01, 0x0A03), (0x0A3C, 0x0A51), (0x0A70, 0x0A71),
+    (0x0A75, 0x0A75), (0x0A81, 0x0A83), (0x0ABC, 0x0ABC), (0x0ABE, 0x0ACD),
+    (0x0AE2, 0x0AE3), (0x0B01, 0x0B03), (0x0B3C, 0x0B3C), (0x0B3E, 0x0B57),
+    (0x0B82, 0x0B82), (0x0BBE, 0x0BCD), (0x0BD7, 0x0BD7), (0x0C00, 0x0C04),
+    (0x0C3E, 0x0C56), (0x0C81, 0x0C83), (0x0CBC, 0x0CBC), (0x0CBE, 0x0CD6),
+    (0x0D00, 0x0D03), (0x0D3B, 0x0D3C), (0x0D3E, 0x0D4D), (0x0D57, 0x0D57),
+    (0x0E31, 0x0E31), (0x0E34, 0x0E3A), (0x0E47, 0x0E4E), (0x0EB1, 0x0EB1),
+    (0x0EB4, 0x0EBC), (0x0EC8, 0x0ECD), (0x0F18, 0x0F19), (0x0F35, 0x0F35),
+    (0x0F37, 0x0F37), (0x0F39, 0x0F39), (0x0F3E, 0x0F3F), (0x0F71, 0x0F84),
+    (0x102B, 0x103E), (0x1056, 0x1059), (0x135D, 0x135F), (0x1712, 0x1714),
+    (0x17B4, 0x17D3), (0x180B, 0x180D), (0x1885, 0x1886), (0x18A9, 0x18A9),
+    (0x1AB0, 0x1AFF), (0x1B00, 0x1B04), (0x1B34, 0x1B44), (0x1DC0, 0x1DFF),
+    (0x20D0, 0x20F0), (0x2CEF, 0x2CF1), (0x2DE0, 0x2DFF), (0x302A, 0x302F),
+    (0x3099, 0x309A), (0xA66F, 0xA672), (0xA674, 0xA67D), (0xA69E, 0xA69F),
+    (0xA8E0, 0xA8F1), (0xFB1E, 0xFB1E), (0xFE00, 0xFE0F), (0xFE20, 0xFE2F),
+    (0x1F3FB, 0x1F3FF), (0xE0020, 0xE007F), (0xE0100, 0xE01EF)
+];
+
+// chinese, japanese and korean characters and other wide scripts that
+// can be broken between any two characters
+const IDEOGRAPHIC: &[(u32, u32)] = &[
+    (0x1100, 0x115F), (0x2E80, 0x2FFF), (0x3040, 0x30FF), (0x3100, 0x318F),
+    (0x31A0, 0x31FF), (0x3200, 0x33FF), (0x3400, 0x4DBF), (0x4E00, 0x9FFF),
+    (0xA000, 0xA4CF), (0xAC00, 0xD7AF), (0xF900, 0xFAFF), (0xFE30, 0xFE4F),
+    (0xFF00, 0xFF60), (0xFFE0, 0xFFE6), (0x20000, 0x2FFFD), (0x30000, 0x3FFFD)
+];
+
+// emoji and pictographs
+const EXTENDED_PICTOGRAPHIC: &[(u32, u32)] = &[
+    (0x00A9, 0x00A9), (0x00AE, 0x00AE), (0x203C, 0x203C), (0x2049, 0x2049),
+    (0x2122, 0x2122), (0x2139, 0x2139), (0x2194, 0x21AA), (0x231A, 0x23FF),
+    (0x24C2, 0x24C2), (0x25AA, 0x25FE), (0x2600, 0x27BF), (0x2934, 0x2935),
+    (0x2B05, 0x2B55), (0x3030, 0x3030), (0x303D, 0x303D), (0x3297, 0x3299),
+    (0x1F000, 0x1F0FF), (0x1F10D, 0x1F1AD), (0x1F201, 0x1F2FF),
+    (0x1F300, 0x1F3FA), (0x1F400, 0x1F64F), (0x1F680, 0x1F6FF),
+    (0x1F700, 0x1F7FF), (0x1F800, 0x1F8FF), (0x1F900, 0x1F9FF),
+    (0x1FA00, 0x1FAFF), (0x1FC00, 0x1FFFD)
+];
+
+fn in_ranges(c: char, ranges: &[(u32, u32)]) -> bool {
+    let c = c as u32;
+    // the tables are sorted so a binary search finds the candidate range
+    match ranges.binary_search_by(|(start, _)| start.cmp(&c)) {
+        Ok(_) => true,
+        Err(0) => false,
+        Err(i) => c <= ranges[i - 1].1
+    }
+}
+
+pub fn is_combining_mark(c: char) -> bool {
+    in_ranges(c, COMBINING_MARKS)
+}
+
+pub fn is_ideographic(c: char) -> bool {
+    in_ranges(c, IDEOGRAPHIC)
+}
+
+pub fn is_extended_pictographic(c: char) -> bool {
+    in_ranges(c, EXTENDED_PICTOGRAPHIC)
+}
+
+pub fn is_regional_indicator(c: char) -> bool {
+    ('\u{1F1E6}'..='\u{1F1FF}').contains(&c)
+}
+
+#[cfg(test)]
+mod unicode_tests {
+    use super::*;
+
+    #[test]
+    fn test_tables_are_sorted() {
+        for table in [COMBINING_MARKS, IDEOGRAPHIC, EXTENDED_PICTOGRAPHIC].iter() {
+            assert!(table.iter().all(|(start, end)| start <= end));
+            assert!(table.windows(2).all(|w| w[0].1 < w[1].0));
+        }
+        assert!(is_combining_mark('\u{0301}'));
+        assert!(!is_combining_mark('e'));
+        assert!(is_ideographic('\u{4E2D}'));
+        assert!(is_extended_pictographic('\u{1F600}'));
+        assert!(!is_extended_pictographic('a'));
+    }
+}
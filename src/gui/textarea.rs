@@ -0,0 +1,576 @@
+use crate::gui::is_point_in_rect;
+use crate::gui::style::BoxStyle;
+use crate::gui::Rect;
+use crate::gui::Bounds;
+use crate::gui::control::Control;
+use crate::gui::keyboard::KeyboardModifiers;
+use crate::gui::draw::layout_text_block;
+use crate::gui::text::layout::TextLayout;
+use crate::gui::text::shape::shape_text;
+use crate::gui::text::segment::next_grapheme_boundary;
+use crate::gui::text::segment::prev_grapheme_boundary;
+use crate::gui::text::segment::next_word_start;
+use crate::gui::text::segment::prev_word_start;
+use crate::gui::text::segment::word_at;
+use crate::gui::text::manager::FontManager;
+use crate::gui::widget::Widget;
+use crate::gui::widget::Event;
+use crate::gui::mouse::MouseButton;
+use crate::gui::keyboard::Key;
+use crate::gui::keyboard::KeyState;
+use crate::gui::keyboard::TextInput;
+use crate::gui::keyboard::EditCommand;
+use crate::gui::draw::draw_textarea;
+use crate::gui::Cursor;
+use crate::gui::PixelBuffer;
+use std::iter::FromIterator;
+use std::time::Instant;
+
+/*
+    multi-line text editing for notes, tags and small config files. works
+    like TextBox but the text is laid out in lines by text::layout, wrapped
+    when the style has word_wrap set and scrolled horizontally otherwise.
+
+    the layout is kept on the control and redone after every edit and
+    resize, the render threads only read it.
+*/
+pub struct TextArea {
+    pub text: Vec::<char>,
+    pub bounds: Bounds,
+    pub bounds_rect: Rect,
+    pub hot: bool,
+    pub active: bool,
+    pub cursor_index: usize,
+    pub selection_index: usize,
+    // added to the text position like TextBox::scroll_offset_x, so they
+    // are 0 or negative
+    pub scroll_offset_x: i32,
+    pub scroll_offset_y: i32,
+    pub show_line_numbers: bool,
+    pub style: BoxStyle,
+    pub layout: TextLayout,
+    // the x Up and Down aim for, so moving through a short line doesn't
+    // lose the column
+    goal_x: Option<i32>
+}
+
+impl TextArea {
+    pub fn new(bounds: Bounds, style: BoxStyle) -> TextArea {
+        TextArea {
+            text: Vec::new(),
+            bounds,
+            bounds_rect: Rect::default(),
+            hot: false,
+            active: false,
+            cursor_index: 0,
+            selection_index: usize::MAX,
+            scroll_offset_x: 0,
+            scroll_offset_y: 0,
+            show_line_numbers: false,
+            style,
+            layout: TextLayout { lines: vec![], width: 0, height: 0, line_height: 1, graphemes: vec![true] },
+            goal_x: None
+        }
+    }
+
+    pub fn get_text(&self) -> String {
+        if self.selection_index == usize::MAX {
+            return String::from_iter(&self.text);
+        }
+        let (start, end) = self.selection_range();
+        String::from_iter(&self.text[start..end])
+    }
+
+    pub fn update_bounds_rect(&mut self, width: i32, height: i32, scale: f32) {
+        self.set_bounds_rect(self.bounds.get_rect(width, height, scale));
+    }
+
+    pub fn set_bounds_rect(&mut self, rect: Rect) {
+        self.bounds_rect = rect;
+        self.relayout();
+        self.set_cursor_index(self.cursor_index);
+    }
+
+    // the box the text is drawn in, inside the padding and right of the
+    // line numbers
+    pub fn text_rect(&self) -> Rect {
+        let style = self.get_scaled_style();
+        let gutter = self.gutter_width();
+        Rect {
+            x: self.bounds_rect.x + style.border_size.left + style.padding_size.left + gutter,
+            y: self.bounds_rect.y + style.border_size.top + style.padding_size.top,
+            w: self.bounds_rect.w - style.border_size.left - style.padding_size.left - style.border_size.right - style.padding_size.right - gutter,
+            h: self.bounds_rect.h - style.border_size.top - style.padding_size.top - style.border_size.bottom - style.padding_size.bottom
+        }
+    }
+
+    // room for the widest line number and a gap of half a digit on both
+    // sides, 0 when line numbers are off
+    pub fn gutter_width(&self) -> i32 {
+        if !self.show_line_numbers {
+            return 0;
+        }
+        let fonts = unsafe { &crate::APPLICATION_STATE.fonts };
+        let style = self.get_scaled_style();
+        let paragraphs = self.text.iter().filter(|c| **c == '\n').count() + 1;
+        let digits = std::cmp::max(2, paragraphs.to_string().len());
+        let widest = vec!['0'; digits];
+        let digit_width = shape_text(&['0'], fonts, fonts.select_style(&style), style.font_size).width;
+        shape_text(&widest, fonts, fonts.select_style(&style), style.font_size).width + digit_width
+    }
+
+    // lays the text out again, needed after anything that changes the text
+    // or the width it wraps at
+    pub fn relayout(&mut self) {
+        let fonts = unsafe { &crate::APPLICATION_STATE.fonts };
+        let style = self.get_scaled_style();
+        let width = self.text_rect().w;
+        self.layout = layout_text_block(&self.text, fonts, fonts.select_style(&style), width, &style);
+    }
+
+    // the gutter takes width from the text so it wraps again
+    pub fn toggle_line_numbers(&mut self) {
+        self.show_line_numbers = !self.show_line_numbers;
+        self.relayout();
+        self.set_cursor_index(self.cursor_index);
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.chars().collect();
+        self.selection_index = usize::MAX;
+        self.scroll_offset_x = 0;
+        self.scroll_offset_y = 0;
+        self.relayout();
+        self.set_cursor_index(0);
+    }
+
+    // inserts text at cursor or replaces text in selection
+    pub fn insert_text(&mut self, text: Option<String>) {
+        let text = match text {
+            Some(t) => t,
+            None => return
+        };
+        self.delete_selection();
+        // pasted windows line endings become plain newlines
+        let chars: Vec<char> = text.chars().filter(|c| *c != '\r').collect();
+        let index = self.cursor_index;
+        self.text.splice(index..index, chars.iter().cloned());
+        self.relayout();
+        self.set_cursor_index(index + chars.len());
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        self.delete_selection();
+        let index = std::cmp::min(self.cursor_index, self.text.len());
+        self.text.insert(index, c);
+        self.relayout();
+        self.set_cursor_index(index + 1);
+    }
+
+    pub fn new_line(&mut self) {
+        self.insert_char('\n');
+    }
+
+    pub fn delete(&mut self) {
+        if self.selection_index != usize::MAX {
+            self.delete_selection();
+        }
+        else if self.cursor_index < self.text.len() {
+            let end = next_grapheme_boundary(&self.text, self.cursor_index);
+            self.text.drain(self.cursor_index..end);
+            self.relayout();
+            self.set_cursor_index(self.cursor_index);
+        }
+    }
+
+    pub fn delete_back(&mut self) {
+        if self.selection_index != usize::MAX {
+            self.delete_selection();
+        }
+        else if self.cursor_index > 0 {
+            let start = prev_grapheme_boundary(&self.text, self.cursor_index);
+            self.text.drain(start..self.cursor_index);
+            self.relayout();
+            self.set_cursor_index(start);
+        }
+    }
+
+    fn delete_selection(&mut self) {
+        if self.selection_index == usize::MAX {
+            return;
+        }
+        let (start, end) = self.selection_range();
+        self.text.drain(start..end);
+        self.selection_index = usize::MAX;
+        self.relayout();
+        self.set_cursor_index(start);
+    }
+
+    fn selection_range(&self) -> (usize, usize) {
+        let start = std::cmp::min(self.cursor_index, self.selection_index);
+        let end = std::cmp::min(std::cmp::max(self.cursor_index, self.selection_index), self.text.len());
+        (start, end)
+    }
+
+    pub fn set_cursor_index(&mut self, i: usize) {
+        self.cursor_index = std::cmp::min(i, self.text.len());
+        self.goal_x = None;
+        self.scroll_to_cursor();
+        crate::CURSOR_TOGGLE.store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    // scrolls just far enough to show the whole line the cursor is on
+    fn scroll_to_cursor(&mut self) {
+        if self.layout.lines.is_empty() {
+            return;
+        }
+        let rect = self.text_rect();
+        let (x, line) = self.layout.caret_position(self.cursor_index);
+        let top = self.layout.lines[line].top;
+        let bottom = top + self.layout.line_height;
+        if top + self.scroll_offset_y < 0 {
+            self.scroll_offset_y = -top;
+        }
+        else if bottom + self.scroll_offset_y > rect.h {
+            self.scroll_offset_y = std::cmp::min(0, rect.h - bottom);
+        }
+        // lines that don't wrap can be wider than the box
+        let caret_width = std::cmp::max(1, crate::gui::scale_i32(2, crate::gui::get_scale_factor()));
+        if x + self.scroll_offset_x < 0 {
+            self.scroll_offset_x = -x;
+        }
+        else if x + caret_width + self.scroll_offset_x > rect.w {
+            self.scroll_offset_x = std::cmp::min(0, rect.w - x - caret_width);
+        }
+    }
+
+    pub fn select_all(&mut self) {
+        self.set_cursor_index(self.text.len());
+        self.selection_index = 0;
+    }
+
+    pub fn arrow_right(&mut self, modifiers: KeyboardModifiers) {
+        self.update_selection_index(modifiers);
+        if modifiers.ctrl {
+            self.ctrl_jump_cursor(1);
+        }
+        else {
+            self.set_cursor_index(next_grapheme_boundary(&self.text, self.cursor_index));
+        }
+    }
+
+    pub fn arrow_left(&mut self, modifiers: KeyboardModifiers) {
+        self.update_selection_index(modifiers);
+        if modifiers.ctrl {
+            self.ctrl_jump_cursor(-1);
+        }
+        else {
+            self.set_cursor_index(prev_grapheme_boundary(&self.text, self.cursor_index));
+        }
+    }
+
+    pub fn arrow_up(&mut self, modifiers: KeyboardModifiers) {
+        self.update_selection_index(modifiers);
+        self.move_lines(-1);
+    }
+
+    pub fn arrow_down(&mut self, modifiers: KeyboardModifiers) {
+        self.update_selection_index(modifiers);
+        self.move_lines(1);
+    }
+
+    // page up and down move the cursor and the view by a box full of lines
+    // less one, so the line at the edge stays in view for context
+    pub fn page_up(&mut self, modifiers: KeyboardModifiers) {
+        self.update_selection_index(modifiers);
+        let lines = self.lines_per_page();
+        self.scroll_offset_y = std::cmp::min(0, self.scroll_offset_y + lines * self.layout.line_height);
+        self.move_lines(-lines);
+    }
+
+    pub fn page_down(&mut self, modifiers: KeyboardModifiers) {
+        self.update_selection_index(modifiers);
+        let lines = self.lines_per_page();
+        let max_scroll = std::cmp::min(0, self.text_rect().h - self.layout.height);
+        self.scroll_offset_y = std::cmp::max(max_scroll, self.scroll_offset_y - lines * self.layout.line_height);
+        self.move_lines(lines);
+    }
+
+    fn lines_per_page(&self) -> i32 {
+        std::cmp::max(1, self.text_rect().h / self.layout.line_height - 1)
+    }
+
+    // moves the cursor up or down by a number of lines keeping its x.
+    // moving past the first or last line goes to the start or end.
+    fn move_lines(&mut self, by: i32) {
+        let (x, line) = self.layout.caret_position(self.cursor_index);
+        let goal_x = self.goal_x.unwrap_or(x);
+        let target = line as i32 + by;
+        if target < 0 {
+            self.set_cursor_index(0);
+        }
+        else if target >= self.layout.lines.len() as i32 {
+            self.set_cursor_index(self.text.len());
+        }
+        else {
+            let index = self.layout.index_at_x(target as usize, goal_x);
+            self.set_cursor_index(index);
+        }
+        self.goal_x = Some(goal_x);
+    }
+
+    // home and end go to the ends of the line on screen, with ctrl to the
+    // ends of the text
+    pub fn home(&mut self, modifiers: KeyboardModifiers) {
+        self.update_selection_index(modifiers);
+        if modifiers.ctrl {
+            self.set_cursor_index(0);
+        }
+        else {
+            let (_, line) = self.layout.caret_position(self.cursor_index);
+            self.set_cursor_index(self.layout.lines[line].start);
+        }
+    }
+
+    pub fn end(&mut self, modifiers: KeyboardModifiers) {
+        self.update_selection_index(modifiers);
+        if modifiers.ctrl {
+            self.set_cursor_index(self.text.len());
+        }
+        else {
+            let (_, line) = self.layout.caret_position(self.cursor_index);
+            self.set_cursor_index(self.layout.index_at_x(line, i32::MAX / 2));
+        }
+    }
+
+    pub fn left_mouse_button_down(&mut self, mouse_x: i32, mouse_y: i32) {
+        let hit = is_point_in_rect(mouse_x, mouse_y, self.get_bounds());
+        self.hot = hit;
+        self.active = hit;
+        if hit {
+            self.selection_index = usize::MAX;
+            self.set_cursor_index(self.caret_index_at(mouse_x, mouse_y));
+        }
+    }
+
+    // double click
+    pub fn select_word_at_cursor(&mut self) {
+        let (start, end) = word_at(&self.text, self.cursor_index);
+        self.selection_index = start;
+        self.set_cursor_index(end);
+    }
+
+    // triple click, the whole paragraph without its line break
+    pub fn select_paragraph_at_cursor(&mut self) {
+        let index = std::cmp::min(self.cursor_index, self.text.len());
+        let start = self.text[..index].iter().rposition(|c| *c == '\n').map_or(0, |i| i + 1);
+        let end = self.text[index..].iter().position(|c| *c == '\n').map_or(self.text.len(), |i| index + i);
+        self.selection_index = start;
+        self.set_cursor_index(end);
+    }
+
+    // the mouse wheel, lines up for positive y and right for positive x.
+    // high resolution wheels send fractions of a line.
+    pub fn scroll_lines(&mut self, lines_x: f32, lines_y: f32) {
+        let rect = self.text_rect();
+        let line_height = self.layout.line_height as f32;
+        let max_y = std::cmp::min(0, rect.h - self.layout.height);
+        let max_x = std::cmp::min(0, rect.w - self.layout.width);
+        self.scroll_offset_y = (self.scroll_offset_y + (lines_y * line_height).round() as i32).clamp(max_y, 0);
+        self.scroll_offset_x = (self.scroll_offset_x - (lines_x * line_height).round() as i32).clamp(max_x, 0);
+    }
+
+    // the caret index closest to a point in window coordinates
+    pub fn caret_index_at(&self, x: i32, y: i32) -> usize {
+        let rect = self.text_rect();
+        self.layout.index_at_point(x - rect.x - self.scroll_offset_x, y - rect.y - self.scroll_offset_y)
+    }
+
+    pub fn copy_to_clipboard(&self) {
+        let method_option = unsafe { crate::APPLICATION_STATE.set_clipboard_text_data };
+        if let Some(method) = method_option {
+            method(&self.get_text());
+        }
+    }
+
+    pub fn cut_to_clipboard(&mut self) {
+        let method_option = unsafe { crate::APPLICATION_STATE.set_clipboard_text_data };
+        if let Some(method) = method_option {
+            method(&self.get_text());
+            if self.selection_index == usize::MAX {
+                self.select_all();
+            }
+            self.delete_selection();
+        }
+    }
+
+    fn update_selection_index(&mut self, modifiers: KeyboardModifiers) {
+        if modifiers.shift && self.selection_index == usize::MAX {
+            self.selection_index = self.cursor_index;
+        }
+        else if !modifiers.shift {
+            self.selection_index = usize::MAX;
+        }
+    }
+
+    // ctrl+left and right jump between the starts of words
+    fn ctrl_jump_cursor(&mut self, by: i32) {
+        if by > 0 {
+            self.set_cursor_index(next_word_start(&self.text, self.cursor_index));
+        }
+        else {
+            self.set_cursor_index(prev_word_start(&self.text, self.cursor_index));
+        }
+    }
+}
+
+impl Widget for TextArea {
+    fn bounds(&self) -> Rect { self.bounds_rect }
+    fn set_bounds_rect(&mut self, rect: Rect) { TextArea::set_bounds_rect(self, rect) }
+
+    fn draw(&self, buffer: &mut PixelBuffer, fonts: &FontManager, draw_cursor: bool) {
+        draw_textarea(buffer, self, fonts, self.active && draw_cursor);
+    }
+
+    fn event(&mut self, event: &Event) -> bool {
+        match event {
+            Event::MouseDown { button: MouseButton::Left, x, y, clicks } => {
+                self.left_mouse_button_down(*x, *y);
+                // a double click selects the word, a triple click the paragraph
+                match clicks {
+                    2 => self.select_word_at_cursor(),
+                    3..=u32::MAX => self.select_paragraph_at_cursor(),
+                    _ => { }
+                }
+            },
+            Event::MouseMove { x, y } => return self.hit_check(*x, *y).0,
+            Event::Wheel { lines_x, lines_y, .. } => self.scroll_lines(*lines_x, *lines_y),
+            Event::Key(key) if key.state == KeyState::Pressed => match key.key {
+                Key::Enter => self.new_line(),
+                Key::Back => self.delete_back(),
+                Key::Delete => self.delete(),
+                Key::Left => self.arrow_left(key.modifiers),
+                Key::Up => self.arrow_up(key.modifiers),
+                Key::Right => self.arrow_right(key.modifiers),
+                Key::Down => self.arrow_down(key.modifiers),
+                Key::Home => self.home(key.modifiers),
+                Key::End => self.end(key.modifiers),
+                Key::PageUp => self.page_up(key.modifiers),
+                Key::PageDown => self.page_down(key.modifiers),
+                _ => return false
+            },
+            // text areas don't show a preedit, they only take the committed text
+            Event::Text(TextInput::Commit(text)) => self.insert_text(Some(text.clone())),
+            Event::Edit(command) => match command {
+                EditCommand::SelectAll => self.select_all(),
+                EditCommand::Copy => self.copy_to_clipboard(),
+                EditCommand::Cut => self.cut_to_clipboard(),
+                EditCommand::Paste(text) => self.insert_text(text.clone()),
+                EditCommand::Undo | EditCommand::Redo => return false
+            },
+            _ => return false
+        }
+        true
+    }
+
+    fn cursor(&self) -> Cursor { Cursor::IBeam }
+    fn focus_style(&self) -> Option<BoxStyle> { Some(self.get_scaled_style()) }
+    fn set_focused(&mut self, focused: bool) { self.active = focused }
+    fn timer_tick(&mut self, _now: Instant) -> bool { self.active }
+}
+
+impl Control for TextArea {
+    fn get_bounds(&self) -> Rect { self.bounds_rect }
+    fn get_hot(&self) -> bool { self.hot }
+    fn set_hot(&mut self, hit: bool) { self.hot = hit }
+
+    fn get_style(&self) -> &BoxStyle {
+        &self.style
+    }
+}
+
+#[cfg(test)]
+mod textarea_tests {
+    use super::*;
+    use crate::gui::text::font::Font;
+
+    const NO_MODIFIERS: KeyboardModifiers = KeyboardModifiers { ctrl: false, alt: false, shift: false, super_key: false };
+    const SHIFT: KeyboardModifiers = KeyboardModifiers { ctrl: false, alt: false, shift: true, super_key: false };
+
+    fn create_textarea_for_test(text: &str) -> TextArea {
+        if unsafe { crate::APPLICATION_STATE.fonts.len() } == 0 {
+            let font = include_bytes!("../../fonts/OpenSans-Regular.ttf") as &[u8];
+            let font = Font::from_bytes(font).unwrap();
+            unsafe { crate::APPLICATION_STATE.fonts.add_font(font); }
+        }
+        let mut style = BoxStyle::textbox_default();
+        style.font_size = 20.0;
+        let mut x = TextArea::new(Bounds::int(10, 10, 300, 100), style);
+        x.update_bounds_rect(1000, 1000, 1.0);
+        x.set_text(text);
+        x
+    }
+
+    #[test]
+    fn test_up_down_keeps_column() {
+        let mut textarea = create_textarea_for_test("a long first line\nab\nanother long line");
+        textarea.set_cursor_index(10);
+        let (x, _) = textarea.layout.caret_position(10);
+        textarea.arrow_down(NO_MODIFIERS);
+        // the short line only goes as far as its end
+        assert_eq!(textarea.cursor_index, 20);
+        textarea.arrow_down(NO_MODIFIERS);
+        let (x2, line) = textarea.layout.caret_position(textarea.cursor_index);
+        assert_eq!(line, 2);
+        assert!((x2 - x).abs() < 8);
+        textarea.arrow_up(NO_MODIFIERS);
+        textarea.arrow_up(NO_MODIFIERS);
+        assert_eq!(textarea.cursor_index, 10);
+        // up from the first line goes to the start, down from the last to the end
+        textarea.arrow_up(NO_MODIFIERS);
+        assert_eq!(textarea.cursor_index, 0);
+        textarea.set_cursor_index(25);
+        textarea.arrow_down(NO_MODIFIERS);
+        assert_eq!(textarea.cursor_index, textarea.text.len());
+    }
+
+    #[test]
+    fn test_selection_spans_lines() {
+        let mut textarea = create_textarea_for_test("one\ntwo\nthree");
+        textarea.set_cursor_index(1);
+        textarea.arrow_down(SHIFT);
+        textarea.arrow_down(SHIFT);
+        textarea.home(SHIFT);
+        assert_eq!(textarea.selection_index, 1);
+        assert_eq!(textarea.get_text(), "ne\ntwo\n");
+        textarea.insert_text(Some(String::from("X\r\nY")));
+        assert_eq!(String::from_iter(&textarea.text), "oX\nYthree");
+        assert_eq!(textarea.layout.lines.len(), 2);
+        assert_eq!(textarea.cursor_index, 4);
+    }
+
+    #[test]
+    fn test_page_down_scrolls() {
+        let text: Vec<String> = (0..50).map(|i| format!("line {}", i)).collect();
+        let mut textarea = create_textarea_for_test(&text.join("\n"));
+        let line_height = textarea.layout.line_height;
+        let per_page = textarea.lines_per_page();
+        textarea.page_down(NO_MODIFIERS);
+        assert_eq!(textarea.layout.caret_position(textarea.cursor_index).1, per_page as usize);
+        assert_eq!(textarea.scroll_offset_y, -per_page * line_height);
+        textarea.page_up(NO_MODIFIERS);
+        assert_eq!(textarea.cursor_index, 0);
+        assert_eq!(textarea.scroll_offset_y, 0);
+
+        // the cursor is kept in view
+        textarea.home(KeyboardModifiers { ctrl: true, alt: false, shift: false, super_key: false });
+        textarea.end(KeyboardModifiers { ctrl: true, alt: false, shift: false, super_key: false });
+        let rect = textarea.text_rect();
+        assert_eq!(textarea.scroll_offset_y, rect.h - textarea.layout.height);
+
+        // line numbers make room on the left
+        textarea.show_line_numbers = true;
+        textarea.relayout();
+        assert!(textarea.text_rect().x > rect.x);
+    }
+}
@@ -6,7 +6,29 @@ use crate::gui::Bounds;
 use crate::gui::control::Control;
 use crate::gui::keyboard::KeyboardModifiers;
 use crate::gui::style::HorizontalAlign;
+use crate::gui::draw::calculate_h_align_offset;
+use crate::gui::draw::shape_line;
+use crate::gui::text::line::truncate_text;
+use crate::gui::text::shape::shape_text;
+use crate::gui::text::segment::grapheme_boundaries;
+use crate::gui::text::segment::next_grapheme_boundary;
+use crate::gui::text::segment::prev_grapheme_boundary;
+use crate::gui::text::segment::next_word_start;
+use crate::gui::text::segment::prev_word_start;
+use crate::gui::text::segment::word_at;
+use crate::gui::text::manager::FontManager;
+use crate::gui::widget::Widget;
+use crate::gui::widget::Event;
+use crate::gui::mouse::MouseButton;
+use crate::gui::keyboard::Key;
+use crate::gui::keyboard::KeyState;
+use crate::gui::keyboard::TextInput;
+use crate::gui::keyboard::EditCommand;
+use crate::gui::draw::draw_textbox;
+use crate::gui::Cursor;
+use crate::gui::PixelBuffer;
 use std::iter::FromIterator;
+use std::time::Instant;
 use std::str::FromStr;
 
 pub struct TextBox {
@@ -19,10 +41,27 @@ pub struct TextBox {
     pub cursor_index: usize, // index of char
     pub scroll_offset_x: i32,
     pub selection_index: usize,
+    // text an input method is composing at the cursor, see set_preedit
+    pub preedit: Vec::<char>,
+    pub preedit_cursor: usize,
     pub style: BoxStyle
 }
 
 impl TextBox {
+    pub fn new(placeholder: &'static str, style: BoxStyle) -> TextBox {
+        TextBox {
+            text: Vec::new(),
+            placeholder,
+            bounds: Bounds::int(0, 0, 0, 0),
+            bounds_rect: Rect::default(),
+            hot: false, active: false,
+            cursor_index: 0, scroll_offset_x: 0,
+            selection_index: usize::MAX,
+            preedit: Vec::new(), preedit_cursor: 0,
+            style
+        }
+    }
+
     pub fn get_text(&self) -> String { 
         let start: usize;
         let end: usize;
@@ -39,8 +78,15 @@ impl TextBox {
         String::from_iter(&self.text[start..end])
     }
 
-    pub fn update_bounds_rect(&mut self, width: i32, height: i32) {
-        self.bounds_rect = self.bounds.get_rect(width, height);
+    pub fn update_bounds_rect(&mut self, width: i32, height: i32, scale: f32) {
+        self.set_bounds_rect(self.bounds.get_rect(width, height, scale));
+    }
+
+    // for a parent layout that has already worked out where it goes
+    pub fn set_bounds_rect(&mut self, rect: Rect) {
+        self.bounds_rect = rect;
+        // the text may have changed size so recalculate the scroll offset
+        self.set_cursor_index(self.cursor_index);
     }
 
     // replaces all text in the control
@@ -68,7 +114,7 @@ impl TextBox {
     fn _insert_text(&mut self, text: &str) {
         for c in text.chars() {
             self.text.insert(self.cursor_index, c);
-            self.increment_cursor_index();
+            self.set_cursor_index(self.cursor_index + 1);
         }
         self.selection_index = usize::MAX;
     }
@@ -85,13 +131,15 @@ impl TextBox {
             self.text.insert(self.cursor_index, c);
             self.selection_index = usize::MAX;
         }
-        self.increment_cursor_index();
+        self.set_cursor_index(self.cursor_index + 1);
     }
 
     pub fn delete(&mut self) {
         if self.selection_index == usize::MAX {
             if self.cursor_index < self.text.len() {
-                self.text.remove(self.cursor_index);
+                // the whole grapheme cluster goes, not just its first char
+                let end = next_grapheme_boundary(&self.text, self.cursor_index);
+                self.text.drain(self.cursor_index..end);
             }
         }
         else {
@@ -123,11 +171,12 @@ impl TextBox {
             self.cursor_index = self.text.len();
         }
         // calculate display offset to keep cursor in view
-        let font = unsafe { &crate::APPLICATION_STATE.fonts[0] };
-        let style = self.get_style();
+        let fonts = unsafe { &crate::APPLICATION_STATE.fonts };
+        let style = self.get_scaled_style();
         let box_width = self.bounds_rect.w - style.border_size.left - style.padding_size.left - style.border_size.right - style.padding_size.right;
-        let (_, text_width, _, char_widths) = crate::gui::draw::measure_string(&self.text, font, style.font_size);
-        let cursor_offset: i32 = char_widths[0..self.cursor_index].iter().sum();
+        let text = self.display_text();
+        let (_, text_width, _, char_widths) = crate::gui::draw::measure_string(&text, fonts, fonts.select_style(&style), style.font_size);
+        let cursor_offset: i32 = char_widths[0..self.cursor_index + self.preedit_cursor].iter().sum();
         let effective_cursor_offset = cursor_offset + self.scroll_offset_x;
         let mut offset: i32 = self.scroll_offset_x;
         if effective_cursor_offset > box_width {
@@ -141,18 +190,24 @@ impl TextBox {
                 offset /= 2;
             }
         }
+        else if style.horizontal_align != HorizontalAlign::Left && text_width <= box_width {
+            // right and justified text that fits is pinned to the edges
+            offset = 0;
+        }
         self.scroll_offset_x = offset;
         crate::CURSOR_TOGGLE.store(true, std::sync::atomic::Ordering::Relaxed);
     }
 
+    // the cursor moves by grapheme clusters so it never ends up between
+    // a letter and its accent or inside an emoji sequence
     pub fn increment_cursor_index(&mut self) {
-        self.set_cursor_index(self.cursor_index + 1);
+        self.set_cursor_index(next_grapheme_boundary(&self.text, self.cursor_index));
     }
 
     // returns true if cursor decremented, false if not
     pub fn decrement_cursor_index(&mut self) -> bool {
         if self.cursor_index > 0 {
-            self.set_cursor_index(self.cursor_index - 1);
+            self.set_cursor_index(prev_grapheme_boundary(&self.text, self.cursor_index));
             return true;
         }
         return false;
@@ -167,7 +222,6 @@ impl TextBox {
         self.update_selection_index(modifiers);
         if modifiers.ctrl {
             self.ctrl_jump_cursor(1);
-            self.increment_cursor_index();
         }
         else {
             self.increment_cursor_index();
@@ -200,6 +254,93 @@ impl TextBox {
         let hit = is_point_in_rect(mouse_x, mouse_y, self.get_bounds());
         self.hot = hit;
         self.active = hit;
+        if hit {
+            self.selection_index = usize::MAX;
+            self.set_cursor_index(self.caret_index_at_x(mouse_x));
+        }
+    }
+
+    // double click
+    pub fn select_word_at_cursor(&mut self) {
+        let (start, end) = word_at(&self.text, self.cursor_index);
+        self.selection_index = start;
+        self.set_cursor_index(end);
+    }
+
+    // the caret index closest to a window x coordinate, the text is shaped
+    // and aligned the same way draw_textbox does it so the caret lands
+    // where the click was
+    pub fn caret_index_at_x(&self, x: i32) -> usize {
+        let fonts = unsafe { &crate::APPLICATION_STATE.fonts };
+        let style = self.get_scaled_style();
+        let box_left = self.bounds_rect.x + style.border_size.left + style.padding_size.left;
+        let box_width = self.bounds_rect.w - style.border_size.left - style.padding_size.left - style.border_size.right - style.padding_size.right;
+        let shaped = shape_line(&self.text, fonts, fonts.select_style(&style), style.font_size, box_width, style.horizontal_align);
+        let h_align_offset = calculate_h_align_offset(box_width, shaped.width, self.scroll_offset_x, style.horizontal_align);
+        shaped.caret_index_at_boundary(x - box_left - h_align_offset - self.scroll_offset_x, &grapheme_boundaries(&self.text))
+    }
+
+    // the text with the preedit in it at the cursor, what gets drawn
+    pub fn display_text(&self) -> Vec<char> {
+        if self.preedit.is_empty() {
+            return self.text.clone();
+        }
+        let mut text = Vec::with_capacity(self.text.len() + self.preedit.len());
+        text.extend_from_slice(&self.text[..self.cursor_index]);
+        text.extend_from_slice(&self.preedit);
+        text.extend_from_slice(&self.text[self.cursor_index..]);
+        text
+    }
+
+    // shows the text an input method is composing at the cursor. it isn't
+    // part of the text until it's committed, a selection is replaced as
+    // soon as composing starts like a typed char would
+    pub fn set_preedit(&mut self, text: &str, cursor: usize) {
+        if self.selection_index != usize::MAX {
+            self.delete();
+        }
+        self.preedit = text.chars().collect();
+        self.preedit_cursor = std::cmp::min(cursor, self.preedit.len());
+        self.set_cursor_index(self.cursor_index);
+    }
+
+    pub fn commit_text(&mut self, text: &str) {
+        self.end_preedit();
+        self.insert_text(Some(String::from(text)));
+    }
+
+    pub fn end_preedit(&mut self) {
+        self.preedit.clear();
+        self.preedit_cursor = 0;
+        self.set_cursor_index(self.cursor_index);
+    }
+
+    // the caret in window coordinates, the full height of the text box
+    pub fn caret_rect(&self) -> Rect {
+        let fonts = unsafe { &crate::APPLICATION_STATE.fonts };
+        let style = self.get_scaled_style();
+        let box_left = self.bounds_rect.x + style.border_size.left + style.padding_size.left;
+        let box_top = self.bounds_rect.y + style.border_size.top + style.padding_size.top;
+        let box_width = self.bounds_rect.w - style.border_size.left - style.padding_size.left - style.border_size.right - style.padding_size.right;
+        let box_height = self.bounds_rect.h - style.border_size.top - style.padding_size.top - style.border_size.bottom - style.padding_size.bottom;
+        let text = self.display_text();
+        let shaped = shape_line(&text, fonts, fonts.select_style(&style), style.font_size, box_width, style.horizontal_align);
+        let h_align_offset = calculate_h_align_offset(box_width, shaped.width, self.scroll_offset_x, style.horizontal_align);
+        let x = shaped.caret_positions[self.cursor_index + self.preedit_cursor];
+        Rect { x: box_left + h_align_offset + self.scroll_offset_x + x, y: box_top, w: 1, h: box_height }
+    }
+
+    // the full text when it's drawn shortened with an ellipsis, for the
+    // tooltip. the text is only shortened while the box isn't being edited.
+    pub fn get_tooltip(&self) -> Option<String> {
+        if self.active {
+            return None;
+        }
+        let fonts = unsafe { &crate::APPLICATION_STATE.fonts };
+        let style = self.get_scaled_style();
+        let box_width = self.bounds_rect.w - style.border_size.left - style.padding_size.left - style.border_size.right - style.padding_size.right;
+        truncate_text(&self.text, fonts, fonts.select_style(&style), style.font_size, box_width, style.text_overflow)
+            .map(|_| String::from_iter(&self.text))
     }
 
     pub fn copy_to_clipboard(&self) {
@@ -230,20 +371,71 @@ impl TextBox {
         }
     }
 
+    // ctrl+left and right jump between the starts of words
     fn ctrl_jump_cursor(&mut self, by: i32) {
-        let mut peek_i = self.cursor_index as i32 + by;
-        let mut i = self.cursor_index as i32;
-        loop {
-            peek_i = peek_i as i32 + by;
-            i = i as i32 + by;
-            if i <= 0 { i = 0; break; }
-            if peek_i >= self.text.len() as i32 { i = self.text.len() as i32; break; }
-            if  self.text[i as usize].is_alphanumeric() && !self.text[peek_i as usize].is_alphanumeric() {
-                break;
-            }
+        if by > 0 {
+            self.set_cursor_index(next_word_start(&self.text, self.cursor_index));
         }
-        self.set_cursor_index(i as usize);
+        else {
+            self.set_cursor_index(prev_word_start(&self.text, self.cursor_index));
+        }
+    }
+}
+
+impl Widget for TextBox {
+    fn bounds(&self) -> Rect { self.bounds_rect }
+    fn set_bounds_rect(&mut self, rect: Rect) { TextBox::set_bounds_rect(self, rect) }
+
+    fn draw(&self, buffer: &mut PixelBuffer, fonts: &FontManager, draw_cursor: bool) {
+        draw_textbox(buffer, self, fonts, self.active && draw_cursor);
     }
+
+    fn event(&mut self, event: &Event) -> bool {
+        match event {
+            Event::MouseDown { button: MouseButton::Left, x, y, clicks } => {
+                self.left_mouse_button_down(*x, *y);
+                // a double click selects the word, a triple click everything
+                match clicks {
+                    2 => self.select_word_at_cursor(),
+                    3..=u32::MAX => self.select_all(),
+                    _ => { }
+                }
+            },
+            Event::MouseMove { x, y } => return self.hit_check(*x, *y).0,
+            Event::Key(key) if key.state == KeyState::Pressed => match key.key {
+                Key::Back => self.delete_back(),
+                Key::Delete => self.delete(),
+                Key::Left => self.arrow_left(key.modifiers),
+                Key::Right => self.arrow_right(key.modifiers),
+                Key::Home => self.home(key.modifiers),
+                Key::End => self.end(key.modifiers),
+                _ => return false
+            },
+            Event::Text(input) => match input {
+                TextInput::Preedit { text, cursor } => self.set_preedit(text, *cursor),
+                TextInput::Commit(text) => self.commit_text(text),
+                TextInput::PreeditEnd => self.end_preedit()
+            },
+            Event::Edit(command) => match command {
+                EditCommand::SelectAll => self.select_all(),
+                EditCommand::Copy => self.copy_to_clipboard(),
+                EditCommand::Cut => self.cut_to_clipboard(),
+                EditCommand::Paste(text) => self.insert_text(text.clone()),
+                EditCommand::Undo | EditCommand::Redo => return false
+            },
+            _ => return false
+        }
+        true
+    }
+
+    fn cursor(&self) -> Cursor { Cursor::IBeam }
+    fn tooltip(&self, _x: i32, _y: i32) -> Option<String> { self.get_tooltip() }
+    fn focus_style(&self) -> Option<BoxStyle> { Some(self.get_scaled_style()) }
+    // the caret shows while it has focus
+    fn set_focused(&mut self, focused: bool) { self.active = focused }
+    fn caret_rect(&self) -> Option<Rect> { Some(TextBox::caret_rect(self)) }
+    // the caret blinks
+    fn timer_tick(&mut self, _now: Instant) -> bool { self.active }
 }
 
 impl Control for TextBox {
@@ -251,7 +443,7 @@ impl Control for TextBox {
     fn get_hot(&self) -> bool { self.hot }
     fn set_hot(&mut self, hit: bool) { self.hot = hit }
 
-    fn get_style<'a>(&'a self) -> &'a BoxStyle {
+    fn get_style(&self) -> &BoxStyle {
         &self.style
     }
 }
@@ -259,13 +451,15 @@ impl Control for TextBox {
 #[cfg(test)]
 mod textbox_tests {
     use super::*;
+    use crate::gui::text::font::Font;
+    use crate::gui::style::TextOverflow;
     const TEXT: &str = "1234567890";
 
     fn create_textbox_for_test() -> TextBox {
         if unsafe { crate::APPLICATION_STATE.fonts.len() } == 0 {
             let font = include_bytes!("../../fonts/OpenSans-Regular.ttf") as &[u8];
-            let font = fontdue::Font::from_bytes(font, fontdue::FontSettings::default()).unwrap();
-            unsafe { crate::APPLICATION_STATE.fonts.push(font); }
+            let font = Font::from_bytes(font).unwrap();
+            unsafe { crate::APPLICATION_STATE.fonts.add_font(font); }
         }
 
         let mut x = TextBox {
@@ -276,6 +470,7 @@ mod textbox_tests {
             hot: false, active: false, 
             cursor_index: 0, scroll_offset_x: 0,
             selection_index: usize::MAX,
+            preedit: Vec::new(), preedit_cursor: 0,
             style: BoxStyle::textbox_default()
         };
         x.set_text(TEXT);
@@ -326,4 +521,102 @@ mod textbox_tests {
         textbox.selection_index = 1;
         assert_eq!(textbox.get_text(), "2345678");
     }
+
+    #[test]
+    fn test_grapheme_editing() {
+        let mut textbox = create_textbox_for_test();
+        let no_modifiers = KeyboardModifiers { ctrl: false, alt: false, shift: false, super_key: false };
+        // e with a combining accent, a zwj family emoji, then a word
+        textbox.set_text("e\u{301}\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467} ok");
+        textbox.set_cursor_index(0);
+        textbox.arrow_right(no_modifiers);
+        assert_eq!(textbox.cursor_index, 2);
+        textbox.arrow_right(no_modifiers);
+        assert_eq!(textbox.cursor_index, 7);
+        textbox.arrow_left(KeyboardModifiers { shift: true, ..no_modifiers });
+        assert_eq!(textbox.get_text(), "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}");
+        textbox.arrow_right(no_modifiers);
+        textbox.delete_back();
+        assert_eq!(textbox.get_text(), "e\u{301} ok");
+        textbox.set_cursor_index(0);
+        textbox.delete();
+        assert_eq!(textbox.get_text(), " ok");
+    }
+
+    #[test]
+    fn test_ctrl_arrows_jump_words() {
+        let mut textbox = create_textbox_for_test();
+        let ctrl = KeyboardModifiers { ctrl: true, alt: false, shift: false, super_key: false };
+        textbox.set_text("don't stop, 3.14 r\u{E9}sum\u{E9}");
+        textbox.set_cursor_index(0);
+        let mut stops = vec![];
+        for _ in 0..5 {
+            textbox.arrow_right(ctrl);
+            stops.push(textbox.cursor_index);
+        }
+        assert_eq!(stops, vec![6, 10, 12, 17, 23]);
+        textbox.arrow_left(ctrl);
+        assert_eq!(textbox.cursor_index, 17);
+        textbox.arrow_left(ctrl);
+        assert_eq!(textbox.cursor_index, 12);
+    }
+
+    #[test]
+    fn test_preedit_and_commit() {
+        let mut textbox = create_textbox_for_test();
+        textbox.set_cursor_index(2);
+        textbox.selection_index = 4;
+        textbox.set_preedit("\u{304B}\u{3093}", 2);
+        // the selection goes as soon as composing starts but nothing is
+        // inserted until the commit
+        assert_eq!(String::from_iter(&textbox.text), "12567890");
+        assert_eq!(String::from_iter(&textbox.display_text()), "12\u{304B}\u{3093}567890");
+        let before = textbox.caret_rect().x;
+        textbox.set_preedit("\u{304B}\u{3093}", 0);
+        assert!(textbox.caret_rect().x < before);
+        textbox.commit_text("\u{6F22}");
+        assert_eq!(String::from_iter(&textbox.text), "12\u{6F22}567890");
+        assert_eq!(textbox.cursor_index, 3);
+        assert!(textbox.preedit.is_empty());
+
+        // a cancelled composition leaves the text alone
+        textbox.set_preedit("\u{B4}", 1);
+        textbox.end_preedit();
+        assert_eq!(String::from_iter(&textbox.display_text()), "12\u{6F22}567890");
+    }
+
+    #[test]
+    fn test_click_places_cursor() {
+        let mut textbox = create_textbox_for_test();
+        let style = textbox.get_scaled_style();
+        let text_left = textbox.bounds_rect.x + style.border_size.left + style.padding_size.left;
+        let fonts = unsafe { &crate::APPLICATION_STATE.fonts };
+        let shaped = shape_text(&textbox.text, fonts, fonts.select_style(&style), style.font_size);
+
+        textbox.left_mouse_button_down(text_left + shaped.caret_positions[4] + 1, 20);
+        assert!(textbox.active);
+        assert_eq!(textbox.cursor_index, 4);
+        textbox.left_mouse_button_down(text_left - 3, 20);
+        assert_eq!(textbox.cursor_index, 0);
+    }
+
+    #[test]
+    fn test_right_align_click_and_tooltip() {
+        let mut textbox = create_textbox_for_test();
+        textbox.style.horizontal_align = HorizontalAlign::Right;
+        textbox.set_cursor_index(0);
+        let style = textbox.get_scaled_style();
+        let text_right = textbox.bounds_rect.x + textbox.bounds_rect.w - style.border_size.right - style.padding_size.right;
+        assert_eq!(textbox.scroll_offset_x, 0);
+        textbox.left_mouse_button_down(text_right - 1, 20);
+        assert_eq!(textbox.cursor_index, 10);
+
+        // long text is shortened when the box isn't being edited
+        textbox.style.text_overflow = TextOverflow::EllipsisMiddle;
+        assert_eq!(textbox.get_tooltip(), None);
+        textbox.active = false;
+        assert_eq!(textbox.get_tooltip(), None);
+        textbox.set_text("a_very_long_file_name_that_will_not_fit_in_the_box.txt");
+        assert_eq!(textbox.get_tooltip().unwrap(), "a_very_long_file_name_that_will_not_fit_in_the_box.txt");
+    }
 }
@@ -0,0 +1,271 @@
+
+/*
+    tile renderer. the back buffer is cut into square tiles and only the
+    tiles touched by damaged rects get redrawn. each tile is drawn into its
+    own PixelBuffer on a worker thread and copied back once every worker
+    is done.
+
+    output is byte for byte the same as drawing the whole buffer on one
+    thread. every draw function only writes pixels inside the buffer it's
+    given and never reads a pixel other than the one it's writing, so a
+    tile that starts from a copy of the back buffer ends up identical to
+    the same area of a full redraw.
+*/
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use crate::gui::PixelBuffer;
+use crate::gui::Rect;
+
+pub const TILE_SIZE: i32 = 128;
+
+// windows smaller than this are drawn on the calling thread, the cost of
+// starting workers and copying tiles isn't worth it
+pub const MIN_PARALLEL_PIXELS: i32 = 1920 * 1080 / 2;
+
+// rects that need to be redrawn before the next paint
+pub struct Damage {
+    pub rects: Vec<Rect>,
+    pub everything: bool
+}
+
+impl Damage {
+    pub const fn new() -> Damage {
+        Damage { rects: Vec::new(), everything: false }
+    }
+
+    pub fn add(&mut self, rect: Rect) {
+        if rect.w > 0 && rect.h > 0 && !self.everything {
+            self.rects.push(rect);
+        }
+    }
+
+    pub fn add_everything(&mut self) {
+        self.everything = true;
+        self.rects.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.everything && self.rects.is_empty()
+    }
+
+    // returns the damaged rects for a buffer of the given size and resets
+    pub fn take(&mut self, width: i32, height: i32) -> Vec<Rect> {
+        let rects = if self.everything {
+            vec![Rect { x: 0, y: 0, w: width, h: height }]
+        }
+        else {
+            std::mem::take(&mut self.rects)
+        };
+        self.everything = false;
+        self.rects.clear();
+        rects
+    }
+}
+
+pub fn default_thread_count() -> usize {
+    match std::thread::available_parallelism() {
+        Ok(n) => n.get(),
+        Err(_) => 1
+    }
+}
+
+// the tiles of the buffer that overlap any of the damaged rects, in row order
+pub fn damaged_tiles(buffer: &PixelBuffer, damage: &[Rect], tile_size: i32) -> Vec<Rect> {
+    let mut tiles = Vec::<Rect>::new();
+    let columns = (buffer.width + tile_size - 1) / tile_size;
+    let rows = (buffer.height + tile_size - 1) / tile_size;
+    for row in 0..rows {
+        for column in 0..columns {
+            let x = buffer.left + column * tile_size;
+            let y = buffer.top + row * tile_size;
+            let tile = Rect {
+                x,
+                y,
+                w: std::cmp::min(tile_size, buffer.left + buffer.width - x),
+                h: std::cmp::min(tile_size, buffer.top + buffer.height - y)
+            };
+            if damage.iter().any(|d| rects_overlap(d, &tile)) {
+                tiles.push(tile);
+            }
+        }
+    }
+    tiles
+}
+
+fn rects_overlap(a: &Rect, b: &Rect) -> bool {
+    a.x < b.x + b.w && b.x < a.x + a.w && a.y < b.y + b.h && b.y < a.y + a.h
+}
+
+/*
+    redraws the damaged tiles of the buffer by calling render once per tile
+    with a buffer covering just that tile. with thread_count of 1 (or a
+    small buffer) the tiles are still used but drawn on this thread.
+*/
+pub fn render_tiled<F>(buffer: &mut PixelBuffer, damage: &[Rect], tile_size: i32, thread_count: usize, render: &F)
+    where F: Fn(&mut PixelBuffer) + Sync {
+
+    let tiles = damaged_tiles(buffer, damage, tile_size);
+    if tiles.is_empty() {
+        return;
+    }
+
+    let thread_count = std::cmp::max(1, std::cmp::min(thread_count, tiles.len()));
+    if thread_count == 1 {
+        for tile in &tiles {
+            let mut tile_buffer = copy_tile(buffer, tile);
+            render(&mut tile_buffer);
+            paste_tile(buffer, &tile_buffer);
+        }
+        return;
+    }
+
+    // workers pull the next tile index until they run out, the finished
+    // tiles are pasted back after the scope so the back buffer is only
+    // ever read while the workers are running
+    let next_tile = AtomicUsize::new(0);
+    let finished = Mutex::new(Vec::<PixelBuffer>::with_capacity(tiles.len()));
+    {
+        let source: &PixelBuffer = buffer;
+        std::thread::scope(|scope| {
+            for _ in 0..thread_count {
+                scope.spawn(|| {
+                    loop {
+                        let index = next_tile.fetch_add(1, Ordering::Relaxed);
+                        if index >= tiles.len() {
+                            break;
+                        }
+                        let mut tile_buffer = copy_tile(source, &tiles[index]);
+                        render(&mut tile_buffer);
+                        finished.lock().unwrap().push(tile_buffer);
+                    }
+                });
+            }
+        });
+    }
+
+    for tile_buffer in finished.into_inner().unwrap() {
+        paste_tile(buffer, &tile_buffer);
+    }
+}
+
+fn copy_tile(buffer: &PixelBuffer, tile: &Rect) -> PixelBuffer {
+    let mut tile_buffer = PixelBuffer::new(tile.x, tile.y, tile.w, tile.h);
+    for y in tile.y..tile.y + tile.h {
+        tile_buffer.row_mut(tile.x, y, tile.w).copy_from_slice(buffer.row(tile.x, y, tile.w));
+    }
+    tile_buffer
+}
+
+fn paste_tile(buffer: &mut PixelBuffer, tile_buffer: &PixelBuffer) {
+    let tile = tile_buffer.get_rect();
+    for y in tile.y..tile.y + tile.h {
+        buffer.row_mut(tile.x, y, tile.w).copy_from_slice(tile_buffer.row(tile.x, y, tile.w));
+    }
+}
+
+#[cfg(test)]
+mod tiles_tests {
+    use super::*;
+    use crate::gui::Pixel;
+    use crate::gui::color::Color;
+    use crate::gui::draw::fill_rect;
+    use crate::gui::draw::draw_image;
+    use crate::gui::draw::draw_textbox;
+    use crate::gui::image::Image;
+    use crate::gui::image::ImageFilter;
+    use crate::gui::textbox::TextBox;
+    use crate::gui::style::BoxStyle;
+    use crate::gui::Bounds;
+    use crate::gui::text::font::Font;
+    use crate::gui::text::manager::FontManager;
+
+    fn load_fonts() -> FontManager {
+        let font = include_bytes!("../../fonts/OpenSans-Regular.ttf") as &[u8];
+        let mut fonts = FontManager::new();
+        fonts.add_font(Font::from_bytes(font).unwrap());
+        fonts
+    }
+
+    fn test_textbox(text: &str, rect: Rect) -> TextBox {
+        TextBox {
+            text: text.chars().collect(),
+            placeholder: "",
+            bounds: Bounds::int(rect.x, rect.y, rect.w, rect.h),
+            bounds_rect: rect,
+            hot: false, active: true,
+            cursor_index: 3, scroll_offset_x: 0,
+            selection_index: 7,
+            preedit: Vec::new(), preedit_cursor: 0,
+            style: BoxStyle::textbox_default()
+        }
+    }
+
+    fn test_image() -> Image {
+        let mut image = Image::new(37, 23);
+        for (i, pixel) in image.pixels.iter_mut().enumerate() {
+            *pixel = Pixel::from_rgba((i * 7) as u8, (i * 3) as u8, (i * 11) as u8, (i * 5) as u8);
+        }
+        image
+    }
+
+    // shapes that straddle tile edges, text, scaled and clipped images
+    fn draw_test_scene(buffer: &mut PixelBuffer, fonts: &FontManager, textbox: &TextBox, image: &Image) {
+        fill_rect(buffer, 0, 0, 1000, 700, Color::DARK_GRAY);
+        fill_rect(buffer, 120, 100, 300, 260, Color::LIGHT_RED);
+        fill_rect(buffer, -50, 600, 2000, 13, Color::WHITE);
+        draw_textbox(buffer, textbox, fonts, true);
+        draw_image(buffer, image, 250, 250, 200, 140, ImageFilter::Bilinear);
+        draw_image(buffer, image, 500, 120, 37, 23, ImageFilter::Nearest);
+        draw_image(buffer, image, 900, 650, 300, 300, ImageFilter::Nearest);
+    }
+
+    fn bytes(buffer: &PixelBuffer) -> Vec<u8> {
+        buffer.pixels.iter().flat_map(|p| vec![p.blue, p.green, p.red, p.alpha]).collect()
+    }
+
+    #[test]
+    fn test_tiled_matches_single_threaded() {
+        let fonts = load_fonts();
+        let textbox = test_textbox("tiles must match exactly", Rect { x: 60, y: 110, w: 500, h: 100 });
+        let image = test_image();
+        let render = |buffer: &mut PixelBuffer| draw_test_scene(buffer, &fonts, &textbox, &image);
+
+        let mut expected = PixelBuffer::new(0, 0, 1000, 700);
+        render(&mut expected);
+
+        let everything = [Rect { x: 0, y: 0, w: 1000, h: 700 }];
+        for &threads in [1, 2, 3, 8].iter() {
+            for &tile_size in [64, 100, 128].iter() {
+                let mut tiled = PixelBuffer::new(0, 0, 1000, 700);
+                render_tiled(&mut tiled, &everything, tile_size, threads, &render);
+                assert!(bytes(&tiled) == bytes(&expected), "threads {} tile size {}", threads, tile_size);
+            }
+        }
+    }
+
+    #[test]
+    fn test_damaged_tiles_only_redraw_damage() {
+        let fonts = load_fonts();
+        let textbox = test_textbox("damage", Rect { x: 60, y: 110, w: 300, h: 60 });
+        let image = test_image();
+        let render = |buffer: &mut PixelBuffer| draw_test_scene(buffer, &fonts, &textbox, &image);
+
+        let mut expected = PixelBuffer::new(0, 0, 1000, 700);
+        render(&mut expected);
+
+        // start from a full render, scribble over one area and repair it
+        // by redrawing only the damaged rect
+        let mut tiled = PixelBuffer::new(0, 0, 1000, 700);
+        let everything = [tiled.get_rect()];
+        render_tiled(&mut tiled, &everything, TILE_SIZE, 4, &render);
+        fill_rect(&mut tiled, 200, 200, 50, 50, Color::RED);
+        fill_rect(&mut tiled, 800, 20, 10, 10, Color::RED);
+        render_tiled(&mut tiled, &[Rect { x: 200, y: 200, w: 50, h: 50 }], TILE_SIZE, 4, &render);
+        assert!(bytes(&tiled) != bytes(&expected));
+        render_tiled(&mut tiled, &[Rect { x: 805, y: 25, w: 1, h: 1 }], TILE_SIZE, 4, &render);
+        assert!(bytes(&tiled) == bytes(&expected));
+
+        assert_eq!(damaged_tiles(&tiled, &[Rect { x: 127, y: 0, w: 2, h: 1 }], TILE_SIZE).len(), 2);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::gui::is_point_in_rect;
+use crate::gui::style::BoxStyle;
+use crate::gui::style::StateStyles;
+use crate::gui::Rect;
+use crate::gui::control::Control;
+use crate::gui::control::ControlState;
+use crate::gui::text::manager::FontManager;
+use crate::gui::widget::Widget;
+use crate::gui::widget::Event;
+use crate::gui::mouse::MouseButton;
+use crate::gui::keyboard::Key;
+use crate::gui::keyboard::KeyState;
+use crate::gui::draw::draw_toggle_switch;
+use crate::gui::Cursor;
+use crate::gui::PixelBuffer;
+
+/*
+    a switch with a label that's on or off, for settings that take effect
+    right away. it flips like a check box, left and right turn it off and
+    on. it raises Event::Changed when it flips.
+*/
+pub struct ToggleSwitch {
+    pub text: &'static str,
+    pub on: bool,
+    pub bounds: Rect,
+    pub state: ControlState,
+    pub styles: StateStyles,
+    changed: bool
+}
+
+impl ToggleSwitch {
+    pub fn new(text: &'static str, on: bool, styles: StateStyles) -> ToggleSwitch {
+        ToggleSwitch { text, on, bounds: Rect::default(), state: ControlState::default(), styles, changed: false }
+    }
+
+    pub fn set_on(&mut self, on: bool) {
+        if self.on != on {
+            self.on = on;
+            self.changed = true;
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.set_on(!self.on);
+    }
+}
+
+impl Widget for ToggleSwitch {
+    fn bounds(&self) -> Rect { self.bounds }
+    fn set_bounds_rect(&mut self, rect: Rect) { self.bounds = rect }
+
+    fn draw(&self, buffer: &mut PixelBuffer, fonts: &FontManager, _draw_cursor: bool) {
+        draw_toggle_switch(buffer, self, fonts);
+    }
+
+    fn event(&mut self, event: &Event) -> bool {
+        if self.state.disabled {
+            return false;
+        }
+        match event {
+            Event::MouseMove { x, y } => return self.hit_check(*x, *y).0,
+            Event::MouseDown { button: MouseButton::Left, x, y, .. } => self.state.active = is_point_in_rect(*x, *y, self.bounds),
+            Event::MouseUp { button: MouseButton::Left, x, y } => {
+                if std::mem::take(&mut self.state.active) && is_point_in_rect(*x, *y, self.bounds) {
+                    self.toggle();
+                }
+            },
+            Event::Key(key) => match (key.key, key.state) {
+                (Key::Space, KeyState::Pressed) => self.state.active = true,
+                (Key::Space, KeyState::Released) if self.state.active => {
+                    self.state.active = false;
+                    self.toggle();
+                },
+                (Key::Left, KeyState::Pressed) => self.set_on(false),
+                (Key::Right, KeyState::Pressed) => self.set_on(true),
+                _ => return false
+            },
+            _ => return false
+        }
+        true
+    }
+
+    fn raised(&mut self) -> Option<Event> {
+        if std::mem::take(&mut self.changed) { Some(Event::Changed) } else { None }
+    }
+
+    fn cursor(&self) -> Cursor { Cursor::Hand }
+
+    fn focus_style(&self) -> Option<BoxStyle> {
+        if self.state.disabled { None } else { Some(self.get_scaled_style()) }
+    }
+
+    fn set_focused(&mut self, focused: bool) {
+        self.state.focused = focused;
+        self.state.active = false;
+    }
+}
+
+impl Control for ToggleSwitch {
+    fn get_bounds(&self) -> Rect { self.bounds }
+    fn get_hot(&self) -> bool { self.state.hot }
+    fn set_hot(&mut self, hit: bool) { self.state.hot = hit }
+    fn get_style(&self) -> &BoxStyle { self.styles.select(&self.state) }
+}
+
+#[cfg(test)]
+mod toggle_tests {
+    use super::*;
+    use crate::gui::keyboard::{KeyCode, KeyEvent, KeyboardModifiers};
+
+    #[test]
+    fn test_keys_switch() {
+        let mut switch = ToggleSwitch::new("Preview", false, StateStyles::control_default());
+        switch.set_bounds_rect(Rect { x: 0, y: 0, w: 200, h: 24 });
+        let key = |key, state| Event::Key(KeyEvent { code: KeyCode::Unknown(0), key, modifiers: KeyboardModifiers::default(), state, repeat: false });
+        switch.event(&key(Key::Right, KeyState::Pressed));
+        assert!(switch.on);
+        assert!(matches!(switch.raised(), Some(Event::Changed)));
+        // already on
+        switch.event(&key(Key::Right, KeyState::Pressed));
+        assert!(switch.raised().is_none());
+        switch.event(&key(Key::Space, KeyState::Pressed));
+        switch.event(&key(Key::Space, KeyState::Released));
+        assert!(!switch.on);
+        switch.event(&Event::MouseDown { button: MouseButton::Left, x: 10, y: 10, clicks: 1 });
+        switch.event(&Event::MouseUp { button: MouseButton::Left, x: 12, y: 10 });
+        assert!(switch.on);
+    }
+}
@@ -0,0 +1,169 @@
+use crate::gui::style::BoxStyle;
+use crate::gui::Rect;
+use crate::gui::scale_i32;
+use crate::gui::get_scale_factor;
+use crate::gui::text::layout::layout_text;
+use crate::gui::text::layout::LayoutOptions;
+use std::time::Duration;
+use std::time::Instant;
+
+/*
+    the text a widget has for the spot under the mouse, shown in a little
+    box once the mouse has rested there for the delay. widgets say what it
+    is with Widget::tooltip, usually the whole of a name that's drawn
+    shortened. the cursor timer checks on it so it shows up to a tick late.
+    it goes away when the mouse moves somewhere with other text or none, on
+    a click, the wheel or a key.
+*/
+pub struct Tooltip {
+    pub style: BoxStyle,
+    pub delay: Duration,
+    // logical pixels from the pointer to the top of the box
+    pub offset: i32,
+    // what it has to stay inside
+    pub window: Rect,
+    // the text under the mouse, where it was and since when
+    waiting: Option<(String, i32, i32, Instant)>,
+    shown: Option<(String, Rect)>
+}
+
+impl Tooltip {
+    pub const fn new(style: BoxStyle) -> Tooltip {
+        Tooltip {
+            style,
+            delay: Duration::from_millis(500),
+            offset: 20,
+            window: Rect { x: 0, y: 0, w: 0, h: 0 },
+            waiting: None,
+            shown: None
+        }
+    }
+
+    // the mouse is over a widget with this text there, or none. the rect of
+    // a tooltip that went away to paint over.
+    pub fn mouse_move(&mut self, x: i32, y: i32, text: Option<String>, now: Instant) -> Option<Rect> {
+        // it stays while the mouse moves over the same text
+        if self.shown.as_ref().is_some_and(|(shown, _)| Some(shown) == text.as_ref()) {
+            return None;
+        }
+        // and the wait goes on, it shows where the mouse ends up
+        if let Some((waiting, wait_x, wait_y, _)) = &mut self.waiting {
+            if Some(&*waiting) == text.as_ref() {
+                *wait_x = x;
+                *wait_y = y;
+                return None;
+            }
+        }
+        self.waiting = text.map(|text| (text, x, y, now));
+        self.shown.take().map(|(_, rect)| rect)
+    }
+
+    // shows the tooltip once the mouse has rested long enough, the rect to
+    // paint when it did
+    pub fn timer_tick(&mut self, now: Instant) -> Option<Rect> {
+        match &self.waiting {
+            Some((_, _, _, since)) if now.saturating_duration_since(*since) >= self.delay => {
+                let (text, x, y, _) = self.waiting.take()?;
+                let rect = self.place(&text, x, y);
+                self.shown = Some((text, rect));
+                Some(rect)
+            },
+            _ => None
+        }
+    }
+
+    // nothing shows until the mouse moves on to some text again
+    pub fn hide(&mut self) -> Option<Rect> {
+        self.waiting = None;
+        self.shown.take().map(|(_, rect)| rect)
+    }
+
+    pub fn shown(&self) -> Option<(&str, Rect)> {
+        self.shown.as_ref().map(|(text, rect)| (text.as_str(), *rect))
+    }
+
+    // below and right of the pointer. it moves left to fit the window and
+    // above the pointer when there's no room below.
+    fn place(&self, text: &str, x: i32, y: i32) -> Rect {
+        let fonts = unsafe { &crate::APPLICATION_STATE.fonts };
+        let style = self.style.scaled(get_scale_factor());
+        let chars: Vec<char> = text.chars().collect();
+        let layout = layout_text(&chars, fonts, fonts.select_style(&style), style.font_size, &LayoutOptions::default());
+        let w = std::cmp::min(self.window.w,
+            layout.width + style.border_size.left + style.padding_size.left + style.border_size.right + style.padding_size.right);
+        let h = layout.height + style.border_size.top + style.padding_size.top + style.border_size.bottom + style.padding_size.bottom;
+        let offset = scale_i32(self.offset, get_scale_factor());
+        let below = y + offset;
+        let top = if below + h <= self.window.y + self.window.h { below } else { y - h - offset / 4 };
+        let left = std::cmp::min(x, self.window.x + self.window.w - w);
+        Rect { x: std::cmp::max(self.window.x, left), y: std::cmp::max(self.window.y, top), w, h }
+    }
+}
+
+// the tooltip for the widget under the mouse, none while a drag has it
+pub fn tooltip_mouse_move(x: i32, y: i32, captured: bool) {
+    let state = unsafe { &mut crate::APPLICATION_STATE };
+    let text = if captured {
+        None
+    }
+    else {
+        state.widgets.hit_test(x, y)
+            .and_then(|path| state.widgets.get(&path))
+            .and_then(|node| node.widget.tooltip(x, y))
+    };
+    if let Some(rect) = state.tooltip.mouse_move(x, y, text, Instant::now()) {
+        crate::invalidate_rect(rect);
+    }
+}
+
+pub fn tooltip_timer_tick(now: Instant) {
+    let tooltip = unsafe { &mut crate::APPLICATION_STATE.tooltip };
+    if let Some(rect) = tooltip.timer_tick(now) {
+        crate::invalidate_rect(rect);
+    }
+}
+
+pub fn hide_tooltip() {
+    let tooltip = unsafe { &mut crate::APPLICATION_STATE.tooltip };
+    if let Some(rect) = tooltip.hide() {
+        crate::invalidate_rect(rect);
+    }
+}
+
+#[cfg(test)]
+mod tooltip_tests {
+    use super::*;
+    use crate::gui::text::font::Font;
+
+    #[test]
+    fn test_delay_and_placement() {
+        if unsafe { crate::APPLICATION_STATE.fonts.len() } == 0 {
+            let font = include_bytes!("../../fonts/OpenSans-Regular.ttf") as &[u8];
+            unsafe { crate::APPLICATION_STATE.fonts.add_font(Font::from_bytes(font).unwrap()); }
+        }
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut tooltip = Tooltip::new(BoxStyle::default());
+        tooltip.window = Rect { x: 0, y: 0, w: 800, h: 600 };
+        let text = || Some("a_very_long_file_name.txt".to_string());
+
+        assert!(tooltip.mouse_move(100, 100, text(), at(0)).is_none());
+        assert!(tooltip.timer_tick(at(250)).is_none());
+        // moving over the same text doesn't start the wait again
+        tooltip.mouse_move(104, 100, text(), at(300));
+        let rect = tooltip.timer_tick(at(500)).unwrap();
+        assert_eq!((rect.x, rect.y), (104, 120));
+        assert_eq!(tooltip.shown().unwrap().0, "a_very_long_file_name.txt");
+        assert!(tooltip.mouse_move(110, 100, text(), at(600)).is_none());
+        assert_eq!(tooltip.mouse_move(110, 100, None, at(700)).map(|r| (r.x, r.y)), Some((rect.x, rect.y)));
+        assert!(tooltip.shown().is_none());
+
+        // in the bottom right corner it moves left and above the pointer
+        tooltip.mouse_move(790, 590, text(), at(800));
+        let rect = tooltip.timer_tick(at(1300)).unwrap();
+        assert_eq!(rect.x + rect.w, 800);
+        assert!(rect.y + rect.h < 590);
+        assert_eq!(tooltip.hide().map(|r| (r.x, r.y)), Some((rect.x, rect.y)));
+        assert!(tooltip.timer_tick(at(2000)).is_none());
+    }
+}
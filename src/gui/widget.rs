@@ -0,0 +1,518 @@
+use crate::gui::is_point_in_rect;
+use crate::gui::Cursor;
+use crate::gui::PixelBuffer;
+use crate::gui::Rect;
+use crate::gui::color::Color;
+use crate::gui::draw::fill_rect;
+use crate::gui::keyboard::EditCommand;
+use crate::gui::keyboard::KeyEvent;
+use crate::gui::keyboard::TextInput;
+use crate::gui::layout::Layout;
+use crate::gui::mouse::MouseButton;
+use crate::gui::style::BoxStyle;
+use crate::gui::text::manager::FontManager;
+use std::any::Any;
+use std::time::Instant;
+
+/*
+    the controls in the window as a tree. every widget lays itself out in
+    the rect it's given, draws itself and handles the events sent to it, and
+    containers hold more widgets as children. mouse events go to the widget
+    under the mouse found by hit testing from the root, keyboard events to
+    the focused one.
+
+    a widget is found again by its path, the index of each child on the way
+    down from the top. paths change when widgets are added or removed above
+    them so they're only kept for as long as the focus or a mouse capture,
+    names are for the application to find its widgets by.
+
+    an event for a widget goes through its parents too, like on the web.
+    first the capture phase from the top down to the widget's parent, then
+    the widget itself, then the bubble phase back up to the top. listeners
+    on the way can set handled to stop it going further, and the widget's
+    own handling counts as handled. at the widget itself its listeners run
+    before it does, so a listener can keep a widget from doing its usual
+    thing.
+*/
+
+pub type WidgetPath = Vec<usize>;
+
+pub enum Event {
+    MouseDown { button: MouseButton, x: i32, y: i32, clicks: u32 },
+    MouseUp { button: MouseButton, x: i32, y: i32 },
+    // every widget sees every move so they can stop being hot
+    MouseMove { x: i32, y: i32 },
+    // lines, positive y is away from the user
+    Wheel { x: i32, y: i32, lines_x: f32, lines_y: f32 },
+    Key(KeyEvent),
+    Text(TextInput),
+    Edit(EditCommand),
+    // raised by a button after a mouse click, enter or space
+    Click,
+    // raised by an input control when the user changed its value
+    Changed
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Phase {
+    Capture,
+    Target,
+    Bubble
+}
+
+// what a listener gets, the rest of the widgets are there to be changed
+pub struct EventContext<'a> {
+    pub event: &'a Event,
+    pub phase: Phase,
+    // the widget the event is for
+    pub target: &'a [usize],
+    // the widget the listener is on
+    pub current: &'a [usize],
+    pub widgets: &'a mut WidgetTree,
+    pub handled: bool
+}
+
+pub type Handler = Box<dyn FnMut(&mut EventContext)>;
+
+struct Listener {
+    capture: bool,
+    handler: Handler
+}
+
+// lets the application get its own types back out of the tree
+pub trait AsAny {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Any> AsAny for T {
+    fn as_any(&self) -> &dyn Any { self }
+    fn as_any_mut(&mut self) -> &mut dyn Any { self }
+}
+
+pub trait Widget: AsAny {
+    // where it is in window coordinates, also what hit testing uses
+    fn bounds(&self) -> Rect;
+    fn set_bounds_rect(&mut self, rect: Rect);
+    // runs on the render threads so it only reads
+    fn draw(&self, buffer: &mut PixelBuffer, fonts: &FontManager, draw_cursor: bool);
+
+    // true when the widget used the event, or for a mouse move when it
+    // needs painting again
+    fn event(&mut self, _event: &Event) -> bool { false }
+
+    fn cursor(&self) -> Cursor { Cursor::Arrow }
+    // the text to show in a tooltip when the mouse rests on a point
+    fn tooltip(&self, _x: i32, _y: i32) -> Option<String> { None }
+
+    // the style the focus ring is drawn in, widgets that take focus have one
+    fn focus_style(&self) -> Option<BoxStyle> { None }
+    fn set_focused(&mut self, _focused: bool) { }
+    // where the input method should put its candidate window
+    fn caret_rect(&self) -> Option<Rect> { None }
+
+    // an event it raised while handling the last one, like a click from a
+    // mouse up, which is sent on through the tree
+    fn raised(&mut self) -> Option<Event> { None }
+    // a command it picked, like a menu item. it runs once the event is done
+    // with since commands change the widgets.
+    fn take_command(&mut self) -> Option<&'static str> { None }
+
+    // every frame while something is moving, true to keep going
+    fn animate(&mut self, _now: Instant) -> bool { false }
+    // on the caret blink timer, true to be painted again
+    fn timer_tick(&mut self, _now: Instant) -> bool { false }
+
+    fn children(&self) -> &[WidgetNode] { &[] }
+    fn children_mut(&mut self) -> &mut [WidgetNode] { &mut [] }
+}
+
+pub struct WidgetNode {
+    pub name: &'static str,
+    pub widget: Box<dyn Widget>,
+    listeners: Vec<Listener>
+}
+
+impl WidgetNode {
+    pub fn new(name: &'static str, widget: impl Widget + 'static) -> WidgetNode {
+        WidgetNode { name, widget: Box::new(widget), listeners: Vec::new() }
+    }
+
+    // listens while the event bubbles up and at the widget itself
+    pub fn on(mut self, handler: impl FnMut(&mut EventContext) + 'static) -> WidgetNode {
+        self.listeners.push(Listener { capture: false, handler: Box::new(handler) });
+        self
+    }
+
+    // listens on the way down, before the children get the event
+    pub fn on_capture(mut self, handler: impl FnMut(&mut EventContext) + 'static) -> WidgetNode {
+        self.listeners.push(Listener { capture: true, handler: Box::new(handler) });
+        self
+    }
+
+    // runs when this widget is clicked, not its children
+    pub fn on_click(self, mut handler: impl FnMut(&mut WidgetTree) + 'static) -> WidgetNode {
+        self.on(move |context| {
+            if let (Event::Click, Phase::Target) = (context.event, context.phase) {
+                handler(context.widgets);
+                context.handled = true;
+            }
+        })
+    }
+
+    // runs when the user changed this widget's value
+    pub fn on_change(self, mut handler: impl FnMut(&mut WidgetTree) + 'static) -> WidgetNode {
+        self.on(move |context| {
+            if let (Event::Changed, Phase::Target) = (context.event, context.phase) {
+                handler(context.widgets);
+                context.handled = true;
+            }
+        })
+    }
+
+    // the box is Any itself so it has to be looked through first
+    pub fn downcast_ref<T: 'static>(&self) -> Option<&T> {
+        (*self.widget).as_any().downcast_ref::<T>()
+    }
+
+    pub fn downcast_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        (*self.widget).as_any_mut().downcast_mut::<T>()
+    }
+}
+
+// a container that draws a background under its children
+pub struct Panel {
+    pub bounds_rect: Rect,
+    pub background: Option<Color>,
+    pub children: Vec<WidgetNode>
+}
+
+impl Panel {
+    pub fn new(background: Option<Color>, children: Vec<WidgetNode>) -> Panel {
+        Panel { bounds_rect: Rect::default(), background, children }
+    }
+}
+
+impl Widget for Panel {
+    fn bounds(&self) -> Rect { self.bounds_rect }
+    fn set_bounds_rect(&mut self, rect: Rect) { self.bounds_rect = rect }
+
+    fn draw(&self, buffer: &mut PixelBuffer, _fonts: &FontManager, _draw_cursor: bool) {
+        if let Some(color) = self.background {
+            let rect = self.bounds_rect;
+            fill_rect(buffer, rect.x, rect.y, rect.w, rect.h, color);
+        }
+    }
+
+    fn children(&self) -> &[WidgetNode] { &self.children }
+    fn children_mut(&mut self) -> &mut [WidgetNode] { &mut self.children }
+}
+
+pub struct WidgetTree {
+    pub children: Vec<WidgetNode>
+}
+
+impl WidgetTree {
+    pub const fn new() -> WidgetTree {
+        WidgetTree { children: Vec::new() }
+    }
+
+    pub fn add(&mut self, node: WidgetNode) {
+        self.children.push(node);
+    }
+
+    pub fn get(&self, path: &[usize]) -> Option<&WidgetNode> {
+        let (first, rest) = path.split_first()?;
+        let mut node = self.children.get(*first)?;
+        for i in rest {
+            node = node.widget.children().get(*i)?;
+        }
+        Some(node)
+    }
+
+    pub fn get_mut(&mut self, path: &[usize]) -> Option<&mut WidgetNode> {
+        let (first, rest) = path.split_first()?;
+        let mut node = self.children.get_mut(*first)?;
+        for i in rest {
+            node = node.widget.children_mut().get_mut(*i)?;
+        }
+        Some(node)
+    }
+
+    // parents before their children, in the order they were added
+    pub fn walk(&self, f: &mut dyn FnMut(&[usize], &WidgetNode)) {
+        fn walk_nodes(nodes: &[WidgetNode], path: &mut WidgetPath, f: &mut dyn FnMut(&[usize], &WidgetNode)) {
+            for (i, node) in nodes.iter().enumerate() {
+                path.push(i);
+                f(path, node);
+                walk_nodes(node.widget.children(), path, f);
+                path.pop();
+            }
+        }
+        walk_nodes(&self.children, &mut Vec::new(), f);
+    }
+
+    pub fn walk_mut(&mut self, f: &mut dyn FnMut(&[usize], &mut WidgetNode)) {
+        fn walk_nodes(nodes: &mut [WidgetNode], path: &mut WidgetPath, f: &mut dyn FnMut(&[usize], &mut WidgetNode)) {
+            for (i, node) in nodes.iter_mut().enumerate() {
+                path.push(i);
+                f(path, node);
+                walk_nodes(node.widget.children_mut(), path, f);
+                path.pop();
+            }
+        }
+        walk_nodes(&mut self.children, &mut Vec::new(), f);
+    }
+
+    pub fn find(&self, name: &str) -> Option<WidgetPath> {
+        let mut found = None;
+        self.walk(&mut |path, node| {
+            if found.is_none() && node.name == name {
+                found = Some(path.to_vec());
+            }
+        });
+        found
+    }
+
+    // the widget with that name when it is a T
+    pub fn widget<T: 'static>(&self, name: &str) -> Option<&T> {
+        let path = self.find(name)?;
+        self.get(&path)?.downcast_ref::<T>()
+    }
+
+    pub fn widget_mut<T: 'static>(&mut self, name: &str) -> Option<&mut T> {
+        let path = self.find(name)?;
+        self.get_mut(&path)?.downcast_mut::<T>()
+    }
+
+    // every T in the tree, a T's own children aren't searched
+    pub fn all<T: 'static>(&self) -> Vec<&T> {
+        fn collect<'a, T: 'static>(nodes: &'a [WidgetNode], found: &mut Vec<&'a T>) {
+            for node in nodes {
+                match node.downcast_ref::<T>() {
+                    Some(widget) => found.push(widget),
+                    None => collect(node.widget.children(), found)
+                }
+            }
+        }
+        let mut found = Vec::new();
+        collect(&self.children, &mut found);
+        found
+    }
+
+    pub fn all_mut<T: 'static>(&mut self) -> Vec<&mut T> {
+        fn collect<'a, T: 'static>(nodes: &'a mut [WidgetNode], found: &mut Vec<&'a mut T>) {
+            for node in nodes {
+                if node.downcast_ref::<T>().is_some() {
+                    found.extend(node.downcast_mut::<T>());
+                }
+                else {
+                    collect(node.widget.children_mut(), found);
+                }
+            }
+        }
+        let mut found = Vec::new();
+        collect(&mut self.children, &mut found);
+        found
+    }
+
+    // gives every widget named in the layout its rect
+    pub fn apply_layout(&mut self, layout: &Layout) {
+        self.walk_mut(&mut |_, node| {
+            if let Some(rect) = layout.get(node.name) {
+                node.widget.set_bounds_rect(rect);
+            }
+        });
+    }
+
+    pub fn draw(&self, buffer: &mut PixelBuffer, fonts: &FontManager, draw_cursor: bool) {
+        self.walk(&mut |_, node| node.widget.draw(buffer, fonts, draw_cursor));
+    }
+
+    // the deepest widget under a point. later children are drawn on top of
+    // earlier ones so they're tried first.
+    pub fn hit_test(&self, x: i32, y: i32) -> Option<WidgetPath> {
+        fn hit_nodes(nodes: &[WidgetNode], x: i32, y: i32, path: &mut WidgetPath) -> bool {
+            for (i, node) in nodes.iter().enumerate().rev() {
+                if is_point_in_rect(x, y, node.widget.bounds()) {
+                    path.push(i);
+                    hit_nodes(node.widget.children(), x, y, path);
+                    return true;
+                }
+            }
+            false
+        }
+        let mut path = Vec::new();
+        if hit_nodes(&self.children, x, y, &mut path) { Some(path) } else { None }
+    }
+
+    // every widget that takes focus with its bounds
+    pub fn focusable(&self) -> Vec<(WidgetPath, Rect)> {
+        let mut found = Vec::new();
+        self.walk(&mut |path, node| {
+            if node.widget.focus_style().is_some() {
+                found.push((path.to_vec(), node.widget.bounds()));
+            }
+        });
+        found
+    }
+
+    // the widget at path or the closest parent of it that takes focus
+    pub fn focusable_ancestor(&self, path: &[usize]) -> Option<WidgetPath> {
+        (1..=path.len()).rev()
+            .map(|len| &path[..len])
+            .find(|p| self.get(p).is_some_and(|node| node.widget.focus_style().is_some()))
+            .map(|p| p.to_vec())
+    }
+
+    // sends an event through the widget's parents to it and back up,
+    // true when something handled it
+    pub fn dispatch(&mut self, path: &[usize], event: &Event) -> bool {
+        for depth in 1..path.len() {
+            if self.run_listeners(&path[..depth], path, event, Phase::Capture) {
+                return true;
+            }
+        }
+        let mut handled = self.run_listeners(path, path, event, Phase::Target);
+        if !handled {
+            handled = match self.get_mut(path) {
+                Some(node) => node.widget.event(event),
+                None => return false
+            };
+        }
+        // a click coming out of a mouse up goes through on its own
+        while let Some(raised) = self.get_mut(path).and_then(|node| node.widget.raised()) {
+            self.dispatch(path, &raised);
+            handled = true;
+        }
+        if handled {
+            return true;
+        }
+        for depth in (1..path.len()).rev() {
+            if self.run_listeners(&path[..depth], path, event, Phase::Bubble) {
+                return true;
+            }
+        }
+        false
+    }
+
+    // the listeners are taken off the widget while they run so they can
+    // change any widget, their own included
+    fn run_listeners(&mut self, at: &[usize], target: &[usize], event: &Event, phase: Phase) -> bool {
+        let mut listeners = match self.get_mut(at) {
+            Some(node) if !node.listeners.is_empty() => std::mem::take(&mut node.listeners),
+            _ => return false
+        };
+        let mut handled = false;
+        for listener in listeners.iter_mut() {
+            let listening = match phase {
+                Phase::Capture => listener.capture,
+                Phase::Target => true,
+                Phase::Bubble => !listener.capture
+            };
+            if !listening {
+                continue;
+            }
+            let mut context = EventContext { event, phase, target, current: at, widgets: self, handled: false };
+            (listener.handler)(&mut context);
+            if context.handled {
+                handled = true;
+                break;
+            }
+        }
+        // keeping any listeners added while they ran
+        if let Some(node) = self.get_mut(at) {
+            listeners.append(&mut node.listeners);
+            node.listeners = listeners;
+        }
+        handled
+    }
+
+    // sends the event to every widget, true when any of them used it
+    pub fn broadcast(&mut self, event: &Event) -> bool {
+        let mut used = false;
+        self.walk_mut(&mut |_, node| {
+            if node.widget.event(event) { used = true }
+        });
+        used
+    }
+}
+
+#[cfg(test)]
+mod widget_tests {
+    use super::*;
+
+    #[test]
+    fn test_hit_test_and_lookup() {
+        let mut tree = WidgetTree::new();
+        tree.add(WidgetNode::new("sidebar", Panel::new(None, vec![])));
+        tree.add(WidgetNode::new("main", Panel::new(None, vec![
+            WidgetNode::new("inner", Panel::new(None, vec![])),
+            WidgetNode::new("overlay", Panel::new(None, vec![]))
+        ])));
+        tree.widget_mut::<Panel>("sidebar").unwrap().set_bounds_rect(Rect { x: 0, y: 0, w: 100, h: 100 });
+        tree.widget_mut::<Panel>("main").unwrap().set_bounds_rect(Rect { x: 100, y: 0, w: 100, h: 100 });
+        tree.widget_mut::<Panel>("inner").unwrap().set_bounds_rect(Rect { x: 100, y: 0, w: 100, h: 50 });
+        tree.widget_mut::<Panel>("overlay").unwrap().set_bounds_rect(Rect { x: 150, y: 0, w: 50, h: 100 });
+
+        assert_eq!(tree.find("inner"), Some(vec![1, 0]));
+        assert_eq!(tree.hit_test(50, 50), Some(vec![0]));
+        assert_eq!(tree.hit_test(120, 20), Some(vec![1, 0]));
+        // the later child is on top
+        assert_eq!(tree.hit_test(160, 20), Some(vec![1, 1]));
+        assert_eq!(tree.hit_test(120, 80), Some(vec![1]));
+        assert_eq!(tree.hit_test(300, 80), None);
+        assert_eq!(tree.all::<Panel>().len(), 2);
+        assert!(tree.focusable().is_empty());
+        assert_eq!(tree.focusable_ancestor(&[1, 0]), None);
+    }
+
+    #[test]
+    fn test_capture_bubble_and_handled() {
+        use crate::gui::button::Button;
+        use crate::gui::style::BoxStyle;
+        use crate::gui::keyboard::{KeyEvent, KeyCode, Key, KeyState, KeyboardModifiers};
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let button = || Button::new("ok", BoxStyle::button_default(), BoxStyle::button_default_hot(), BoxStyle::button_default_active());
+        let (capture_seen, bubble_seen) = (seen.clone(), seen.clone());
+        let mut tree = WidgetTree::new();
+        tree.add(WidgetNode::new("toolbar", Panel::new(None, vec![
+            WidgetNode::new("ok", button()).on_click(|widgets| {
+                widgets.widget_mut::<Button>("cancel").unwrap().text = "changed";
+            }),
+            WidgetNode::new("cancel", button())
+        ])).on_capture(move |context| {
+            capture_seen.borrow_mut().push((context.phase, context.target.to_vec()));
+            // the toolbar keeps keys from the cancel button
+            if context.target == [0, 1] {
+                context.handled = true;
+            }
+        }).on(move |context| {
+            bubble_seen.borrow_mut().push((context.phase, context.target.to_vec()));
+        }));
+
+        let enter = Event::Key(KeyEvent {
+            code: KeyCode::Enter, key: Key::Enter, modifiers: KeyboardModifiers::default(), state: KeyState::Pressed, repeat: false
+        });
+        assert!(tree.dispatch(&[0, 0], &enter));
+        assert_eq!(tree.widget::<Button>("cancel").unwrap().text, "changed");
+        assert_eq!(tree.widget::<Button>("ok").unwrap().click_count, 1);
+
+        assert!(tree.dispatch(&[0, 1], &enter));
+        assert_eq!(tree.widget::<Button>("cancel").unwrap().click_count, 0);
+
+        // nothing handles the wheel so it bubbles up to the toolbar
+        assert!(!tree.dispatch(&[0, 0], &Event::Wheel { x: 0, y: 0, lines_x: 0.0, lines_y: 1.0 }));
+        assert_eq!(*seen.borrow(), [
+            (Phase::Capture, vec![0, 0]),
+            // the click from enter goes through the toolbar on its own
+            (Phase::Capture, vec![0, 0]),
+            (Phase::Capture, vec![0, 1]),
+            (Phase::Capture, vec![0, 0]),
+            (Phase::Bubble, vec![0, 0])
+        ]);
+    }
+}
@@ -12,28 +12,80 @@ mod gui;
 use self::gui::Cursor;
 use crate::gui::color::Color;
 use crate::gui::textbox::TextBox;
+use crate::gui::textarea::TextArea;
+use crate::gui::listview::ListView;
+use crate::gui::splitter::Splitter;
+use crate::gui::splitter::load_splitters;
+use crate::gui::splitter::save_splitters;
+use crate::gui::widget::WidgetTree;
+use crate::gui::widget::WidgetNode;
+use crate::gui::widget::Panel;
+use crate::gui::widget::Event;
+use crate::gui::widget::EventContext;
+use crate::gui::menu::Menu;
+use crate::gui::menu::MenuItem;
+use crate::gui::menubar::MenuBar;
+use crate::gui::tabbar::TabBar;
+use crate::gui::palette::CommandPalette;
+use crate::gui::palette::PaletteEntry;
+use crate::gui::focus::set_focus;
+use crate::gui::mouse::MouseButton;
+use crate::gui::keyboard::Key;
+use crate::gui::keyboard::KeyState;
 use crate::gui::button::Button;
-// use crate::gui::view::View;
-// use crate::gui::view::ViewBehavior;
 use crate::gui::draw::fill_rect;
 use crate::gui::draw::draw_button;
-use crate::gui::draw::draw_textbox;
+use crate::gui::draw::draw_focus_ring;
+use crate::gui::draw::draw_tooltip;
+use crate::gui::focus::FocusManager;
+use crate::gui::mouse::MouseState;
+use crate::gui::tooltip::Tooltip;
+use crate::gui::tooltip::tooltip_timer_tick;
+use crate::gui::focus::focus_ring;
+use crate::gui::command::CommandRegistry;
+use crate::gui::keymap::Keymap;
+use crate::gui::keymap::load_keymap;
+use crate::gui::keymap::chord_to_string;
+use crate::gui::keyboard::EditCommand;
+use crate::gui::keyboard::edit_command;
 use crate::gui::Rect;
 use crate::gui::Bounds;
+use crate::gui::BoundsField;
+use crate::gui::layout::LayoutNode;
+use crate::gui::layout::Place;
+use crate::gui::layout::Anchors;
 use crate::gui::style::BoxStyle;
 use crate::gui::style::BoxSize;
 use crate::gui::style::HorizontalAlign;
 use crate::gui::style::VerticalAlign;
+use crate::gui::style::FontWeight;
+use crate::gui::style::TextOverflow;
+use crate::gui::style::DEFAULT_FONT_FAMILY;
 use crate::gui::PixelBuffer;
+use crate::gui::tiles::Damage;
+use crate::gui::tiles::render_tiled;
+use crate::gui::tiles::default_thread_count;
+use crate::gui::tiles::TILE_SIZE;
+use crate::gui::tiles::MIN_PARALLEL_PIXELS;
+use crate::gui::text::font::Font;
+use crate::gui::text::manager::FontManager;
 
 use crate::win32::platform_run;
 use crate::win32::set_text_into_clipboard;
 use crate::win32::invalidate_window;
 use crate::win32::send_cursor_timer_tick;
+use crate::win32::get_clipboard_text;
+use crate::win32::close_window;
 
 type SetClipBoardTextData = fn(&str) -> ();
 type THEME = crate::gui::color::DarkTheme;
 
+// logical pixels
+const MENU_BAR_HEIGHT: i32 = 26;
+const PATH_HEIGHT: i32 = 30;
+const TAB_HEIGHT: i32 = 30;
+const HEADER_HEIGHT: i32 = 26;
+
 const FILE_PATH_BOX_STYLE: BoxStyle = BoxStyle {
     border_color: Color::RED,
     border_size: BoxSize::single(0),
@@ -42,39 +94,153 @@ const FILE_PATH_BOX_STYLE: BoxStyle = BoxStyle {
     text_color: THEME::TEXT,
     highlight_color: THEME::HIGHLIGHT,
     text_highlight_color: THEME::TEXT,
+    font_family: DEFAULT_FONT_FAMILY,
+    font_weight: FontWeight::REGULAR,
+    italic: false,
     font_size: 20.0,
     vertical_align: VerticalAlign::Center,
-    horizontal_align: HorizontalAlign::Left
+    horizontal_align: HorizontalAlign::Left,
+    text_overflow: TextOverflow::EllipsisMiddle,
+    word_wrap: false,
+    line_height: 1.0,
+    line_spacing: 0,
+    focus_color: THEME::FOCUS,
+    focus_size: 1
+};
+
+// headers, the active tab title and selected items are drawn in bold
+const COLUMN_HEADER_STYLE: BoxStyle = BoxStyle {
+    border_size: BoxSize { left:0, right:0, top:0, bottom:1 },
+    border_color: THEME::TITLE_BAR,
+    background_color: THEME::BACKGROUND_LIGHT,
+    font_weight: FontWeight::BOLD,
+    ..FILE_PATH_BOX_STYLE
+};
+
+const TAB_STYLE: BoxStyle = BoxStyle {
+    background_color: THEME::TAB_INACTIVE,
+    horizontal_align: HorizontalAlign::Center,
+    ..FILE_PATH_BOX_STYLE
+};
+
+const TAB_ACTIVE_STYLE: BoxStyle = BoxStyle {
+    background_color: THEME::TAB_ACTIVE,
+    font_weight: FontWeight::BOLD,
+    ..TAB_STYLE
+};
+
+// notes on the selected file, wrapped to the width of the preview pane
+const NOTES_BOX_STYLE: BoxStyle = BoxStyle {
+    padding_size: BoxSize::single(4),
+    vertical_align: VerticalAlign::Top,
+    text_overflow: TextOverflow::Clip,
+    word_wrap: true,
+    ..FILE_PATH_BOX_STYLE
+};
+
+const ITEM_STYLE: BoxStyle = BoxStyle {
+    background_color: THEME::BACKGROUND,
+    ..FILE_PATH_BOX_STYLE
+};
+
+const ITEM_SELECTED_STYLE: BoxStyle = BoxStyle {
+    background_color: THEME::HIGHLIGHT,
+    font_weight: FontWeight::BOLD,
+    ..ITEM_STYLE
+};
+
+const MENU_STYLE: BoxStyle = BoxStyle {
+    border_size: BoxSize::single(1),
+    border_color: THEME::TITLE_BAR,
+    padding_size: BoxSize::single(0),
+    background_color: THEME::BACKGROUND_LIGHT,
+    text_overflow: TextOverflow::Clip,
+    font_size: 16.0,
+    ..FILE_PATH_BOX_STYLE
+};
+
+const MENU_BAR_STYLE: BoxStyle = BoxStyle {
+    border_size: BoxSize { left:0, right:0, top:0, bottom:1 },
+    background_color: THEME::TITLE_BAR,
+    ..MENU_STYLE
+};
+
+const PALETTE_STYLE: BoxStyle = BoxStyle {
+    padding_size: BoxSize { left:8, right:8, top:0, bottom:0 },
+    focus_size: 0,
+    ..MENU_STYLE
+};
+
+const PALETTE_SELECTED_STYLE: BoxStyle = BoxStyle {
+    background_color: THEME::HIGHLIGHT,
+    ..PALETTE_STYLE
+};
+
+const PALETTE_QUERY_STYLE: BoxStyle = BoxStyle {
+    border_size: BoxSize::single(1),
+    border_color: THEME::FOCUS,
+    background_color: THEME::BACKGROUND,
+    ..PALETTE_STYLE
+};
+
+const TOOLTIP_STYLE: BoxStyle = BoxStyle {
+    padding_size: BoxSize { left:6, right:6, top:3, bottom:3 },
+    font_size: 14.0,
+    ..MENU_STYLE
 };
 
 struct ApplicationState {
     set_clipboard_text_data: Option<SetClipBoardTextData>,
     needs_redraw: bool,
+    damage: Damage,
+    render_threads: usize,
+    scale_factor: f32,
     cursor: Cursor,
-    fonts: Vec::<fontdue::Font>,
-    buttons: Vec::<Button>,
-    textboxes: Vec::<TextBox>
-}
-
-struct View {
-    active: bool,
-    subviews: Vec::<View>,
-    buttons: Vec::<Button>,
-    textboxes: Vec::<TextBox>
+    fonts: FontManager,
+    focus: FocusManager,
+    mouse: MouseState,
+    tooltip: Tooltip,
+    commands: CommandRegistry,
+    keymap: Keymap,
+    widgets: WidgetTree,
+    // the folder the file list shows
+    folder: Option<std::path::PathBuf>,
+    // the folder of every tab, the active one is the folder above
+    tabs: Vec<std::path::PathBuf>,
+    // a file cut or copied in the file list, true when it moves on paste
+    file_clipboard: Option<(std::path::PathBuf, bool)>,
+    folders_first: bool,
+    // a splitter moved so the panes are laid out again before the next
+    // paint, and saved when it was let go
+    needs_layout: bool,
+    layout_changed: bool
 }
 
 static mut APPLICATION_STATE : ApplicationState = ApplicationState {
     set_clipboard_text_data: None,
     needs_redraw: true,
+    damage: Damage::new(),
+    render_threads: 1,
+    scale_factor: 1.0,
     cursor: gui::Cursor::NotSet,
-    fonts: vec![],
-    buttons: vec![],
-    textboxes: vec![]
+    fonts: FontManager::new(),
+    focus: FocusManager::new(),
+    mouse: MouseState::new(),
+    tooltip: Tooltip::new(TOOLTIP_STYLE),
+    commands: CommandRegistry::new(),
+    keymap: Keymap::new(),
+    widgets: WidgetTree::new(),
+    folder: None,
+    tabs: Vec::new(),
+    file_clipboard: None,
+    folders_first: true,
+    needs_layout: false,
+    layout_changed: false
 };
 
-static mut VIEWS: Vec::<View> = vec![];
-
 static mut GLOBAL_BACK_BUFFER: PixelBuffer = PixelBuffer {
+    left: 0,
+    top: 0,
     height: 0,
     width: 0,
     pixels: vec![]
@@ -82,20 +248,27 @@ static mut GLOBAL_BACK_BUFFER: PixelBuffer = PixelBuffer {
 
 static CURSOR_TOGGLE: AtomicBool = AtomicBool::new(true);
 
-fn button_on_click(button: &mut Button) {
-    unsafe {
-        button.click_count += 1;
-        APPLICATION_STATE.textboxes[0].set_text(&format!("{} was clicked {} times", button.text, button.click_count));
-    }
+// a button that says how often it was clicked in the path box
+fn counting_button(name: &'static str, text: &'static str) -> WidgetNode {
+    let button = Button::new(text, BoxStyle::button_default(), BoxStyle::button_default_hot(), BoxStyle::button_default_active());
+    WidgetNode::new(name, button).on_click(move |widgets| {
+        let count = widgets.widget::<Button>(name).map_or(0, |button| button.click_count);
+        if let Some(path) = widgets.widget_mut::<TextBox>("path") {
+            path.set_text(&format!("{} was clicked {} times", text, count));
+        }
+    })
 }
 
 fn main() {
     unsafe {
         APPLICATION_STATE.set_clipboard_text_data = Some(set_text_into_clipboard);
+        APPLICATION_STATE.render_threads = default_thread_count();
     }
 
     //init_test_view();
     init_primary_view();
+    register_commands();
+    load_keymap();
 
     std::thread::spawn(||{
         let mut b = false;
@@ -109,19 +282,64 @@ fn main() {
     platform_run();
 }
 
+// the edit commands go to the focused control
+fn register_commands() {
+    let commands = unsafe { &mut APPLICATION_STATE.commands };
+    commands.register("edit.select_all", "Select All", || edit_command(EditCommand::SelectAll));
+    commands.register("edit.copy", "Copy", || edit_command(EditCommand::Copy));
+    commands.register("edit.paste", "Paste", || edit_command(EditCommand::Paste(get_clipboard_text())));
+    commands.register("edit.cut", "Cut", || edit_command(EditCommand::Cut));
+    commands.register("edit.undo", "Undo", || edit_command(EditCommand::Undo));
+    commands.register("edit.redo", "Redo", || edit_command(EditCommand::Redo));
+    commands.register("view.toggle_line_numbers", "Toggle Line Numbers", toggle_line_numbers);
+    commands.register("files.open", "Open", open_selected_file);
+    commands.register("files.open_in_new_tab", "Open in New Tab", open_selected_in_new_tab);
+    commands.register("files.cut", "Cut File", || put_selected_on_clipboard(true));
+    commands.register("files.copy", "Copy File", || put_selected_on_clipboard(false));
+    commands.register("files.paste", "Paste File", paste_file);
+    commands.register("files.parent", "Up One Folder", open_parent_folder);
+    commands.register("files.folders_first", "Folders First", toggle_folders_first);
+    commands.register("files.home", "Home Folder", open_home_folder);
+    commands.register("tabs.duplicate", "Duplicate Tab", duplicate_tab);
+    commands.register("tabs.close_others", "Close Other Tabs", close_other_tabs);
+    commands.register("view.command_palette", "Command Palette", open_command_palette);
+    commands.register("keys.reload", "Reload Key Bindings", load_keymap);
+    commands.register("app.exit", "Exit", close_window);
+}
+
+fn toggle_line_numbers() {
+    let widgets = unsafe { &mut APPLICATION_STATE.widgets };
+    for textarea in widgets.all_mut::<TextArea>() {
+        textarea.toggle_line_numbers();
+    }
+    update_window();
+}
+
+// blinks the carets and hides scrollbars
 fn cursor_timer_tick() {
-    let textboxes = unsafe { &APPLICATION_STATE.textboxes };
-    for textbox in textboxes {
-        if textbox.active {
-            let toggle = CURSOR_TOGGLE.load(Ordering::Relaxed);
-            unsafe { crate::draw_textbox(&mut GLOBAL_BACK_BUFFER, textbox, &APPLICATION_STATE.fonts[0], toggle) };
-            update_window();
+    let now = std::time::Instant::now();
+    let widgets = unsafe { &mut APPLICATION_STATE.widgets };
+    widgets.walk_mut(&mut |_, node| {
+        if node.widget.timer_tick(now) {
+            invalidate_rect(node.widget.bounds());
         }
-    }
+    });
+    tooltip_timer_tick(now);
 }
 
+// redraws the whole window on the next paint
 fn update_window() {
     unsafe {
+        APPLICATION_STATE.damage.add_everything();
+        APPLICATION_STATE.needs_redraw = true;
+        invalidate_window();
+    }
+}
+
+// redraws only the tiles under rect on the next paint
+fn invalidate_rect(rect: Rect) {
+    unsafe {
+        APPLICATION_STATE.damage.add(rect);
         APPLICATION_STATE.needs_redraw = true;
         invalidate_window();
     }
@@ -133,10 +351,108 @@ fn handle_window_resize(width: i32, height: i32) {
         GLOBAL_BACK_BUFFER.pixels = vec![crate::gui::Pixel::default(); pixel_size];
         GLOBAL_BACK_BUFFER.width = width;
         GLOBAL_BACK_BUFFER.height = height;
-        for textbox in &mut APPLICATION_STATE.textboxes {
-            textbox.update_bounds_rect(width, height);
+        APPLICATION_STATE.damage.add_everything();
+        layout_panes();
+    }
+}
+
+// called by the platform layer when the window first learns its dpi and
+// whenever it moves to a monitor with a different one
+fn handle_scale_factor_change(scale: f32) {
+    unsafe {
+        if APPLICATION_STATE.scale_factor == scale {
+            return;
         }
-        //views[0].update_bounds_rect(width, height);
+        APPLICATION_STATE.scale_factor = scale;
+    }
+    layout_panes();
+    update_window();
+}
+
+/*
+    the window is three panes side by side, the sidebar, the files and the
+    preview, with a splitter between each. the tabs and the path box run
+    along the top of the files and the preview, the column header along
+    the top of the files. the splitters are placed first, each
+    between its neighbours, then the panes are laid out around them.
+*/
+pub fn layout_panes() {
+    let state = unsafe { &mut APPLICATION_STATE };
+    let (width, height) = unsafe { (GLOBAL_BACK_BUFFER.width, GLOBAL_BACK_BUFFER.height) };
+    let scale = state.scale_factor;
+    let bar_height = gui::scale_i32(MENU_BAR_HEIGHT, scale);
+    let path_height = gui::scale_i32(PATH_HEIGHT, scale);
+    let tab_height = gui::scale_i32(TAB_HEIGHT, scale);
+    let widgets = &mut state.widgets;
+    if let Some(bar) = widgets.widget_mut::<MenuBar>("menubar") {
+        bar.window = Rect { x: 0, y: 0, w: width, h: height };
+    }
+    state.tooltip.window = Rect { x: 0, y: 0, w: width, h: height };
+    let preview_x = widgets.widget::<Splitter>("preview.splitter").map_or(width, |s| s.get_rect().x);
+    if let Some(sidebar) = widgets.widget_mut::<Splitter>("sidebar.splitter") {
+        sidebar.update_bounds_rect(Rect { x: 0, y: bar_height, w: preview_x, h: height - bar_height }, width, scale);
+    }
+    let files_x = widgets.widget::<Splitter>("sidebar.splitter").map_or(0, |s| s.get_rect().x + s.scaled_thickness());
+    let top = bar_height + tab_height + path_height;
+    if let Some(preview) = widgets.widget_mut::<Splitter>("preview.splitter") {
+        preview.update_bounds_rect(Rect { x: files_x, y: top, w: width - files_x, h: height - top }, width, scale);
+    }
+    let (sidebar, preview) = match (widgets.widget::<Splitter>("sidebar.splitter"), widgets.widget::<Splitter>("preview.splitter")) {
+        (Some(sidebar), Some(preview)) => (sidebar.get_rect(), preview.get_rect()),
+        _ => return
+    };
+    // the splitters are in physical pixels already, as a fraction of the
+    // parent they come back to the same pixel, the half keeps rounding from
+    // losing one
+    let pixels = |x: i32, of: i32| BoundsField::FLOAT((x as f32 + 0.5) / std::cmp::max(1, of) as f32);
+    let root = LayoutNode::column("", 0, vec![
+        LayoutNode::new("menubar").height(BoundsField::INT(MENU_BAR_HEIGHT)),
+        LayoutNode::row("", 0, vec![
+            LayoutNode::new("sidebar").width(pixels(sidebar.x, width)),
+            LayoutNode::new("").width(pixels(sidebar.w, width)),
+            LayoutNode::column("", 0, vec![
+                LayoutNode::new("tabs").height(BoundsField::INT(TAB_HEIGHT)),
+                LayoutNode::new("path").height(BoundsField::INT(PATH_HEIGHT)),
+                LayoutNode::row("", 0, vec![
+                    LayoutNode::column("", 0, vec![
+                        LayoutNode::new("files.header").height(BoundsField::INT(HEADER_HEIGHT)),
+                        LayoutNode::new("files").grow(1.0)
+                    ]).width(pixels(preview.x - files_x, width - files_x)),
+                    LayoutNode::new("").width(pixels(preview.w, width - files_x)),
+                    LayoutNode::overlay("preview", vec![
+                        LayoutNode::new("notes").height(BoundsField::INT(200))
+                            .place(Place::Anchors(Anchors { left: Some(0), top: Some(2), right: Some(0), bottom: None }))
+                    ]).padding(8).grow(1.0)
+                ]).grow(1.0)
+            ]).grow(1.0)
+        ]).grow(1.0)
+    ]);
+    widgets.apply_layout(&root.layout(Rect { x: 0, y: 0, w: width, h: height }, scale));
+}
+
+// the splitters moved, the panes follow before the next paint
+pub fn request_layout(save: bool) {
+    unsafe {
+        APPLICATION_STATE.needs_layout = true;
+        APPLICATION_STATE.layout_changed |= save;
+    }
+    update_window();
+}
+
+// %APPDATA%\filex\layout.conf
+fn load_layout() {
+    let mut splitters = unsafe { APPLICATION_STATE.widgets.all_mut::<Splitter>() };
+    if let Some(text) = gui::config_path("layout.conf").and_then(|path| std::fs::read_to_string(path).ok()) {
+        load_splitters(&mut splitters, &text);
+    }
+}
+
+pub fn save_layout() {
+    let splitters = unsafe { APPLICATION_STATE.widgets.all::<Splitter>() };
+    if let Some(path) = gui::config_path("layout.conf") {
+        // a layout that can't be saved just isn't restored next time
+        let _ = path.parent().map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&path, save_splitters(&splitters)));
     }
 }
 
@@ -146,25 +462,8 @@ fn handle_window_resize(width: i32, height: i32) {
 //         let font = fontdue::Font::from_bytes(font, fontdue::FontSettings::default()).unwrap();
 //         APPLICATION_STATE.fonts.push(font);
 
-//         APPLICATION_STATE.buttons.push(Button {
-//             text: "Click Me!",
-//             bounds: gui::Rect { x: 300, y: 300, w: 150, h: 50 },
-//             hot: false, active: false, click_count: 0,
-//             on_click: Some(button_on_click), 
-//             style: BoxStyle::button_default(),
-//             style_hot: BoxStyle::button_default_hot(),
-//             style_active: BoxStyle::button_default_active()
-//         });
-
-//         APPLICATION_STATE.buttons.push(Button {
-//             text: "BUY NOW",
-//             bounds: Rect { x: 500, y: 300, w: 150, h: 50 },
-//             hot: false, active: false, click_count: 0,
-//             on_click: Some(button_on_click),
-//             style: BoxStyle::button_default(),
-//             style_hot: BoxStyle::button_default_hot(),
-//             style_active: BoxStyle::button_default_active()
-//         });
+//         APPLICATION_STATE.widgets.add(counting_button("click", "Click Me!"));
+//         APPLICATION_STATE.widgets.add(counting_button("buy", "BUY NOW"));
 
 //         APPLICATION_STATE.textboxes.push(TextBox {
 //             text: Vec::new(),
@@ -189,7 +488,7 @@ fn handle_window_resize(width: i32, height: i32) {
 //     fill_rect(&mut buffer, 0, height / 4 - 2, width, 4, Color::DARK_GRAY);
 //     fill_rect(&mut buffer, 0, height / 4 * 3 - 2, width, 4, Color::DARK_GRAY);
 
-//     let font = unsafe { &APPLICATION_STATE.fonts[0] };
+//     let fonts = unsafe { &APPLICATION_STATE.fonts };
 //     let textboxes = unsafe { &APPLICATION_STATE.textboxes };
 //     let buttons = unsafe { &APPLICATION_STATE.buttons };
 
@@ -204,49 +503,457 @@ fn handle_window_resize(width: i32, height: i32) {
 
 fn init_primary_view() {
     unsafe {
-        let font = include_bytes!("../fonts/OpenSans-Regular.ttf") as &[u8];
-        let font = fontdue::Font::from_bytes(font, fontdue::FontSettings::default()).unwrap();
-        APPLICATION_STATE.fonts.push(font);
-
-        APPLICATION_STATE.textboxes.push(TextBox {
+        let regular = include_bytes!("../fonts/OpenSans-Regular.ttf") as &[u8];
+        let bold = include_bytes!("../fonts/OpenSans-Bold.ttf") as &[u8];
+        APPLICATION_STATE.fonts.add_font(Font::from_bytes(regular).unwrap());
+        APPLICATION_STATE.fonts.add_font(Font::from_bytes(bold).unwrap());
+        APPLICATION_STATE.fonts.discover_system_fonts();
+
+        APPLICATION_STATE.widgets.add(WidgetNode::new("tabs", TabBar::new(TAB_STYLE, TAB_ACTIVE_STYLE))
+            .on_change(tab_changed)
+            .on(tabs_context_menu));
+        APPLICATION_STATE.widgets.add(WidgetNode::new("path", TextBox {
             text: Vec::new(),
             placeholder: "",
-            bounds: Bounds::variable_horizontal(0.2, 0, 0.8, 30),
+            bounds: Bounds::variable_horizontal(0.2, 0, 0.8, PATH_HEIGHT),
             bounds_rect: Rect::default(),
             hot: false, active: false, 
             cursor_index: 0, scroll_offset_x: 0,
             selection_index: usize::MAX,
+            preedit: Vec::new(), preedit_cursor: 0,
             style: FILE_PATH_BOX_STYLE
-        });
+        }));
+
+        // the files in the middle pane, under the path. a click on the
+        // header switches between folders first and mixed in
+        let header = Button::new("Name", COLUMN_HEADER_STYLE, COLUMN_HEADER_STYLE, COLUMN_HEADER_STYLE);
+        APPLICATION_STATE.widgets.add(WidgetNode::new("files.header", header).on_click(header_clicked));
+        let files = ListView::new(
+            Bounds { x: BoundsField::FLOAT(0.2), y: BoundsField::INT(PATH_HEIGHT), w: BoundsField::FLOAT(0.4), h: BoundsField::FLOAT(1.0) },
+            26, ITEM_STYLE, ITEM_SELECTED_STYLE);
+        APPLICATION_STATE.widgets.add(WidgetNode::new("files", files).on(files_context_menu));
+
+        APPLICATION_STATE.widgets.add(WidgetNode::new("preview", Panel::new(None, vec![
+            WidgetNode::new("notes", TextArea::new(Bounds::variable_horizontal(0.61, 40, 0.38, 200), NOTES_BOX_STYLE))
+        ])));
+
+        // the sidebar folds away to the left and the preview to the right.
+        // they're last so they're on top of the panes they overlap a little.
+        let mut sidebar = Splitter::new("sidebar", 0.2, false);
+        sidebar.max_before = Some(500);
+        APPLICATION_STATE.widgets.add(WidgetNode::new("sidebar.splitter", sidebar));
+        APPLICATION_STATE.widgets.add(WidgetNode::new("preview.splitter", Splitter::new("preview", 0.6, true)));
+        // the menus and the palette go over everything when they're open
+        APPLICATION_STATE.widgets.add(WidgetNode::new("menubar", MenuBar::new(MENU_BAR_STYLE, MENU_STYLE, vec![
+            ("File", file_bar_menu),
+            ("Edit", edit_bar_menu),
+            ("View", view_bar_menu),
+            ("Go", go_bar_menu),
+            ("Tools", tools_bar_menu),
+            ("Help", help_bar_menu)
+        ])));
+        APPLICATION_STATE.widgets.add(WidgetNode::new("menu", Menu::new(MENU_STYLE)));
+        APPLICATION_STATE.widgets.add(WidgetNode::new("palette", CommandPalette::new(PALETTE_STYLE, PALETTE_SELECTED_STYLE, PALETTE_QUERY_STYLE)));
+        load_layout();
+        open_folder(std::env::current_dir().unwrap_or_default());
+    }
+}
 
-        // let mut view = View::default();
-        // view.bounds = Bounds::int(200, 200, 100, 100);
-        // view.behavior = ViewBehavior::Button;
-        // view.style = BoxStyle {
-        //         background_color: THEME::TAB_INACTIVE,
-        //         ..Default::default()
-        // };
-        // view.style_hot = BoxStyle {
-        //         background_color: THEME::TAB_ACTIVE,
-        //         ..Default::default()
-        //     };
-        // view.style_active = BoxStyle {
-        //         background_color: THEME::HIGHLIGHT,
-        //         ..Default::default()
-        //     };
+// the listeners run while the tree is busy with the event, so they show
+// the folder through the tree they're given rather than by open_folder
+fn header_clicked(widgets: &mut WidgetTree) {
+    let state = unsafe { &mut APPLICATION_STATE };
+    state.folders_first = !state.folders_first;
+    if let Some(folder) = &state.folder {
+        show_folder(widgets, folder, state.folders_first);
+    }
+}
 
-        // views.push(view);
+fn tab_changed(widgets: &mut WidgetTree) {
+    if let Some(index) = widgets.widget::<TabBar>("tabs").map(|tabs| tabs.active) {
+        switch_tab(widgets, index);
+    }
+}
+
+fn switch_tab(widgets: &mut WidgetTree, index: usize) {
+    let state = unsafe { &mut APPLICATION_STATE };
+    if let Some(folder) = state.tabs.get(index).cloned() {
+        if let Some(tabs) = widgets.widget_mut::<TabBar>("tabs") {
+            tabs.active = index;
+        }
+        show_folder(widgets, &folder, state.folders_first);
+        state.folder = Some(folder);
     }
 }
 
+// by name, with the folders before the files or mixed in with them
+fn list_directory(path: &std::path::Path, folders_first: bool) -> Vec<String> {
+    let mut entries: Vec<(bool, String)> = match std::fs::read_dir(path) {
+        Ok(dir) => dir.filter_map(|e| e.ok())
+            .map(|e| (folders_first && !e.path().is_dir(), e.file_name().to_string_lossy().into_owned()))
+            .collect(),
+        Err(_) => Vec::new()
+    };
+    entries.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.to_lowercase().cmp(&b.1.to_lowercase())));
+    entries.into_iter().map(|(_, name)| name).collect()
+}
+
+// a folder in the file list and its path above it
+fn show_folder(widgets: &mut WidgetTree, path: &std::path::Path, folders_first: bool) {
+    if let Some(files) = widgets.widget_mut::<ListView>("files") {
+        files.set_items(list_directory(path, folders_first));
+    }
+    if let Some(path_box) = widgets.widget_mut::<TextBox>("path") {
+        path_box.set_text(&path.to_string_lossy());
+    }
+}
+
+// shows a folder in the active tab
+fn open_folder(path: std::path::PathBuf) {
+    let state = unsafe { &mut APPLICATION_STATE };
+    show_folder(&mut state.widgets, &path, state.folders_first);
+    let active = state.widgets.widget::<TabBar>("tabs").map_or(0, |tabs| tabs.active);
+    match state.tabs.get_mut(active) {
+        Some(tab) => *tab = path.clone(),
+        None => state.tabs.push(path.clone())
+    }
+    state.folder = Some(path);
+    update_tab_titles();
+    update_window();
+}
+
+// the tabs are named after their folders, a drive root after the drive
+fn update_tab_titles() {
+    let state = unsafe { &mut APPLICATION_STATE };
+    if let Some(tabs) = state.widgets.widget_mut::<TabBar>("tabs") {
+        tabs.titles = state.tabs.iter()
+            .map(|path| path.file_name().unwrap_or(path.as_os_str()).to_string_lossy().into_owned())
+            .collect();
+        tabs.active = std::cmp::min(tabs.active, tabs.titles.len().saturating_sub(1));
+    }
+}
+
+// adds a tab after the others and shows the folder in it
+fn open_in_new_tab(path: std::path::PathBuf) {
+    let state = unsafe { &mut APPLICATION_STATE };
+    state.tabs.push(path.clone());
+    if let Some(tabs) = state.widgets.widget_mut::<TabBar>("tabs") {
+        tabs.active = state.tabs.len() - 1;
+    }
+    open_folder(path);
+}
+
+fn duplicate_tab() {
+    let folder = unsafe { APPLICATION_STATE.folder.clone() };
+    if let Some(folder) = folder {
+        open_in_new_tab(folder);
+    }
+}
+
+fn close_other_tabs() {
+    let state = unsafe { &mut APPLICATION_STATE };
+    if let Some(folder) = state.folder.clone() {
+        state.tabs = vec![folder];
+        if let Some(tabs) = state.widgets.widget_mut::<TabBar>("tabs") {
+            tabs.active = 0;
+        }
+        update_tab_titles();
+        update_window();
+    }
+}
+
+// the file or folder selected in the file list
+fn selected_path() -> Option<std::path::PathBuf> {
+    let state = unsafe { &APPLICATION_STATE };
+    let selected = state.widgets.widget::<ListView>("files")
+        .and_then(|files| files.selected.map(|i| files.items[i].clone()));
+    state.folder.as_ref().zip(selected).map(|(folder, name)| folder.join(name))
+}
+
+// only folders open so far, files need something to open them with
+fn open_selected_file() {
+    if let Some(path) = selected_path().filter(|path| path.is_dir()) {
+        open_folder(path);
+    }
+}
+
+fn open_selected_in_new_tab() {
+    if let Some(path) = selected_path().filter(|path| path.is_dir()) {
+        open_in_new_tab(path);
+    }
+}
+
+// the file is only remembered here, nothing happens to it until it's
+// pasted
+fn put_selected_on_clipboard(cut: bool) {
+    let path = selected_path();
+    unsafe { APPLICATION_STATE.file_clipboard = path.map(|path| (path, cut)); }
+}
+
+// into the folder being shown under the same name, a name that's taken
+// is left alone. a folder can't go into itself.
+fn paste_file() {
+    let state = unsafe { &mut APPLICATION_STATE };
+    let (from, cut) = match &state.file_clipboard {
+        Some(clipboard) => clipboard.clone(),
+        None => return
+    };
+    let (folder, name) = match (&state.folder, from.file_name()) {
+        (Some(folder), Some(name)) if !folder.starts_with(&from) => (folder.clone(), name.to_owned()),
+        _ => return
+    };
+    let to = folder.join(name);
+    if to.exists() {
+        return;
+    }
+    // like saving the layout, a paste that fails just doesn't show up
+    let pasted = if cut { std::fs::rename(&from, &to) } else { copy_path(&from, &to) };
+    if pasted.is_ok() && cut {
+        state.file_clipboard = None;
+    }
+    open_folder(folder);
+}
+
+// a file, or a folder with everything in it
+fn copy_path(from: &std::path::Path, to: &std::path::Path) -> std::io::Result<()> {
+    if from.is_dir() {
+        std::fs::create_dir(to)?;
+        for entry in std::fs::read_dir(from)? {
+            let entry = entry?;
+            copy_path(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    }
+    else {
+        std::fs::copy(from, to).map(|_| ())
+    }
+}
+
+fn open_parent_folder() {
+    let parent = unsafe { APPLICATION_STATE.folder.as_ref().and_then(|f| f.parent()).map(|p| p.to_path_buf()) };
+    if let Some(parent) = parent {
+        open_folder(parent);
+    }
+}
+
+fn open_home_folder() {
+    if let Some(home) = std::env::var_os("USERPROFILE").or_else(|| std::env::var_os("HOME")) {
+        open_folder(std::path::PathBuf::from(home));
+    }
+}
+
+// every command with its key binding, the palette takes the focus until
+// it closes
+fn open_command_palette() {
+    let state = unsafe { &mut APPLICATION_STATE };
+    let entries = state.commands.commands().iter().map(|command| PaletteEntry {
+        command: command.name,
+        title: command.title,
+        keys: state.keymap.chord_for(command.name).map(|chord| chord_to_string(&chord))
+    }).collect();
+    let window = unsafe { Rect { x: 0, y: 0, w: GLOBAL_BACK_BUFFER.width, h: GLOBAL_BACK_BUFFER.height } };
+    if let Some(palette) = state.widgets.widget_mut::<CommandPalette>("palette") {
+        palette.open(entries, window);
+    }
+    set_focus(state.widgets.find("palette"));
+    state.focus.focus_visible = false;
+}
+
+fn toggle_folders_first() {
+    let state = unsafe { &mut APPLICATION_STATE };
+    state.folders_first = !state.folders_first;
+    if let Some(folder) = state.folder.clone() {
+        open_folder(folder);
+    }
+}
+
+// commands nobody registered yet show greyed out, with their key binding
+// when they have one
+fn command_item(title: &'static str, command: &'static str) -> MenuItem {
+    let state = unsafe { &APPLICATION_STATE };
+    MenuItem::command(title, command)
+        .enabled(state.commands.get(command).is_some())
+        .accelerator(state.keymap.chord_for(command).map(|chord| chord_to_string(&chord)))
+}
+
+fn file_menu() -> Vec<MenuItem> {
+    let folders_first = unsafe { APPLICATION_STATE.folders_first };
+    vec![
+        command_item("Open", "files.open"),
+        command_item("Open in New Tab", "files.open_in_new_tab"),
+        command_item("Up One Folder", "files.parent"),
+        MenuItem::separator(),
+        command_item("Cut", "files.cut"),
+        command_item("Copy", "files.copy"),
+        command_item("Paste", "files.paste"),
+        MenuItem::separator(),
+        MenuItem::submenu("Sort", vec![
+            command_item("Folders First", "files.folders_first").checked(folders_first)
+        ])
+    ]
+}
+
+fn tab_menu() -> Vec<MenuItem> {
+    vec![
+        command_item("Duplicate", "tabs.duplicate"),
+        command_item("Close Others", "tabs.close_others")
+    ]
+}
+
+// the menu bar, built when a menu opens so it shows what's enabled and
+// bound right now
+fn file_bar_menu() -> Vec<MenuItem> {
+    vec![
+        command_item("Open", "files.open"),
+        command_item("Open in New Tab", "files.open_in_new_tab"),
+        MenuItem::separator(),
+        command_item("Duplicate Tab", "tabs.duplicate"),
+        command_item("Close Other Tabs", "tabs.close_others"),
+        MenuItem::separator(),
+        command_item("Exit", "app.exit")
+    ]
+}
+
+fn edit_bar_menu() -> Vec<MenuItem> {
+    vec![
+        command_item("Undo", "edit.undo"),
+        command_item("Redo", "edit.redo"),
+        MenuItem::separator(),
+        command_item("Cut", "edit.cut"),
+        command_item("Copy", "edit.copy"),
+        command_item("Paste", "edit.paste"),
+        MenuItem::separator(),
+        command_item("Select All", "edit.select_all")
+    ]
+}
+
+fn view_bar_menu() -> Vec<MenuItem> {
+    let state = unsafe { &APPLICATION_STATE };
+    let line_numbers = state.widgets.all::<TextArea>().first().is_some_and(|t| t.show_line_numbers);
+    vec![
+        command_item("Command Palette", "view.command_palette"),
+        MenuItem::separator(),
+        command_item("Line Numbers", "view.toggle_line_numbers").checked(line_numbers),
+        MenuItem::submenu("Sort", vec![
+            command_item("Folders First", "files.folders_first").checked(state.folders_first)
+        ])
+    ]
+}
+
+fn go_bar_menu() -> Vec<MenuItem> {
+    vec![
+        command_item("Up One Folder", "files.parent"),
+        command_item("Home Folder", "files.home")
+    ]
+}
+
+fn tools_bar_menu() -> Vec<MenuItem> {
+    vec![
+        command_item("Reload Key Bindings", "keys.reload")
+    ]
+}
+
+fn help_bar_menu() -> Vec<MenuItem> {
+    vec![
+        command_item("All Commands", "view.command_palette")
+    ]
+}
+
+// a right click on the file list selects the file under the mouse and
+// opens the menu there, the menu key and shift+f10 open it under the
+// selected file
+fn files_context_menu(context: &mut EventContext) {
+    let at = match context.event {
+        Event::MouseDown { button: MouseButton::Right, x, y, .. } => {
+            if let Some(files) = context.widgets.widget_mut::<ListView>("files") {
+                if let Some(index) = files.item_at(*x, *y) {
+                    files.select(index);
+                }
+            }
+            Some((*x, *y))
+        },
+        Event::Key(key) if key.state == KeyState::Pressed && (key.key == Key::ContextMenu || key.key == Key::F(10) && key.modifiers.shift) => {
+            context.widgets.widget::<ListView>("files").map(|files| {
+                let view = files.scroll.bounds_rect;
+                let (offset_x, offset_y) = files.scroll.offset();
+                match files.selected {
+                    Some(i) => {
+                        let rect = files.item_rect(i);
+                        (view.x + offset_x + rect.x + rect.h, view.y + offset_y + rect.y + rect.h)
+                    },
+                    None => (view.x, view.y)
+                }
+            })
+        },
+        _ => None
+    };
+    if let Some((x, y)) = at {
+        let window = unsafe { Rect { x: 0, y: 0, w: GLOBAL_BACK_BUFFER.width, h: GLOBAL_BACK_BUFFER.height } };
+        if let Some(menu) = context.widgets.widget_mut::<Menu>("menu") {
+            menu.open(file_menu(), x, y, window);
+        }
+        context.handled = true;
+    }
+}
+
+// a right click on a tab switches to it and opens the tab menu there
+fn tabs_context_menu(context: &mut EventContext) {
+    if let Event::MouseDown { button: MouseButton::Right, x, y, .. } = context.event {
+        if let Some(index) = context.widgets.widget::<TabBar>("tabs").and_then(|tabs| tabs.tab_at(*x, *y)) {
+            switch_tab(context.widgets, index);
+            let window = unsafe { Rect { x: 0, y: 0, w: GLOBAL_BACK_BUFFER.width, h: GLOBAL_BACK_BUFFER.height } };
+            if let Some(menu) = context.widgets.widget_mut::<Menu>("menu") {
+                menu.open(tab_menu(), *x, *y, window);
+            }
+            context.handled = true;
+        }
+    }
+}
+
+// moves smooth scrolling along a frame. anything still moving asks for
+// another paint so it keeps going until it gets there.
+fn step_animations() {
+    let now = std::time::Instant::now();
+    let widgets = unsafe { &mut APPLICATION_STATE.widgets };
+    widgets.walk_mut(&mut |_, node| {
+        if node.widget.animate(now) {
+            invalidate_rect(node.widget.bounds());
+        }
+    });
+}
+
 fn update_back_buffer() {
+    let state = unsafe { &mut APPLICATION_STATE };
+    if std::mem::take(&mut state.needs_layout) {
+        layout_panes();
+    }
+    if std::mem::take(&mut state.layout_changed) {
+        save_layout();
+    }
+    step_animations();
     let buffer = unsafe { &mut GLOBAL_BACK_BUFFER };
-    let draw_cursor = CURSOR_TOGGLE.load(Ordering::Relaxed);
+    let damage = unsafe { APPLICATION_STATE.damage.take(buffer.width, buffer.height) };
+    let threads = if buffer.width * buffer.height < MIN_PARALLEL_PIXELS {
+        1
+    }
+    else {
+        unsafe { APPLICATION_STATE.render_threads }
+    };
     let width = buffer.width;
     let height = buffer.height;
-    fill_rect(buffer, 0, 0, width / 5, height, THEME::BACKGROUND_LIGHT);
-    fill_rect(buffer, width /5, 0, width * 4 / 5, height, THEME::BACKGROUND);
-    fill_rect(buffer, width * 3 / 5, 0, 4, height, THEME::BACKGROUND_LIGHT);
+    // read once so the blink thread can't flip the caret between tiles
+    let draw_cursor = CURSOR_TOGGLE.load(Ordering::Relaxed);
+    render_tiled(buffer, &damage, TILE_SIZE, threads, &|tile: &mut PixelBuffer| draw_scene(tile, width, height, draw_cursor));
+}
+
+// draws everything in window coordinates, the buffer may only be one tile
+// of the window. this runs on the render threads so it must only read the
+// application state.
+fn draw_scene(buffer: &mut PixelBuffer, width: i32, height: i32, draw_cursor: bool) {
+    let widgets = unsafe { &APPLICATION_STATE.widgets };
+    let sidebar_width = widgets.widget::<Splitter>("sidebar.splitter").map_or(width / 5, |s| s.get_rect().x);
+    fill_rect(buffer, 0, 0, sidebar_width, height, THEME::BACKGROUND_LIGHT);
+    fill_rect(buffer, sidebar_width, 0, width - sidebar_width, height, THEME::BACKGROUND);
     // fill_rect(&mut buffer, 0, 0, width, height, Color::LIGHT_GRAY);
     // fill_rect(&mut buffer, 0, height / 2 - 2, width, 4, Color::DARK_GRAY);
     // fill_rect(&mut buffer, width / 2 - 2, 0, 4, height, Color::DARK_GRAY);
@@ -254,26 +961,11 @@ fn update_back_buffer() {
     // fill_rect(&mut buffer, 0, height / 4 - 2, width, 4, Color::DARK_GRAY);
     // fill_rect(&mut buffer, 0, height / 4 * 3 - 2, width, 4, Color::DARK_GRAY);
 
-    let font = unsafe { &APPLICATION_STATE.fonts[0] };
-    let textboxes = unsafe { &APPLICATION_STATE.textboxes };
-    //let view = unsafe { &views[0] };
-    // let buttons = unsafe { &APPLICATION_STATE.buttons };
+    let fonts = unsafe { &APPLICATION_STATE.fonts };
+    widgets.draw(buffer, fonts, draw_cursor);
 
-    for textbox in textboxes {
-        draw_textbox(buffer, &textbox, &font, textbox.active && draw_cursor);
+    if let Some((bounds, style)) = focus_ring() {
+        draw_focus_ring(buffer, &bounds, &style);
     }
-
-    //draw_view(buffer, &view);
-
-    // for button in buttons {
-    //     draw_button(buffer, &button, &font);
-    // }
+    draw_tooltip(buffer, unsafe { &APPLICATION_STATE.tooltip }, fonts);
 }
-
-// fn draw_view(buffer: &mut PixelBuffer, view: &View) {
-//     let bounds = view.bounds_rect;
-//     let style = *view.get_style();
-//     fill_rect(buffer, bounds.x, bounds.y, bounds.w, bounds.h, style.background_color);
-// }
-
-
@@ -9,11 +9,21 @@ use crate::gui:: {
     Cursor,
 };
 use crate::gui::is_point_in_rect_a;
-use crate::gui::keyboard::KeyboardInput;
 use crate::gui::keyboard::KeyboardModifiers;
-use crate::gui::mouse::handle_left_mouse_button_down;
-use crate::gui::mouse::handle_left_mouse_button_up;
-use crate::gui::keyboard::keyboard_keydown;
+use crate::gui::mouse::handle_mouse_button_down;
+use crate::gui::mouse::handle_mouse_button_up;
+use crate::gui::mouse::handle_mouse_capture_lost;
+use crate::gui::mouse::handle_mouse_wheel;
+use crate::gui::mouse::MouseButton;
+use crate::gui::keyboard::keyboard_event;
+use crate::gui::keyboard::KeyEvent;
+use crate::gui::keyboard::KeyState;
+use crate::gui::keyboard::KeyCode;
+use crate::gui::keyboard::Key;
+use crate::gui::keyboard::text_input;
+use crate::gui::keyboard::text_input_caret_rect;
+use crate::gui::keyboard::TextInput;
+use crate::gui::keyboard::Utf16Decoder;
 use crate::update_back_buffer;
 use crate::cursor_timer_tick;
 use crate::gui::mouse::handle_mouse_move;
@@ -32,7 +42,9 @@ use self::winapi::shared::windef:: {
     HDC,
     HBITMAP,
     RECT,
-    HCURSOR
+    POINT,
+    HCURSOR,
+    DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2
 };
 
 use self::winapi::shared::ntdef:: {
@@ -74,7 +86,9 @@ use self::winapi::shared::minwindef::{
     HGLOBAL,
     LPVOID,
     TRUE,
-    FALSE
+    FALSE,
+    BOOL,
+    LOWORD
 };
 
 //use self::winapi::shared::ntdef::LPCWSTR;
@@ -126,15 +140,52 @@ use self::winapi::um::winuser::{
     WM_USER,
     WM_CREATE,
     WM_DESTROY,
+    WM_CLOSE,
     WM_PAINT,
     WM_SIZE,
     WM_SETCURSOR,
     WM_MOUSEMOVE,
     WM_LBUTTONDOWN,
     WM_LBUTTONUP,
+    WM_RBUTTONDOWN,
+    WM_RBUTTONUP,
+    WM_MBUTTONDOWN,
+    WM_MBUTTONUP,
+    WM_XBUTTONDOWN,
+    WM_XBUTTONUP,
+    WM_MOUSEWHEEL,
+    WM_MOUSEHWHEEL,
+    WM_CAPTURECHANGED,
+    WHEEL_DELTA,
+    XBUTTON1,
+    GET_WHEEL_DELTA_WPARAM,
+    GET_XBUTTON_WPARAM,
+    SetCapture,
+    ReleaseCapture,
+    GetCapture,
+    ScreenToClient,
+    GetDoubleClickTime,
+    GetSystemMetrics,
+    SM_CXDOUBLECLK,
+    SystemParametersInfoW,
+    SPI_GETWHEELSCROLLLINES,
+    SPI_GETWHEELSCROLLCHARS,
+    WHEEL_PAGESCROLL,
+    MK_LBUTTON,
+    MK_RBUTTON,
+    MK_MBUTTON,
+    MK_XBUTTON1,
+    MK_XBUTTON2,
     WM_KEYDOWN,
+    WM_SYSKEYDOWN,
+    WM_SYSKEYUP,
     WM_KEYUP,
     WM_CHAR,
+    WM_DEADCHAR,
+    WM_IME_STARTCOMPOSITION,
+    WM_IME_COMPOSITION,
+    WM_IME_ENDCOMPOSITION,
+    WM_DPICHANGED,
 
     // virtual key codes
     VK_ESCAPE,
@@ -146,10 +197,34 @@ use self::winapi::um::winuser::{
     VK_DOWN,
     VK_HOME,
     VK_END,
+    VK_PRIOR, // page up
+    VK_NEXT, // page down
+    VK_RETURN,
+    VK_TAB,
     VK_SHIFT,
     VK_CONTROL,
     VK_MENU, // ALT
     VK_CAPITAL,
+    VK_SPACE,
+    VK_INSERT,
+    VK_F1,
+    VK_F24,
+    VK_LWIN,
+    VK_RWIN,
+    VK_NUMLOCK,
+    VK_SCROLL,
+    VK_SNAPSHOT,
+    VK_PAUSE,
+    VK_APPS,
+    VK_NUMPAD0,
+    VK_NUMPAD9,
+    VK_MULTIPLY,
+    VK_ADD,
+    VK_SUBTRACT,
+    VK_DIVIDE,
+    VK_DECIMAL,
+    MapVirtualKeyW,
+    MAPVK_VK_TO_CHAR,
 
     // clipboard
     IsClipboardFormatAvailable,
@@ -172,11 +247,21 @@ use self::winapi::um::winuser::{
     IDC_WAIT,
     IDC_HAND,
     IDC_IBEAM,
+    IDC_SIZEWE,
 
     // icons
     LoadIconW,
     IDI_APPLICATION,
 
+    // dpi
+    SetProcessDpiAwarenessContext,
+    GetDpiForWindow,
+    SetWindowPos,
+    SWP_NOMOVE,
+    SWP_NOZORDER,
+    SWP_NOACTIVATE,
+    USER_DEFAULT_SCREEN_DPI,
+
     // Painting
     BeginPaint,
     EndPaint,
@@ -185,6 +270,31 @@ use self::winapi::um::winuser::{
     InvalidateRect
 };
 
+// imm32, the input method manager. declared here because winapi doesn't
+// bind the composition string functions
+type HIMC = *mut c_void;
+
+#[repr(C)]
+#[allow(non_snake_case)]
+struct COMPOSITIONFORM {
+    dwStyle: DWORD,
+    ptCurrentPos: POINT,
+    rcArea: RECT
+}
+
+const GCS_COMPSTR: DWORD = 0x0008;
+const GCS_CURSORPOS: DWORD = 0x0080;
+const GCS_RESULTSTR: DWORD = 0x0800;
+const CFS_POINT: DWORD = 0x0002;
+
+#[link(name = "imm32")]
+extern "system" {
+    fn ImmGetContext(h_wnd: HWND) -> HIMC;
+    fn ImmReleaseContext(h_wnd: HWND, himc: HIMC) -> BOOL;
+    fn ImmGetCompositionStringW(himc: HIMC, index: DWORD, buffer: LPVOID, buffer_len: DWORD) -> LONG;
+    fn ImmSetCompositionWindow(himc: HIMC, form: *mut COMPOSITIONFORM) -> BOOL;
+}
+
 struct Win32PixelBuffer {
     info: BITMAPINFO,
     data: PixelBuffer
@@ -194,15 +304,34 @@ static mut WINDOW_BITMAP_INFO: BITMAPINFO = create_window_bitamp_info();
 static mut CURSOR_ARROW: HCURSOR = null_mut();
 static mut CURSOR_HAND: HCURSOR = null_mut();
 static mut CURSOR_IBEAM: HCURSOR = null_mut();
+static mut CURSOR_SIZEWE: HCURSOR = null_mut();
 static mut WINDOW_HANDLE: HWND = null_mut();
+static mut UTF16_DECODER: Utf16Decoder = Utf16Decoder::new();
+// a dead key was pressed and is shown as a preedit until the next WM_CHAR
+static mut DEAD_KEY_PENDING: bool = false;
+// the keymap used the last key down, so the WM_CHAR it makes isn't typed
+static mut SKIP_NEXT_CHAR: bool = false;
 
 const WM_USER_CURSOR_TIMER_TICK: UINT = WM_USER + 101;
 
+// initial window size in logical pixels
+const WINDOW_WIDTH: i32 = 1000;
+const WINDOW_HEIGHT: i32 = 500;
+
 pub fn platform_run() {
     unsafe {
+        // per monitor v2 makes windows send WM_DPICHANGED instead of bitmap
+        // stretching our window when it moves between monitors. this needs
+        // windows 10 1703 or newer.
+        SetProcessDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2);
         CURSOR_ARROW = LoadCursorW(null_mut(), IDC_ARROW);
         CURSOR_HAND = LoadCursorW(null_mut(), IDC_HAND);
         CURSOR_IBEAM = LoadCursorW(null_mut(), IDC_IBEAM);
+        CURSOR_SIZEWE = LoadCursorW(null_mut(), IDC_SIZEWE);
+        // double clicks use the system's speed and the size of the box the
+        // second click has to land in
+        crate::APPLICATION_STATE.mouse.clicks.interval = std::time::Duration::from_millis(GetDoubleClickTime() as u64);
+        crate::APPLICATION_STATE.mouse.clicks.distance = GetSystemMetrics(SM_CXDOUBLECLK) / 2;
     }
     create_window("FileX", "FileX");
     run_message_loop();
@@ -239,8 +368,8 @@ fn create_window(name: &str, title: &str) {
             WS_OVERLAPPEDWINDOW | WS_VISIBLE,
             CW_USEDEFAULT,
             CW_USEDEFAULT,
-            1000,
-            500,
+            WINDOW_WIDTH,
+            WINDOW_HEIGHT,
             null_mut(),
             null_mut(),
             hinstance,
@@ -248,6 +377,17 @@ fn create_window(name: &str, title: &str) {
         );
 
         WINDOW_HANDLE = handle;
+
+        // the window is created at 96 dpi sizes, grow it to match the
+        // monitor it opened on
+        let scale = GetDpiForWindow(handle) as f32 / USER_DEFAULT_SCREEN_DPI as f32;
+        crate::handle_scale_factor_change(scale);
+        if scale != 1.0 {
+            SetWindowPos(handle, null_mut(), 0, 0,
+                (WINDOW_WIDTH as f32 * scale) as i32,
+                (WINDOW_HEIGHT as f32 * scale) as i32,
+                SWP_NOMOVE | SWP_NOZORDER | SWP_NOACTIVATE);
+        }
     }
 }
 
@@ -261,19 +401,32 @@ unsafe extern "system" fn win32_wnd_proc(h_wnd: HWND, msg: UINT, w_param: WPARAM
                 Cursor::Hand => { SetCursor(CURSOR_HAND); 1 },
                 Cursor::Arrow => { SetCursor(CURSOR_ARROW); 1 },
                 Cursor::IBeam => { SetCursor(CURSOR_IBEAM); 1 },
+                Cursor::ResizeHorizontal => { SetCursor(CURSOR_SIZEWE); 1 },
                 Cursor::NotSet => DefWindowProcW(h_wnd, msg, w_param, l_param)
             }
         },
         WM_MOUSEMOVE => handle_wm_mouse_move(h_wnd, msg, w_param, l_param),
-        WM_LBUTTONDOWN => handle_wm_button_click(h_wnd, msg, w_param, l_param,),
-        WM_LBUTTONUP => handle_wm_button_click(h_wnd, msg, w_param, l_param),
-        WM_KEYDOWN => handle_wm_keydown(h_wnd, msg, w_param, l_param),
-        WM_KEYUP => 0,
+        WM_LBUTTONDOWN | WM_LBUTTONUP |
+        WM_RBUTTONDOWN | WM_RBUTTONUP |
+        WM_MBUTTONDOWN | WM_MBUTTONUP => handle_wm_button_click(h_wnd, msg, w_param, l_param),
+        // windows wants TRUE back for the x buttons
+        WM_XBUTTONDOWN | WM_XBUTTONUP => { handle_wm_button_click(h_wnd, msg, w_param, l_param); TRUE as LRESULT },
+        WM_MOUSEWHEEL | WM_MOUSEHWHEEL => handle_wm_mouse_wheel(h_wnd, msg, w_param, l_param),
+        WM_CAPTURECHANGED => { if l_param as HWND != h_wnd { handle_mouse_capture_lost(); } 0 },
+        WM_KEYDOWN | WM_KEYUP | WM_SYSKEYDOWN | WM_SYSKEYUP => handle_wm_key(h_wnd, msg, w_param, l_param),
         WM_CHAR => handle_wm_char(h_wnd, msg, w_param, l_param),
+        WM_DEADCHAR => handle_wm_deadchar(w_param),
+        // with nothing focused that takes text the input method shows its
+        // own composition window
+        WM_IME_STARTCOMPOSITION if text_input_caret_rect().is_none() => DefWindowProcW(h_wnd, msg, w_param, l_param),
+        WM_IME_STARTCOMPOSITION => { position_composition_window(h_wnd); 0 },
+        WM_IME_COMPOSITION => handle_wm_ime_composition(h_wnd, msg, w_param, l_param),
+        WM_IME_ENDCOMPOSITION => { text_input(TextInput::PreeditEnd); 0 },
         WM_CREATE => 0,
         WM_DESTROY => { PostQuitMessage(0); 0 },
         WM_PAINT => handle_wm_paint(h_wnd),
         WM_SIZE => handle_wm_size(h_wnd),
+        WM_DPICHANGED => handle_wm_dpichanged(h_wnd, w_param, l_param),
         WM_USER_CURSOR_TIMER_TICK => { cursor_timer_tick(); 0 },
         _ => DefWindowProcW(h_wnd, msg, w_param, l_param)
     }
@@ -283,6 +436,11 @@ pub fn send_cursor_timer_tick() {
     unsafe { PostMessageW(WINDOW_HANDLE, WM_USER_CURSOR_TIMER_TICK, 0, 0) };
 }
 
+// closes the window the same way its close button does
+pub fn close_window() {
+    unsafe { PostMessageW(WINDOW_HANDLE, WM_CLOSE, 0, 0) };
+}
+
 pub fn invalidate_window() {
     unsafe {
         InvalidateRect(WINDOW_HANDLE, null_mut(), 0);
@@ -307,69 +465,274 @@ fn run_message_loop () {
 }
 
 unsafe fn handle_wm_char(_h_wnd: HWND, _msg: UINT, w_param: WPARAM, _l_param: LPARAM) -> LRESULT {
-    let ctrl_down = (0 != GetAsyncKeyState(VK_CONTROL));
-    if (!ctrl_down) {
-        let c = std::char::decode_utf16([w_param as u16].iter().cloned()).nth(0).unwrap().unwrap();
-        if !c.is_control() {
-            keyboard_keydown(KeyboardInput::Char(c));
+    // the key after a dead key has arrived, combined with it or not
+    if DEAD_KEY_PENDING {
+        DEAD_KEY_PENDING = false;
+        text_input(TextInput::PreeditEnd);
+    }
+    if SKIP_NEXT_CHAR {
+        SKIP_NEXT_CHAR = false;
+        return 0;
+    }
+    // altgr arrives as ctrl+alt, the chars it types (@ on german layouts)
+    // aren't shortcuts and still go to the text
+    let shortcut = (0 != GetAsyncKeyState(VK_CONTROL)) && (0 == GetAsyncKeyState(VK_MENU));
+    // chars outside the bmp come as two messages, one per surrogate
+    if let Some(c) = UTF16_DECODER.push(w_param as u16) {
+        if !shortcut && !c.is_control() {
+            text_input(TextInput::Commit(c.to_string()));
         }
     }
     0
 }
 
-unsafe fn handle_wm_keydown(h_wnd: HWND, msg: UINT, w_param: WPARAM, l_param: LPARAM) -> LRESULT {
-    match w_param as i32 {
-        0x41 => { // A
-            if 0 != GetAsyncKeyState(VK_CONTROL) {
-                keyboard_keydown(KeyboardInput::Ctrl_A);
-            }
-        }, 
-        0x43 => { // C
-            if 0 != GetAsyncKeyState(VK_CONTROL) {
-                keyboard_keydown(KeyboardInput::Ctrl_C);
-            }
-        },
-        0x56 => { // V
-            if 0 != GetAsyncKeyState(VK_CONTROL) {
-                let cp_text = get_text_from_clipboard(h_wnd);
-                keyboard_keydown(KeyboardInput::Ctrl_V(cp_text));
-            }
-        },
-        0x58 => { // X
-            if 0 != GetAsyncKeyState(VK_CONTROL) {
-                keyboard_keydown(KeyboardInput::Ctrl_X);
-            }
-        },
-        0x59 => { // Y
-            if 0 != GetAsyncKeyState(VK_CONTROL) {
-                keyboard_keydown(KeyboardInput::Ctrl_Y);
-            }
-        },
-        0x5A => { // Z
-            if 0 != GetAsyncKeyState(VK_CONTROL) {
-                keyboard_keydown(KeyboardInput::Ctrl_Z);
-            }
-        },
-        VK_ESCAPE => keyboard_keydown(KeyboardInput::Escape),
-        
-        VK_BACK => keyboard_keydown(KeyboardInput::Back),
-        VK_DELETE => keyboard_keydown(KeyboardInput::Delete),
-        VK_SHIFT => keyboard_keydown(KeyboardInput::Shift),
-        VK_CONTROL => keyboard_keydown(KeyboardInput::Ctrl),
-        VK_MENU => keyboard_keydown(KeyboardInput::Alt),
-        VK_CAPITAL => keyboard_keydown(KeyboardInput::CapsLock),
-        
-        VK_LEFT => keyboard_keydown(KeyboardInput::ArrowLeft(get_keyboard_modifiers())),
-        VK_UP => keyboard_keydown(KeyboardInput::ArrowUp(get_keyboard_modifiers())),
-        VK_RIGHT => keyboard_keydown(KeyboardInput::ArrowRight(get_keyboard_modifiers())),
-        VK_DOWN => keyboard_keydown(KeyboardInput::ArrowDown(get_keyboard_modifiers())),
-        VK_HOME => keyboard_keydown(KeyboardInput::Home(get_keyboard_modifiers())),
-        VK_END => keyboard_keydown(KeyboardInput::End(get_keyboard_modifiers())),
-        _ => { DefWindowProcW(h_wnd, msg, w_param, l_param); }
+// a dead key like the accent keys on european layouts. the accent is shown
+// at the cursor until the next key combines with it into a WM_CHAR
+unsafe fn handle_wm_deadchar(w_param: WPARAM) -> LRESULT {
+    if let Some(c) = std::char::from_u32(w_param as u32) {
+        DEAD_KEY_PENDING = true;
+        text_input(TextInput::Preedit { text: c.to_string(), cursor: 1 });
+    }
+    0
+}
+
+// the ime sends the string being composed and the finished string through
+// here. the text box draws the composition itself so nothing goes to
+// DefWindowProcW, which would open the default composition window and
+// send the result again as WM_CHARs.
+unsafe fn handle_wm_ime_composition(h_wnd: HWND, msg: UINT, w_param: WPARAM, l_param: LPARAM) -> LRESULT {
+    // nothing focused takes text, windows turns the result into WM_CHAR
+    // messages instead of the text getting dropped
+    if text_input_caret_rect().is_none() {
+        return DefWindowProcW(h_wnd, msg, w_param, l_param);
+    }
+    let himc = ImmGetContext(h_wnd);
+    if himc.is_null() {
+        return DefWindowProcW(h_wnd, msg, w_param, l_param);
+    }
+    let flags = l_param as DWORD;
+    if flags & GCS_RESULTSTR != 0 {
+        if let Some(text) = get_composition_string(himc, GCS_RESULTSTR) {
+            text_input(TextInput::Commit(text));
+        }
+    }
+    if flags & GCS_COMPSTR != 0 {
+        match get_composition_string(himc, GCS_COMPSTR) {
+            Some(text) => {
+                // the cursor comes in utf-16 units, the text box counts chars
+                let cursor_units = if flags & GCS_CURSORPOS != 0 {
+                    ImmGetCompositionStringW(himc, GCS_CURSORPOS, null_mut(), 0) as usize
+                }
+                else {
+                    text.encode_utf16().count()
+                };
+                let mut units = 0;
+                let cursor = text.chars().take_while(|c| { units += c.len_utf16(); units <= cursor_units }).count();
+                text_input(TextInput::Preedit { text, cursor });
+            },
+            None => text_input(TextInput::PreeditEnd)
+        }
+    }
+    else if flags == 0 {
+        // the composition was cancelled
+        text_input(TextInput::PreeditEnd);
+    }
+    ImmReleaseContext(h_wnd, himc);
+    position_composition_window(h_wnd);
+    0
+}
+
+unsafe fn get_composition_string(himc: HIMC, index: DWORD) -> Option<String> {
+    let bytes = ImmGetCompositionStringW(himc, index, null_mut(), 0);
+    if bytes <= 0 {
+        return None;
+    }
+    let mut buffer = vec![0u16; bytes as usize / 2];
+    ImmGetCompositionStringW(himc, index, buffer.as_mut_ptr() as LPVOID, bytes as DWORD);
+    Some(String::from_utf16_lossy(&buffer))
+}
+
+// keeps the ime's candidate list under the caret of the focused text box
+unsafe fn position_composition_window(h_wnd: HWND) {
+    let caret = match text_input_caret_rect() {
+        Some(r) => r,
+        None => return
+    };
+    let himc = ImmGetContext(h_wnd);
+    if himc.is_null() {
+        return;
+    }
+    let mut form = COMPOSITIONFORM {
+        dwStyle: CFS_POINT,
+        ptCurrentPos: POINT { x: caret.x, y: caret.y },
+        rcArea: RECT { left: 0, top: 0, right: 0, bottom: 0 }
+    };
+    ImmSetCompositionWindow(himc, &mut form);
+    ImmReleaseContext(h_wnd, himc);
+}
+
+// key down and up, system keys too. alt combinations and f10 come as
+// system keys and what the application doesn't use of those goes on to
+// windows so alt+f4 and alt+space keep working.
+unsafe fn handle_wm_key(h_wnd: HWND, msg: UINT, w_param: WPARAM, l_param: LPARAM) -> LRESULT {
+    let event = key_event(msg, w_param, l_param);
+    if event.state == KeyState::Pressed {
+        SKIP_NEXT_CHAR = false;
+    }
+    let handled = keyboard_event(event);
+    // the WM_CHAR this key makes isn't typed when something already used it
+    if handled && event.state == KeyState::Pressed {
+        SKIP_NEXT_CHAR = true;
+    }
+    if !handled && (msg == WM_SYSKEYDOWN || msg == WM_SYSKEYUP) {
+        return DefWindowProcW(h_wnd, msg, w_param, l_param);
     }
     0
 }
 
+// l_param has the scan code in bits 16 to 23, bit 24 set for the extended
+// keys, bit 30 set when the key was already down and bit 31 on release
+unsafe fn key_event(msg: UINT, w_param: WPARAM, l_param: LPARAM) -> KeyEvent {
+    let scan_code = ((l_param >> 16) & 0xFF) as u32;
+    let extended = (l_param >> 24) & 1 == 1;
+    let state = if msg == WM_KEYUP || msg == WM_SYSKEYUP { KeyState::Released } else { KeyState::Pressed };
+    KeyEvent {
+        code: key_code_from_scan_code(scan_code, extended),
+        key: key_from_virtual_key(w_param as i32),
+        modifiers: get_keyboard_modifiers(),
+        state,
+        repeat: state == KeyState::Pressed && (l_param >> 30) & 1 == 1
+    }
+}
+
+// what the key means with the current layout. letters, digits and
+// punctuation come from the layout so ctrl+z is the z key wherever the
+// layout puts it. the numpad sends different virtual keys with num lock
+// on and off so that's already taken care of.
+unsafe fn key_from_virtual_key(vk: i32) -> Key {
+    match vk {
+        VK_ESCAPE => Key::Escape,
+        VK_RETURN => Key::Enter,
+        VK_TAB => Key::Tab,
+        VK_SPACE => Key::Space,
+        VK_BACK => Key::Back,
+        VK_DELETE => Key::Delete,
+        VK_INSERT => Key::Insert,
+        VK_LEFT => Key::Left,
+        VK_UP => Key::Up,
+        VK_RIGHT => Key::Right,
+        VK_DOWN => Key::Down,
+        VK_HOME => Key::Home,
+        VK_END => Key::End,
+        VK_PRIOR => Key::PageUp,
+        VK_NEXT => Key::PageDown,
+        VK_SHIFT => Key::Shift,
+        VK_CONTROL => Key::Ctrl,
+        VK_MENU => Key::Alt,
+        VK_LWIN | VK_RWIN => Key::Super,
+        VK_CAPITAL => Key::CapsLock,
+        VK_NUMLOCK => Key::NumLock,
+        VK_SCROLL => Key::ScrollLock,
+        VK_SNAPSHOT => Key::PrintScreen,
+        VK_PAUSE => Key::Pause,
+        VK_APPS => Key::ContextMenu,
+        VK_F1..=VK_F24 => Key::F((vk - VK_F1 + 1) as u8),
+        VK_NUMPAD0..=VK_NUMPAD9 => Key::Char((b'0' + (vk - VK_NUMPAD0) as u8) as char),
+        VK_MULTIPLY => Key::Char('*'),
+        VK_ADD => Key::Char('+'),
+        VK_SUBTRACT => Key::Char('-'),
+        VK_DIVIDE => Key::Char('/'),
+        VK_DECIMAL => Key::Char('.'),
+        _ => {
+            // the high bit marks dead keys
+            let c = MapVirtualKeyW(vk as u32, MAPVK_VK_TO_CHAR) & 0x7FFF;
+            match std::char::from_u32(c).filter(|c| !c.is_control()) {
+                Some(c) => Key::Char(c.to_uppercase().next().unwrap_or(c)),
+                None => Key::Unidentified
+            }
+        }
+    }
+}
+
+// the physical key from its set 1 scan code. the extended bit tells the
+// keys that share a scan code apart, the arrows from the numpad ones and
+// the right ctrl and alt from the left.
+fn key_code_from_scan_code(scan_code: u32, extended: bool) -> KeyCode {
+    const ROW_Q: &[u8] = b"QWERTYUIOP";
+    const ROW_A: &[u8] = b"ASDFGHJKL";
+    const ROW_Z: &[u8] = b"ZXCVBNM";
+    match (scan_code, extended) {
+        (0x01, _) => KeyCode::Escape,
+        (0x02..=0x0A, _) => KeyCode::Digit((scan_code - 0x01) as u8),
+        (0x0B, _) => KeyCode::Digit(0),
+        (0x0C, _) => KeyCode::Minus,
+        (0x0D, _) => KeyCode::Equal,
+        (0x0E, _) => KeyCode::Backspace,
+        (0x0F, _) => KeyCode::Tab,
+        (0x10..=0x19, _) => KeyCode::Letter(ROW_Q[(scan_code - 0x10) as usize] as char),
+        (0x1A, _) => KeyCode::BracketLeft,
+        (0x1B, _) => KeyCode::BracketRight,
+        (0x1C, false) => KeyCode::Enter,
+        (0x1C, true) => KeyCode::NumpadEnter,
+        (0x1D, false) => KeyCode::ControlLeft,
+        (0x1D, true) => KeyCode::ControlRight,
+        (0x1E..=0x26, _) => KeyCode::Letter(ROW_A[(scan_code - 0x1E) as usize] as char),
+        (0x27, _) => KeyCode::Semicolon,
+        (0x28, _) => KeyCode::Quote,
+        (0x29, _) => KeyCode::Backquote,
+        (0x2A, _) => KeyCode::ShiftLeft,
+        (0x2B, _) => KeyCode::Backslash,
+        (0x2C..=0x32, _) => KeyCode::Letter(ROW_Z[(scan_code - 0x2C) as usize] as char),
+        (0x33, _) => KeyCode::Comma,
+        (0x34, _) => KeyCode::Period,
+        (0x35, false) => KeyCode::Slash,
+        (0x35, true) => KeyCode::NumpadDivide,
+        (0x36, _) => KeyCode::ShiftRight,
+        (0x37, false) => KeyCode::NumpadMultiply,
+        (0x37, true) => KeyCode::PrintScreen,
+        (0x38, false) => KeyCode::AltLeft,
+        (0x38, true) => KeyCode::AltRight,
+        (0x39, _) => KeyCode::Space,
+        (0x3A, _) => KeyCode::CapsLock,
+        (0x3B..=0x44, _) => KeyCode::F((scan_code - 0x3A) as u8),
+        (0x45, false) => KeyCode::Pause,
+        (0x45, true) => KeyCode::NumLock,
+        (0x46, _) => KeyCode::ScrollLock,
+        (0x47, false) => KeyCode::Numpad(7),
+        (0x47, true) => KeyCode::Home,
+        (0x48, false) => KeyCode::Numpad(8),
+        (0x48, true) => KeyCode::ArrowUp,
+        (0x49, false) => KeyCode::Numpad(9),
+        (0x49, true) => KeyCode::PageUp,
+        (0x4A, _) => KeyCode::NumpadSubtract,
+        (0x4B, false) => KeyCode::Numpad(4),
+        (0x4B, true) => KeyCode::ArrowLeft,
+        (0x4C, _) => KeyCode::Numpad(5),
+        (0x4D, false) => KeyCode::Numpad(6),
+        (0x4D, true) => KeyCode::ArrowRight,
+        (0x4E, _) => KeyCode::NumpadAdd,
+        (0x4F, false) => KeyCode::Numpad(1),
+        (0x4F, true) => KeyCode::End,
+        (0x50, false) => KeyCode::Numpad(2),
+        (0x50, true) => KeyCode::ArrowDown,
+        (0x51, false) => KeyCode::Numpad(3),
+        (0x51, true) => KeyCode::PageDown,
+        (0x52, false) => KeyCode::Numpad(0),
+        (0x52, true) => KeyCode::Insert,
+        (0x53, false) => KeyCode::NumpadDecimal,
+        (0x53, true) => KeyCode::Delete,
+        (0x56, _) => KeyCode::IntlBackslash,
+        (0x57, _) => KeyCode::F(11),
+        (0x58, _) => KeyCode::F(12),
+        (0x5B, true) => KeyCode::SuperLeft,
+        (0x5C, true) => KeyCode::SuperRight,
+        (0x5D, true) => KeyCode::ContextMenu,
+        (0x64..=0x6E, _) => KeyCode::F((scan_code - 0x64 + 13) as u8),
+        (0x76, _) => KeyCode::F(24),
+        _ => KeyCode::Unknown(scan_code | if extended { 0x100 } else { 0 })
+    }
+}
+
 unsafe fn handle_wm_mouse_move(h_wnd: HWND, _msg: UINT, _w_param: WPARAM, l_param: LPARAM) -> LRESULT {
     let mouse_x = GET_X_LPARAM(l_param);
     let mouse_y = GET_Y_LPARAM(l_param);
@@ -399,22 +762,70 @@ unsafe fn handle_wm_mouse_move(h_wnd: HWND, _msg: UINT, _w_param: WPARAM, l_para
     return 0;
 }
 
-unsafe fn handle_wm_button_click(_h_wnd: HWND, msg: UINT, _w_param: WPARAM, l_param: LPARAM) -> LRESULT {
+// the window holds on to the mouse while any button is down so a drag that
+// leaves the window still gets its button up
+unsafe fn handle_wm_button_click(h_wnd: HWND, msg: UINT, w_param: WPARAM, l_param: LPARAM) -> LRESULT {
     let mouse_x = GET_X_LPARAM(l_param);
     let mouse_y = GET_Y_LPARAM(l_param);
-    match msg {
-        WM_LBUTTONDOWN => handle_left_mouse_button_down(mouse_x, mouse_y),
-        WM_LBUTTONUP => handle_left_mouse_button_up(mouse_x, mouse_y),
-        _ => { }
+    let x_button = if GET_XBUTTON_WPARAM(w_param) == XBUTTON1 { MouseButton::Back } else { MouseButton::Forward };
+    let (button, down) = match msg {
+        WM_LBUTTONDOWN => (MouseButton::Left, true),
+        WM_LBUTTONUP => (MouseButton::Left, false),
+        WM_RBUTTONDOWN => (MouseButton::Right, true),
+        WM_RBUTTONUP => (MouseButton::Right, false),
+        WM_MBUTTONDOWN => (MouseButton::Middle, true),
+        WM_MBUTTONUP => (MouseButton::Middle, false),
+        WM_XBUTTONDOWN => (x_button, true),
+        _ => (x_button, false)
+    };
+    if down {
+        if GetCapture() != h_wnd {
+            SetCapture(h_wnd);
+        }
+        handle_mouse_button_down(button, mouse_x, mouse_y);
+    }
+    else {
+        let buttons = MK_LBUTTON | MK_RBUTTON | MK_MBUTTON | MK_XBUTTON1 | MK_XBUTTON2;
+        handle_mouse_button_up(button, mouse_x, mouse_y);
+        // w_param has the buttons that are still down
+        if (w_param & buttons) == 0 && GetCapture() == h_wnd {
+            ReleaseCapture();
+        }
     }
     return 0;
 }
 
+// the wheel delta is in 120ths of a notch, high resolution wheels send
+// less than that at a time. the position comes in screen coordinates.
+unsafe fn handle_wm_mouse_wheel(h_wnd: HWND, msg: UINT, w_param: WPARAM, l_param: LPARAM) -> LRESULT {
+    let mut point = POINT { x: GET_X_LPARAM(l_param), y: GET_Y_LPARAM(l_param) };
+    ScreenToClient(h_wnd, &mut point);
+    let notches = GET_WHEEL_DELTA_WPARAM(w_param) as f32 / WHEEL_DELTA as f32;
+    let setting = if msg == WM_MOUSEHWHEEL { SPI_GETWHEELSCROLLCHARS } else { SPI_GETWHEELSCROLLLINES };
+    let mut lines_per_notch: UINT = 3;
+    SystemParametersInfoW(setting, 0, &mut lines_per_notch as *mut UINT as *mut c_void, 0);
+    // scrolling a page per notch isn't supported, it scrolls the default
+    if lines_per_notch == WHEEL_PAGESCROLL {
+        lines_per_notch = 3;
+    }
+    let lines = notches * lines_per_notch as f32;
+    let modifiers = get_keyboard_modifiers();
+    if msg == WM_MOUSEHWHEEL {
+        handle_mouse_wheel(point.x, point.y, lines, 0.0, modifiers);
+    }
+    else {
+        handle_mouse_wheel(point.x, point.y, 0.0, lines, modifiers);
+    }
+    0
+}
+
 fn handle_wm_paint(h_wnd: HWND) -> LRESULT {
     unsafe {
+        // cleared first so a frame that asks for the next one while it's
+        // being drawn gets it
         if crate::APPLICATION_STATE.needs_redraw {
-            crate::update_back_buffer();
             crate::APPLICATION_STATE.needs_redraw = false;
+            crate::update_back_buffer();
         }
 
         let mut ps =  mem::MaybeUninit::<PAINTSTRUCT>::zeroed().assume_init();
@@ -454,6 +865,23 @@ fn handle_wm_size(h_wnd: HWND) -> LRESULT {
     return 0;
 }
 
+// sent when the window moves to a monitor with a different dpi or the user
+// changes the scaling. lparam is the rect windows suggests for the new dpi,
+// using it keeps the window the same logical size. SetWindowPos causes a
+// WM_SIZE which reallocates the back buffer and relayouts at the new scale.
+unsafe fn handle_wm_dpichanged(h_wnd: HWND, w_param: WPARAM, l_param: LPARAM) -> LRESULT {
+    let dpi = LOWORD(w_param as DWORD);
+    crate::handle_scale_factor_change(dpi as f32 / USER_DEFAULT_SCREEN_DPI as f32);
+    let suggested = &*(l_param as *const RECT);
+    SetWindowPos(h_wnd, null_mut(),
+        suggested.left,
+        suggested.top,
+        suggested.right - suggested.left,
+        suggested.bottom - suggested.top,
+        SWP_NOZORDER | SWP_NOACTIVATE);
+    0
+}
+
 const fn create_window_bitamp_info() -> BITMAPINFO {
     let bit_count = 32;
     BITMAPINFO { 
@@ -497,6 +925,10 @@ unsafe fn get_text_from_clipboard(h_wnd: HWND) -> Option<String> {
     return result;
 }
 
+pub fn get_clipboard_text() -> Option<String> {
+    unsafe { get_text_from_clipboard(WINDOW_HANDLE) }
+}
+
 pub fn set_text_into_clipboard(text: &str) { //(h_wnd: HWND, s: &str) {
     unsafe {
         //if TRUE == OpenClipboard(h_wnd) {
@@ -541,6 +973,7 @@ unsafe fn get_keyboard_modifiers() -> KeyboardModifiers {
     KeyboardModifiers {
         ctrl: (0 != GetAsyncKeyState(VK_CONTROL)),
         alt: (0 != GetAsyncKeyState(VK_MENU)),
-        shift: (0 != GetAsyncKeyState(VK_SHIFT))
+        shift: (0 != GetAsyncKeyState(VK_SHIFT)),
+        super_key: (0 != GetAsyncKeyState(VK_LWIN)) || (0 != GetAsyncKeyState(VK_RWIN))
     }
 }
\ No newline at end of file